@@ -0,0 +1,46 @@
+//! An on-target benchmark binary: `main` runs each benchmark one after the other
+
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() -> ! {
+    extern "C" {
+        fn main() -> !;
+    }
+
+    unsafe { main() }
+}
+
+#[export_name = "main"]
+unsafe extern "C" fn __bench_entry() -> ! {
+    fill();
+    sum();
+
+    loop {}
+}
+
+#[inline(never)]
+fn fill() {
+    X.store(1, Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn sum() {
+    let mut buffer = [0u32; 16];
+    for (i, x) in buffer.iter_mut().enumerate() {
+        *x = X.load(Ordering::Relaxed) + i as u32;
+    }
+    consume(&buffer);
+}
+
+#[inline(never)]
+fn consume(buffer: &[u32; 16]) {
+    X.store(buffer.iter().sum(), Ordering::Relaxed);
+}
@@ -0,0 +1,34 @@
+#![no_main]
+#![no_std]
+
+use core::{
+    arch::global_asm,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() {
+    extern "C" {
+        fn bar();
+    }
+
+    foo();
+    unsafe { bar() }
+}
+
+#[inline(never)]
+#[no_mangle]
+extern "C" fn foo() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+// `bar` is another name of `foo`
+global_asm!(
+    ".global bar
+     .type bar, %function
+     .set bar, foo"
+);
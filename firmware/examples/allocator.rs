@@ -0,0 +1,48 @@
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    ptr,
+};
+
+use panic_halt as _;
+
+#[global_allocator]
+static HEAP: Bump = Bump {
+    memory: UnsafeCell::new([0; 256]),
+    next: UnsafeCell::new(0),
+};
+
+// a bump allocator that never frees memory
+struct Bump {
+    memory: UnsafeCell<[u8; 256]>,
+    next: UnsafeCell<usize>,
+}
+
+unsafe impl Sync for Bump {}
+
+unsafe impl GlobalAlloc for Bump {
+    #[inline(never)]
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let start = (*self.next.get() + layout.align() - 1) & !(layout.align() - 1);
+        let end = start + layout.size();
+        if end > 256 {
+            return ptr::null_mut();
+        }
+        *self.next.get() = end;
+        (self.memory.get() as *mut u8).add(start)
+    }
+
+    unsafe fn dealloc(&self, _: *mut u8, _: Layout) {}
+}
+
+#[no_mangle]
+fn _start() {
+    let x = Box::new(unsafe { ptr::read_volatile(&0u32) });
+    unsafe { ptr::write_volatile(&mut 0, *x) }
+}
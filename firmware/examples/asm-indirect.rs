@@ -0,0 +1,43 @@
+#![no_main]
+#![no_std]
+
+use core::{
+    arch::global_asm,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+extern "C" {
+    fn call_both(f: extern "C" fn(), g: extern "C" fn());
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe { call_both(foo, bar) }
+}
+
+extern "C" fn foo() {
+    X.store(1, Ordering::Relaxed);
+}
+
+extern "C" fn bar() {
+    X.store(2, Ordering::Relaxed);
+}
+
+// hand-written function that performs two indirect function calls
+global_asm!(
+    ".section .text.call_both,\"ax\",%progbits
+     .global call_both
+     .type call_both,%function
+     .thumb_func
+call_both:
+     push {{r4, lr}}
+     mov r4, r1
+     blx r0
+     blx r4
+     pop {{r4, pc}}
+     .size call_both, . - call_both"
+);
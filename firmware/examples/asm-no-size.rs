@@ -0,0 +1,34 @@
+#![no_main]
+#![no_std]
+
+use core::arch::global_asm;
+
+use panic_halt as _;
+
+extern "C" {
+    fn outer();
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe { outer() }
+}
+
+// hand-written functions without `.size` directives
+global_asm!(
+    ".section .text.outer,\"ax\",%progbits
+     .global outer
+     .type outer,%function
+     .thumb_func
+outer:
+     push {{r7, lr}}
+     bl inner
+     pop {{r7, pc}}
+
+     .global inner
+     .type inner,%function
+     .thumb_func
+inner:
+     push {{r4, lr}}
+     pop {{r4, pc}}"
+);
@@ -0,0 +1,26 @@
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() {
+    // two call sites
+    foo();
+    bar();
+    foo();
+}
+
+#[inline(never)]
+fn foo() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn bar() {
+    X.store(X.load(Ordering::Relaxed) + 2, Ordering::Relaxed);
+}
@@ -0,0 +1,39 @@
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() {
+    unsafe { hot(ptr::read_volatile(&0)) }
+}
+
+// `hot` usually only calls `light`; the rare error path in `cold` has a much larger frame
+#[inline(never)]
+#[no_mangle]
+unsafe fn hot(x: u32) {
+    if x == 0 {
+        cold(x);
+    }
+    light(x);
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn light(x: u32) {
+    let mut buffer = [0u32; 4];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn cold(x: u32) {
+    let mut buffer = [0u32; 64];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+}
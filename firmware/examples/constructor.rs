@@ -0,0 +1,27 @@
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+// a static constructor; the startup code runs it before `_start`
+#[used]
+#[link_section = ".init_array"]
+static CONSTRUCTOR: extern "C" fn() = constructor;
+
+extern "C" fn constructor() {
+    let x = 0;
+    unsafe {
+        // force `x` to be on the stack
+        core::ptr::read_volatile(&&x);
+    }
+    X.store(1, Ordering::Relaxed);
+}
+
+#[no_mangle]
+fn _start() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
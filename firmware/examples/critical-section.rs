@@ -0,0 +1,64 @@
+#![no_main]
+#![no_std]
+
+use core::{arch::asm, hint, ptr};
+
+use panic_halt as _;
+
+#[repr(C)]
+pub struct VectorTable {
+    initial_sp: u32,
+    reset: unsafe extern "C" fn(),
+    exceptions: [Option<unsafe extern "C" fn()>; 14],
+    interrupts: [unsafe extern "C" fn(); 1],
+}
+
+#[link_section = ".vector_table"]
+#[no_mangle]
+static VECTOR_TABLE: VectorTable = VectorTable {
+    initial_sp: 0x2000_4000,
+    reset: _start,
+    exceptions: [None; 14],
+    interrupts: [TIMER0],
+};
+
+#[no_mangle]
+unsafe extern "C" fn _start() {
+    ptr::read_volatile(&VECTOR_TABLE);
+
+    asm!("cpsid i");
+    update(ptr::read_volatile(&0));
+    asm!("cpsie i");
+    report();
+}
+
+// only runs with interrupts masked
+#[inline(never)]
+#[no_mangle]
+unsafe fn update(x: u32) {
+    let mut buffer = [0u32; 8];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+    store(x);
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn store(x: u32) {
+    ptr::write_volatile(0x2000_0000 as *mut u32, x);
+}
+
+// runs with interrupts enabled
+#[inline(never)]
+#[no_mangle]
+unsafe fn report() {
+    store(0);
+}
+
+#[no_mangle]
+unsafe extern "C" fn TIMER0() {
+    let mut samples = [0u32; 4];
+    hint::black_box(&mut samples);
+    store(samples[0]);
+}
@@ -0,0 +1,30 @@
+#![no_main]
+#![no_std]
+
+use core::hint;
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() {
+    let mut guards = [Guard(0), Guard(1)];
+    work(&mut guards);
+}
+
+#[inline(never)]
+fn work(guards: &mut [Guard]) {
+    let mut buffer = [0u8; 16];
+    hint::black_box(&mut buffer);
+    hint::black_box(guards);
+}
+
+struct Guard(u8);
+
+impl Drop for Guard {
+    #[inline(never)]
+    fn drop(&mut self) {
+        // the destructor uses more stack than the rest of the program
+        let mut buffer = [self.0; 64];
+        hint::black_box(&mut buffer);
+    }
+}
@@ -0,0 +1,112 @@
+#![no_main]
+#![no_std]
+
+use core::{
+    arch::global_asm,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+extern "C" {
+    fn c_library();
+}
+
+#[no_mangle]
+fn _start(f: extern "C" fn(i32)) -> usize {
+    // call via function pointer
+    f(1);
+
+    unsafe { c_library() }
+
+    // keep this function in the resulting binary
+    foo as usize
+}
+
+extern "C" fn foo(x: i32) {
+    X.store(x as u32, Ordering::Relaxed);
+}
+
+// stand-in for a C library compiled with `-g`: `c_helper` doesn't appear in the LLVM IR but the
+// DWARF information describes its prototype, `void c_helper(int)`
+global_asm!(
+    ".section .text.c_library,\"ax\",%progbits
+     .global c_library
+     .type c_library,%function
+     .thumb_func
+c_library:
+     push {{r7, lr}}
+     movs r0, #0
+     bl c_helper
+     pop {{r7, pc}}
+     .size c_library, . - c_library
+
+     .global c_helper
+     .type c_helper,%function
+     .thumb_func
+c_helper:
+     bx lr
+     .size c_helper, . - c_helper
+
+     // placed after the compiler generated debug information, which assumes it comes first
+     .section .debug_abbrev,\"\",%progbits
+     .subsection 1
+.Labbrev:
+     .uleb128 1
+     .uleb128 0x11 // DW_TAG_compile_unit
+     .byte 1
+     .uleb128 0x13 // DW_AT_language
+     .uleb128 0x05 // DW_FORM_data2
+     .byte 0, 0
+     .uleb128 2
+     .uleb128 0x2e // DW_TAG_subprogram
+     .byte 1
+     .uleb128 0x03 // DW_AT_name
+     .uleb128 0x08 // DW_FORM_string
+     .uleb128 0x3f // DW_AT_external
+     .uleb128 0x0c // DW_FORM_flag
+     .byte 0, 0
+     .uleb128 3
+     .uleb128 0x05 // DW_TAG_formal_parameter
+     .byte 0
+     .uleb128 0x49 // DW_AT_type
+     .uleb128 0x13 // DW_FORM_ref4
+     .byte 0, 0
+     .uleb128 4
+     .uleb128 0x24 // DW_TAG_base_type
+     .byte 0
+     .uleb128 0x03 // DW_AT_name
+     .uleb128 0x08 // DW_FORM_string
+     .uleb128 0x3e // DW_AT_encoding
+     .uleb128 0x0b // DW_FORM_data1
+     .uleb128 0x0b // DW_AT_byte_size
+     .uleb128 0x0b // DW_FORM_data1
+     .byte 0, 0
+     .byte 0
+
+     .section .debug_info,\"\",%progbits
+     .subsection 1
+.Lcu:
+     .4byte .Lcu_end - .Lcu_version
+.Lcu_version:
+     .2byte 4
+     .4byte .Labbrev
+     .byte 4
+     .uleb128 1
+     .2byte 0x0c // DW_LANG_C99
+     .uleb128 2
+     .asciz \"c_helper\"
+     .byte 1
+     .uleb128 3
+     .4byte .Lint - .Lcu
+     .byte 0
+.Lint:
+     .uleb128 4
+     .asciz \"int\"
+     .byte 5 // DW_ATE_signed
+     .byte 4
+     .byte 0
+.Lcu_end:"
+);
@@ -0,0 +1,29 @@
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() {
+    unsafe { log(ptr::read_volatile(&0)) }
+}
+
+// the `logging` feature formats the message in a large buffer
+#[cfg(feature = "logging")]
+#[inline(never)]
+#[no_mangle]
+unsafe fn log(x: u32) {
+    let mut buffer = [0u32; 64];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+}
+
+#[cfg(not(feature = "logging"))]
+#[inline(never)]
+#[no_mangle]
+unsafe fn log(x: u32) {
+    ptr::write_volatile(&mut 0, x);
+}
@@ -0,0 +1,27 @@
+#![no_main]
+#![no_std]
+
+use core::arch::asm;
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() {
+    nop();
+    push_pop();
+}
+
+// the inline assembly doesn't use the stack
+#[inline(never)]
+fn nop() {
+    unsafe { asm!("nop") }
+}
+
+// LLVM doesn't see the `push` so it reports that this function doesn't use the stack
+#[inline(never)]
+fn push_pop() {
+    unsafe {
+        asm!("push {{r0}}");
+        asm!("pop {{r0}}");
+    }
+}
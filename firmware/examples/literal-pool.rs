@@ -0,0 +1,28 @@
+#![no_main]
+#![no_std]
+
+use core::arch::global_asm;
+
+use panic_halt as _;
+
+extern "C" {
+    fn lookup();
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe { lookup() }
+}
+
+// the symbol size covers the lookup table that follows the code
+global_asm!(
+    ".section .text.lookup,\"ax\",%progbits
+     .global lookup
+     .type lookup,%function
+     .thumb_func
+     lookup:
+     bx lr
+     .p2align 2
+     .word 1, 2, 3, 4
+     .size lookup, . - lookup"
+);
@@ -0,0 +1,42 @@
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() {
+    // NOTE `setjmp` and `longjmp` are stand-ins for the C library functions
+    if setjmp() == 0 {
+        parse();
+    }
+}
+
+#[inline(never)]
+fn parse() {
+    if X.load(Ordering::Relaxed) == 0 {
+        fail();
+    }
+}
+
+#[inline(never)]
+fn fail() {
+    unsafe { longjmp() }
+}
+
+#[inline(never)]
+#[no_mangle]
+extern "C" fn setjmp() -> u32 {
+    X.load(Ordering::Relaxed)
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe extern "C" fn longjmp() -> ! {
+    loop {
+        X.store(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,33 @@
+#![no_main]
+#![no_std]
+
+use core::{
+    hint,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() -> ! {
+    init();
+
+    loop {
+        work();
+    }
+}
+
+// one-time initialization that uses more stack than the main loop
+#[inline(never)]
+fn init() {
+    let mut buffer = [0u32; 16];
+    hint::black_box(&mut buffer);
+    X.store(buffer[0], Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn work() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
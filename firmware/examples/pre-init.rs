@@ -0,0 +1,30 @@
+#![no_main]
+#![no_std]
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+// the startup code calls it through `__pre_init`, an alias that the LLVM IR provides
+#[no_mangle]
+extern "C" fn DefaultPreInit() {
+    let x = 0;
+    unsafe {
+        // force `x` to be on the stack
+        ptr::read_volatile(&&x);
+    }
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        // keep `DefaultPreInit` in the binary
+        ptr::read_volatile(&(DefaultPreInit as extern "C" fn()));
+    }
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
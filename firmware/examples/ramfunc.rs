@@ -0,0 +1,21 @@
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn _start() {
+    ramfunc();
+    X.store(1, Ordering::Relaxed);
+}
+
+// functions that are executed from RAM are placed in their own section
+#[inline(never)]
+#[link_section = ".ramfunc"]
+fn ramfunc() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
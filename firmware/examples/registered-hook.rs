@@ -0,0 +1,52 @@
+#![feature(used_with_arg)]
+#![no_main]
+#![no_std]
+
+use core::{
+    ptr,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+#[used(linker)]
+#[link_section = "handlers"]
+static HANDLERS: [fn(); 2] = [foo, bar];
+
+// a function pointer with the same signature that's not registered in the section
+static mut HOOK: fn() = baz;
+
+extern "C" {
+    static __start_handlers: u8;
+    static __stop_handlers: u8;
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        let mut handler = &__start_handlers as *const u8 as *const fn();
+        while handler < &__stop_handlers as *const u8 as *const fn() {
+            (*handler)();
+            handler = handler.add(1);
+        }
+
+        ptr::read_volatile(ptr::addr_of!(HOOK))();
+    }
+}
+
+#[inline(never)]
+fn foo() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn bar() {
+    X.store(X.load(Ordering::Relaxed) + 2, Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn baz() {
+    X.store(X.load(Ordering::Relaxed) + 3, Ordering::Relaxed);
+}
@@ -0,0 +1,41 @@
+#![feature(used_with_arg)]
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use panic_halt as _;
+
+static X: AtomicU32 = AtomicU32::new(0);
+
+// function pointers collected in a linker section; the linker generates the `__start_*` and
+// `__stop_*` symbols that delimit it
+#[used(linker)]
+#[link_section = "handlers"]
+static HANDLERS: [fn(); 2] = [foo, bar];
+
+extern "C" {
+    static __start_handlers: u8;
+    static __stop_handlers: u8;
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        let mut handler = &__start_handlers as *const u8 as *const fn();
+        while handler < &__stop_handlers as *const u8 as *const fn() {
+            (*handler)();
+            handler = handler.add(1);
+        }
+    }
+}
+
+#[inline(never)]
+fn foo() {
+    X.store(X.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+}
+
+#[inline(never)]
+fn bar() {
+    X.store(X.load(Ordering::Relaxed) + 2, Ordering::Relaxed);
+}
@@ -0,0 +1,49 @@
+#![no_main]
+#![no_std]
+
+use core::{arch::asm, ptr};
+
+use panic_halt as _;
+
+// scratch stack of 256 bytes
+#[no_mangle]
+static mut SCRATCH: [u64; 32] = [0; 32];
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        on_scratch();
+        store(1);
+    }
+}
+
+// calls `filter` on the scratch stack
+#[inline(never)]
+#[no_mangle]
+unsafe fn on_scratch() {
+    asm!(
+        "mov r4, sp",
+        "mov sp, {top}",
+        "bl filter",
+        "mov sp, r4",
+        top = in(reg) ptr::addr_of_mut!(SCRATCH).add(1),
+        out("r4") _,
+        clobber_abi("C"),
+    );
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn filter() {
+    let mut taps = [0u32; 32];
+    for tap in taps.iter_mut() {
+        ptr::write_volatile(tap, ptr::read_volatile(0x2000_0000 as *const u32));
+    }
+    store(taps[0]);
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn store(x: u32) {
+    ptr::write_volatile(0x2000_0004 as *mut u32, x);
+}
@@ -0,0 +1,60 @@
+#![no_main]
+#![no_std]
+
+use core::arch::global_asm;
+
+use panic_halt as _;
+
+extern "C" {
+    fn a();
+    fn b();
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        a();
+        b();
+    }
+}
+
+#[no_mangle]
+extern "C" fn heavy() {
+    let mut buffer = [0u32; 8];
+    for word in buffer.iter_mut() {
+        unsafe { core::ptr::write_volatile(word, 1) }
+    }
+}
+
+// `a` and `b` jump to their common tail, `shared_tail`, which calls `heavy` and then pops their
+// frames
+global_asm!(
+    ".section .text.shared_tail,\"ax\",%progbits
+     .global a
+     .type a,%function
+     .thumb_func
+     a:
+     push {{r4, lr}}
+     sub sp, #16
+     movs r4, #1
+     add sp, #16
+     b shared_tail
+     .size a, . - a
+
+     .global b
+     .type b,%function
+     .thumb_func
+     b:
+     push {{r4, r5, r6, lr}}
+     movs r4, #2
+     b shared_tail
+     .size b, . - b
+
+     .global shared_tail
+     .type shared_tail,%function
+     .thumb_func
+     shared_tail:
+     bl heavy
+     pop {{r4, pc}}
+     .size shared_tail, . - shared_tail"
+);
@@ -0,0 +1,58 @@
+#![no_main]
+#![no_std]
+
+use core::arch::global_asm;
+
+use panic_halt as _;
+
+extern "C" {
+    fn scratch();
+    fn branchy();
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe {
+        scratch();
+        branchy();
+    }
+}
+
+// a hand-written function whose `.stack_sizes` entry doesn't match its prologue
+global_asm!(
+    ".section .text.scratch,\"ax\",%progbits
+     .global scratch
+     .type scratch,%function
+     .thumb_func
+scratch:
+     push {{r7, lr}}
+     sub sp, #16
+     add sp, #16
+     pop {{r7, pc}}
+     .size scratch, . - scratch
+
+     .section .stack_sizes,\"o\",%progbits,.text.scratch
+     .long scratch
+     .uleb128 8"
+);
+
+// a hand-written function that claims to not use the stack but pushes onto it; its branch makes the
+// machine code analysis give up on computing how much
+global_asm!(
+    ".section .text.branchy,\"ax\",%progbits
+     .global branchy
+     .type branchy,%function
+     .thumb_func
+branchy:
+     push {{r7, lr}}
+     cmp r0, #0
+     beq 1f
+     nop
+1:
+     pop {{r7, pc}}
+     .size branchy, . - branchy
+
+     .section .stack_sizes,\"o\",%progbits,.text.branchy
+     .long branchy
+     .uleb128 0"
+);
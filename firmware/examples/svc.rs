@@ -0,0 +1,32 @@
+#![no_main]
+#![no_std]
+
+use core::arch::asm;
+
+use panic_halt as _;
+
+// the "kernel" dispatches the system calls through this table
+#[no_mangle]
+static SYSCALLS: [fn(); 2] = [sys_yield, sys_write];
+
+#[no_mangle]
+fn _start() {
+    // keep the table (and the system calls) in the final binary
+    unsafe {
+        core::ptr::read_volatile(&SYSCALLS);
+
+        asm!("svc 0");
+        asm!("svc 1");
+    }
+}
+
+#[inline(never)]
+fn sys_yield() {
+    unsafe { asm!("nop") }
+}
+
+#[inline(never)]
+fn sys_write() {
+    let mut buffer = [0u8; 64];
+    unsafe { core::ptr::write_volatile(&mut buffer[0], 1) }
+}
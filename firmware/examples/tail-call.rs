@@ -0,0 +1,31 @@
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() {
+    unsafe { caller(ptr::read_volatile(&0)) }
+}
+
+// the frame of `caller` is popped before it jumps to `callee`
+#[inline(never)]
+#[no_mangle]
+unsafe fn caller(x: u32) {
+    let mut buffer = [0u32; 16];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+    callee(x)
+}
+
+#[inline(never)]
+#[no_mangle]
+unsafe fn callee(x: u32) {
+    let mut buffer = [0u32; 8];
+    for word in buffer.iter_mut() {
+        ptr::write_volatile(word, x);
+    }
+}
@@ -0,0 +1,46 @@
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+static mut NEXT: Option<fn(u32)> = None;
+
+#[no_mangle]
+fn _start() {
+    spawn(task_a);
+    spawn(task_b);
+    schedule();
+}
+
+// stand-in for an RTOS scheduler that switches to the stack of the next task
+#[inline(never)]
+fn schedule() {
+    unsafe {
+        if let Some(task) = ptr::read_volatile(ptr::addr_of!(NEXT)) {
+            task(0)
+        }
+    }
+}
+
+#[inline(never)]
+fn spawn(task: fn(u32)) {
+    unsafe { ptr::write_volatile(ptr::addr_of_mut!(NEXT), Some(task)) }
+}
+
+#[inline(never)]
+fn task_a(arg: u32) {
+    let mut buffer = [0u32; 32];
+    for word in buffer.iter_mut() {
+        unsafe { ptr::write_volatile(word, arg) }
+    }
+}
+
+#[inline(never)]
+fn task_b(arg: u32) {
+    let mut buffer = [0u32; 4];
+    for word in buffer.iter_mut() {
+        unsafe { ptr::write_volatile(word, arg) }
+    }
+}
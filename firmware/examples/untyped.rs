@@ -0,0 +1,42 @@
+#![no_main]
+#![no_std]
+
+use core::arch::global_asm;
+
+use panic_halt as _;
+
+extern "C" {
+    fn outer();
+}
+
+#[no_mangle]
+fn _start() {
+    unsafe { outer() }
+}
+
+// hand-written functions that the LLVM IR knows nothing about
+global_asm!(
+    ".section .text.outer,\"ax\",%progbits
+     .global outer
+     .type outer,%function
+     .thumb_func
+outer:
+     push {{r7, lr}}
+     bl OUTLINED_FUNCTION_0
+     bl inner
+     pop {{r7, pc}}
+     .size outer, . - outer
+
+     .type OUTLINED_FUNCTION_0,%function
+     .thumb_func
+OUTLINED_FUNCTION_0:
+     bx lr
+     .size OUTLINED_FUNCTION_0, . - OUTLINED_FUNCTION_0
+
+     .global inner
+     .type inner,%function
+     .thumb_func
+inner:
+     bx lr
+     .size inner, . - inner"
+);
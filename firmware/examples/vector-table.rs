@@ -0,0 +1,57 @@
+#![no_main]
+#![no_std]
+
+use core::{hint, ptr};
+
+use panic_halt as _;
+
+#[repr(C)]
+pub struct VectorTable {
+    initial_sp: u32,
+    reset: unsafe extern "C" fn(),
+    exceptions: [Option<unsafe extern "C" fn()>; 14],
+    interrupts: [unsafe extern "C" fn(); 4],
+}
+
+// `UART0` (IRQ 0) was meant to be installed but its entry points at the default handler
+#[link_section = ".vector_table"]
+#[no_mangle]
+static VECTOR_TABLE: VectorTable = VectorTable {
+    initial_sp: 0x2000_4000,
+    reset: _start,
+    exceptions: [None; 14],
+    interrupts: [DefaultHandler, TIMER0, DefaultHandler, DefaultHandler],
+};
+
+static mut HANDLERS: [Option<unsafe extern "C" fn()>; 1] = [None];
+
+#[no_mangle]
+unsafe extern "C" fn _start() {
+    ptr::read_volatile(&VECTOR_TABLE);
+    ptr::write_volatile(ptr::addr_of_mut!(HANDLERS[0]), Some(UART0));
+
+    // NVIC: enable IRQ 0 (`UART0`), IRQ 1 (`TIMER0`) and IRQ 34
+    const ISER: *mut u32 = 0xE000_E100 as *mut u32;
+    ptr::write_volatile(ISER, 0b11);
+    ptr::write_volatile(ISER.add(1), 1 << 2);
+
+    loop {}
+}
+
+#[no_mangle]
+unsafe extern "C" fn DefaultHandler() {
+    loop {}
+}
+
+#[no_mangle]
+unsafe extern "C" fn TIMER0() {
+    // a buffer on the stack
+    let mut samples = [0u32; 4];
+    hint::black_box(&mut samples);
+    ptr::write_volatile(0x4000_0000 as *mut u32, samples[0]);
+}
+
+#[no_mangle]
+unsafe extern "C" fn UART0() {
+    ptr::write_volatile(0x4000_1000 as *mut u32, 1);
+}
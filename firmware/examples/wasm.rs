@@ -0,0 +1,31 @@
+#![no_main]
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+#[no_mangle]
+pub extern "C" fn _start(x: u32) -> u32 {
+    let f = if x == 0 { foo as fn(u32) -> u32 } else { bar };
+
+    // call via function pointer
+    unsafe { ptr::read_volatile(&f)(x) }
+}
+
+// allocates its buffer on the shadow stack
+#[inline(never)]
+fn foo(x: u32) -> u32 {
+    let mut buf = [0u32; 16];
+    for (i, slot) in buf.iter_mut().enumerate() {
+        unsafe { ptr::write_volatile(slot, x + i as u32) }
+    }
+
+    buf.iter().map(|slot| unsafe { ptr::read_volatile(slot) }).sum()
+}
+
+// leaf function; uses no shadow stack
+#[inline(never)]
+fn bar(x: u32) -> u32 {
+    x.wrapping_mul(3)
+}
@@ -0,0 +1,31 @@
+#![no_std]
+
+use core::ptr;
+
+use panic_halt as _;
+
+extern "C" {
+    // provided by the C program that links this library
+    fn log_value(value: u32);
+}
+
+#[no_mangle]
+pub extern "C" fn sensor_read() -> u32 {
+    let mut samples = [0u32; 16];
+    for sample in samples.iter_mut() {
+        *sample = unsafe { ptr::read_volatile(0x4000_0000 as *const u32) };
+    }
+    let sum = average(&samples);
+    unsafe { log_value(sum) }
+    sum
+}
+
+#[no_mangle]
+pub extern "C" fn sensor_reset() {
+    unsafe { ptr::write_volatile(0x4000_0004 as *mut u32, 1) }
+}
+
+#[inline(never)]
+fn average(samples: &[u32]) -> u32 {
+    samples.iter().sum::<u32>() / samples.len() as u32
+}
@@ -0,0 +1,50 @@
+//! An on-target test binary like the ones `#[defmt_test::tests]` produces
+
+#![no_main]
+#![no_std]
+
+use panic_halt as _;
+
+#[no_mangle]
+fn _start() -> ! {
+    extern "C" {
+        fn main() -> !;
+    }
+
+    unsafe { main() }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static X: AtomicU32 = AtomicU32::new(0);
+
+    // what `#[defmt_test::tests]` expands to: the tests are called one after the other
+    #[export_name = "main"]
+    unsafe extern "C" fn __defmt_test_entry() -> ! {
+        small();
+        big();
+
+        loop {}
+    }
+
+    #[inline(never)]
+    fn small() {
+        X.store(1, Ordering::Relaxed);
+    }
+
+    #[inline(never)]
+    fn big() {
+        let mut buffer = [0u32; 16];
+        for (i, x) in buffer.iter_mut().enumerate() {
+            *x = X.load(Ordering::Relaxed) + i as u32;
+        }
+        consume(&buffer);
+    }
+
+    #[inline(never)]
+    fn consume(buffer: &[u32; 16]) {
+        X.store(buffer.iter().sum(), Ordering::Relaxed);
+    }
+}
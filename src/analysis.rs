@@ -0,0 +1,382 @@
+//! Whole program stack usage analysis as a library
+//!
+//! [`analyze`] performs the same analysis as `cargo call-stack --elf (..) --ll (..)` but returns
+//! the call graph instead of printing a report, for build scripts and custom CI checks that want
+//! to inspect the graph without parsing the dot or JSON output.
+//!
+//! ``` no_run
+//! use cargo_call_stack::analysis::{analyze, AnalysisInput, Options};
+//!
+//! let input = AnalysisInput::new(
+//!     "target/thumbv7m-none-eabi/release/app",
+//!     "app.ll",
+//!     "thumbv7m-none-eabi",
+//! );
+//! let mut options = Options::default();
+//! options.override_stack = vec![("asm_routine".to_owned(), 16)];
+//! let graph = analyze(&input, &options)?;
+//! for &root in &graph.roots {
+//!     let function = &graph.functions[root];
+//!     println!("{}: {:?}", function.name, function.max);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Warnings are emitted through the `log` crate; install a logger to see them.
+
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+
+use crate::{classify, config};
+
+pub(crate) mod graph;
+pub(crate) mod load;
+pub(crate) mod propagate;
+
+/// The files to analyze
+#[derive(Clone, Debug)]
+pub struct AnalysisInput {
+    /// The ELF file (or WebAssembly module) of the program
+    pub elf: PathBuf,
+    /// The LLVM IR of the program, produced with `--emit=llvm-ir,obj`, `-Zemit-stack-sizes` and fat
+    /// LTO; not used if the program is a WebAssembly module
+    pub ll: PathBuf,
+    /// The object file, with a `.stack_sizes` section, of the program; defaults to the `.o` file
+    /// next to `ll`
+    pub obj: Option<PathBuf>,
+    /// Target triple for which the program was compiled
+    pub target: String,
+    /// Target triple of the machine that runs the analysis; used to find the `llvm-tools` of the
+    /// toolchain, which are otherwise looked up in the `PATH`
+    pub host: Option<String>,
+    /// The `compiler_builtins` crate that was linked into the program, if it was built from source
+    pub compiler_builtins: Option<CompilerBuiltins>,
+    /// Additional LLVM IR files, e.g. C code compiled with `clang -S -emit-llvm`
+    pub extra_ll: Vec<PathBuf>,
+    /// Additional object files (`.o`) or archives (`.a`) whose stack usage information to use
+    pub extra_obj: Vec<PathBuf>,
+    /// File that maps LLVM IR symbol names to ELF symbol names, one `ir_name -> elf_name` per line
+    pub symbol_map: Option<PathBuf>,
+    /// The directories that the final link searched for libraries and linker scripts (`-L`)
+    pub search_paths: Vec<PathBuf>,
+    /// The native libraries of the final link (`-l`), e.g. `static=foo`
+    pub native_libs: Vec<String>,
+    /// The linker scripts of the final link (`-T`)
+    pub linker_scripts: Vec<String>,
+    /// The symbols that the final link wrapped with `--wrap`
+    pub wrapped_symbols: Vec<String>,
+}
+
+impl AnalysisInput {
+    /// The program `elf` with the LLVM IR `ll`, compiled for `target`
+    pub fn new(elf: impl Into<PathBuf>, ll: impl Into<PathBuf>, target: impl Into<String>) -> Self {
+        AnalysisInput {
+            elf: elf.into(),
+            ll: ll.into(),
+            obj: None,
+            target: target.into(),
+            host: None,
+            compiler_builtins: None,
+            extra_ll: vec![],
+            extra_obj: vec![],
+            symbol_map: None,
+            search_paths: vec![],
+            native_libs: vec![],
+            linker_scripts: vec![],
+            wrapped_symbols: vec![],
+        }
+    }
+}
+
+/// The `compiler_builtins` crate of a program built with `-Zbuild-std`
+#[derive(Clone, Debug)]
+pub struct CompilerBuiltins {
+    /// The `.rlib` archive
+    pub rlib: PathBuf,
+    /// The LLVM IR of the crate; not needed with [`Options::no_builtins_analysis`]
+    pub ll: Option<PathBuf>,
+}
+
+/// The options of the analysis; the defaults match those of `cargo call-stack`
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Options {
+    /// Print how the call graph was built to stderr, e.g. which symbol names were treated as
+    /// aliases of the same function
+    pub verbose: bool,
+    /// The program is a library; the functions it exports are the roots of the call graph
+    pub lib: Option<Library>,
+    /// The program is the on-target integration test of this name; each test function is a root
+    pub test: Option<String>,
+    /// The program is the on-target benchmark of this name; each benchmark function is a root
+    pub bench: Option<String>,
+    /// Don't add the potential callees of indirect function calls to the call graph
+    pub ignore_indirect: bool,
+    /// Which functions the formatting machinery of `core::fmt` may call through the function
+    /// pointer of its arguments
+    pub fmt_heuristic: FmtHeuristic,
+    /// Add the return address that call instructions push onto the stack to the stack usage of
+    /// each function that calls others
+    pub call_overhead: bool,
+    /// The local stack usage and the callees of the functions the analysis can't see into
+    pub config: Option<config::Config>,
+    /// The local stack usage, in bytes, of these functions; takes precedence over `config`
+    pub override_stack: Vec<(String, u64)>,
+    /// Don't analyze `compiler_builtins`; its functions have unknown stack usage
+    pub no_builtins_analysis: bool,
+    /// What to do when LLVM's local stack usage of a function doesn't match the machine code
+    /// analysis
+    pub on_mismatch: OnMismatch,
+    /// Treat the contents of these linker sections as function pointers registered at build time;
+    /// the distributed slices of `config` are added to these
+    pub distributed_slice: Vec<String>,
+    /// Account for the static constructors in the max stack usage of the entry point
+    pub include_constructors: bool,
+    /// The `longjmp` calls in each function (first) return to the function that called `setjmp`
+    /// (second)
+    pub longjmp_target: Vec<(String, String)>,
+    /// The `svc #NUMBER` instructions perform the system call that the function implements
+    pub syscall: Vec<(u8, String)>,
+    /// Array of function pointers, indexed by the `svc` number, of the system calls
+    pub syscall_table: Option<String>,
+    /// Don't stack the frame of a function on top of the functions it tail calls
+    pub tail_calls: bool,
+    /// Leave the calls performed with interrupts masked out of the max stack usage of the reset
+    /// handler when it's preempted
+    pub critical_sections: bool,
+    /// Compute the max stack usage of this function before it enters its main loop and within
+    /// the loop separately
+    pub main_loop: Option<String>,
+    /// Compute the max stack usage of each root through the calls that run destructors separately
+    pub drop_paths: bool,
+    /// The function is the entry point of a task that runs on its own stack of this many bytes;
+    /// the tasks of `config` are added to these
+    pub task: Vec<(String, u64)>,
+    /// The function (first) switches the stack pointer to the static buffer (second); the
+    /// secondary stacks of `config` are added to these
+    pub secondary_stack: Vec<(String, String)>,
+    /// Rules that classify the symbols, e.g. as `runtime` or `vendor-blob`
+    pub classify: Option<classify::Rules>,
+    /// Don't warn about missing stack usage or type information of the functions of these classes
+    pub quiet_class: Vec<String>,
+    /// Only analyze the call graph that starts from these functions; `*` and `?` are wildcards
+    pub starts: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            verbose: false,
+            lib: None,
+            test: None,
+            bench: None,
+            ignore_indirect: false,
+            fmt_heuristic: FmtHeuristic::Loose,
+            call_overhead: false,
+            config: None,
+            override_stack: vec![],
+            no_builtins_analysis: false,
+            on_mismatch: OnMismatch::Max,
+            distributed_slice: vec![],
+            include_constructors: false,
+            longjmp_target: vec![],
+            syscall: vec![],
+            syscall_table: None,
+            tail_calls: false,
+            critical_sections: false,
+            main_loop: None,
+            drop_paths: false,
+            task: vec![],
+            secondary_stack: vec![],
+            classify: None,
+            quiet_class: vec![],
+            starts: vec![],
+        }
+    }
+}
+
+impl Options {
+    // whether the per-symbol warnings about `name` are suppressed by `--quiet-class`
+    pub(crate) fn is_quiet(&self, name: &str) -> bool {
+        self.classify.as_ref().is_some_and(|rules| {
+            let class = rules.classify(name);
+            self.quiet_class.iter().any(|quiet| quiet == class)
+        }) || self.config.as_ref().is_some_and(|config| {
+            // the missing information is in the `--config` file
+            config
+                .function(name)
+                .is_some_and(|function| function.stack.is_some())
+        })
+    }
+
+    // `task` followed by the tasks of the `--config` file; `--task` takes precedence
+    pub(crate) fn tasks(&self) -> Vec<(String, u64)> {
+        let mut tasks = self.task.clone();
+        for (function, stack) in self.config.iter().flat_map(|config| &config.tasks) {
+            if !self.task.iter().any(|(task, _)| task == function) {
+                tasks.push((function.clone(), *stack));
+            }
+        }
+        tasks
+    }
+
+    // `distributed_slice` followed by the sections of the `--config` file that it doesn't list
+    pub(crate) fn distributed_slices(&self) -> Vec<String> {
+        let mut sections = self.distributed_slice.clone();
+        for section in self
+            .config
+            .iter()
+            .flat_map(|config| &config.distributed_slices)
+        {
+            if !sections.contains(section) {
+                sections.push(section.clone());
+            }
+        }
+        sections
+    }
+}
+
+/// The kind of library that is analyzed
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Library {
+    /// A shared library (`cdylib`); the functions it exports are the roots
+    Shared,
+    /// A static library (`staticlib`) linked into an executable; its public functions are the
+    /// roots
+    Static,
+}
+
+/// Which functions the indirect calls of the `core::fmt` machinery (`ArgumentV1.formatter`) reach
+#[derive(ValueEnum, PartialEq, Debug, Clone, Copy)]
+pub enum FmtHeuristic {
+    /// Any function with signature `fn(&_, &mut core::fmt::Formatter) -> bool`
+    Loose,
+    /// Only the implementations of the `core::fmt` traits (`Display`, `Debug`, etc.) with that
+    /// signature
+    Strict,
+    /// No special case; these calls are matched by signature like any other indirect call
+    Off,
+}
+
+/// What to do when LLVM's local stack usage of a function doesn't match the machine code analysis
+#[derive(ValueEnum, PartialEq, Debug, Clone, Copy)]
+pub enum OnMismatch {
+    /// Report every mismatch and use the larger of the two results
+    Max,
+    /// Report every mismatch and abort the analysis
+    Error,
+    /// Report every mismatch and use LLVM's result
+    Warn,
+    /// Use the result of the machine code analysis; `--verbose` lists the mismatches
+    PreferElf,
+    /// Use LLVM's result; `--verbose` lists the mismatches
+    PreferLlvm,
+}
+
+/// The call graph of a program, annotated with stack usage information
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CallGraph {
+    /// The nodes of the graph; `Call` and `roots` refer to them by index
+    pub functions: Vec<Function>,
+    /// The edges of the graph
+    pub calls: Vec<Call>,
+    /// The entry points: the start points, the functions that no one calls, e.g. the reset and
+    /// interrupt handlers, or the roots selected with `task` and the like
+    pub roots: Vec<usize>,
+}
+
+/// A node of the call graph
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Function {
+    /// The demangled name of the function
+    pub name: String,
+    /// Local stack usage, in bytes; `None` if unknown
+    pub local: Option<u64>,
+    /// Max stack usage, in bytes, of this function and the functions it calls; `None` if not
+    /// computed, e.g. because the function is part of a cycle
+    pub max: Option<Max>,
+    /// The node stands for the callees of an indirect call (function pointer or trait object
+    /// call) rather than a function of the program
+    pub indirect: bool,
+}
+
+/// Max stack usage
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Max {
+    /// Exactly this number of bytes
+    Exact(u64),
+    /// At least this number of bytes, e.g. because the stack usage of some callee is unknown
+    LowerBound(u64),
+}
+
+impl Max {
+    /// The number of bytes, disregarding whether it's exact or a lower bound
+    pub fn bytes(self) -> u64 {
+        match self {
+            Max::Exact(n) | Max::LowerBound(n) => n,
+        }
+    }
+}
+
+/// An edge of the call graph
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Call {
+    /// Index of the calling function
+    pub caller: usize,
+    /// Index of the called function
+    pub callee: usize,
+    /// Number of call sites
+    pub sites: usize,
+}
+
+/// Analyzes the stack usage of an already built program
+pub fn analyze(input: &AnalysisInput, options: &Options) -> anyhow::Result<CallGraph> {
+    let cfg = load::Cfg::query(&input.target)?;
+    let inputs = load::Inputs::read(input, &cfg, options)?;
+    let program = graph::build(&inputs, &cfg, options, false)?;
+    let mut analysis = propagate::analyze(program, options)?;
+
+    // here we shorten the name of the symbols if it doesn't result in ambiguity
+    for (idx, name) in analysis.shortened.drain(..) {
+        analysis.g[idx].name = analysis.interner.intern(name);
+    }
+
+    let g = &analysis.g;
+    let interner = &analysis.interner;
+    let roots = if analysis.roots.is_empty() {
+        graph::uncalled(g)
+    } else {
+        analysis.roots.clone()
+    };
+    Ok(CallGraph {
+        functions: g
+            .node_weights()
+            .map(|node| Function {
+                name: interner.demangled(node.name).to_owned(),
+                local: match node.local {
+                    graph::Local::Exact(n) => Some(n),
+                    graph::Local::Unknown => None,
+                },
+                max: node.max.map(|max| match max {
+                    graph::Max::Exact(n) => Max::Exact(n),
+                    graph::Max::LowerBound(n) => Max::LowerBound(n),
+                }),
+                indirect: node.dashed,
+            })
+            .collect(),
+        calls: g
+            .raw_edges()
+            .iter()
+            .map(|edge| Call {
+                caller: edge.source().index(),
+                callee: edge.target().index(),
+                sites: edge.weight,
+            })
+            .collect(),
+        roots: roots.iter().map(|root| root.index()).collect(),
+    })
+}
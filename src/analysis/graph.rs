@@ -0,0 +1,3549 @@
+//! Call graph construction
+//!
+//! The nodes are the functions in the symbol table of the ELF file; the edges come from the LLVM
+//! IR and, on ARM Cortex-M, from the machine code. Indirect calls become fictitious nodes whose
+//! callees are the functions with a matching signature.
+
+use core::{
+    cmp,
+    fmt::{self, Write as _},
+    ops,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use log::{error, warn};
+use object::Object;
+use petgraph::{
+    graph::{DiGraph, NodeIndex},
+    Direction, Graph,
+};
+
+use crate::{
+    analysis::{
+        load::{archive_symbols, object_stack_sizes, parse_symbol_map, Cfg, Inputs},
+        FmtHeuristic, Library, OnMismatch, Options,
+    },
+    dwarf, elf,
+    intern::{dehash, Interner, Symbol},
+    ir::{self, Abi, DebugInfo, FnSig, Item, Stmt, Type},
+    libcall, suggest,
+    thumb::{self, CallKind, MaskingKind, StackSwitchKind, Tag},
+    wasm,
+    wrapper::WRAPPER_HINT,
+};
+
+// the call graph of the program, before the stack usage is propagated through it
+pub(crate) struct Program<'a> {
+    pub(crate) g: Graph<Node, usize>,
+    pub(crate) indices: BTreeMap<Symbol, NodeIndex>,
+    pub(crate) interner: Interner<'a>,
+    // the explicit roots; if empty, the functions that are not called are the roots
+    pub(crate) roots: Vec<NodeIndex>,
+    pub(crate) has_stack_usage_info: bool,
+    // where the functions of the crate are defined
+    pub(crate) locations: HashMap<Symbol, (PathBuf, u32)>,
+    // call edges that exist in the LLVM-IR but not in the ELF
+    pub(crate) stale: HashSet<(NodeIndex, NodeIndex)>,
+    // (ARM Cortex-M only) calls that the machine code only performs as tail calls
+    pub(crate) tail_calls: HashSet<(NodeIndex, NodeIndex)>,
+    // (ARM Cortex-M only) calls performed with interrupts masked
+    pub(crate) masked_calls: HashSet<(NodeIndex, NodeIndex)>,
+    pub(crate) main_loop: Option<MainLoop>,
+    pub(crate) vectors: Option<Vectors>,
+    pub(crate) secondary_stacks: Vec<SecondaryStack>,
+    pub(crate) return_address_size: u64,
+    pub(crate) target: Target,
+    // the start address of each function and its node and size
+    pub(crate) addr2node: BTreeMap<u64, (NodeIndex, u64)>,
+    // `None` if the program is a WebAssembly module
+    pub(crate) elf: Option<object::File<'a>>,
+}
+
+// builds the call graph of the program; `with_locations` also records where each function of the
+// crate is defined
+pub(crate) fn build<'a>(
+    inputs: &'a Inputs,
+    cfg: &Cfg,
+    options: &Options,
+    with_locations: bool,
+) -> anyhow::Result<Program<'a>> {
+    if cfg.is_wasm() {
+        return wasm(inputs, options);
+    }
+
+    // used to figure out which floating point and integer operations lower to library calls
+    let fpu = libcall::Fpu::new(&cfg.arch, &cfg.abi, &cfg.features);
+    let is_arm = cfg.arch == "arm";
+    let pointer_width = cfg.pointer_width;
+    // how arguments are passed; the signatures of indirect calls are compared in this shape
+    let calling_convention = Abi {
+        register_width: pointer_width,
+        soft_float: match &*cfg.arch {
+            "arm" => !cfg.abi.ends_with("hf"),
+            "aarch64" => cfg.abi == "softfloat",
+            "riscv32" | "riscv64" => !fpu.single,
+            _ => false,
+        },
+    };
+
+    // a `staticlib` is an archive of object files; it's analyzed like the executable that results
+    // from linking all of them
+    let is_staticlib = options.lib == Some(Library::Static);
+
+    let items = ir::parse(&inputs.ll).map_err(|e| {
+        anyhow!(
+            "failed to parse application's LLVM IR from `{}`: {}",
+            inputs.ll_path.display(),
+            e
+        )
+    })?;
+
+    // the LLVM IR, the object file and the ELF must come from the same build; a stale file, e.g.
+    // one left behind by an interrupted build, would silently produce a wrong call graph
+    check_consistency(
+        &items,
+        &inputs.obj,
+        &inputs.ll_path,
+        &inputs.obj_path,
+        &inputs.path,
+    )?;
+
+    // with `no_builtins_analysis` we only need the names of the functions in `compiler_builtins`;
+    // those are listed in the symbol table of the archive
+    let compiler_builtins_symbols = match &inputs.compiler_builtins_rlib {
+        Some((path, bytes)) if options.no_builtins_analysis => archive_symbols(path, bytes)?,
+        _ => HashSet::new(),
+    };
+    let is_builtin = |name: &str| {
+        options.no_builtins_analysis
+            && (compiler_builtins_symbols.contains(name)
+                || rustc_demangle::demangle(name)
+                    .to_string()
+                    .starts_with("compiler_builtins::"))
+    };
+    let (compiler_builtins_items, compiler_builtins_stack_sizes) = inputs.compiler_builtins()?;
+
+    // IR from other languages gives type information and call edges to the symbols that the Rust
+    // code links to
+    let mut extra_items = vec![];
+    for (path, ll) in &inputs.extra_lls {
+        extra_items.extend(ir::parse(ll).map_err(|e| {
+            anyhow!(
+                "failed to parse extra LLVM IR from `{}`: {}",
+                path.display(),
+                e
+            )
+        })?);
+    }
+
+    let symbol_map = parse_symbol_map(&inputs.symbol_map)
+        .map_err(|e| anyhow!("failed to parse the symbol map: {}", e))?;
+    let wrapped = inputs
+        .wrapped_symbols
+        .iter()
+        .flat_map(|symbol| {
+            [
+                (symbol.clone(), format!("__wrap_{}", symbol)),
+                (format!("__real_{}", symbol), symbol.clone()),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    for (name, ll) in &inputs.bitcode_lls {
+        extra_items.extend(
+            ir::parse(ll).map_err(|e| anyhow!("failed to parse LLVM IR from `{}`: {}", name, e))?,
+        );
+    }
+
+    // source location of the functions defined in the crate; metadata ids are local to each module
+    // so only the crate's own IR is used
+    let debug_locations = if with_locations {
+        debug_locations(&items)
+    } else {
+        HashMap::new()
+    };
+
+    // symbols that are defined more than once, e.g. local functions with the same name in different
+    // codegen units or files; these are reported at the end of the analysis
+    let mut homonyms = BTreeMap::<&str, Vec<_>>::new();
+
+    // NOTE(BTreeMap) iterate in a deterministic order
+    let mut defines = BTreeMap::new();
+    let mut declares = BTreeMap::new();
+    // `@__pre_init = alias void (), ptr @DefaultPreInit` -> (`__pre_init`, `DefaultPreInit`)
+    let mut ir_aliases = vec![];
+    for item in items
+        .into_iter()
+        .chain(compiler_builtins_items)
+        .chain(extra_items)
+    {
+        match item {
+            Item::Define(def) => {
+                // the first definition wins
+                if defines.contains_key(def.name) {
+                    homonyms.entry(def.name).or_default();
+                } else {
+                    defines.insert(def.name, def);
+                }
+            }
+
+            Item::Declare(decl) => {
+                declares.insert(decl.name, decl);
+            }
+
+            Item::Alias(alias, aliasee) => ir_aliases.push((alias, aliasee)),
+
+            _ => {}
+        }
+    }
+
+    let target = &*cfg.target;
+
+    // we know how to analyze the machine code in the ELF file for these targets thus we have more
+    // information and need less LLVM-IR hacks
+    let target_ = match target {
+        "thumbv6m-none-eabi" => Target::Thumbv6m,
+        "thumbv7m-none-eabi" | "thumbv7em-none-eabi" | "thumbv7em-none-eabihf" => Target::Thumbv7m,
+        _ => Target::Other,
+    };
+
+    // extract stack size information
+    // the `.o` file doesn't have address information so we just keep the stack usage information
+    if !elf::has_stack_sizes(&inputs.obj) {
+        if target_.is_thumb() {
+            warn!(
+                "the object file has no `.stack_sizes` section; the stack usage of functions will \
+                 be derived from their machine code, which is less precise. {}",
+                WRAPPER_HINT
+            );
+        } else {
+            error!(
+                "the object file has no `.stack_sizes` section: rustc was invoked without \
+                 `-Zemit-stack-sizes` or the target doesn't support it; there won't be stack usage \
+                 information for the crate. {}",
+                WRAPPER_HINT
+            );
+        }
+    }
+    let mut stack_sizes: HashMap<_, _> = elf::analyze_object(&inputs.obj)?
+        .into_iter()
+        .map(|size| (size.name.to_owned(), size.stack))
+        .collect();
+
+    stack_sizes.extend(compiler_builtins_stack_sizes);
+
+    // objects not produced by rustc, e.g. assembly startup files or prebuilt vendor libraries. These
+    // only contain stack usage information if they were compiled with `-fstack-size-section`
+    let mut definitions = HashMap::new();
+    for (path, objects) in &inputs.extra_objs {
+        let mut found = 0;
+        let mut machine_code = false;
+        for (name, obj) in objects {
+            if obj.starts_with(b"\x7fELF") {
+                machine_code = true;
+                found += object_stack_sizes(name, obj, &mut stack_sizes, &mut definitions)?;
+            }
+        }
+
+        if machine_code && found == 0 {
+            warn!("`{}` contains no stack usage information", path.display());
+        }
+    }
+
+    // static native libraries may have been compiled with `-fstack-size-section`
+    for (lib, archive) in &inputs.native_libs {
+        if let Some((archive, objects)) = archive {
+            let mut found = 0;
+            for (name, obj) in objects {
+                if obj.starts_with(b"\x7fELF") {
+                    found += object_stack_sizes(name, obj, &mut stack_sizes, &mut definitions)?;
+                }
+            }
+
+            if options.verbose {
+                eprintln!(
+                    "link: native library `{}` ({}): stack usage information for {} functions",
+                    lib,
+                    archive.display(),
+                    found
+                );
+            }
+        } else if options.verbose {
+            eprintln!("link: native library `{}`", lib);
+        }
+    }
+
+    if options.verbose {
+        for path in &inputs.search_paths {
+            eprintln!("link: search path `{}`", path.display());
+        }
+        for (script, path) in &inputs.linker_scripts {
+            match path {
+                Some(path) => eprintln!("link: linker script `{}` ({})", script, path.display()),
+                None => eprintln!("link: linker script `{}` (not found)", script),
+            }
+        }
+        for symbol in &inputs.wrapped_symbols {
+            eprintln!("link: `--wrap={}`", symbol);
+        }
+    }
+
+    // extract list of "live" symbols (symbols that have not been GC-ed by the linker)
+    // this time we use the ELF and not the object file
+    let elf = elf::parse(&inputs.elf)?;
+    let mut symbols = elf::analyze_executable(&elf)?;
+    // the stack usage information of the input objects, if the linker kept it, by address
+    let linked_stack_sizes = elf::linked_stack_sizes(&elf).unwrap_or_else(|e| {
+        warn!(
+            "couldn't parse the `.stack_sizes` section of the ELF: {}",
+            e
+        );
+        HashMap::new()
+    });
+
+    // clear the thumb bit
+    if target_.is_thumb() {
+        symbols.defined = symbols
+            .defined
+            .into_iter()
+            .map(|(k, v)| (k & !1, v))
+            .collect();
+    }
+
+    // remove version strings (`@@GLIBC_2.2.5`, `@GLIBC_2.2.5`) from undefined symbols
+    symbols.undefined = symbols.undefined.into_iter().map(unversioned).collect();
+
+    // the functions exported by a shared library, or the public functions of a static one, can be
+    // called by its host; they are the roots of the call graph
+    let exports = if options.lib.is_some() {
+        let exports = if is_staticlib {
+            elf::global_functions(&elf)
+        } else {
+            elf::exported_functions(&elf)?
+        };
+        if exports.is_empty() {
+            warn!("`{}` doesn't export any function", inputs.path.display());
+        }
+        exports
+    } else {
+        HashSet::new()
+    };
+
+    let mut g = DiGraph::<Node, usize>::new();
+    let mut indices = BTreeMap::<Symbol, _>::new();
+    let mut interner = Interner::new();
+
+    let mut indirects: HashMap<FnSig, Indirect> = HashMap::new();
+    // functions that could be called by `ArgumentV1.formatter`
+    let mut fmts = HashSet::new();
+
+    // Some functions may be aliased; we map aliases to a single name. For example, if `foo`,
+    // `bar` and `baz` all have the same address then this maps contains: `foo -> foo`, `bar -> foo`
+    // and `baz -> foo`.
+    let mut aliases = HashMap::new();
+
+    // we do a first pass over all the definitions to collect methods in `impl Trait for Type`
+    let mut default_methods = HashSet::new();
+    for name in defines.keys() {
+        // the names are interned here so that each one is demangled only once
+        let sym = interner.intern(*name);
+        let demangled = interner.demangled(sym);
+
+        // `<crate::module::Type as crate::module::Trait>::method::hdeadbeef`
+        if demangled.starts_with("<") {
+            if let Some((_, rhs)) = demangled.split_once(" as ") {
+                // rhs = `crate::module::Trait>::method::hdeadbeef`
+                let mut parts = rhs.splitn(2, ">::");
+
+                if let (Some(trait_), Some(rhs)) = (parts.next(), parts.next()) {
+                    // trait_ = `crate::module::Trait`, rhs = `method::hdeadbeef`
+
+                    if let Some(method) = dehash(rhs) {
+                        default_methods.insert(format!("{}::{}", trait_, method));
+                    }
+                }
+            }
+        }
+    }
+
+    // the linker may keep only one of the names of an IR alias
+    let elf_names = symbols
+        .defined
+        .values()
+        .flat_map(|sym| sym.names().iter().copied())
+        .collect::<HashSet<_>>();
+
+    // the PLT entries of a shared object; calls to one of them reach the function it imports
+    let plt = elf::plt_entries(&elf);
+    let imports = plt.iter().map(|(_, _, name)| *name).collect::<HashSet<_>>();
+
+    // the C prototypes of the functions that have no type information in the LLVM IR, e.g. the ones
+    // in C libraries compiled with `-g`
+    let untyped = elf_names
+        .iter()
+        .copied()
+        .filter(|name| {
+            !name.starts_with('$')
+                && !defines.contains_key(name)
+                && declares
+                    .get(name)
+                    .map(|decl| decl.sig.is_none())
+                    .unwrap_or(true)
+                && !is_never_called_indirectly(name)
+                && !is_builtin(name)
+        })
+        .collect::<HashSet<_>>();
+    let dwarf_sigs = if untyped.is_empty() {
+        HashMap::new()
+    } else {
+        dwarf::signatures(&elf, &untyped).unwrap_or_else(|e| {
+            warn!("couldn't parse the DWARF information: {}", e);
+            HashMap::new()
+        })
+    };
+
+    // add all real nodes
+    let mut has_stack_usage_info = false;
+    let mut has_untyped_symbols = false;
+    let mut addr2node = BTreeMap::new();
+    let mut node_locations = HashMap::new();
+    // (address, name, target, size) of the veneers inserted by the linker
+    let mut pending_veneers = vec![];
+    for (address, sym) in &symbols.defined {
+        let names = sym.names();
+        // filter out tags
+        let mut names = names
+            .iter()
+            .filter_map(|&name| {
+                if name == "$a"
+                    || name.starts_with("$a.")
+                    || name == "$x"
+                    || name.starts_with("$x.")
+                {
+                    None
+                } else {
+                    Some(name)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // a veneer is collapsed into the function it jumps to once that one has a node
+        if let [name] = names[..] {
+            if let Some(target) = veneer_target(name).filter(|target| {
+                (elf_names.contains(target) || imports.contains(target))
+                    && veneer_target(target).is_none()
+            }) {
+                pending_veneers.push((*address, name, target, sym.size()));
+                continue;
+            }
+        }
+
+        // calls to any of the names the LLVM IR gives to this function must resolve to this node
+        let in_elf = names.len();
+        add_ir_aliases(&mut names, &ir_aliases, &elf_names);
+
+        let (canonical_name, reason) = if names.len() > 1 {
+            // if one of the aliases appears in the `stack_sizes` dictionary, use that
+            if let Some(needle) = names.iter().find(|name| stack_sizes.contains_key(&***name)) {
+                (*needle, "it has stack usage information")
+            } else {
+                // otherwise, pick the first name that's not a tag
+                (
+                    names[0],
+                    "no name has stack usage information; it's the first one",
+                )
+            }
+        } else {
+            (names[0], "")
+        };
+
+        for name in names.iter().copied() {
+            aliases.insert(name, canonical_name);
+        }
+
+        if options.verbose && names.len() > 1 {
+            let others = names
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| **name != canonical_name)
+                .map(|(i, name)| {
+                    if i < in_elf {
+                        format!("`{}`", name)
+                    } else {
+                        format!("`{}` (LLVM IR alias)", name)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            eprintln!(
+                "aliases: {} -> `{}` at {:#010x}: {}",
+                others, canonical_name, address, reason
+            );
+        }
+
+        let is_builtin = is_builtin(canonical_name);
+        let stack = match definitions.get(canonical_name) {
+            // e.g. `static` C functions in different members of an archive; only the `.stack_sizes`
+            // section of the ELF, if the linker kept it, tells them apart
+            Some(stacks) if stacks.iter().any(|stack| *stack != stacks[0]) => {
+                let stack = linked_stack_sizes.get(&(address & !1)).cloned();
+                if stack.is_none() {
+                    warn!(
+                        "several object files define a function named `{}`; couldn't tell which \
+                         one is at address {:#x} because the ELF has no `.stack_sizes` section",
+                        canonical_name, address
+                    );
+                }
+                stack
+            }
+            _ => stack_sizes.get(canonical_name).cloned(),
+        };
+        if stack.is_none() {
+            // NOTE `compiler_builtins` functions are reported at the end of the analysis
+            if !target_.is_thumb() && !is_builtin && !options.is_quiet(canonical_name) {
+                warn!("no stack usage information for `{}`", canonical_name);
+            }
+        } else {
+            has_stack_usage_info = true;
+        }
+
+        let symbol = interner.intern(canonical_name);
+        let idx = g.add_node(Node(symbol, stack, false));
+        if let Some(first) = indices.get(&symbol).cloned() {
+            // same name, different address; calls to this name (in the LLVM IR) are assumed to
+            // reach all the functions that have it
+            let homonyms = homonyms.entry(canonical_name).or_default();
+            if homonyms.is_empty() {
+                homonyms.push(first);
+            }
+            homonyms.push(idx);
+        } else {
+            indices.insert(symbol, idx);
+        }
+
+        let _out = addr2node.insert(*address, (idx, sym.size()));
+        debug_assert!(_out.is_none());
+
+        if let Some(location) = names.iter().find_map(|name| debug_locations.get(name)) {
+            node_locations.insert(idx, location);
+        }
+
+        if let Some(def) = names.iter().filter_map(|name| defines.get(name)).next() {
+            match (&def.sig.inputs[..], def.sig.output.as_ref()) {
+                _ if is_fmt_callee(
+                    options.fmt_heuristic,
+                    interner.short(g[idx].name),
+                    &def.sig,
+                ) =>
+                {
+                    fmts.insert(idx);
+                    g[idx].formatter = true;
+                }
+
+                // with opaque pointers only the name tells the `core::fmt` trait methods apart
+                ([Type::OpaquePointer, Type::OpaquePointer], Some(output))
+                    if **output == Type::Integer(1)
+                        && is_formatter(interner.short(g[idx].name)) =>
+                {
+                    g[idx].formatter = true;
+                }
+
+                _ => {}
+            }
+
+            indirects
+                .entry(def.sig.clone())
+                .or_default()
+                .callees
+                .insert(idx);
+        } else if let Some(sig) = names
+            .iter()
+            .filter_map(|name| declares.get(name).and_then(|decl| decl.sig.clone()))
+            .next()
+        {
+            indirects.entry(sig).or_default().callees.insert(idx);
+        } else if let Some(sig) = names.iter().find_map(|name| dwarf_sigs.get(*name)) {
+            indirects
+                .entry(sig.clone())
+                .or_default()
+                .callees
+                .insert(idx);
+        } else if is_never_called_indirectly(canonical_name) {
+            // harmless: untyped but never the callee of an indirect call
+            if options.verbose {
+                eprintln!(
+                    "untyped: `{}` is never called indirectly; ignored by the indirect calls",
+                    canonical_name
+                );
+            }
+        } else if !is_builtin {
+            // without type information this function could be the callee of any indirect call
+            has_untyped_symbols = true;
+            if !options.is_quiet(canonical_name) {
+                warn!(
+                    "no type information for `{}`; every indirect function call may reach it",
+                    canonical_name
+                );
+            }
+        }
+    }
+
+    // imported functions have no symbol in the image; the PLT entry that jumps to one stands in
+    // for it. Their stack usage is unknown
+    for (address, size, name) in plt {
+        let idx = if let Some(idx) = find_node(&indices, &interner, name) {
+            idx
+        } else {
+            let sym = interner.intern(name);
+            let idx = g.add_node(Node(sym, None, false));
+            indices.insert(sym, idx);
+            aliases.insert(name, name);
+            idx
+        };
+        addr2node.insert(address, (idx, size));
+
+        if options.verbose {
+            eprintln!("plt: `{}` at {:#010x}", name, address);
+        }
+    }
+
+    // calls to a veneer reach the function it jumps to; the veneer itself runs on top of the frame
+    // of its caller, which accounts for its stack usage (see the machine code analysis)
+    let mut veneers = BTreeMap::new();
+    for (address, name, target, size) in pending_veneers {
+        let canonical_name = aliases[target];
+        aliases.insert(name, canonical_name);
+        let idx = node_named(&indices, &interner, canonical_name);
+        addr2node.insert(address, (idx, size));
+        veneers.insert(address, (address, size));
+
+        if options.verbose {
+            eprintln!(
+                "aliases: `{}` -> `{}` at {:#010x}: linker-generated veneer",
+                name, canonical_name, address
+            );
+        }
+    }
+
+    // `--wrap=foo` makes the calls to `foo` reach `__wrap_foo` and the calls to `__real_foo` reach
+    // `foo`; an explicit symbol map takes precedence
+    for (ir_name, elf_name) in &wrapped {
+        if let Some(canonical_name) = aliases.get(&**elf_name).copied() {
+            aliases.insert(ir_name, canonical_name);
+
+            if options.verbose {
+                eprintln!(
+                    "aliases: `{}` -> `{}`: `--wrap` link argument",
+                    ir_name, canonical_name
+                );
+            }
+        }
+    }
+
+    // symbols that were renamed at link time (e.g. `--wrap=malloc`) have different names in the IR
+    // and in the ELF
+    for (ir_name, elf_name) in symbol_map {
+        if let Some(canonical_name) = aliases.get(elf_name).copied() {
+            aliases.insert(ir_name, canonical_name);
+
+            if options.verbose {
+                eprintln!(
+                    "aliases: `{}` -> `{}`: symbol map entry `{} -> {}`",
+                    ir_name, canonical_name, ir_name, elf_name
+                );
+            }
+        } else {
+            warn!(
+                "symbol map: `{}` not found in the ELF; ignoring `{} -> {}`",
+                elf_name, ir_name, elf_name
+            );
+        }
+    }
+
+    let mut roots = exports
+        .iter()
+        .filter_map(|name| aliases.get(name))
+        .flat_map(|canonical_name| {
+            homonyms
+                .get(canonical_name)
+                .cloned()
+                .unwrap_or_else(|| vec![node_named(&indices, &interner, canonical_name)])
+        })
+        .collect::<Vec<_>>();
+    roots.sort();
+    roots.dedup();
+
+    // functions that contain inline assembly, which LLVM's stack usage analysis ignores; these are
+    // reported at the end of the analysis
+    let mut fns_containing_asm = BTreeMap::<&str, InlineAsm>::new();
+    // (ARM Cortex-M only) IRQ number -> the functions that enable it in the NVIC
+    let mut enabled_irqs = BTreeMap::<u64, BTreeSet<NodeIndex>>::new();
+    // to avoid printing several warnings about the same thing
+    let mut llvm_seen = HashSet::new();
+    // add edges
+    let mut edges: HashMap<_, HashSet<_>> = HashMap::new(); // NodeIdx -> [NodeIdx]
+    let mut defined = HashSet::new(); // functions that are `define`-d in the LLVM-IR
+    let mut ir_calls: HashMap<_, Vec<_>> = HashMap::new(); // NodeIdx -> [NodeIdx]
+
+    // call edges that exist in the LLVM-IR but not in the ELF
+    let mut stale = HashSet::new();
+    // (ARM Cortex-M only) calls that the machine code only performs as tail calls (`B` to another
+    // function); the frame of the caller has been popped by the time the callee runs
+    let mut tail_calls = HashSet::new();
+    // (ARM Cortex-M only) calls performed with interrupts masked, i.e. within a critical section
+    let mut masked_calls = HashSet::new();
+    // the signatures of the callees of indirect calls as the calling convention sees them
+    let canonical_sigs = indirects
+        .keys()
+        .map(|sig| (sig.clone(), sig.canonicalize(calling_convention)))
+        .collect::<HashMap<_, _>>();
+    for define in defines.values() {
+        let canonical_name = match aliases.get(&define.name) {
+            Some(canonical_name) => canonical_name,
+            None => {
+                // this symbol was GC-ed by the linker, skip
+                continue;
+            }
+        };
+        defined.insert(*canonical_name);
+        let caller = node_named(&indices, &interner, canonical_name);
+        let callees_seen = edges.entry(caller).or_default();
+
+        for stmt in &define.stmts {
+            match stmt {
+                Stmt::Asm(expr) => {
+                    fns_containing_asm
+                        .entry(*canonical_name)
+                        .or_insert_with(|| InlineAsm {
+                            first: expr.to_string(),
+                            count: 0,
+                            verdict: AsmVerdict::Unverified,
+                        })
+                        .count += 1;
+                }
+
+                // this is basically `(mem::transmute<*const u8, fn()>(&__some_symbol))()`
+                Stmt::BitcastCall(sym) => {
+                    // XXX we have some type information for this call but it's unclear if we should
+                    // try harder -- does this ever occur in pure Rust programs?
+
+                    let sym = sym.expect("BUG? unnamed symbol is being invoked");
+                    let callee = if let Some(idx) = find_node(&indices, &interner, sym) {
+                        idx
+                    } else {
+                        warn!("no stack information for `{}`", sym);
+
+                        let sym = interner.intern(sym);
+                        let idx = g.add_node(Node(sym, None, false));
+                        indices.insert(sym, idx);
+                        idx
+                    };
+
+                    add_call(&mut g, caller, callee);
+                    callees_seen.insert(callee);
+                }
+
+                Stmt::MemoryCall(call) => {
+                    if target_.is_thumb() {
+                        // the machine code tells which function, if any, each call site calls
+                        continue;
+                    }
+
+                    for name in libcall::memory_call(call, is_arm, pointer_width) {
+                        if let Some(callee) = find_node(&indices, &interner, name) {
+                            add_call(&mut g, caller, callee);
+                            callees_seen.insert(callee);
+                        }
+                    }
+                }
+
+                Stmt::DirectCall(func) => {
+                    match *func {
+                        // no-op / debug-info
+                        "llvm.dbg.value" => continue,
+                        "llvm.dbg.declare" => continue,
+
+                        // no-op / compiler-hint
+                        "llvm.assume" => continue,
+
+                        // lowers to a single instruction
+                        "llvm.trap" => continue,
+
+                        _ => {}
+                    }
+
+                    // no-op / compiler-hint
+                    if func.starts_with("llvm.lifetime.start")
+                        || func.starts_with("llvm.lifetime.end")
+                    {
+                        continue;
+                    }
+
+                    let mut call = |callee| {
+                        add_call(&mut g, caller, callee);
+                        callees_seen.insert(callee);
+                    };
+
+                    if target_.is_thumb() && func.starts_with("llvm.") {
+                        // we'll analyze the machine code in the ELF file to figure out what these
+                        // lower to
+                        continue;
+                    }
+
+                    // the arguments of this memory intrinsic couldn't be parsed (see
+                    // `Stmt::MemoryCall`); it may lower to any of the variants
+                    if let Some(names) = libcall::memory(func, is_arm) {
+                        for name in names {
+                            if let Some(callee) = find_node(&indices, &interner, name) {
+                                call(callee);
+                            }
+                        }
+
+                        continue;
+                    }
+
+                    // XXX unclear whether these produce library calls on some platforms or not
+                    if func.starts_with("llvm.abs.")
+                        || func.starts_with("llvm.bswap.")
+                        || func.starts_with("llvm.ctlz.")
+                        || func.starts_with("llvm.cttz.")
+                        || func.starts_with("llvm.sadd.with.overflow.")
+                        || func.starts_with("llvm.smul.with.overflow.")
+                        || func.starts_with("llvm.ssub.with.overflow.")
+                        || func.starts_with("llvm.uadd.sat.")
+                        || func.starts_with("llvm.uadd.with.overflow.")
+                        || func.starts_with("llvm.umax.")
+                        || func.starts_with("llvm.umin.")
+                        || func.starts_with("llvm.umul.with.overflow.")
+                        || func.starts_with("llvm.usub.sat.")
+                        || func.starts_with("llvm.usub.with.overflow.")
+                        || func.starts_with("llvm.vector.reduce.")
+                        || func.starts_with("llvm.x86.sse2.pmovmskb.")
+                        || *func == "llvm.x86.sse2.pause"
+                    {
+                        if !llvm_seen.contains(func) {
+                            llvm_seen.insert(func);
+                            warn!("assuming that `{}` directly lowers to machine code", func);
+                        }
+
+                        continue;
+                    }
+
+                    // noalias metadata does not lower to machine code
+                    if *func == "llvm.experimental.noalias.scope.decl" {
+                        continue;
+                    }
+
+                    assert!(
+                        !func.starts_with("llvm."),
+                        "BUG: unhandled llvm intrinsic: {}",
+                        func
+                    );
+
+                    // some intrinsics can be directly lowered to machine code
+                    // if the intrinsic has no corresponding node (symbol in the output ELF) assume
+                    // that it has been lowered to machine code
+                    const SYMBOLLESS_INTRINSICS: &[&str] = &["memcmp"];
+                    if SYMBOLLESS_INTRINSICS.contains(func)
+                        && find_node(&indices, &interner, func).is_none()
+                    {
+                        continue;
+                    }
+
+                    // use canonical name
+                    let callee = if let Some(canon) = aliases.get(func) {
+                        node_named(&indices, &interner, canon)
+                    } else {
+                        let idx = if let Some(idx) = find_node(&indices, &interner, func) {
+                            idx
+                        } else {
+                            let sym = interner.intern(*func);
+                            let idx = g.add_node(Node(sym, None, false));
+                            indices.insert(sym, idx);
+
+                            idx
+                        };
+
+                        if !symbols.undefined.contains(func) {
+                            // the callee is neither defined nor imported by the ELF: the linker
+                            // removed it or the LLVM-IR comes from a different build
+                            stale.insert((caller, idx));
+                        }
+
+                        idx
+                    };
+
+                    add_call(&mut g, caller, callee);
+                    callees_seen.insert(callee);
+                    ir_calls.entry(caller).or_default().push(callee);
+
+                    if let Some(homonyms) = aliases.get(func).and_then(|canon| homonyms.get(canon))
+                    {
+                        for callee in homonyms {
+                            add_call(&mut g, caller, *callee);
+                            callees_seen.insert(*callee);
+                        }
+                    }
+                }
+
+                Stmt::IndirectCall(sig) => {
+                    let sig = sig.canonicalize(calling_convention);
+                    for (key_sig, indirect) in &mut indirects {
+                        if canonical_sigs[key_sig].loosely_equal(&sig) {
+                            indirect.called = true;
+                            *indirect.callers.entry(caller).or_insert(0) += 1;
+                        }
+                    }
+                }
+
+                // on ARM Cortex-M these calls are found in the machine code
+                Stmt::Operation(op) if !target_.is_thumb() => {
+                    let float = libcall::float(op, fpu, is_arm);
+                    let integer = libcall::integer(op, pointer_width, is_arm);
+                    for name in float.into_iter().chain(integer.iter().map(|s| &**s)) {
+                        let name = aliases.get(name).copied().unwrap_or(name);
+                        if let Some(callee) = find_node(&indices, &interner, name) {
+                            add_call(&mut g, caller, callee);
+                            callees_seen.insert(callee);
+                        }
+                    }
+                }
+
+                Stmt::VolatileStore(address, value) if target_.is_thumb() => {
+                    // writing a 1 to a bit of the NVIC's Interrupt Set-Enable Registers (ISER)
+                    // enables the corresponding IRQ
+                    const NVIC_ISER: u64 = 0xe000_e100;
+                    if (NVIC_ISER..NVIC_ISER + 16 * 4).contains(address) {
+                        let first = (address - NVIC_ISER) / 4 * 32;
+                        for bit in (0..32).filter(|bit| value & (1 << bit) != 0) {
+                            enabled_irqs.entry(first + bit).or_default().insert(caller);
+                        }
+                    }
+                }
+
+                Stmt::Label(_)
+                | Stmt::Terminator(_)
+                | Stmt::Comment
+                | Stmt::Other
+                | Stmt::Operation(_)
+                | Stmt::VolatileStore(..) => {}
+            }
+        }
+    }
+
+    // functions registered in linker sections (e.g. `.init_array` or `#[link_section]` statics) are
+    // called by the code that iterates over the section; if there's no such code in the program,
+    // e.g. the dynamic loader runs them, they are roots
+    let distributed_slices = options.distributed_slices();
+    let registrations = elf::registrations(&elf, &distributed_slices);
+    // static constructors, which run before `main`
+    let mut constructors = vec![];
+    for section in &distributed_slices {
+        if !registrations.iter().any(|r| r.section == *section) {
+            warn!("distributed slice: section `{}` not found", section);
+        }
+    }
+
+    for registrations in registrations {
+        let callees = registrations
+            .addresses
+            .iter()
+            .filter_map(|address| {
+                // function pointers have the thumb bit set
+                let address = if target_.is_thumb() {
+                    address & !1
+                } else {
+                    *address
+                };
+                addr2node.get(&address).map(|(idx, _)| *idx)
+            })
+            .collect::<Vec<_>>();
+        if callees.is_empty() {
+            continue;
+        }
+
+        let callers = defines
+            .values()
+            .filter(|define| {
+                registrations
+                    .start
+                    .iter()
+                    .any(|symbol| define.symbols.contains(symbol))
+            })
+            .filter_map(|define| {
+                aliases
+                    .get(define.name)
+                    .map(|name| node_named(&indices, &interner, name))
+            })
+            .collect::<Vec<_>>();
+
+        if callers.is_empty() {
+            if matches!(&*registrations.section, ".preinit_array" | ".init_array") {
+                constructors.extend(callees.iter().copied());
+            }
+
+            // NOTE if there are no explicit roots the functions that are not called are the roots
+            if !roots.is_empty() {
+                roots.extend(callees);
+                roots.sort();
+                roots.dedup();
+            }
+        } else {
+            for caller in callers {
+                let callees_seen = edges.entry(caller).or_default();
+                for callee in &callees {
+                    if callees_seen.insert(*callee) {
+                        g.add_edge(caller, *callee, 1);
+                    }
+                }
+
+                // the call site that invokes the registered functions can only reach them; the
+                // other call sites with the same signature in the caller still reach any function
+                // with that signature
+                for indirect in indirects.values_mut() {
+                    if !indirect
+                        .callees
+                        .iter()
+                        .any(|callee| callees.contains(callee))
+                    {
+                        continue;
+                    }
+
+                    if let Some(sites) = indirect.callers.get_mut(&caller) {
+                        *sites -= 1;
+                        if *sites == 0 {
+                            indirect.callers.remove(&caller);
+                            if indirect.callers.is_empty() {
+                                indirect.called = false;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // `__pre_init` (`cortex-m-rt`) is usually an IR alias of the `#[pre_init]` function or of
+    // `DefaultPreInit`; it runs before `main` like the static constructors
+    for (alias, _) in &ir_aliases {
+        if *alias != "__pre_init" {
+            continue;
+        }
+
+        if let Some(canonical_name) = aliases.get(alias) {
+            let idx = node_named(&indices, &interner, canonical_name);
+            if !constructors.contains(&idx) {
+                constructors.push(idx);
+
+                if !roots.is_empty() {
+                    roots.push(idx);
+                    roots.sort();
+                    roots.dedup();
+                }
+            }
+        }
+    }
+
+    // the entry point runs the constructors before calling `main`
+    if options.include_constructors && !constructors.is_empty() {
+        let entry = if target_.is_thumb() {
+            elf.entry() & !1
+        } else {
+            elf.entry()
+        };
+
+        if let Some((entry, _)) = addr2node.get(&entry) {
+            let callees_seen = edges.entry(*entry).or_default();
+            for constructor in &constructors {
+                if callees_seen.insert(*constructor) {
+                    g.add_edge(*entry, *constructor, 1);
+                }
+            }
+        } else {
+            warn!(
+                "--include-constructors: no function at the entry point ({:#x})",
+                entry
+            );
+        }
+    }
+
+    // the allocator shims (`__rust_alloc` & co.) that have no LLVM IR forward to the registered
+    // allocator: the `#[global_allocator]` (`__rg_alloc` & co. or, on newer toolchains, its
+    // `GlobalAlloc` implementation) or the default allocator of `std` (`__rdl_alloc` & co.)
+    let mut shims = aliases
+        .iter()
+        .filter(|(name, _)| !defines.contains_key(**name))
+        .filter_map(|(name, canonical)| match allocator_symbol(name) {
+            Some(("__rust_", op)) => Some((op, *canonical)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    shims.sort_unstable();
+    shims.dedup();
+    for (op, shim) in shims {
+        let registered = aliases
+            .iter()
+            .filter(|(name, _)| allocator_symbol(name) == Some(("__rg_", op)))
+            .map(|(_, canonical)| *canonical)
+            .collect::<BTreeSet<_>>();
+        let implementations = if !registered.is_empty() {
+            registered
+        } else {
+            let implementations = indices
+                .iter()
+                .filter(|(_, idx)| global_alloc_op(interner.short(g[**idx].name)) == Some(op))
+                .map(|(sym, _)| interner.name(*sym))
+                .collect::<BTreeSet<_>>();
+            if implementations.is_empty() {
+                aliases
+                    .iter()
+                    .filter(|(name, _)| allocator_symbol(name) == Some(("__rdl_", op)))
+                    .map(|(_, canonical)| *canonical)
+                    .collect()
+            } else {
+                implementations
+            }
+        };
+
+        let caller = node_named(&indices, &interner, shim);
+        let callees_seen = edges.entry(caller).or_default();
+        for implementation in implementations {
+            let callee = node_named(&indices, &interner, implementation);
+            if callee != caller && callees_seen.insert(callee) {
+                g.add_edge(caller, callee, 1);
+
+                if options.verbose {
+                    eprintln!("allocator: `{}` -> `{}`", shim, implementation);
+                }
+            }
+        }
+    }
+
+    // the LLVM IR of a symbol that's defined more than once can't be attributed to a single
+    // function; assume it describes all of them
+    for nodes in homonyms.values() {
+        if let Some((first, rest)) = nodes.split_first() {
+            let callees = g.neighbors(*first).collect::<Vec<_>>();
+            for node in rest {
+                let callees_seen = edges.entry(*node).or_default();
+                for callee in &callees {
+                    if callees_seen.insert(*callee) {
+                        g.add_edge(*node, *callee, 1);
+                    }
+                }
+            }
+
+            for indirect in indirects.values_mut() {
+                if let Some(sites) = indirect.callers.get(first).cloned() {
+                    indirect
+                        .callers
+                        .extend(rest.iter().map(|node| (*node, sites)));
+                }
+            }
+        }
+    }
+
+    // here we parse the machine code in the ELF file to find out edges that don't appear in the
+    // LLVM-IR (e.g. `fadd` operation, `call llvm.umul.with.overflow`, etc.) or are difficult to
+    // disambiguate from the LLVM-IR (e.g. does this `llvm.memcpy` lower to a call to
+    // `__aebi_memcpy`, a call to `__aebi_memcpy4` or machine instructions?)
+    // (`--main-loop` only) caller -> the call sites and intra-function branches in its machine code
+    let mut sites = HashMap::new();
+    // `svc` number -> the functions that implement the system call
+    let mut syscalls = BTreeMap::<u8, Vec<NodeIndex>>::new();
+    if !target_.is_thumb() && (!options.syscall.is_empty() || options.syscall_table.is_some()) {
+        warn!("--syscall: only the ARM Cortex-M targets are supported");
+    }
+    for (number, function) in &options.syscall {
+        let callees = g
+            .node_indices()
+            .filter(|idx| is_named(&interner, g[*idx].name, function))
+            .collect::<Vec<_>>();
+        if callees.is_empty() {
+            warn!(
+                "--syscall: function `{}` not found{}",
+                function,
+                suggestions(&g, &interner, function)
+            );
+        } else {
+            syscalls.entry(*number).or_default().extend(callees);
+        }
+    }
+    if let Some(table) = &options.syscall_table {
+        match elf::table(&elf, table) {
+            Ok(addresses) => {
+                for (number, address) in (0..=u8::MAX).zip(addresses) {
+                    if address == 0 {
+                        // unused entry
+                    } else if let Some(callee) = function_at(&addr2node, address & !1) {
+                        syscalls.entry(number).or_default().push(callee);
+                    } else {
+                        warn!(
+                            "--syscall-table: entry {} of `{}` ({:#x}) is not a function",
+                            number, table, address
+                        );
+                    }
+                }
+            }
+            Err(e) => warn!("--syscall-table: {}", e),
+        }
+    }
+
+    // `--secondary-stack` takes precedence over the `[secondary-stacks]` of the `--config` file,
+    // which may also give the size of the buffer
+    let declared = options
+        .secondary_stack
+        .iter()
+        .map(|(function, symbol)| (function, symbol, None))
+        .chain(
+            options
+                .config
+                .iter()
+                .flat_map(|config| &config.secondary_stacks)
+                .filter(|stack| {
+                    !options
+                        .secondary_stack
+                        .iter()
+                        .any(|(function, _)| *function == stack.function)
+                })
+                .map(|stack| (&stack.function, &stack.symbol, stack.size)),
+        );
+    let mut secondary_stacks = declared
+        .filter_map(|(function, symbol, size)| {
+            let size = match size.map_or_else(|| elf::symbol_size(&elf, symbol), Ok) {
+                Ok(0) => {
+                    warn!(
+                        "secondary stack: the size of `{}` is unknown; give it with `size` in the \
+                         `[secondary-stacks]` table of the configuration file",
+                        symbol
+                    );
+                    return None;
+                }
+                Ok(size) => size,
+                Err(e) => {
+                    warn!("secondary stack: {}", e);
+                    return None;
+                }
+            };
+
+            Some(SecondaryStack {
+                function: function.clone(),
+                buffer: Some((symbol.clone(), size)),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if target_.is_thumb() {
+        let tags = elf::tags(&elf);
+        let addresses = elf::symbol_addresses(&elf);
+        let big_endian = elf::big_endian_code(&elf);
+
+        // functions may live in sections other than `.text` (e.g. `.init` or RAM functions)
+        let code = elf::code_sections(&elf);
+
+        if code.is_empty() {
+            error!("no executable section found")
+        }
+
+        // (function, data bytes, size) of the functions that embed data, e.g. literal pools
+        let mut data = vec![];
+        // functions without a prologue; they don't push anything onto the stack
+        let mut no_prologue = HashSet::new();
+        // functions whose local stack usage according to LLVM doesn't match the machine code
+        let mut mismatches = vec![];
+        // functions that mask interrupts and the calls they perform with interrupts masked
+        let mut masking = BTreeSet::new();
+        // functions that switch stacks and the instructions that do it
+        let mut stack_switches = BTreeMap::new();
+        // the stack usage of the veneers and the (caller, veneer) calls that go through them
+        let mut veneer_stacks = HashMap::new();
+        let mut veneer_calls = vec![];
+        for (address, sym) in &symbols.defined {
+            let address = *address as u32;
+            let canonical_name = aliases[&sym.names()[0]];
+            let mut size = sym.size() as u32;
+
+            let section = code.iter().find(|(start, bytes)| {
+                address >= *start && address - *start + size <= bytes.len() as u32
+            });
+            let (saddr, bytes) = if let Some(section) = section {
+                *section
+            } else {
+                warn!(
+                    "`{}` is not located in an executable section; skipping its analysis",
+                    canonical_name
+                );
+                continue;
+            };
+
+            if size == 0 {
+                // try harder at finding out the size of this symbol
+                if let Ok(needle) = tags.binary_search_by(|tag| tag.0.cmp(&address)) {
+                    let start = tags[needle];
+                    if start.1 == Tag::Thumb {
+                        if let Some(end) = tags.get(needle + 1) {
+                            if end.1 == Tag::Thumb {
+                                size = end.0 - start.0;
+                            }
+                        }
+                    }
+                }
+
+                // the function can't extend past the next symbol (tags may cover several
+                // functions) or the end of its section; without tags, that's our best guess
+                let end = match addresses.binary_search(&(address & !1)) {
+                    Ok(i) => addresses.get(i + 1),
+                    Err(i) => addresses.get(i),
+                }
+                .cloned()
+                .unwrap_or(u32::MAX)
+                .min(saddr + bytes.len() as u32);
+
+                if size == 0 || address + size > end {
+                    size = end.saturating_sub(address);
+                }
+            }
+
+            let start = (address - saddr) as usize;
+            let end = start + size as usize;
+            let analysis = match thumb::analyze(
+                &bytes[start..end],
+                address,
+                target_ == Target::Thumbv7m,
+                big_endian,
+                &tags,
+            ) {
+                Ok(analysis) => analysis,
+                Err(e) => {
+                    warn!("{} in `{}`; skipping its analysis", e, canonical_name);
+                    continue;
+                }
+            };
+            let (modifies_sp, our_stack) = (analysis.modifies_sp, analysis.stack);
+            if veneers.contains_key(&u64::from(address)) {
+                // the jump to the target is not a call
+                let stack = if modifies_sp { our_stack } else { Some(0) };
+                if stack.is_none() {
+                    warn!(
+                        "no stack usage information for the veneer `{}`",
+                        sym.names()[0]
+                    );
+                }
+                veneer_stacks.insert(u64::from(address), stack.unwrap_or(0));
+                continue;
+            }
+            // the addresses of the indirect function calls
+            let indirect = analysis
+                .calls
+                .iter()
+                .filter(|call| call.kind == CallKind::Indirect)
+                .map(|call| format!("{:#x}", (address as i64 + i64::from(call.site)) as u64))
+                .collect::<Vec<_>>();
+            let caller = addr2node[&u64::from(address)].0;
+            if analysis.data != 0 {
+                data.push((caller, analysis.data, size));
+            }
+            if !modifies_sp {
+                no_prologue.insert(caller);
+            }
+
+            // sanity check
+            let mut our_stack = our_stack;
+            if let Some(stack) = our_stack {
+                if (stack != 0) != modifies_sp {
+                    error!(
+                        "BUG: our analysis reported that `{}` both uses {} bytes of stack and \
+                         it does{} modify SP; ignoring its result",
+                        canonical_name,
+                        stack,
+                        if !modifies_sp { " not" } else { "" }
+                    );
+                    our_stack = None;
+                }
+            }
+
+            if let Some(asm) = fns_containing_asm.get_mut(canonical_name) {
+                asm.verdict = match (g[caller].local, our_stack) {
+                    (Local::Exact(llvm), Some(actual)) if llvm != actual => {
+                        AsmVerdict::Corrected { llvm, actual }
+                    }
+                    (_, Some(_)) => AsmVerdict::Confirmed,
+                    // the stack pointer is never modified
+                    (_, None) if !modifies_sp => AsmVerdict::Confirmed,
+                    (_, None) => AsmVerdict::Unverified,
+                };
+            }
+
+            // check the correctness of `modifies_sp` and `our_stack`
+            // also override LLVM's results when they appear to be wrong
+            let node = &mut g[caller];
+            if let Local::Exact(ref mut llvm_stack) = node.local {
+                if let Some(stack) = our_stack {
+                    if *llvm_stack != stack && fns_containing_asm.contains_key(canonical_name) {
+                        // LLVM's stack usage analysis ignores inline asm, so its results can
+                        // be wrong here; this is reported at the end of the analysis
+                        node.source = Source::Corrected { llvm: *llvm_stack };
+                        *llvm_stack = stack;
+                    } else if is_outlined_function(canonical_name) {
+                        // ^ functions produced by LLVM's function outliner are not properly
+                        // analyzed by LLVM's emit-stack-sizes pass and are all assigned a stack
+                        // usage of 0 bytes, which is sometimes wrong
+                        if *llvm_stack == 0 && stack != *llvm_stack {
+                            warn!(
+                                "LLVM reported that `{}` uses {} bytes of stack but \
+                                 our analysis reported {} bytes; overriding LLVM's result \
+                                 (function was produced by LLVM's function outlining pass)",
+                                canonical_name, llvm_stack, stack
+                            );
+
+                            node.source = Source::Corrected { llvm: *llvm_stack };
+                            *llvm_stack = stack;
+                        }
+                    } else if *llvm_stack != stack {
+                        // in all other cases our results should match
+                        mismatches.push(Mismatch {
+                            function: caller,
+                            llvm: *llvm_stack,
+                            machine_code: stack,
+                        });
+                    }
+                } else if *llvm_stack != 0 && !modifies_sp {
+                    // the function never touches the stack pointer
+                    mismatches.push(Mismatch {
+                        function: caller,
+                        llvm: *llvm_stack,
+                        machine_code: 0,
+                    });
+                } else if *llvm_stack == 0 && modifies_sp {
+                    // the function pushes onto the stack but our analysis gave up (e.g. on a
+                    // function with branches) so neither number can be trusted
+                    warn!(
+                        "LLVM reported that `{}` uses 0 bytes of stack but the machine code \
+                         modifies the stack pointer; ignoring LLVM's result",
+                        canonical_name
+                    );
+                    node.local = Local::Unknown;
+                    node.source = Source::Unknown;
+                }
+            } else if let Some(stack) = our_stack {
+                g[caller].local = Local::Exact(stack);
+                g[caller].source = Source::MachineCode;
+                has_stack_usage_info = true;
+            } else if !modifies_sp {
+                // this happens when the function contains intra-branches and our analysis gives
+                // up (`our_stack == None`)
+                g[caller].local = Local::Exact(0);
+                g[caller].source = Source::MachineCode;
+            }
+
+            if g[caller].local == Local::Unknown
+                && !is_builtin(canonical_name)
+                && !options.is_quiet(canonical_name)
+            {
+                warn!("no stack usage information for `{}`", canonical_name);
+            }
+
+            if indirect.is_empty() || defined.contains(canonical_name) {
+                // no indirect function calls or they are described by the LLVM-IR
+            } else if options.ignore_indirect {
+                warn!(
+                    "ignoring the indirect function call performed by `{}` at {}",
+                    canonical_name,
+                    indirect.join(", ")
+                );
+            } else {
+                // this function performs an indirect function call and we have no type
+                // information to narrow down the list of callees so inject the uncertainty
+                // in the form of a call to an unknown function with unknown stack usage
+
+                warn!(
+                    "`{}` performs an indirect function call at {} and there's \
+                     no type information about the operation",
+                    canonical_name,
+                    indirect.join(", ")
+                );
+                let callee = g.add_node(Node(interner.intern("?"), None, false));
+                g.add_edge(caller, callee, indirect.len());
+            }
+
+            // (start, end) of the critical sections, in program order; the branches are ignored
+            let mut critical = vec![];
+            let mut masked = None;
+            for instr in &analysis.masking {
+                match (instr.kind, masked) {
+                    (MaskingKind::Disable, None) | (MaskingKind::Msr, None) => {
+                        masked = Some(instr.site)
+                    }
+                    (MaskingKind::Enable, Some(start)) | (MaskingKind::Msr, Some(start)) => {
+                        critical.push((start, instr.site));
+                        masked = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(start) = masked {
+                // interrupts are not unmasked before returning
+                critical.push((start, i32::MAX));
+            }
+            if !analysis.masking.is_empty() {
+                masking.insert(caller);
+            }
+            if let Some(switch) = analysis.stack_switches.first() {
+                let (instr, rn) = match switch.kind {
+                    StackSwitchKind::Mov(rn) => ("mov sp", rn),
+                    StackSwitchKind::Msp(rn) => ("msr msp", rn),
+                    StackSwitchKind::Psp(rn) => ("msr psp", rn),
+                };
+                stack_switches.insert(
+                    caller,
+                    format!(
+                        "`{}, r{}` at {:#x}",
+                        instr,
+                        rn,
+                        (address as i64 + i64::from(switch.site)) as u64
+                    ),
+                );
+            }
+
+            // callee -> number of call sites
+            let mut machine_calls = HashMap::<_, usize>::new();
+            // (site, callee)
+            let mut calls = vec![];
+            // callees that are reached through at least one call that's not a tail call
+            let mut not_tail = HashSet::new();
+            for call in &analysis.calls {
+                let offset = match (call.kind, call.target) {
+                    // `rust-lld` turns the calls to the symbols that a `staticlib` leaves
+                    // undefined into calls to the next instruction; the LLVM-IR has their callees
+                    (CallKind::Direct, Some(offset)) if is_staticlib && offset == call.site + 4 => {
+                        continue
+                    }
+                    (_, Some(offset)) => offset,
+                    (CallKind::Supervisor(number), None) if !syscalls.is_empty() => {
+                        let callees = syscalls.get(&number).cloned().unwrap_or_else(|| {
+                            // the SVCall handler may dispatch it to any of them
+                            warn!(
+                                "`svc #{}` at {:#x} in `{}` is not a known system call; assuming \
+                                 it reaches any of them",
+                                number,
+                                (address as i64 + i64::from(call.site)) as u64,
+                                canonical_name
+                            );
+                            syscalls.values().flatten().copied().collect()
+                        });
+
+                        for callee in callees {
+                            *machine_calls.entry(callee).or_default() += 1;
+                            calls.push((call.site, callee));
+                            not_tail.insert(callee);
+                        }
+                        continue;
+                    }
+                    // indirect function calls were handled above; `svc` instructions are ignored
+                    // unless the system calls have been described
+                    (_, None) => continue,
+                };
+
+                let addr = (address as i64 + i64::from(offset)) as u64;
+                let callee = match function_at(&addr2node, addr) {
+                    Some(idx) => idx,
+                    None => unknown_code(addr, &mut g, &mut indices, &mut interner),
+                };
+                if let Some(veneer) = function_at(&veneers, addr) {
+                    veneer_calls.push((caller, veneer));
+                }
+                *machine_calls.entry(callee).or_default() += 1;
+                calls.push((call.site, callee));
+                if critical
+                    .iter()
+                    .any(|(start, end)| *start < call.site && call.site < *end)
+                {
+                    masked_calls.insert((caller, callee));
+                }
+                if !matches!(call.kind, CallKind::Tail | CallKind::ConditionalTail) {
+                    not_tail.insert(callee);
+                }
+            }
+
+            // (site, target) of the intra-function branches
+            let branches = analysis
+                .branches
+                .iter()
+                .map(|branch| (branch.site, branch.target))
+                .collect::<Vec<_>>();
+
+            if options.main_loop.is_some() {
+                sites.insert(caller, (calls, branches));
+            }
+
+            // the machine code has the final say on the number of call sites
+            let callees_seen = edges.entry(caller).or_default();
+            for (callee, sites) in &machine_calls {
+                callees_seen.insert(*callee);
+                g.update_edge(caller, *callee, *sites);
+            }
+
+            // outlined functions may contain the epilogue of the caller so its frame may still be
+            // on the stack when they run
+            for callee in machine_calls.keys() {
+                if !not_tail.contains(callee)
+                    && !is_outlined_function(interner.name(g[*callee].name))
+                {
+                    tail_calls.insert((caller, *callee));
+                }
+            }
+
+            // direct calls in the LLVM-IR that the machine code doesn't perform
+            for callee in ir_calls.get(&caller).into_iter().flatten() {
+                if !machine_calls.contains_key(callee) {
+                    stale.insert((caller, *callee));
+                }
+            }
+        }
+
+        // LLVM may merge the identical tails of several functions into a block with its own
+        // symbol that they all jump to; it runs on top of the frame of the function that jumped
+        // to it, which is still on the stack, so those jumps are not tail calls
+        let mut tail_callers = BTreeMap::<_, Vec<_>>::new();
+        for (caller, callee) in &tail_calls {
+            tail_callers.entry(*callee).or_default().push(*caller);
+        }
+        for (tail, mut callers) in tail_callers {
+            let is_shared_tail = callers.len() > 1
+                && no_prologue.contains(&tail)
+                && g.neighbors_directed(tail, Direction::Incoming).count() == callers.len();
+            if !is_shared_tail {
+                continue;
+            }
+
+            for caller in &callers {
+                tail_calls.remove(&(*caller, tail));
+            }
+
+            if options.verbose {
+                callers.sort();
+                eprintln!(
+                    "shared tail: `{}` is the tail of {}",
+                    interner.demangled(g[tail].name),
+                    callers
+                        .iter()
+                        .map(|caller| format!("`{}`", interner.demangled(g[*caller].name)))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+
+        if options.verbose {
+            for (function, bytes, size) in &data {
+                let function = interner.demangled(g[*function].name);
+                eprintln!("data: `{}`: {} of {} bytes", function, bytes, size);
+            }
+            eprintln!(
+                "data: {} bytes skipped in {} function(s)",
+                data.iter()
+                    .map(|(_, bytes, _)| u64::from(*bytes))
+                    .sum::<u64>(),
+                data.len()
+            );
+        }
+
+        if options.verbose && !masking.is_empty() {
+            critical_sections(&g, &interner, &masking, &masked_calls);
+        }
+
+        // the callees of the functions that switch stacks don't run on the stack of their callers;
+        // unless `--secondary-stack` describes it, the size of that stack is unknown
+        let mut list = String::new();
+        for (function, switch) in stack_switches {
+            let name = g[function].name;
+            if secondary_stacks
+                .iter()
+                .any(|stack| is_named(&interner, name, &stack.function))
+            {
+                continue;
+            }
+
+            let _ = write!(list, "\n    {}: {}", interner.demangled(name), switch);
+            secondary_stacks.push(SecondaryStack {
+                function: interner.name(name).to_owned(),
+                buffer: None,
+            });
+        }
+        if !list.is_empty() {
+            warn!(
+                "the following functions switch to a different stack; the functions they call are \
+                 analyzed as separate roots whose stack usage is not checked against the size of \
+                 that stack (see `--secondary-stack`):{}",
+                list
+            );
+        }
+
+        if !mismatches.is_empty() {
+            resolve_mismatches(&mut g, &interner, &mismatches, options)?;
+        }
+
+        // the veneer pushes onto the stack of its caller; it pops everything before jumping to its
+        // target so this over-approximates the max stack usage by at most the size of the veneer's
+        // frame
+        let mut veneer_overhead = HashMap::<_, u64>::new();
+        for (caller, veneer) in veneer_calls {
+            let stack = veneer_stacks.get(&veneer).copied().unwrap_or(0);
+            let overhead = veneer_overhead.entry(caller).or_default();
+            *overhead = (*overhead).max(stack);
+        }
+        for (caller, overhead) in veneer_overhead {
+            if let Local::Exact(n) = &mut g[caller].local {
+                *n += overhead;
+            }
+        }
+    }
+
+    let unanalyzed_builtins = g
+        .node_weights()
+        .filter(|node| node.local == Local::Unknown && is_builtin(interner.name(node.name)))
+        .count();
+    if unanalyzed_builtins != 0 {
+        warn!(
+            "--no-builtins-analysis: the stack usage of {} `compiler_builtins` functions is \
+             unknown; it can be provided with `--override-stack`",
+            unanalyzed_builtins
+        );
+    }
+
+    // add fictitious nodes for indirect function calls
+    // this is a bit weird but for some reason `ArgumentV1.formatter` sometimes lowers to different
+    // LLVM types. In theory it should always be: `i1 (*%fmt::Void, *&core::fmt::Formatter)*` but
+    // sometimes the type of the first argument is `%fmt::Void`, sometimes it's `%core::fmt::Void`,
+    // sometimes is `%core::fmt::Void.12` and on occasion it's even `%SomeRandomType`
+    //
+    // To cope with this weird fact the following piece of code will try to find the right LLVM
+    // type.
+    let all_maybe_void = indirects
+        .keys()
+        .filter_map(|sig| match (&sig.inputs[..], sig.output.as_ref()) {
+            ([Type::Pointer(receiver), Type::Pointer(formatter)], Some(output))
+                if **formatter == Type::Alias("core::fmt::Formatter")
+                    && **output == Type::Integer(1) =>
+            {
+                if let Type::Alias(receiver) = **receiver {
+                    Some(receiver)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let one_true_void = if all_maybe_void.contains(&"fmt::Void") {
+        Some("fmt::Void")
+    } else {
+        all_maybe_void
+            .iter()
+            .filter_map(|maybe_void| {
+                // this could be `core::fmt::Void` or `core::fmt::Void.12`
+                if maybe_void.starts_with("core::fmt::Void") {
+                    Some(*maybe_void)
+                } else {
+                    None
+                }
+            })
+            .next()
+            .or_else(|| {
+                if all_maybe_void.len() == 1 {
+                    // we got a random type!
+                    Some(all_maybe_void[0])
+                } else {
+                    None
+                }
+            })
+    };
+
+    if !fmts.is_empty() {
+        let mut captured = fmts
+            .iter()
+            .map(|idx| interner.demangled(g[*idx].name))
+            .collect::<Vec<_>>();
+        captured.sort_unstable();
+
+        if options.verbose {
+            eprintln!(
+                "the formatter heuristic captured {} functions:",
+                captured.len()
+            );
+            for name in &captured {
+                eprintln!("    {}", name);
+            }
+        }
+
+        // the loose heuristic also captures user functions that happen to have the same signature
+        let mut misfires = fmts
+            .iter()
+            .filter(|idx| !is_formatter(interner.short(g[**idx].name)))
+            .map(|idx| interner.demangled(g[*idx].name))
+            .collect::<Vec<_>>();
+        if !misfires.is_empty() {
+            misfires.sort_unstable();
+            warn!(
+                "the formatter heuristic made these functions, which don't implement a `core::fmt` \
+                 trait, callees of the formatting machinery: {}; `--fmt-heuristic strict` leaves \
+                 them out",
+                misfires.join(", ")
+            );
+        }
+    }
+
+    // the indirect calls that may reach the untyped symbols
+    let mut unbounded = vec![];
+    for (mut sig, indirect) in indirects {
+        if !indirect.called {
+            continue;
+        }
+
+        let one_true_void = one_true_void.filter(|_| options.fmt_heuristic != FmtHeuristic::Off);
+        let callees = if let Some(one_true_void) = one_true_void {
+            match (&sig.inputs[..], sig.output.as_ref()) {
+                // special case: this is `ArgumentV1.formatter` a pseudo trait object
+                ([Type::Pointer(void), Type::Pointer(fmt)], Some(output))
+                    if **void == Type::Alias(one_true_void)
+                        && **fmt == Type::Alias("core::fmt::Formatter")
+                        && **output == Type::Integer(1) =>
+                {
+                    if fmts.is_empty() {
+                        error!("BUG? no callees for `{}`", sig.to_string());
+                    }
+
+                    // canonicalize the signature
+                    if one_true_void != "fmt::Void" {
+                        sig.inputs[0] = Type::Alias("fmt::Void");
+                    }
+
+                    &fmts
+                }
+
+                _ => &indirect.callees,
+            }
+        } else {
+            &indirect.callees
+        };
+
+        let mut name = sig.to_string();
+        // append '*' to denote that this is a function pointer
+        name.push('*');
+
+        if options.ignore_indirect {
+            let mut callers = indirect
+                .callers
+                .keys()
+                .map(|caller| interner.demangled(g[*caller].name))
+                .collect::<Vec<_>>();
+            callers.sort_unstable();
+
+            for caller in callers {
+                warn!(
+                    "ignoring the indirect function call (`{}`) in `{}`",
+                    name, caller
+                );
+            }
+
+            continue;
+        }
+
+        let call = g.add_node(Node(interner.intern(name.clone()), Some(0), true));
+        g[call].callees = Some(callees.len());
+
+        for (caller, sites) in &indirect.callers {
+            g.add_edge(*caller, call, *sites);
+        }
+
+        if has_untyped_symbols && may_reach_untyped(&sig) {
+            // add an edge between this and a potential extern / untyped symbol
+            let extern_sym = g.add_node(Node(interner.intern("?"), None, false));
+            g.add_edge(call, extern_sym, 1);
+            unbounded.push(name);
+        } else {
+            if callees.is_empty() {
+                error!("BUG? no callees for `{}`", name);
+            }
+        }
+
+        for callee in callees {
+            g.add_edge(call, *callee, 1);
+        }
+    }
+
+    if !unbounded.is_empty() {
+        unbounded.sort();
+        warn!(
+            "the program contains untyped, external symbols (e.g. linked in from binary blobs); \
+             the following indirect function calls can not be bounded: {}",
+            unbounded.join(", ")
+        );
+    }
+
+    if !homonyms.is_empty() {
+        // tell the homonyms apart by their address
+        for (address, (idx, _)) in &addr2node {
+            if homonyms.contains_key(interner.name(g[*idx].name)) {
+                let name = format!("{}@{:#010x}", interner.demangled(g[*idx].name), address);
+                g[*idx].name = interner.intern(name);
+            }
+        }
+
+        let mut list = String::new();
+        for (name, nodes) in &homonyms {
+            let _ = write!(list, "\n    {}", rustc_demangle::demangle(name));
+            if nodes.is_empty() {
+                list.push_str(" (LLVM IR; only the first definition was analyzed)");
+            } else {
+                let _ = write!(list, " ({} functions)", nodes.len());
+            }
+        }
+
+        warn!(
+            "the following symbols are defined more than once; calls to them are assumed to \
+             reach any of their definitions:{}",
+            list
+        );
+    }
+
+    if !fns_containing_asm.is_empty() {
+        let mut list = String::new();
+        let mut unverified = 0;
+        for (name, asm) in &fns_containing_asm {
+            let _ = write!(
+                list,
+                "\n    {:#}: asm!(\"{}\")",
+                rustc_demangle::demangle(name),
+                asm.first
+            );
+            if asm.count > 1 {
+                let _ = write!(list, " and {} more", asm.count - 1);
+            }
+
+            match asm.verdict {
+                AsmVerdict::Confirmed => list.push_str(" (confirmed by the machine code analysis)"),
+                AsmVerdict::Corrected { llvm, actual } => {
+                    let _ = write!(
+                        list,
+                        " (corrected from {} to {} bytes by the machine code analysis)",
+                        llvm, actual
+                    );
+                }
+                AsmVerdict::Unverified => {
+                    unverified += 1;
+                    list.push_str(" (unverified)");
+                }
+            }
+        }
+
+        warn!(
+            "LLVM's stack usage analysis ignores inline assembly; it was assumed to not use the \
+             stack in the following functions:{}",
+            list
+        );
+
+        if unverified != 0 {
+            warn!(
+                "the stack usage of {} function(s) with inline assembly could not be verified \
+                 and may be higher than reported",
+                unverified
+            );
+        }
+    }
+
+    // (ARM Cortex-M only) the exception and interrupt handlers are roots of the call graph
+    let mut vectors = None;
+    if target_.is_thumb() {
+        if let Some(table) = elf::vector_table(&elf) {
+            check_vector_table(
+                &table,
+                &enabled_irqs,
+                &g,
+                &interner,
+                &symbols.defined,
+                &addr2node,
+            );
+
+            vectors = Vectors::new(&table, &addr2node, exception_frame_size(target));
+        }
+    }
+    if let Some(vectors) = &vectors {
+        // NOTE if there are no explicit roots the functions that are not called are the roots
+        if roots.is_empty() {
+            roots = uncalled(&g);
+        }
+        roots.push(vectors.reset);
+        roots.extend(vectors.handlers.iter().cloned());
+        roots.sort();
+        roots.dedup();
+    }
+
+    let locations = node_locations
+        .into_iter()
+        .map(|(idx, (file, line))| (g[idx].name, (file.clone(), *line)))
+        .collect();
+
+    if let Some(test) = &options.test {
+        roots = tests("--test", test, &g, &indices, &interner);
+    } else if let Some(bench) = &options.bench {
+        roots = tests("--bench", bench, &g, &indices, &interner);
+    }
+
+    let main_loop = match &options.main_loop {
+        Some(function) if target_.is_thumb() => main_loop(function, &g, &interner, &sites),
+        Some(_) => {
+            warn!("--main-loop: only the ARM Cortex-M targets are supported");
+            None
+        }
+        None => None,
+    };
+    if options.tail_calls && !target_.is_thumb() {
+        warn!("--tail-calls: only the ARM Cortex-M targets are supported");
+    }
+
+    Ok(Program {
+        g,
+        indices,
+        interner,
+        roots,
+        has_stack_usage_info,
+        locations,
+        stale,
+        tail_calls,
+        masked_calls,
+        main_loop,
+        vectors,
+        secondary_stacks,
+        return_address_size: return_address_size(target),
+        target: target_,
+        addr2node,
+        elf: Some(elf),
+    })
+}
+
+// whether `name` is `function`'s mangled name, its demangled name or its demangled name without
+// the hash
+pub(crate) fn is_named(interner: &Interner, name: Symbol, function: &str) -> bool {
+    interner.name(name) == function
+        || interner.demangled(name) == function
+        || interner.dehashed(name) == Some(function)
+}
+
+// the node of the function named `name`, if it's in the call graph
+pub(crate) fn find_node(
+    indices: &BTreeMap<Symbol, NodeIndex>,
+    interner: &Interner,
+    name: &str,
+) -> Option<NodeIndex> {
+    indices.get(&interner.lookup(name)?).copied()
+}
+
+// the node of the function named `name`, which must be in the call graph
+pub(crate) fn node_named(
+    indices: &BTreeMap<Symbol, NodeIndex>,
+    interner: &Interner,
+    name: &str,
+) -> NodeIndex {
+    find_node(indices, interner, name).expect("UNREACHABLE")
+}
+
+// adds a call site from `caller` to `callee`; the edge weight is the number of call sites
+pub(crate) fn add_call(g: &mut Graph<Node, usize>, caller: NodeIndex, callee: NodeIndex) {
+    if let Some(edge) = g.find_edge(caller, callee) {
+        g[edge] += 1;
+    } else {
+        g.add_edge(caller, callee, 1);
+    }
+}
+
+// suffix for a "function not found" message that lists the functions whose names are similar to
+// `function`
+pub(crate) fn suggestions(g: &Graph<Node, usize>, interner: &Interner, function: &str) -> String {
+    let names = g
+        .node_weights()
+        .filter(|node| !node.dashed)
+        .map(|node| interner.short(node.name))
+        .collect::<Vec<_>>();
+
+    suggest::did_you_mean(&suggest::similar(function, names))
+}
+
+// the functions that are not called by any other function
+pub(crate) fn uncalled(g: &Graph<Node, usize>) -> Vec<NodeIndex> {
+    g.node_indices()
+        .filter(|idx| {
+            g.neighbors_directed(*idx, Direction::Incoming)
+                .all(|caller| caller == *idx)
+        })
+        .collect()
+}
+
+// returns the node of the function located at `addr`; `addr2node` maps the start address of each
+// function to its node and size
+pub(crate) fn function_at<T>(addr2node: &BTreeMap<u64, (T, u64)>, addr: u64) -> Option<T>
+where
+    T: Copy,
+{
+    // the address may be off by one due to the thumb bit being set in the symbol value but not in
+    // the branch target, or vice versa
+    for addr in [addr, addr | 1, addr & !1] {
+        if let Some((node, _)) = addr2node.get(&addr) {
+            return Some(*node);
+        }
+    }
+
+    // branch into the body of a function
+    let (start, (node, size)) = addr2node.range(..=addr | 1).next_back()?;
+    let start = start & !1;
+    if addr >= start && addr < start + size {
+        Some(*node)
+    } else {
+        None
+    }
+}
+
+// the functions of the `test` crate that the entry point of the test harness, `main`, calls, e.g.
+// the `#[test]` functions of a `#[defmt_test::tests]` module, plus `main` itself. The tests run
+// one after the other on the stack of the entry point
+pub(crate) fn tests(
+    option: &str,
+    test: &str,
+    g: &Graph<Node, usize>,
+    indices: &BTreeMap<Symbol, NodeIndex>,
+    interner: &Interner,
+) -> Vec<NodeIndex> {
+    let Some(main) = find_node(indices, interner, "main") else {
+        warn!("{}: the binary has no `main` function", option);
+        return vec![];
+    };
+
+    let krate = format!("{}::", test.replace('-', "_"));
+    let mut roots = g
+        .neighbors_directed(main, Direction::Outgoing)
+        .filter(|callee| interner.demangled(g[*callee].name).starts_with(&krate))
+        .collect::<Vec<_>>();
+
+    if roots.is_empty() {
+        warn!(
+            "{}: `main` doesn't call any function of `{}`; the ones that were inlined into it can \
+             be kept separate with `#[inline(never)]`",
+            option, test
+        );
+    }
+
+    roots.push(main);
+    roots.sort();
+    roots.dedup();
+    roots
+}
+
+// splits the calls of `function` into those it performs before entering its main loop, the last
+// loop in its machine code, and those it performs within it. Calls that can't be placed, e.g. the
+// ones that only appear in the LLVM-IR, are assumed to happen in both phases
+#[allow(clippy::type_complexity)]
+pub(crate) fn main_loop(
+    function: &str,
+    g: &Graph<Node, usize>,
+    interner: &Interner,
+    sites: &HashMap<NodeIndex, (Vec<(i32, NodeIndex)>, Vec<(i32, i32)>)>,
+) -> Option<MainLoop> {
+    let mut caller = g
+        .node_indices()
+        .find(|idx| is_named(interner, g[*idx].name, function));
+
+    let mut frames = vec![];
+    while let Some(current) = caller {
+        frames.push(current);
+
+        let Some((calls, branches)) = sites.get(&current) else {
+            break;
+        };
+
+        // the main loop ends with the last backward branch
+        if let Some(&(latch, mut head)) = branches
+            .iter()
+            .filter(|(site, target)| target <= site)
+            .max_by_key(|(site, _)| *site)
+        {
+            // a loop that starts earlier may jump back from within the last one (e.g. a loop
+            // whose condition was moved to its end)
+            while let Some(target) = branches
+                .iter()
+                .filter(|(site, target)| *site >= head && *site <= latch && *target < head)
+                .map(|(_, target)| *target)
+                .min()
+            {
+                head = target;
+            }
+
+            let mut init = vec![];
+            let mut steady = vec![];
+            for (site, callee) in calls {
+                if *site < head {
+                    init.push(*callee);
+                } else if *site <= latch {
+                    steady.push(*callee);
+                } else {
+                    init.push(*callee);
+                    steady.push(*callee);
+                }
+            }
+
+            for callee in g.neighbors(current) {
+                if !calls.iter().any(|(_, idx)| *idx == callee) {
+                    init.push(callee);
+                    steady.push(callee);
+                }
+            }
+
+            return Some(MainLoop {
+                frames,
+                init,
+                steady,
+            });
+        }
+
+        // follow trampolines like `cortex-m-rt`'s `main`, which only calls the user's `main`
+        let mut callees = g.neighbors(current);
+        caller = match (callees.next(), callees.next()) {
+            (Some(callee), None) if !frames.contains(&callee) => Some(callee),
+            _ => None,
+        };
+    }
+
+    warn!("--main-loop: no main loop found in `{}`", function);
+
+    None
+}
+
+// (ARM Cortex-M only) warns about the IRQs that the program enables but whose vector table entry
+// is missing or points at the default handler, and about the functions that are neither called
+// nor installed in the vector table, e.g. an interrupt handler whose name doesn't match the one of
+// the device's interrupt
+pub(crate) fn check_vector_table(
+    vectors: &[u64],
+    enabled_irqs: &BTreeMap<u64, BTreeSet<NodeIndex>>,
+    g: &Graph<Node, usize>,
+    interner: &Interner,
+    functions: &BTreeMap<u64, elf::Function>,
+    addr2node: &BTreeMap<u64, (NodeIndex, u64)>,
+) {
+    // `cortex-m-rt`'s handler of the exceptions and interrupts that the program doesn't handle
+    const DEFAULT_HANDLERS: &[&str] = &["DefaultHandler", "DefaultHandler_"];
+
+    let functions = functions
+        .iter()
+        .map(|(address, function)| (*address, (function, function.size())))
+        .collect::<BTreeMap<_, _>>();
+    let default_handler = |address: u64| {
+        function_at(&functions, address).and_then(|function| {
+            function
+                .names()
+                .iter()
+                .find(|name| DEFAULT_HANDLERS.contains(name))
+        })
+    };
+    let names = |nodes: &BTreeSet<NodeIndex>| {
+        nodes
+            .iter()
+            .map(|idx| format!("`{}`", interner.demangled(g[*idx].name)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    // the first entry is the initial value of the stack pointer; the external interrupts start
+    // after the 15 system exceptions
+    let interrupts = vectors.get(16..).unwrap_or(&[]);
+    for (irq, enablers) in enabled_irqs {
+        match interrupts.get(*irq as usize) {
+            None => warn!(
+                "IRQ {} is enabled by {} but the vector table has no entry for it",
+                irq,
+                names(enablers)
+            ),
+            Some(address) => {
+                if let Some(default_handler) = default_handler(*address) {
+                    warn!(
+                        "IRQ {} is enabled by {} but its vector table entry points at the default \
+                         handler, `{}`",
+                        irq,
+                        names(enablers),
+                        default_handler
+                    );
+                }
+            }
+        }
+    }
+
+    let installed = vectors
+        .iter()
+        .skip(1)
+        .filter(|address| **address != 0)
+        .filter_map(|address| function_at(addr2node, *address))
+        .collect::<HashSet<_>>();
+    let uninstalled = g
+        .node_indices()
+        .filter(|idx| {
+            !g[*idx].dashed
+                && !installed.contains(idx)
+                && g.neighbors_directed(*idx, Direction::Incoming)
+                    .next()
+                    .is_none()
+        })
+        .collect::<BTreeSet<_>>();
+    if !uninstalled.is_empty() {
+        warn!(
+            "the following functions are neither called nor installed in the vector table; if \
+             they are interrupt handlers, check that their names match the device's interrupts: {}",
+            names(&uninstalled)
+        );
+    }
+}
+
+// a function that switches the stack pointer to a different stack
+pub(crate) struct SecondaryStack {
+    pub(crate) function: String,
+    // the static buffer that holds the stack and its size; unknown if the stack switch was found
+    // in the machine code
+    pub(crate) buffer: Option<(String, u64)>,
+}
+
+// checks that the LLVM IR, the object file and the ELF were produced by the same build
+pub(crate) fn check_consistency(
+    items: &[Item],
+    obj: &[u8],
+    ll_path: &Path,
+    obj_path: &Path,
+    elf_path: &Path,
+) -> anyhow::Result<()> {
+    let missing = missing_functions(items, &elf::object_functions(obj)?);
+    if !missing.is_empty() {
+        let mut list = missing
+            .iter()
+            .take(5)
+            .map(|name| format!("`{}`", rustc_demangle::demangle(name)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if missing.len() > 5 {
+            let _ = write!(list, " and {} more", missing.len() - 5);
+        }
+
+        bail!(
+            "the LLVM IR file `{}` and the object file `{}` come from different builds: the \
+             following functions are defined in the LLVM IR but not in the object file: {}; \
+             run `cargo clean` and try again",
+            ll_path.display(),
+            obj_path.display(),
+            list
+        );
+    }
+
+    // the ELF is linked from the object file so it can't be older than it
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    if let (Some(obj_modified), Some(elf_modified)) = (modified(obj_path), modified(elf_path)) {
+        if elf_modified < obj_modified {
+            bail!(
+                "the ELF file `{}` is older than the object file `{}`; was the build \
+                 interrupted? run `cargo clean` and try again",
+                elf_path.display(),
+                obj_path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// returns the functions defined in the LLVM IR that are missing from the object file
+// NOTE the converse is not an error: the object file also contains functions that are not in the
+// LLVM IR, e.g. the ones produced by LLVM's function outliner or defined in `global_asm!`
+pub(crate) fn missing_functions<'a>(
+    items: &[Item<'a>],
+    obj_functions: &HashSet<&str>,
+) -> Vec<&'a str> {
+    let mut missing = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Define(def) if !obj_functions.contains(def.name) => Some(def.name),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+    missing
+}
+
+// maps the (mangled) name of each function to the file and line where it's defined
+pub(crate) fn debug_locations<'a>(items: &[Item<'a>]) -> HashMap<&'a str, (PathBuf, u32)> {
+    let mut subprograms = HashMap::new();
+    let mut files = HashMap::new();
+    for item in items {
+        match item {
+            Item::DebugInfo(id, DebugInfo::Subprogram { file, line }) => {
+                subprograms.insert(*id, (*file, *line));
+            }
+
+            Item::DebugInfo(
+                id,
+                DebugInfo::File {
+                    filename,
+                    directory,
+                },
+            ) => {
+                // `join` keeps `filename` as it is if it's an absolute path
+                files.insert(*id, Path::new(directory).join(filename));
+            }
+
+            _ => {}
+        }
+    }
+
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Define(def) => {
+                let (file, line) = subprograms.get(&def.dbg?)?;
+                Some((def.name, (files.get(file)?.clone(), *line)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// adds to `names` the other names of the IR aliases (transitively) that are not in the ELF
+pub(crate) fn add_ir_aliases<'a>(
+    names: &mut Vec<&'a str>,
+    ir_aliases: &[(&'a str, &'a str)],
+    elf_names: &HashSet<&str>,
+) {
+    loop {
+        let mut changed = false;
+        for (alias, aliasee) in ir_aliases {
+            for (name, other) in [(alias, aliasee), (aliasee, alias)] {
+                if names.contains(name) && !names.contains(other) && !elf_names.contains(other) {
+                    names.push(other);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+}
+
+// returns the node that represents the code at `addr`, which is not covered by any symbol (e.g. a
+// bootloader located outside the image)
+pub(crate) fn unknown_code(
+    addr: u64,
+    g: &mut Graph<Node, usize>,
+    indices: &mut BTreeMap<Symbol, NodeIndex>,
+    interner: &mut Interner,
+) -> NodeIndex {
+    let name = format!("?@{:#010x}", addr);
+    if let Some(idx) = find_node(indices, interner, &name) {
+        return idx;
+    }
+
+    warn!(
+        "branch to address {:#010x}, which has no symbol; assuming unknown stack usage",
+        addr
+    );
+
+    let sym = interner.intern(name);
+    let idx = g.add_node(Node(sym, None, false));
+    indices.insert(sym, idx);
+    idx
+}
+
+// reports the functions that mask interrupts and the functions that only run with interrupts masked:
+// those that are only called from critical sections, directly or through other such functions
+pub(crate) fn critical_sections(
+    g: &Graph<Node, usize>,
+    interner: &Interner,
+    masking: &BTreeSet<NodeIndex>,
+    masked_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) {
+    for function in masking {
+        let mut callees = masked_calls
+            .iter()
+            .filter(|(caller, _)| caller == function)
+            .map(|(_, callee)| format!("`{}`", interner.demangled(g[*callee].name)))
+            .collect::<Vec<_>>();
+        callees.sort();
+        callees.dedup();
+
+        if callees.is_empty() {
+            eprintln!(
+                "critical section: `{}` masks interrupts",
+                interner.demangled(g[*function].name)
+            );
+        } else {
+            eprintln!(
+                "critical section: `{}` masks interrupts; calls {} with interrupts masked",
+                interner.demangled(g[*function].name),
+                callees.join(", ")
+            );
+        }
+    }
+
+    // the functions that can be reached from the roots without going through a masked call
+    let roots = uncalled(g);
+    let reachable = |follow: &dyn Fn(NodeIndex, NodeIndex) -> bool| {
+        let mut seen = roots.iter().copied().collect::<HashSet<_>>();
+        let mut stack = roots.clone();
+        while let Some(caller) = stack.pop() {
+            for callee in g.neighbors(caller) {
+                if follow(caller, callee) && seen.insert(callee) {
+                    stack.push(callee);
+                }
+            }
+        }
+        seen
+    };
+    let unmasked = reachable(&|caller, callee| !masked_calls.contains(&(caller, callee)));
+    let masked = reachable(&|_, _| true)
+        .into_iter()
+        .filter(|idx| !unmasked.contains(idx))
+        .collect::<Vec<_>>();
+
+    let mut masked = masked
+        .into_iter()
+        .map(|idx| interner.demangled(g[idx].name).to_string())
+        .collect::<Vec<_>>();
+    masked.sort();
+    for function in masked {
+        eprintln!(
+            "critical section: `{}` only runs with interrupts masked",
+            function
+        );
+    }
+}
+
+// the local stack usage that LLVM reported for `function` doesn't match the machine code analysis
+pub(crate) struct Mismatch {
+    pub(crate) function: NodeIndex,
+    pub(crate) llvm: u64,
+    pub(crate) machine_code: u64,
+}
+
+// applies the `--on-mismatch` policy
+pub(crate) fn resolve_mismatches(
+    g: &mut Graph<Node, usize>,
+    interner: &Interner,
+    mismatches: &[Mismatch],
+    options: &Options,
+) -> anyhow::Result<()> {
+    for mismatch in mismatches {
+        let name = interner.demangled(g[mismatch.function].name);
+        let diagnostic = format!(
+            "LLVM reported that `{}` uses {} bytes of stack but the machine code uses {} bytes",
+            name, mismatch.llvm, mismatch.machine_code
+        );
+        match options.on_mismatch {
+            OnMismatch::Max => warn!("{}; using the larger result", diagnostic),
+            OnMismatch::Error => error!("{}", diagnostic),
+            OnMismatch::Warn => warn!("{}; using LLVM's result", diagnostic),
+            OnMismatch::PreferElf | OnMismatch::PreferLlvm if options.verbose => {
+                eprintln!("mismatch: {}", diagnostic)
+            }
+            OnMismatch::PreferElf | OnMismatch::PreferLlvm => {}
+        }
+
+        let prefer_elf = match options.on_mismatch {
+            OnMismatch::Max => mismatch.machine_code > mismatch.llvm,
+            OnMismatch::PreferElf => true,
+            OnMismatch::Error | OnMismatch::Warn | OnMismatch::PreferLlvm => false,
+        };
+        if prefer_elf {
+            let node = &mut g[mismatch.function];
+            node.local = Local::Exact(mismatch.machine_code);
+            node.source = Source::Corrected {
+                llvm: mismatch.llvm,
+            };
+        }
+    }
+
+    if options.on_mismatch == OnMismatch::Error {
+        bail!(
+            "the stack usage reported by LLVM doesn't match the machine code analysis for {} \
+             function(s); use --on-mismatch to pick a result",
+            mismatches.len()
+        );
+    }
+
+    Ok(())
+}
+
+// a function that contains inline assembly
+pub(crate) struct InlineAsm {
+    // the first `asm!` statement in the function
+    pub(crate) first: String,
+    // the number of `asm!` statements in the function
+    pub(crate) count: usize,
+    pub(crate) verdict: AsmVerdict,
+}
+
+// whether the machine code analysis confirmed the assumption that inline assembly doesn't use the
+// stack
+#[derive(Clone, Copy)]
+pub(crate) enum AsmVerdict {
+    Confirmed,
+    Corrected { llvm: u64, actual: u64 },
+    // the machine code was not analyzed (e.g. not an ARM Cortex-M target) or the analysis gave up
+    Unverified,
+}
+
+#[derive(Clone)]
+pub(crate) struct Node {
+    pub(crate) name: Symbol,
+    pub(crate) local: Local,
+    pub(crate) max: Option<Max>,
+    pub(crate) dashed: bool,
+    // the local stack usage was provided by the user
+    pub(crate) overridden: bool,
+    // (fictitious nodes only) the number of functions the indirect call may reach
+    pub(crate) callees: Option<usize>,
+    // the `--budget` that applies to this function
+    pub(crate) budget: Option<Budget>,
+    // the `--classify` class of this function
+    pub(crate) class: Option<Symbol>,
+    // where `local` comes from
+    pub(crate) source: Source,
+    // (`--disassembly`) the file that contains the disassembly of this function
+    pub(crate) disassembly: Option<Symbol>,
+    // the function implements one of the `core::fmt` traits, e.g. `Display::fmt`
+    pub(crate) formatter: bool,
+}
+
+#[allow(non_snake_case)]
+pub(crate) fn Node(name: Symbol, stack: Option<u64>, dashed: bool) -> Node {
+    Node {
+        name,
+        local: stack.map(Local::Exact).unwrap_or(Local::Unknown),
+        max: None,
+        dashed,
+        overridden: false,
+        callees: None,
+        budget: None,
+        class: None,
+        disassembly: None,
+        formatter: false,
+        source: match stack {
+            // fictitious nodes perform no work of their own
+            Some(_) if dashed => Source::Assumed,
+            Some(_) => Source::Llvm,
+            None => Source::Unknown,
+        },
+    }
+}
+
+/// The provenance of the local stack usage of a function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Source {
+    // the `.stack_sizes` section that LLVM emits (`-Z emit-stack-sizes`)
+    Llvm,
+    // the analysis of the machine code; LLVM reported nothing
+    MachineCode,
+    // the analysis of the machine code replaced the value that LLVM reported, e.g. because LLVM
+    // ignores inline assembly
+    Corrected { llvm: u64 },
+    // `--override-stack`
+    Override,
+    // the `--config` file
+    Config,
+    // the analysis of the WebAssembly bytecode
+    Bytecode,
+    // not measured, e.g. the fictitious nodes of indirect function calls
+    Assumed,
+    Unknown,
+}
+
+impl Source {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Source::Llvm => "llvm",
+            Source::MachineCode => "machine_code",
+            Source::Corrected { .. } => "corrected",
+            Source::Override => "override",
+            Source::Config => "config",
+            Source::Bytecode => "bytecode",
+            Source::Assumed => "assumed",
+            Source::Unknown => "unknown",
+        }
+    }
+}
+
+/// Local stack usage
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Local {
+    Exact(u64),
+    Unknown,
+}
+
+impl fmt::Display for Local {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Local::Exact(n) => write!(f, "{}", n),
+            Local::Unknown => f.write_str("?"),
+        }
+    }
+}
+
+impl From<Local> for Max {
+    fn from(local: Local) -> Self {
+        match local {
+            Local::Exact(n) => Max::Exact(n),
+            Local::Unknown => Max::LowerBound(0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Max {
+    Exact(u64),
+    LowerBound(u64),
+}
+
+impl ops::Add<Local> for Max {
+    type Output = Max;
+
+    fn add(self, rhs: Local) -> Max {
+        match (self, rhs) {
+            (Max::Exact(lhs), Local::Exact(rhs)) => Max::Exact(lhs + rhs),
+            (Max::Exact(lhs), Local::Unknown) => Max::LowerBound(lhs),
+            (Max::LowerBound(lhs), Local::Exact(rhs)) => Max::LowerBound(lhs + rhs),
+            (Max::LowerBound(lhs), Local::Unknown) => Max::LowerBound(lhs),
+        }
+    }
+}
+
+impl ops::Add<Max> for Max {
+    type Output = Max;
+
+    fn add(self, rhs: Max) -> Max {
+        match (self, rhs) {
+            (Max::Exact(lhs), Max::Exact(rhs)) => Max::Exact(lhs + rhs),
+            (Max::Exact(lhs), Max::LowerBound(rhs)) => Max::LowerBound(lhs + rhs),
+            (Max::LowerBound(lhs), Max::Exact(rhs)) => Max::LowerBound(lhs + rhs),
+            (Max::LowerBound(lhs), Max::LowerBound(rhs)) => Max::LowerBound(lhs + rhs),
+        }
+    }
+}
+
+impl Max {
+    // the number of bytes, disregarding whether it's exact or a lower bound
+    pub(crate) fn bytes(self) -> u64 {
+        match self {
+            Max::Exact(n) | Max::LowerBound(n) => n,
+        }
+    }
+}
+
+pub(crate) fn max_of(mut iter: impl Iterator<Item = Max>) -> Option<Max> {
+    iter.next().map(|first| iter.fold(first, max))
+}
+
+pub(crate) fn max(lhs: Max, rhs: Max) -> Max {
+    match (lhs, rhs) {
+        (Max::Exact(lhs), Max::Exact(rhs)) => Max::Exact(cmp::max(lhs, rhs)),
+        (Max::Exact(lhs), Max::LowerBound(rhs)) => Max::LowerBound(cmp::max(lhs, rhs)),
+        (Max::LowerBound(lhs), Max::Exact(rhs)) => Max::LowerBound(cmp::max(lhs, rhs)),
+        (Max::LowerBound(lhs), Max::LowerBound(rhs)) => Max::LowerBound(cmp::max(lhs, rhs)),
+    }
+}
+
+impl fmt::Display for Max {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Max::Exact(n) => write!(f, "= {}", n),
+            Max::LowerBound(n) => write!(f, ">= {}", n),
+        }
+    }
+}
+
+/// Stack budget of a function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Budget {
+    pub(crate) bytes: u64,
+    pub(crate) status: Status,
+}
+
+/// How the max stack usage of a function compares to its budget; ordered from best to worst
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Status {
+    Ok,
+    // above `--budget-warning` percent of the budget, or a lower bound that's within the budget
+    Near,
+    Over,
+}
+
+impl Budget {
+    pub(crate) fn new(max: Max, bytes: u64, warning: u64) -> Budget {
+        let status = match max {
+            Max::Exact(n) | Max::LowerBound(n) if n > bytes => Status::Over,
+            Max::Exact(n) if u128::from(n) * 100 <= u128::from(bytes) * u128::from(warning) => {
+                Status::Ok
+            }
+            _ => Status::Near,
+        };
+
+        Budget { bytes, status }
+    }
+
+    // e.g. `[OVER LIMIT: budget 4096]`; `color` highlights the status with ANSI escape codes
+    pub(crate) fn marker(&self, color: bool) -> String {
+        let (status, code) = match self.status {
+            Status::Ok => ("OK", 32),
+            Status::Near => ("NEAR LIMIT", 33),
+            Status::Over => ("OVER LIMIT", 31),
+        };
+
+        if color {
+            format!("[\x1b[{}m{}\x1b[0m: budget {}]", code, status, self.bytes)
+        } else {
+            format!("[{}: budget {}]", status, self.bytes)
+        }
+    }
+}
+
+// removes the version string (`@@GLIBC_2.2.5`, `@GLIBC_2.2.5`) of an undefined symbol, if it has one
+pub(crate) fn unversioned(sym: &str) -> &str {
+    match sym.rsplit_once('@') {
+        Some((name, version))
+            if !version.is_empty()
+                && version
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') =>
+        {
+            let name = name.strip_suffix('@').unwrap_or(name);
+            if name.is_empty() {
+                sym
+            } else {
+                name
+            }
+        }
+        _ => sym,
+    }
+}
+
+// a strongly connected component of the call graph
+pub(crate) struct Cycle {
+    pub(crate) members: Vec<NodeIndex>,
+    // combined local stack usage of the members of the cycle
+    pub(crate) local: Max,
+}
+
+// (ARM Cortex-M only) the functions installed in the vector table
+pub(crate) struct Vectors {
+    pub(crate) reset: NodeIndex,
+    // the exception and interrupt handlers, which can preempt the program at any point
+    pub(crate) handlers: Vec<NodeIndex>,
+    // the size of the exception frame
+    pub(crate) frame: u64,
+}
+
+impl Vectors {
+    pub(crate) fn new(
+        table: &[u64],
+        addr2node: &BTreeMap<u64, (NodeIndex, u64)>,
+        frame: u64,
+    ) -> Option<Vectors> {
+        // the first entry is the initial value of the stack pointer
+        let reset = function_at(addr2node, *table.get(1)?)?;
+        let mut handlers = table
+            .iter()
+            .skip(2)
+            .filter(|address| **address != 0)
+            .filter_map(|address| function_at(addr2node, *address))
+            .filter(|handler| *handler != reset)
+            .collect::<Vec<_>>();
+        handlers.sort();
+        handlers.dedup();
+
+        Some(Vectors {
+            reset,
+            handlers,
+            frame,
+        })
+    }
+
+    // `None` if the reset handler or all the handlers were removed from the graph
+    pub(crate) fn remap(self, old2new: &BTreeMap<NodeIndex, NodeIndex>) -> Option<Vectors> {
+        let handlers = self
+            .handlers
+            .iter()
+            .filter_map(|handler| old2new.get(handler).cloned())
+            .collect::<Vec<_>>();
+        if handlers.is_empty() {
+            return None;
+        }
+
+        Some(Vectors {
+            reset: *old2new.get(&self.reset)?,
+            handlers,
+            frame: self.frame,
+        })
+    }
+}
+
+// the calls that the function that contains the main loop performs before and within the loop
+pub(crate) struct MainLoop {
+    // the function passed to `--main-loop` followed by the trampolines that lead to the function
+    // that contains the loop; all their frames are on the stack while the loop runs
+    pub(crate) frames: Vec<NodeIndex>,
+    pub(crate) init: Vec<NodeIndex>,
+    pub(crate) steady: Vec<NodeIndex>,
+}
+
+impl MainLoop {
+    pub(crate) fn remap(self, old2new: &BTreeMap<NodeIndex, NodeIndex>) -> Option<MainLoop> {
+        let remap = |indices: Vec<NodeIndex>| {
+            indices
+                .iter()
+                .map(|idx| old2new.get(idx).cloned())
+                .collect::<Option<Vec<_>>>()
+        };
+
+        Some(MainLoop {
+            frames: remap(self.frames)?,
+            init: remap(self.init)?,
+            steady: remap(self.steady)?,
+        })
+    }
+}
+
+// used to track indirect function calls (`fn` pointers)
+#[derive(Default, Debug)]
+pub(crate) struct Indirect {
+    pub(crate) called: bool,
+    // caller -> number of call sites
+    pub(crate) callers: HashMap<NodeIndex, usize>,
+    pub(crate) callees: HashSet<NodeIndex>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Target {
+    Other,
+    Thumbv6m,
+    Thumbv7m,
+}
+
+impl Target {
+    pub(crate) fn is_thumb(&self) -> bool {
+        match *self {
+            Target::Thumbv6m | Target::Thumbv7m => true,
+            Target::Other => false,
+        }
+    }
+}
+
+// the number of bytes that a call instruction pushes onto the stack; on most targets the return
+// address is passed in a register and saved, if needed, by the callee as part of its frame
+pub(crate) fn return_address_size(target: &str) -> u64 {
+    let arch = target.split('-').next().unwrap_or(target);
+    match arch {
+        "x86_64" => 8,
+        "i386" | "i586" | "i686" => 4,
+        // the ATmega2560 and ATmega2561 have a 22-bit program counter
+        "avr" if target.contains("atmega256") => 3,
+        "avr" | "msp430" => 2,
+        _ => 0,
+    }
+}
+
+// (ARM Cortex-M only) the worst-case number of bytes that the hardware pushes onto the stack on
+// exception entry: 8 registers or, with an FPU, 26 words that include the floating point
+// registers, plus 4 bytes of padding that keep the stack 8-byte aligned
+pub(crate) fn exception_frame_size(target: &str) -> u64 {
+    let words = if target.ends_with("eabihf") { 26 } else { 8 };
+    words * 4 + 4
+}
+
+// LLVM's function outliner pass produces symbols of the form `OUTLINED_FUNCTION_NNN` where `NNN` is
+// a monotonically increasing number
+pub(crate) fn is_outlined_function(name: &str) -> bool {
+    if let Some(number) = name.strip_prefix("OUTLINED_FUNCTION_") {
+        number.parse::<u64>().is_ok()
+    } else {
+        false
+    }
+}
+
+// whether an indirect call with signature `sig` may reach a function without type information,
+// e.g. one linked in from a C binary blob. C code can't implement the methods of Rust trait
+// objects, whose receiver is `{}*`, nor the formatters that `ArgumentV1.formatter` calls
+pub(crate) fn may_reach_untyped(sig: &FnSig) -> bool {
+    match sig.inputs.first() {
+        Some(Type::Pointer(receiver)) => match &**receiver {
+            Type::Struct(fields) => !fields.is_empty(),
+            Type::Alias(alias) => *alias != "fmt::Void",
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+// whether `heuristic` makes the function `name` (demangled, without hash), whose signature is
+// `sig`, a callee of the indirect calls of the formatting machinery: `fn(&_, &mut fmt::Formatter)
+// -> fmt::Result`
+pub(crate) fn is_fmt_callee(heuristic: FmtHeuristic, name: &str, sig: &FnSig) -> bool {
+    match (&sig.inputs[..], sig.output.as_ref()) {
+        ([Type::Pointer(..), Type::Pointer(fmt)], Some(output))
+            if **fmt == Type::Alias("core::fmt::Formatter") && **output == Type::Integer(1) =>
+        {
+            match heuristic {
+                FmtHeuristic::Loose => true,
+                FmtHeuristic::Strict => is_formatter(name),
+                FmtHeuristic::Off => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// whether `name` (demangled, without hash) is a method of a `core::fmt` trait, e.g.
+// `<T as core::fmt::Debug>::fmt` or `core::fmt::num::<impl core::fmt::Display for u32>::fmt`
+pub(crate) fn is_formatter(name: &str) -> bool {
+    name.ends_with(">::fmt")
+        && (name.contains(" as core::fmt::") || name.contains("<impl core::fmt::"))
+}
+
+// untyped functions that are never called through function pointers, so they don't make indirect
+// function calls unbounded
+pub(crate) fn is_never_called_indirectly(name: &str) -> bool {
+    // functions produced by LLVM's function outliner (as of LLVM 14.0.6)
+    is_outlined_function(name)
+        // the allocator shims and implementations, which Rust code calls by name
+        || allocator_symbol(name).is_some()
+        // thunks and veneers inserted by the linker
+        || veneer_target(name).is_some()
+}
+
+// the function that a thunk or veneer inserted by the linker to reach a far away function, or to
+// switch between ARM and Thumb state, jumps to, e.g. `foo` for `__foo_veneer` and
+// `__foo_from_thumb` (GNU ld) or for `__ThumbV7PILongThunk_foo` (LLD)
+pub(crate) fn veneer_target(name: &str) -> Option<&str> {
+    let name = name.strip_prefix("__")?;
+    if let Some((kind, target)) = name.split_once("Thunk_") {
+        if !target.is_empty() && kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Some(target);
+        }
+    }
+
+    ["_veneer", "_from_arm", "_from_thumb", "_change_to_arm"]
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .filter(|target| !target.is_empty())
+}
+
+// the symbols of the Rust allocator: the shims that Rust code calls (`__rust_alloc` & co.), the
+// functions that the `#[global_allocator]` attribute generates (`__rg_alloc` & co.) and the default
+// allocator of `std` (`__rdl_alloc` & co.). Returns the prefix and the operation, where
+// `__rust_alloc_error_handler` is the `oom` operation. Newer toolchains mangle these names, e.g.
+// `_RNvCs1234_7___rustc12___rust_alloc`
+pub(crate) fn allocator_symbol(name: &str) -> Option<(&'static str, &str)> {
+    let name = if name.starts_with("_R") {
+        // length-prefixed identifiers that start with `_` are separated from their length by `_`
+        name.split_once("7___rustc")?
+            .1
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .strip_prefix('_')?
+    } else {
+        name
+    };
+
+    ["__rust_", "__rg_", "__rdl_"].iter().find_map(|prefix| {
+        let op = match name.strip_prefix(prefix)? {
+            "alloc_error_handler" if *prefix == "__rust_" => "oom",
+            "oom" if *prefix != "__rust_" => "oom",
+            op @ ("alloc" | "dealloc" | "realloc" | "alloc_zeroed") => op,
+            _ => return None,
+        };
+        Some((*prefix, op))
+    })
+}
+
+// the operation of a `GlobalAlloc` implementation, e.g. `alloc` for
+// `<allocator::Bump as core::alloc::global::GlobalAlloc>::alloc`; `name` is demangled and without
+// hash
+pub(crate) fn global_alloc_op(name: &str) -> Option<&str> {
+    let (_, op) = name
+        .strip_prefix('<')?
+        .rsplit_once(" as core::alloc::global::GlobalAlloc>::")?;
+    Some(op)
+}
+
+// builds the call graph of a WebAssembly module
+pub(crate) fn wasm<'a>(inputs: &'a Inputs, options: &Options) -> anyhow::Result<Program<'a>> {
+    let path = &inputs.path;
+    let module = wasm::analyze(&inputs.elf)
+        .map_err(|e| anyhow!("failed to analyze `{}`: {}", path.display(), e))?;
+
+    let mut g = DiGraph::<Node, usize>::new();
+    let mut indices = BTreeMap::<Symbol, _>::new();
+    let mut interner = Interner::new();
+
+    // add all real nodes
+    let mut has_stack_usage_info = false;
+    let mut nodes = vec![];
+    for (i, function) in module.functions.iter().enumerate() {
+        let name: Cow<str> = match function.name {
+            Some(name) => name.into(),
+            // stripped modules have no "name" section
+            None => format!("wasm-function[{}]", i).into(),
+        };
+
+        if let Some(e) = &function.error {
+            warn!(
+                "couldn't decode the body of `{}`: {}; its stack usage and callees are unknown",
+                name, e
+            );
+        } else if function.stack.is_some() {
+            has_stack_usage_info = true;
+        } else if !function.imported && !options.is_quiet(&name) {
+            warn!("no stack usage information for `{}`", name);
+        }
+
+        let sym = interner.intern(name.clone());
+        let idx = g.add_node(Node(sym, function.stack, false));
+        if function.stack.is_some() {
+            g[idx].source = Source::Bytecode;
+        }
+        indices.insert(sym, idx);
+        nodes.push(idx);
+    }
+
+    // add edges
+    let mut indirects = HashMap::new();
+    for (function, &caller) in module.functions.iter().zip(&nodes) {
+        for callee in &function.calls {
+            let callee = *nodes
+                .get(*callee as usize)
+                .ok_or_else(|| anyhow!("call to undefined function {}", callee))?;
+            add_call(&mut g, caller, callee);
+        }
+
+        for ty in &function.indirect_calls {
+            let sig = module
+                .types
+                .get(*ty as usize)
+                .ok_or_else(|| anyhow!("call_indirect uses undefined type {}", ty))?;
+
+            // functions pointers with the same signature share a fictitious node
+            let call = *indirects.entry(sig).or_insert_with(|| {
+                let mut name = sig.to_string();
+                // append '*' to denote that this is a function pointer
+                name.push('*');
+                g.add_node(Node(interner.intern(name), Some(0), true))
+            });
+
+            add_call(&mut g, caller, call);
+        }
+    }
+
+    // `call_indirect` can only reach the functions in the table whose type matches the type used
+    // in the instruction
+    for (sig, call) in &indirects {
+        let callees = module
+            .table
+            .iter()
+            .filter_map(|index| {
+                let function = module.functions.get(*index as usize)?;
+                if module.types.get(function.ty as usize) == Some(*sig) {
+                    Some(nodes[*index as usize])
+                } else {
+                    None
+                }
+            })
+            .collect::<BTreeSet<_>>();
+
+        if callees.is_empty() {
+            warn!("no function in the table has type `{}`", sig);
+        }
+        g[*call].callees = Some(callees.len());
+
+        for callee in callees {
+            g.add_edge(*call, callee, 1);
+        }
+    }
+
+    let mut roots = module
+        .exports
+        .iter()
+        .filter_map(|(_, index)| nodes.get(*index as usize).cloned())
+        .collect::<Vec<_>>();
+    roots.sort();
+    roots.dedup();
+
+    if options.main_loop.is_some() {
+        warn!("--main-loop: only the ARM Cortex-M targets are supported");
+    }
+    if options.tail_calls {
+        warn!("--tail-calls: only the ARM Cortex-M targets are supported");
+    }
+    if !options.secondary_stack.is_empty()
+        || options
+            .config
+            .as_ref()
+            .is_some_and(|config| !config.secondary_stacks.is_empty())
+    {
+        warn!("--secondary-stack: only ELF files are supported");
+    }
+
+    Ok(Program {
+        g,
+        indices,
+        interner,
+        roots,
+        has_stack_usage_info,
+        locations: HashMap::new(),
+        stale: HashSet::new(),
+        tail_calls: HashSet::new(),
+        masked_calls: HashSet::new(),
+        main_loop: None,
+        vectors: None,
+        secondary_stacks: vec![],
+        return_address_size: 0,
+        target: Target::Other,
+        addr2node: BTreeMap::new(),
+        elf: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::ir::{FnSig, Type};
+
+    #[test]
+    fn is_never_called_indirectly() {
+        assert!(super::is_never_called_indirectly("OUTLINED_FUNCTION_0"));
+        assert!(super::is_never_called_indirectly("__memcpy_veneer"));
+        assert!(super::is_never_called_indirectly("__main_from_thumb"));
+        assert!(super::is_never_called_indirectly(
+            "__ThumbV7PILongThunk_ramfunc"
+        ));
+        assert!(super::is_never_called_indirectly(
+            "__AArch64ADRPThunk_callback"
+        ));
+
+        assert!(!super::is_never_called_indirectly("vendor_callback"));
+        assert!(!super::is_never_called_indirectly("__aeabi_memcpy"));
+        assert!(!super::is_never_called_indirectly("OUTLINED_FUNCTION_"));
+    }
+
+    #[test]
+    fn function_at() {
+        let mut addr2node = BTreeMap::new();
+        addr2node.insert(0x101, ("foo", 8));
+        addr2node.insert(0x108, ("bar", 4));
+        addr2node.insert(0x200, ("baz", 0));
+
+        // thumb bit set or clear
+        assert_eq!(super::function_at(&addr2node, 0x101), Some("foo"));
+        assert_eq!(super::function_at(&addr2node, 0x100), Some("foo"));
+        assert_eq!(super::function_at(&addr2node, 0x109), Some("bar"));
+        assert_eq!(super::function_at(&addr2node, 0x201), Some("baz"));
+
+        // within the body of a function
+        assert_eq!(super::function_at(&addr2node, 0x104), Some("foo"));
+        assert_eq!(super::function_at(&addr2node, 0x10a), Some("bar"));
+
+        // past the end of a function
+        assert_eq!(super::function_at(&addr2node, 0x10c), None);
+        assert_eq!(super::function_at(&addr2node, 0x202), None);
+        assert_eq!(super::function_at(&addr2node, 0x80), None);
+    }
+
+    #[test]
+    fn unknown_code() {
+        use petgraph::graph::DiGraph;
+
+        use crate::intern::Interner;
+
+        let mut interner = Interner::new();
+        let mut g = DiGraph::new();
+        let mut indices = BTreeMap::new();
+
+        let a = super::unknown_code(0x0800_0100, &mut g, &mut indices, &mut interner);
+        let b = super::unknown_code(0x0800_0200, &mut g, &mut indices, &mut interner);
+        assert_ne!(a, b);
+        assert_eq!(interner.name(g[a].name), "?@0x08000100");
+        // the stack usage of the code is unknown
+        assert!(matches!(g[a].local, super::Local::Unknown));
+
+        // branches to the same address share the node
+        assert_eq!(
+            super::unknown_code(0x0800_0100, &mut g, &mut indices, &mut interner),
+            a
+        );
+        assert_eq!(g.node_count(), 2);
+    }
+
+    #[test]
+    fn veneer_target() {
+        // LLD
+        assert_eq!(
+            super::veneer_target("__Thumbv7ABSLongThunk__ZN7ramfunc7ramfunc17h9b0d6482bf54d8a1E"),
+            Some("_ZN7ramfunc7ramfunc17h9b0d6482bf54d8a1E")
+        );
+        assert_eq!(
+            super::veneer_target("__ThumbV7PILongThunk_ramfunc"),
+            Some("ramfunc")
+        );
+        assert_eq!(
+            super::veneer_target("__AArch64ADRPThunk_callback"),
+            Some("callback")
+        );
+
+        // GNU ld
+        assert_eq!(super::veneer_target("__memcpy_veneer"), Some("memcpy"));
+        assert_eq!(super::veneer_target("__main_from_thumb"), Some("main"));
+        assert_eq!(
+            super::veneer_target("___ZN3foo3bar17h0123456789abcdefE_veneer"),
+            Some("_ZN3foo3bar17h0123456789abcdefE")
+        );
+
+        assert_eq!(super::veneer_target("__aeabi_memcpy"), None);
+        assert_eq!(super::veneer_target("_veneer"), None);
+        assert_eq!(super::veneer_target("__veneer"), None);
+        assert_eq!(super::veneer_target("__my::Thunk_impl"), None);
+    }
+
+    #[test]
+    fn add_ir_aliases() {
+        let ir_aliases = [("__pre_init", "DefaultPreInit"), ("pre_init", "__pre_init")];
+
+        // the linker only kept the alias
+        let mut names = vec!["__pre_init"];
+        super::add_ir_aliases(
+            &mut names,
+            &ir_aliases,
+            &["__pre_init"].iter().copied().collect(),
+        );
+        assert_eq!(names, ["__pre_init", "DefaultPreInit", "pre_init"]);
+
+        // `DefaultPreInit` is a different function in the ELF
+        let mut names = vec!["__pre_init"];
+        let elf_names = ["__pre_init", "DefaultPreInit"].iter().copied().collect();
+        super::add_ir_aliases(&mut names, &ir_aliases, &elf_names);
+        assert_eq!(names, ["__pre_init", "pre_init"]);
+    }
+
+    #[test]
+    fn missing_functions() {
+        let ll = "define void @foo() {\nstart:\n  ret void\n}\n\n\
+                  define void @bar() {\nstart:\n  ret void\n}\n\n\
+                  declare void @baz()\n";
+        let items = crate::ir::parse(ll).unwrap();
+
+        // the object file may contain functions that are not in the LLVM IR
+        let obj_functions = ["foo", "bar", "OUTLINED_FUNCTION_0"]
+            .iter()
+            .copied()
+            .collect();
+        assert!(super::missing_functions(&items, &obj_functions).is_empty());
+
+        // `bar` was added to the source code after the object file was produced
+        let obj_functions = ["foo", "baz"].iter().copied().collect();
+        assert_eq!(super::missing_functions(&items, &obj_functions), ["bar"]);
+    }
+
+    #[test]
+    fn allocator_symbol() {
+        use super::allocator_symbol;
+
+        assert_eq!(allocator_symbol("__rust_alloc"), Some(("__rust_", "alloc")));
+        assert_eq!(
+            allocator_symbol("__rust_alloc_error_handler"),
+            Some(("__rust_", "oom"))
+        );
+        assert_eq!(allocator_symbol("__rg_realloc"), Some(("__rg_", "realloc")));
+        assert_eq!(allocator_symbol("__rdl_oom"), Some(("__rdl_", "oom")));
+        assert_eq!(
+            allocator_symbol("_RNvCs1234_7___rustc19___rust_alloc_zeroed"),
+            Some(("__rust_", "alloc_zeroed"))
+        );
+
+        assert_eq!(allocator_symbol("__rust_no_alloc_shim_is_unstable"), None);
+        assert_eq!(allocator_symbol("__rust_oom"), None);
+        assert_eq!(allocator_symbol("__rust_allocate"), None);
+    }
+
+    #[test]
+    fn global_alloc_op() {
+        use crate::intern::Interner;
+
+        let mut interner = Interner::new();
+        let mut op = |name| {
+            let name = interner.intern(name);
+            super::global_alloc_op(interner.short(name)).map(str::to_owned)
+        };
+
+        // `#[global_allocator]` on toolchains that don't emit `__rg_alloc` & co.
+        assert_eq!(
+            op("_ZN68_$LT$allocator..Bump$u20$as$u20$core..alloc..global..GlobalAlloc$GT$5alloc17h0877dac0c9269468E")
+                .as_deref(),
+            Some("alloc")
+        );
+        assert_eq!(
+            op("_ZN68_$LT$allocator..Bump$u20$as$u20$core..alloc..global..GlobalAlloc$GT$7dealloc17h5d1f3a2b4c6e8f90E")
+                .as_deref(),
+            Some("dealloc")
+        );
+
+        assert_eq!(
+            op("_ZN58_$LT$allocator..Bump$u20$as$u20$core..alloc..Allocator$GT$8allocate17h0123456789abcdefE"),
+            None
+        );
+        assert_eq!(op("__rust_alloc"), None);
+
+        // the hash is not part of the operation
+        let dealloc = interner.intern(
+            "_ZN68_$LT$allocator..Bump$u20$as$u20$core..alloc..global..GlobalAlloc$GT$7dealloc17h5d1f3a2b4c6e8f90E",
+        );
+        assert!(interner
+            .demangled(dealloc)
+            .ends_with("::dealloc::h5d1f3a2b4c6e8f90"));
+    }
+
+    #[test]
+    fn may_reach_untyped() {
+        let sig = |receiver| FnSig {
+            inputs: vec![Type::Pointer(Box::new(receiver)), Type::Integer(32)],
+            output: Some(Box::new(Type::Integer(1))),
+        };
+
+        // trait objects and `ArgumentV1.formatter`
+        assert!(!super::may_reach_untyped(&sig(Type::Struct(vec![]))));
+        assert!(!super::may_reach_untyped(&sig(Type::Alias("fmt::Void"))));
+
+        assert!(super::may_reach_untyped(&sig(Type::Integer(8))));
+        assert!(super::may_reach_untyped(&sig(Type::Alias("app::State"))));
+        assert!(super::may_reach_untyped(&FnSig {
+            inputs: vec![],
+            output: None,
+        }));
+    }
+
+    #[test]
+    fn unversioned() {
+        assert_eq!(super::unversioned("memcpy@@GLIBC_2.14"), "memcpy");
+        assert_eq!(super::unversioned("memcpy@GLIBC_2.2.5"), "memcpy");
+        assert_eq!(super::unversioned("memcpy"), "memcpy");
+        // not a version string
+        assert_eq!(super::unversioned("foo@bar@GLIBC_2.2.5"), "foo@bar");
+        assert_eq!(super::unversioned("foo@<bar>"), "foo@<bar>");
+        assert_eq!(super::unversioned("@GLIBC_2.2.5"), "@GLIBC_2.2.5");
+    }
+
+    #[test]
+    fn is_fmt_callee() {
+        use super::FmtHeuristic;
+
+        let sig = FnSig {
+            inputs: vec![
+                Type::Pointer(Box::new(Type::Alias("app::Config"))),
+                Type::Pointer(Box::new(Type::Alias("core::fmt::Formatter"))),
+            ],
+            output: Some(Box::new(Type::Integer(1))),
+        };
+        let display = "<app::Config as core::fmt::Display>::fmt";
+        let user = "app::Config::check";
+
+        assert!(super::is_fmt_callee(FmtHeuristic::Loose, display, &sig));
+        assert!(super::is_fmt_callee(FmtHeuristic::Loose, user, &sig));
+        assert!(super::is_fmt_callee(FmtHeuristic::Strict, display, &sig));
+        assert!(!super::is_fmt_callee(FmtHeuristic::Strict, user, &sig));
+        assert!(!super::is_fmt_callee(FmtHeuristic::Off, display, &sig));
+
+        // a different signature
+        let sig = FnSig {
+            output: None,
+            ..sig
+        };
+        assert!(!super::is_fmt_callee(FmtHeuristic::Loose, user, &sig));
+    }
+
+    #[test]
+    fn is_formatter() {
+        assert!(super::is_formatter("<&T as core::fmt::Display>::fmt"));
+        assert!(super::is_formatter(
+            "core::fmt::num::imp::<impl core::fmt::Display for u32>::fmt"
+        ));
+        assert!(!super::is_formatter("core::fmt::write"));
+        assert!(!super::is_formatter(
+            "<app::W as core::fmt::Write>::write_str"
+        ));
+    }
+
+    #[test]
+    fn vectors() {
+        use petgraph::graph::NodeIndex;
+
+        use super::Vectors;
+
+        let (reset, nmi, handler, default) = (
+            NodeIndex::new(0),
+            NodeIndex::new(1),
+            NodeIndex::new(2),
+            NodeIndex::new(3),
+        );
+        let addr2node = BTreeMap::from([
+            (0x100, (reset, 0x10)),
+            (0x110, (nmi, 0x10)),
+            (0x120, (handler, 0x10)),
+            (0x130, (default, 0x10)),
+        ]);
+
+        // initial SP, reset, NMI, unused entries, the handler and the default handler (installed
+        // twice); addresses have the thumb bit set
+        let table = [0x2000_4000, 0x101, 0x111, 0, 0, 0x121, 0x131, 0x131];
+        let vectors = Vectors::new(&table, &addr2node, 36).unwrap();
+        assert_eq!(vectors.reset, reset);
+        assert_eq!(vectors.handlers, [nmi, handler, default]);
+
+        assert!(Vectors::new(&[0x2000_4000], &addr2node, 36).is_none());
+
+        // the start point only reaches the handler
+        let old2new = BTreeMap::from([(reset, NodeIndex::new(0)), (handler, NodeIndex::new(1))]);
+        let vectors = vectors.remap(&old2new).unwrap();
+        assert_eq!(vectors.handlers, [NodeIndex::new(1)]);
+    }
+
+    #[test]
+    fn budget() {
+        use super::{Budget, Max, Status};
+
+        let status = |max, bytes| Budget::new(max, bytes, 80).status;
+
+        assert_eq!(status(Max::Exact(80), 100), Status::Ok);
+        assert_eq!(status(Max::Exact(81), 100), Status::Near);
+        assert_eq!(status(Max::Exact(100), 100), Status::Near);
+        assert_eq!(status(Max::Exact(101), 100), Status::Over);
+        // a lower bound is never known to be within the budget
+        assert_eq!(status(Max::LowerBound(0), 100), Status::Near);
+        assert_eq!(status(Max::LowerBound(101), 100), Status::Over);
+    }
+
+    #[test]
+    fn return_address_size() {
+        assert_eq!(super::return_address_size("x86_64-unknown-linux-gnu"), 8);
+        assert_eq!(super::return_address_size("i686-unknown-linux-gnu"), 4);
+        assert_eq!(super::return_address_size("thumbv7m-none-eabi"), 0);
+        assert_eq!(super::return_address_size("aarch64-unknown-none"), 0);
+        assert_eq!(super::return_address_size("avr-unknown-gnu-atmega328"), 2);
+        assert_eq!(super::return_address_size("avr-unknown-gnu-atmega2560"), 3);
+        assert_eq!(super::return_address_size("msp430-none-elf"), 2);
+    }
+}
@@ -0,0 +1,428 @@
+//! Reading the files of the analysis
+//!
+//! Everything the later stages need is read into memory up front. The call graph borrows the
+//! symbol names from these buffers, so they outlive it.
+
+use core::cell::OnceCell;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    iter,
+    path::{Path, PathBuf},
+    process::Command,
+    str,
+};
+
+use anyhow::{anyhow, bail};
+use ar::Archive;
+use log::warn;
+
+use crate::{
+    analysis::{AnalysisInput, Options},
+    bitcode, cache, elf,
+    ir::{self, Item},
+};
+
+// what `rustc --print=cfg` says about the target
+pub(crate) struct Cfg {
+    pub(crate) target: String,
+    pub(crate) is_no_std: bool,
+    pub(crate) arch: String,
+    pub(crate) abi: String,
+    pub(crate) pointer_width: usize,
+    pub(crate) features: HashSet<String>,
+}
+
+impl Cfg {
+    pub(crate) fn query(target: &str) -> anyhow::Result<Self> {
+        let mut cfg = Cfg {
+            target: target.to_owned(),
+            is_no_std: false,
+            arch: String::new(),
+            abi: String::new(),
+            pointer_width: 32,
+            features: HashSet::new(),
+        };
+
+        let output = Command::new("rustc")
+            .args(["--print=cfg", "--target", target])
+            .output()?;
+        for line in str::from_utf8(&output.stdout)?.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim_matches('"');
+                match key {
+                    "target_os" if value == "none" => cfg.is_no_std = true,
+                    "target_arch" => cfg.arch = value.to_owned(),
+                    "target_abi" => cfg.abi = value.to_owned(),
+                    "target_pointer_width" => cfg.pointer_width = value.parse()?,
+                    "target_feature" => {
+                        cfg.features.insert(value.to_owned());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(cfg)
+    }
+
+    pub(crate) fn is_wasm(&self) -> bool {
+        self.target.starts_with("wasm32-")
+    }
+}
+
+// the contents of the files of the analysis
+#[derive(Default)]
+pub(crate) struct Inputs {
+    // the ELF file (or WebAssembly module)
+    pub(crate) path: PathBuf,
+    pub(crate) elf: Vec<u8>,
+    pub(crate) ll_path: PathBuf,
+    pub(crate) ll: String,
+    pub(crate) obj_path: PathBuf,
+    pub(crate) obj: Vec<u8>,
+    // `compiler_builtins`' rlib; its LLVM IR is only read if the cache misses
+    pub(crate) compiler_builtins_rlib: Option<(PathBuf, Vec<u8>)>,
+    compiler_builtins_ll_path: Option<PathBuf>,
+    compiler_builtins_ll: OnceCell<String>,
+    cache_path: Option<PathBuf>,
+    cache: Option<String>,
+    pub(crate) extra_lls: Vec<(PathBuf, String)>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) extra_objs: Vec<(PathBuf, Vec<(String, Vec<u8>)>)>,
+    // the LLVM IR of the `extra_objs` that contain bitcode
+    pub(crate) bitcode_lls: Vec<(String, String)>,
+    pub(crate) symbol_map: String,
+    pub(crate) search_paths: Vec<PathBuf>,
+    // the static native libraries that were found in the search paths, and their members
+    #[allow(clippy::type_complexity)]
+    pub(crate) native_libs: Vec<(String, Option<(PathBuf, Vec<(String, Vec<u8>)>)>)>,
+    pub(crate) linker_scripts: Vec<(String, Option<PathBuf>)>,
+    pub(crate) wrapped_symbols: Vec<String>,
+}
+
+impl Inputs {
+    pub(crate) fn read(
+        input: &AnalysisInput,
+        cfg: &Cfg,
+        options: &Options,
+    ) -> anyhow::Result<Self> {
+        let path = input.elf.clone();
+
+        if cfg.is_wasm() {
+            // WebAssembly modules contain all the information we need; no need to look at LLVM IR
+            let elf = fs::read(&path).map_err(|e| {
+                anyhow!(
+                    "couldn't open WebAssembly module `{}`: {}",
+                    path.display(),
+                    e
+                )
+            })?;
+            return Ok(Inputs {
+                path,
+                elf,
+                ..Inputs::default()
+            });
+        }
+
+        let elf = fs::read(&path)
+            .map_err(|e| anyhow!("couldn't open ELF file `{}`: {}", path.display(), e))?;
+
+        let ll_path = input.ll.clone();
+        let obj_path = input
+            .obj
+            .clone()
+            .unwrap_or_else(|| ll_path.with_extension("o"));
+        let ll = fs::read_to_string(&ll_path)
+            .map_err(|e| anyhow!("couldn't read LLVM IR from `{}`: {}", ll_path.display(), e))?;
+        let obj = fs::read(&obj_path)
+            .map_err(|e| anyhow!("couldn't read object file `{}`: {}", obj_path.display(), e))?;
+
+        // the analysis of `compiler_builtins` is reused across runs
+        let compiler_builtins_rlib = input
+            .compiler_builtins
+            .as_ref()
+            .map(|compiler_builtins| {
+                let path = &compiler_builtins.rlib;
+                fs::read(path)
+                    .map(|bytes| (path.clone(), bytes))
+                    .map_err(|e| anyhow!("couldn't read `{}`: {}", path.display(), e))
+            })
+            .transpose()?;
+        // with `no_builtins_analysis` only the symbol table of the archive is needed
+        let compiler_builtins_ll_path = input
+            .compiler_builtins
+            .as_ref()
+            .and_then(|compiler_builtins| compiler_builtins.ll.clone())
+            .filter(|_| !options.no_builtins_analysis);
+        // hashing the archive is not free so it's skipped when the analysis is not going to be used
+        let cache_path = compiler_builtins_rlib
+            .as_ref()
+            .filter(|_| !options.no_builtins_analysis)
+            .map(|(path, bytes)| cache::path(path, bytes));
+        let cache = cache_path
+            .as_ref()
+            .and_then(|cache_path| fs::read_to_string(cache_path).ok());
+
+        // IR from other languages gives type information and call edges to the symbols that the
+        // Rust code links to
+        let extra_lls = input
+            .extra_ll
+            .iter()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .map(|ll| (path.clone(), ll))
+                    .map_err(|e| anyhow!("couldn't read LLVM IR from `{}`: {}", path.display(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let symbol_map = if let Some(path) = &input.symbol_map {
+            fs::read_to_string(path)
+                .map_err(|e| anyhow!("couldn't read symbol map `{}`: {}", path.display(), e))?
+        } else {
+            String::new()
+        };
+
+        // objects compiled with `-flto` contain LLVM bitcode instead of machine code; analyze them
+        // like the `extra_ll` files
+        let extra_objs = input
+            .extra_obj
+            .iter()
+            .map(|path| objects(path).map(|objects| (path.clone(), objects)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut llvm_dis = None;
+        let mut bitcode_lls = vec![];
+        for (name, obj) in extra_objs.iter().flat_map(|(_, objects)| objects) {
+            if bitcode::is_bitcode(obj) {
+                let llvm_dis =
+                    llvm_dis.get_or_insert_with(|| bitcode::llvm_dis(input.host.as_deref()));
+                let ll = bitcode::disassemble(llvm_dis, obj)
+                    .map_err(|e| anyhow!("couldn't disassemble `{}`: {}", name, e))?;
+                bitcode_lls.push((name.clone(), ll));
+            }
+        }
+
+        // the linker scripts and the native libraries of the final link are searched for in the
+        // `-L` directories and then in the current directory
+        let search = |file: &str| {
+            input
+                .search_paths
+                .iter()
+                .map(|dir| dir.join(file))
+                .chain(iter::once(PathBuf::from(file)))
+                .find(|path| path.is_file())
+        };
+
+        // static native libraries may have been compiled with `-fstack-size-section`
+        let mut native_libs = vec![];
+        for lib in &input.native_libs {
+            // `[KIND[:MODIFIERS]=]NAME[:RENAME]`
+            let (kind, name) = match lib.split_once('=') {
+                Some((kind, name)) => (kind.split(':').next().unwrap_or(kind), name),
+                None => ("", &**lib),
+            };
+            let name = name.split(':').next().unwrap_or(name);
+            let archive = match kind {
+                "" | "static" => search(&format!("lib{}.a", name)),
+                _ => None,
+            };
+
+            let archive = archive
+                .filter(|archive| !input.extra_obj.contains(archive))
+                .map(|archive| objects(&archive).map(|objects| (archive, objects)))
+                .transpose()?;
+            native_libs.push((lib.clone(), archive));
+        }
+
+        let linker_scripts = input
+            .linker_scripts
+            .iter()
+            .map(|script| (script.clone(), search(script)))
+            .collect();
+
+        Ok(Inputs {
+            path,
+            elf,
+            ll_path,
+            ll,
+            obj_path,
+            obj,
+            compiler_builtins_rlib,
+            compiler_builtins_ll_path,
+            compiler_builtins_ll: OnceCell::new(),
+            cache_path,
+            cache,
+            extra_lls,
+            extra_objs,
+            bitcode_lls,
+            symbol_map,
+            search_paths: input.search_paths.clone(),
+            native_libs,
+            linker_scripts,
+            wrapped_symbols: input.wrapped_symbols.clone(),
+        })
+    }
+
+    // the items and the stack usage information of `compiler_builtins`, from the cache if possible
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn compiler_builtins(
+        &self,
+    ) -> anyhow::Result<(Vec<Item<'_>>, HashMap<String, u64>)> {
+        if let Some(Ok(analysis)) = self.cache.as_deref().map(cache::deserialize) {
+            return Ok(analysis);
+        }
+
+        // there's no `compiler_builtins` to analyze or it's not analyzed
+        let (Some((rlib_path, rlib)), Some(ll_path)) = (
+            &self.compiler_builtins_rlib,
+            &self.compiler_builtins_ll_path,
+        ) else {
+            return Ok((vec![], HashMap::new()));
+        };
+
+        if self.compiler_builtins_ll.get().is_none() {
+            let ll = fs::read_to_string(ll_path).map_err(|e| {
+                anyhow!(
+                    "couldn't read `compiler_builtins` LLVM IR from `{}`: {}",
+                    ll_path.display(),
+                    e
+                )
+            })?;
+            let _ = self.compiler_builtins_ll.set(ll);
+        }
+        let ll = self.compiler_builtins_ll.get().expect("UNREACHABLE");
+        let items = ir::parse(ll).map_err(|e| {
+            anyhow!(
+                "failed to parse `compiler_builtins` LLVM IR from `{}`: {}",
+                ll_path.display(),
+                e
+            )
+        })?;
+
+        let mut stack_sizes = HashMap::new();
+        for (name, obj) in archive_members(rlib_path, rlib.clone())? {
+            if name.contains("compiler_builtins") && name.ends_with(".o)") {
+                // the symbols of Rust functions are unique
+                object_stack_sizes(&name, &obj, &mut stack_sizes, &mut HashMap::new())?;
+            }
+        }
+
+        let cache_path = self.cache_path.as_ref().expect("UNREACHABLE");
+        if let Some(contents) = cache::serialize(&items, &stack_sizes) {
+            if let Err(e) = fs::write(cache_path, contents) {
+                warn!("couldn't write `{}`: {}", cache_path.display(), e);
+            }
+        }
+
+        Ok((items, stack_sizes))
+    }
+}
+
+// parses a symbol map: one `ir_name -> elf_name` pair per line; empty lines and lines that start
+// with `#` are ignored
+pub(crate) fn parse_symbol_map(map: &str) -> anyhow::Result<Vec<(&str, &str)>> {
+    map.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let mut parts = line.splitn(2, "->").map(str::trim);
+
+            match (parts.next(), parts.next()) {
+                (Some(ir_name), Some(elf_name)) if !ir_name.is_empty() && !elf_name.is_empty() => {
+                    Ok((ir_name, elf_name))
+                }
+                _ => bail!("line {}: expected `ir_name -> elf_name`", i + 1),
+            }
+        })
+        .collect()
+}
+
+// returns the members of an archive, or the file itself if it's not an archive, along with their
+// names
+pub(crate) fn objects(path: &Path) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("couldn't read `{}`: {}", path.display(), e))?;
+    archive_members(path, bytes)
+}
+
+// like `objects` but `bytes` are the contents of `path`
+pub(crate) fn archive_members(
+    path: &Path,
+    bytes: Vec<u8>,
+) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    if !bytes.starts_with(b"!<arch>\n") {
+        return Ok(vec![(path.display().to_string(), bytes)]);
+    }
+
+    let mut objects = vec![];
+    let mut ar = Archive::new(&bytes[..]);
+    while let Some(entry) = ar.next_entry() {
+        let mut entry =
+            entry.map_err(|e| anyhow!("malformed archive `{}`: {}", path.display(), e))?;
+        let name = format!(
+            "{}({})",
+            path.display(),
+            String::from_utf8_lossy(entry.header().identifier())
+        );
+
+        let mut buf = vec![];
+        entry.read_to_end(&mut buf)?;
+        objects.push((name, buf));
+    }
+
+    Ok(objects)
+}
+
+// returns the names in the symbol table of an archive, i.e. the symbols that its members define
+pub(crate) fn archive_symbols(path: &Path, bytes: &[u8]) -> anyhow::Result<HashSet<String>> {
+    let err = |e| anyhow!("malformed archive `{}`: {}", path.display(), e);
+
+    let mut ar = Archive::new(io::Cursor::new(bytes));
+    let symbols = ar
+        .symbols()
+        .map_err(err)?
+        .map(|symbol| String::from_utf8_lossy(symbol).into_owned())
+        .collect();
+
+    Ok(symbols)
+}
+
+// extracts the stack usage information of an object file; returns the number of functions that
+// have stack usage information. `definitions` also records the stack usage of every definition of
+// a name, which reveals local functions with the same name in different objects
+pub(crate) fn object_stack_sizes(
+    name: &str,
+    obj: &[u8],
+    stack_sizes: &mut HashMap<String, u64>,
+    definitions: &mut HashMap<String, Vec<u64>>,
+) -> anyhow::Result<usize> {
+    let sizes =
+        elf::analyze_object(obj).map_err(|e| anyhow!("failed to analyze `{}`: {}", name, e))?;
+    let found = sizes.len();
+    for size in sizes {
+        stack_sizes.insert(size.name.to_owned(), size.stack);
+        definitions
+            .entry(size.name.to_owned())
+            .or_default()
+            .push(size.stack);
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parse_symbol_map() {
+        assert_eq!(
+            super::parse_symbol_map(
+                "# --wrap=malloc\nmalloc -> __wrap_malloc\n\n  free->__wrap_free  \n"
+            )
+            .unwrap(),
+            [("malloc", "__wrap_malloc"), ("free", "__wrap_free")]
+        );
+
+        assert!(super::parse_symbol_map("malloc __wrap_malloc").is_err());
+        assert!(super::parse_symbol_map("malloc ->").is_err());
+    }
+}
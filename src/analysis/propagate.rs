@@ -0,0 +1,1093 @@
+//! Stack usage propagation
+//!
+//! The user-provided information (`config`, `override_stack`, etc.) is applied to the call graph,
+//! the graph is filtered down to the start points, if any, and the max stack usage of each function
+//! is computed from the local stack usage of the functions it calls.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use anyhow::bail;
+use log::{error, warn};
+use petgraph::{
+    algo,
+    graph::{DiGraph, NodeIndex},
+    visit::{Dfs, EdgeRef, Reversed, Topo},
+    Direction, Graph,
+};
+use regex::Regex;
+
+use crate::{
+    analysis::{
+        graph::{
+            find_node, is_named, max, max_of, node_named, suggestions, uncalled, Cycle, Local, Max,
+            Node, Program, SecondaryStack, Source,
+        },
+        Options,
+    },
+    config,
+    intern::{Interner, Symbol},
+};
+
+// the call graph annotated with the max stack usage of each function
+pub(crate) struct Analysis<'a> {
+    pub(crate) g: Graph<Node, usize>,
+    pub(crate) interner: Interner<'a>,
+    // the explicit roots; if empty, the functions that are not called are the roots
+    pub(crate) roots: Vec<NodeIndex>,
+    // the nodes of the start points
+    pub(crate) starts: Vec<NodeIndex>,
+    pub(crate) cycles: Vec<Cycle>,
+    // calls whose callee doesn't stack its frame on top of the caller's
+    pub(crate) tail_calls: HashSet<(NodeIndex, NodeIndex)>,
+    // names that can be shortened without introducing ambiguity
+    pub(crate) shortened: Vec<(NodeIndex, String)>,
+    // where the functions of the crate are defined
+    pub(crate) locations: HashMap<Symbol, (PathBuf, u32)>,
+    pub(crate) phases: Option<Phases>,
+    pub(crate) preemption: Option<Preemption>,
+    pub(crate) drop_paths: Vec<DropPath>,
+    // the entry points of the tasks and the size of their stacks
+    pub(crate) tasks: Vec<(String, u64)>,
+    // the functions that run on each secondary stack
+    pub(crate) on_secondary_stacks: Vec<(SecondaryStack, Vec<NodeIndex>)>,
+}
+
+// applies the user-provided information to the call graph and computes the max stack usage of
+// each function
+pub(crate) fn analyze<'a>(program: Program<'a>, options: &Options) -> anyhow::Result<Analysis<'a>> {
+    let Program {
+        mut g,
+        indices,
+        mut interner,
+        mut roots,
+        mut has_stack_usage_info,
+        locations,
+        stale,
+        tail_calls,
+        mut masked_calls,
+        mut main_loop,
+        mut vectors,
+        secondary_stacks,
+        return_address_size,
+        ..
+    } = program;
+
+    if let Some(config) = &options.config {
+        apply_config(&mut g, &mut interner, config, &mut has_stack_usage_info);
+    }
+
+    for (symbol, stack) in &options.override_stack {
+        // accept the mangled name, the demangled name or the demangled name without the hash
+        let mut found = false;
+        for node in g.node_weights_mut() {
+            if is_named(&interner, node.name, symbol) {
+                node.local = Local::Exact(*stack);
+                node.overridden = true;
+                node.source = Source::Override;
+                found = true;
+            }
+        }
+
+        if found {
+            has_stack_usage_info = true;
+        } else {
+            warn!(
+                "--override-stack: function `{}` not found{}",
+                symbol,
+                suggestions(&g, &interner, symbol)
+            );
+        }
+    }
+
+    if let Some(rules) = &options.classify {
+        for node in g.node_weights_mut() {
+            // fictitious nodes are not functions
+            if !node.dashed {
+                let class = rules
+                    .classify_demangled(interner.name(node.name), interner.short(node.name))
+                    .to_owned();
+                node.class = Some(interner.intern(class));
+            }
+        }
+    }
+
+    // calls whose callee doesn't stack its frame on top of the caller's
+    let mut tail_calls = if options.tail_calls {
+        tail_calls
+    } else {
+        HashSet::new()
+    };
+
+    check_longjmps(&mut g, &interner, &options.longjmp_target);
+    let tasks = options.tasks();
+    split_tasks(&mut g, &interner, &tasks, &mut roots);
+    let mut on_secondary_stacks =
+        split_secondary_stacks(&mut g, &interner, secondary_stacks, &mut roots);
+
+    if options.call_overhead && return_address_size == 0 {
+        warn!(
+            "--call-overhead: on this target the return address is already part of the stack \
+             usage of the callee"
+        );
+    } else if options.call_overhead {
+        // the return address is pushed onto the stack right below the frame of the caller so
+        // it's accounted for there; fictitious nodes don't perform the calls themselves and the
+        // user-provided numbers are used as they are
+        let callers = g
+            .node_indices()
+            .filter(|idx| {
+                let node = &g[*idx];
+                !node.dashed && !node.overridden && g.neighbors(*idx).next().is_some()
+            })
+            .collect::<Vec<_>>();
+        for caller in callers {
+            if let Local::Exact(n) = &mut g[caller].local {
+                *n += return_address_size;
+            }
+        }
+    }
+
+    // whether a symbol name is ambiguous after removing the hash; fictitious nodes are not
+    // functions so they don't count
+    let mut ambiguous = HashMap::<&str, u32>::new();
+    for node in g.node_weights().filter(|node| !node.dashed) {
+        if let Some(dehashed) = interner.dehashed(node.name) {
+            *ambiguous.entry(dehashed).or_insert(0) += 1;
+        }
+    }
+
+    // names that can be shortened without introducing ambiguity
+    let mut shortened = g
+        .node_indices()
+        .filter(|idx| !g[*idx].dashed)
+        .filter_map(|idx| {
+            let dehashed = interner.dehashed(g[idx].name)?;
+            if ambiguous[dehashed] == 1 {
+                Some((idx, dehashed.to_owned()))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // the nodes of the start points in the filtered graph
+    let mut start_nodes = vec![];
+    let start_points = &options.starts;
+    if !start_points.is_empty() {
+        let mut starts = vec![];
+        for name in start_points {
+            for start in start_point(&g, &indices, &interner, name)? {
+                if !starts.contains(&start) {
+                    starts.push(start);
+                }
+            }
+        }
+
+        // create a new graph that only contains nodes reachable from the start points
+        let (g2, one2two) = reachable(&g, &starts);
+
+        // functions that can only be reached through calls that exist in the LLVM-IR but not
+        // in the ELF; their presence indicates that the two files come from different builds
+        let mut live = HashSet::new();
+        let mut stack = starts.clone();
+        while let Some(caller) = stack.pop() {
+            if live.insert(caller) {
+                stack.extend(
+                    g.neighbors(caller)
+                        .filter(|callee| !stale.contains(&(caller, *callee))),
+                );
+            }
+        }
+        let mut stripped = one2two
+            .keys()
+            .filter(|idx| !live.contains(idx))
+            .map(|idx| interner.demangled(g[*idx].name).to_owned())
+            .collect::<Vec<_>>();
+        if !stripped.is_empty() {
+            stripped.sort();
+            warn!(
+                "the following functions are only reachable from the start point through \
+                 calls that exist in the LLVM-IR but not in the ELF (is the LLVM-IR \
+                 stale?): {}",
+                stripped.join(", ")
+            );
+        }
+
+        start_nodes = starts
+            .iter()
+            .map(|start| one2two[start])
+            .collect::<Vec<_>>();
+        main_loop = main_loop.and_then(|main_loop| main_loop.remap(&one2two));
+        vectors = vectors.and_then(|vectors| vectors.remap(&one2two));
+
+        // replace the old graph
+        g = g2;
+        shortened = shortened
+            .into_iter()
+            .filter_map(|(idx, name)| one2two.get(&idx).map(|idx| (*idx, name)))
+            .collect();
+        // each start point gets its own max stack usage report
+        roots = start_nodes
+            .iter()
+            .cloned()
+            .chain(
+                roots
+                    .iter()
+                    .filter_map(|root| one2two.get(root).cloned())
+                    .filter(|root| !start_nodes.contains(root)),
+            )
+            .collect();
+        tail_calls = tail_calls
+            .iter()
+            .filter_map(|(caller, callee)| Some((*one2two.get(caller)?, *one2two.get(callee)?)))
+            .collect();
+        masked_calls = masked_calls
+            .iter()
+            .filter_map(|(caller, callee)| Some((*one2two.get(caller)?, *one2two.get(callee)?)))
+            .collect();
+        for (_, entries) in &mut on_secondary_stacks {
+            *entries = entries
+                .iter()
+                .filter_map(|entry| one2two.get(entry).cloned())
+                .collect();
+        }
+    }
+
+    let cycles = if has_stack_usage_info {
+        propagate(&mut g, &tail_calls)
+    } else {
+        error!("The graph has zero stack usage information; skipping max stack usage analysis");
+        vec![]
+    };
+
+    // the max stack usage of the main loop's function before and after it enters the loop
+    let phases = main_loop.map(|main_loop| {
+        let local = main_loop
+            .frames
+            .iter()
+            .fold(Max::Exact(0), |local, frame| local + g[*frame].local);
+        let phase = |callees: &[NodeIndex]| {
+            max_of(
+                callees
+                    .iter()
+                    .map(|callee| g[*callee].max.expect("UNREACHABLE")),
+            )
+            .map(|max| max + local)
+            .unwrap_or(local)
+        };
+
+        let name = g[main_loop.frames[0]].name;
+        Phases {
+            function: interner.short(name).to_owned(),
+            init: phase(&main_loop.init),
+            steady: phase(&main_loop.steady),
+        }
+    });
+
+    // the program's entry point preempted by the exception or interrupt handler that uses the most
+    // stack; both run on the main stack
+    let preemption = vectors.and_then(|vectors| {
+        let handler = vectors
+            .handlers
+            .iter()
+            .cloned()
+            .max_by_key(|handler| g[*handler].max.map(Max::bytes))?;
+        let name = |idx: NodeIndex| interner.short(g[idx].name).to_owned();
+
+        let mut entry = g[vectors.reset].max?;
+        if options.critical_sections && !masked_calls.is_empty() {
+            // (`--critical-sections`) the handlers only run on top of the calls that are
+            // performed with interrupts enabled
+            let mut unmasked = g.clone();
+            unmasked.retain_edges(|g, edge| {
+                let (caller, callee) = g.edge_endpoints(edge).expect("UNREACHABLE");
+                !masked_calls.contains(&(caller, callee))
+            });
+            for node in unmasked.node_weights_mut() {
+                node.max = None;
+            }
+            propagate(&mut unmasked, &tail_calls);
+            entry = unmasked[vectors.reset].max?;
+        }
+
+        Some(Preemption {
+            max: entry + g[handler].max? + Local::Exact(vectors.frame),
+            entry: name(vectors.reset),
+            handler: name(handler),
+            frame: vectors.frame,
+        })
+    });
+
+    // (`--drop-paths`) the max stack usage of each root with and without the destructors
+    let drop_paths = if options.drop_paths && has_stack_usage_info {
+        let split = split_drop_paths(&g, &interner, &tail_calls);
+        let roots = if roots.is_empty() {
+            uncalled(&g)
+        } else {
+            roots.clone()
+        };
+        roots
+            .into_iter()
+            .filter_map(|root| {
+                let (straight, drop) = split[&root];
+                Some(DropPath {
+                    function: interner.short(g[root].name).to_owned(),
+                    straight: straight?,
+                    drop: drop?,
+                })
+            })
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+
+    Ok(Analysis {
+        g,
+        interner,
+        roots,
+        starts: start_nodes,
+        cycles,
+        tail_calls,
+        shortened,
+        locations,
+        phases,
+        preemption,
+        drop_paths,
+        tasks,
+        on_secondary_stacks,
+    })
+}
+
+// computes the max stack usage of every node, whose `max` must be unset, and returns the cycles
+pub(crate) fn propagate(
+    g: &mut Graph<Node, usize>,
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> Vec<Cycle> {
+    let mut cycles = vec![];
+    if algo::is_cyclic_directed(&*g) {
+        let sccs = algo::kosaraju_scc(&*g);
+
+        // iterate over SCCs (Strongly Connected Components) in reverse topological order
+        for scc in &sccs {
+            let first = scc[0];
+
+            let is_a_cycle = scc.len() > 1
+                || g.neighbors_directed(first, Direction::Outgoing)
+                    .any(|n| n == first);
+
+            if is_a_cycle {
+                let mut scc_local =
+                    max_of(scc.iter().map(|node| g[*node].local.into())).expect("UNREACHABLE");
+
+                // the cumulative stack usage is only exact when all nodes do *not* use the stack
+                if let Max::Exact(n) = scc_local {
+                    if n != 0 {
+                        scc_local = Max::LowerBound(n)
+                    }
+                }
+
+                let neighbors_max = max_of(scc.iter().flat_map(|inode| {
+                    g.neighbors_directed(*inode, Direction::Outgoing)
+                        .filter_map(|neighbor| {
+                            if scc.contains(&neighbor) {
+                                // we only care about the neighbors of the SCC
+                                None
+                            } else {
+                                Some(g[neighbor].max.expect("UNREACHABLE"))
+                            }
+                        })
+                }));
+
+                cycles.push(Cycle {
+                    members: scc.clone(),
+                    local: scc_local,
+                });
+
+                for inode in scc {
+                    let node = &mut g[*inode];
+                    if let Some(max) = neighbors_max {
+                        node.max = Some(max + scc_local);
+                    } else {
+                        node.max = Some(scc_local);
+                    }
+                }
+            } else {
+                g[first].max = Some(max_through_callees(g, first, tail_calls));
+            }
+        }
+    } else {
+        // compute max stack usage
+        let mut topo = Topo::new(Reversed(&*g));
+        while let Some(node) = topo.next(Reversed(&*g)) {
+            debug_assert!(g[node].max.is_none());
+
+            g[node].max = Some(max_through_callees(g, node, tail_calls));
+        }
+    }
+
+    cycles
+}
+
+// the max stack usage of `caller`, whose callees' max stack usage is known; the frames of tail
+// called functions replace the frame of `caller` rather than stack on top of it
+pub(crate) fn max_through_callees(
+    g: &Graph<Node, usize>,
+    caller: NodeIndex,
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> Max {
+    let (tail, other): (Vec<_>, Vec<_>) = g
+        .neighbors_directed(caller, Direction::Outgoing)
+        .partition(|callee| tail_calls.contains(&(caller, *callee)));
+    let max_of_callees = |callees: Vec<NodeIndex>| {
+        max_of(
+            callees
+                .into_iter()
+                .map(|callee| g[callee].max.expect("UNREACHABLE")),
+        )
+    };
+
+    let local = g[caller].local;
+    let stacked = max_of_callees(other)
+        .map(|max| max + local)
+        .unwrap_or_else(|| local.into());
+    max_of_callees(tail).map_or(stacked, |tail| max(stacked, tail))
+}
+
+// adds the local stack usage and the callees that the `--config` file describes to the call graph
+pub(crate) fn apply_config(
+    g: &mut Graph<Node, usize>,
+    interner: &mut Interner,
+    config: &config::Config,
+    has_stack_usage_info: &mut bool,
+) {
+    for function in &config.functions {
+        let callers = g
+            .node_indices()
+            .filter(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, &function.name))
+            .collect::<Vec<_>>();
+
+        if callers.is_empty() {
+            warn!(
+                "--config: function `{}` not found{}",
+                function.name,
+                suggestions(g, interner, &function.name)
+            );
+            continue;
+        }
+
+        for caller in &callers {
+            if let Some(stack) = function.stack {
+                let node = &mut g[*caller];
+                node.local = Local::Exact(stack);
+                node.overridden = true;
+                node.source = Source::Config;
+                *has_stack_usage_info = true;
+            }
+        }
+
+        for callee in &function.calls {
+            let mut callees = g
+                .node_indices()
+                .filter(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, callee))
+                .collect::<Vec<_>>();
+            if callees.is_empty() {
+                // e.g. a function of the C library that nothing else calls; its stack usage is
+                // unknown unless the file also describes it
+                let node = g.add_node(Node(interner.intern(callee.clone()), None, false));
+                callees.push(node);
+            }
+
+            for caller in &callers {
+                for callee in &callees {
+                    if g.find_edge(*caller, *callee).is_none() {
+                        g.add_edge(*caller, *callee, 1);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// `longjmp` discards the frames between its caller and the function that called `setjmp`, so it
+// can only be accounted for by the call graph if that function is still on the stack. The max
+// stack usage of the callers of `longjmp` is reported as a lower bound unless each of them has a
+// `--longjmp-target` that satisfies this
+pub(crate) fn check_longjmps(
+    g: &mut Graph<Node, usize>,
+    interner: &Interner,
+    targets: &[(String, String)],
+) {
+    const SETJMP: &[&str] = &["setjmp", "_setjmp", "sigsetjmp", "__sigsetjmp"];
+    const LONGJMP: &[&str] = &["longjmp", "_longjmp", "siglongjmp", "__longjmp_chk"];
+
+    let named = |names: &[&str]| {
+        g.node_indices()
+            .filter(|idx| names.contains(&interner.name(g[*idx].name)))
+            .collect::<Vec<_>>()
+    };
+    let setjmps = named(SETJMP);
+    let longjmps = named(LONGJMP);
+
+    let setjmp_callers = setjmps
+        .iter()
+        .flat_map(|setjmp| g.neighbors_directed(*setjmp, Direction::Incoming))
+        .collect::<HashSet<_>>();
+
+    for (function, target) in targets {
+        if !g
+            .node_indices()
+            .any(|idx| is_named(interner, g[idx].name, function))
+        {
+            warn!(
+                "--longjmp-target: function `{}` not found{}",
+                function,
+                suggestions(g, interner, function)
+            );
+        }
+
+        if !setjmp_callers
+            .iter()
+            .any(|idx| is_named(interner, g[*idx].name, target))
+        {
+            warn!(
+                "--longjmp-target: `{}` is not a function that calls `setjmp`",
+                target
+            );
+        }
+    }
+
+    for longjmp in longjmps {
+        let mut unknown = vec![];
+        for caller in g.neighbors_directed(longjmp, Direction::Incoming) {
+            let name = g[caller].name;
+            let declared = targets
+                .iter()
+                .filter(|(function, _)| is_named(interner, name, function))
+                .map(|(_, target)| target)
+                .collect::<Vec<_>>();
+
+            if declared.is_empty() {
+                unknown.push(caller);
+            }
+
+            for target in declared {
+                // the target must be on the stack when `caller` runs
+                let on_stack = setjmp_callers.iter().any(|idx| {
+                    is_named(interner, g[*idx].name, target)
+                        && algo::has_path_connecting(&*g, *idx, caller, None)
+                });
+
+                if !on_stack {
+                    warn!(
+                        "--longjmp-target: `{}` can't return to `{}` because it's not called \
+                         from it",
+                        interner.demangled(name),
+                        target
+                    );
+                    unknown.push(caller);
+                }
+            }
+        }
+
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+
+            warn!(
+                "the following functions call `{}` but its target is unknown; their max stack \
+                 usage is a lower bound unless the function that called `setjmp` is declared \
+                 with `--longjmp-target FUNCTION=TARGET`: {}",
+                interner.name(g[longjmp].name),
+                unknown
+                    .iter()
+                    .map(|idx| interner.demangled(g[*idx].name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            g[longjmp].local = Local::Unknown;
+            g[longjmp].source = Source::Unknown;
+        }
+    }
+}
+
+// the tasks run on their own stacks so the calls that start them (e.g. through the indirect call
+// of a scheduler) don't grow the stack of the caller; they become roots of the call graph
+pub(crate) fn split_tasks(
+    g: &mut Graph<Node, usize>,
+    interner: &Interner,
+    tasks: &[(String, u64)],
+    roots: &mut Vec<NodeIndex>,
+) {
+    let mut entries = HashSet::new();
+    for (function, _) in tasks {
+        let matches = g
+            .node_indices()
+            .filter(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, function))
+            .collect::<Vec<_>>();
+
+        if matches.is_empty() {
+            warn!(
+                "task: function `{}` not found{}",
+                function,
+                suggestions(g, interner, function)
+            );
+        }
+
+        entries.extend(matches);
+    }
+
+    // calls from within the task (i.e. recursion) are on the task's own stack
+    let spawns = g
+        .edge_references()
+        .filter(|edge| {
+            entries.contains(&edge.target())
+                && !algo::has_path_connecting(&*g, edge.target(), edge.source(), None)
+        })
+        .map(|edge| edge.id())
+        .collect::<Vec<_>>();
+    // removing an edge invalidates the index of the last one
+    for edge in spawns.into_iter().rev() {
+        g.remove_edge(edge);
+    }
+
+    // without explicit roots the functions that no one calls are reported, tasks included
+    if !roots.is_empty() {
+        let mut entries = entries.into_iter().collect::<Vec<_>>();
+        entries.sort();
+        for entry in entries {
+            if !roots.contains(&entry) {
+                roots.push(entry);
+            }
+        }
+    }
+}
+
+// the callees of the functions that switch to a secondary stack run on that stack so their frames
+// don't stack on top of the frame of the caller; they become roots of the call graph. Returns the
+// functions that run on each secondary stack
+pub(crate) fn split_secondary_stacks(
+    g: &mut Graph<Node, usize>,
+    interner: &Interner,
+    stacks: Vec<SecondaryStack>,
+    roots: &mut Vec<NodeIndex>,
+) -> Vec<(SecondaryStack, Vec<NodeIndex>)> {
+    let mut on_stacks = vec![];
+    for stack in stacks {
+        let switches = g
+            .node_indices()
+            .filter(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, &stack.function))
+            .collect::<Vec<_>>();
+
+        if switches.is_empty() {
+            warn!(
+                "--secondary-stack: function `{}` not found{}",
+                stack.function,
+                suggestions(g, interner, &stack.function)
+            );
+        }
+
+        let mut calls = g
+            .edge_references()
+            .filter(|edge| switches.contains(&edge.source()))
+            .map(|edge| (edge.id(), edge.target()))
+            .collect::<Vec<_>>();
+        // removing an edge invalidates the index of the last one
+        calls.reverse();
+        let mut entries = vec![];
+        for (edge, callee) in calls {
+            g.remove_edge(edge);
+            if !g[callee].dashed && !entries.contains(&callee) {
+                entries.push(callee);
+            }
+        }
+        entries.sort();
+
+        // without explicit roots the functions that no one calls are reported
+        if !roots.is_empty() {
+            for entry in &entries {
+                if !roots.contains(entry) {
+                    roots.push(*entry);
+                }
+            }
+        }
+
+        on_stacks.push((stack, entries));
+    }
+
+    on_stacks
+}
+
+// the nodes of the start point `name`: the function of that name or, if `name` contains wildcards,
+// all the functions whose name matches
+pub(crate) fn start_point(
+    g: &Graph<Node, usize>,
+    indices: &BTreeMap<Symbol, NodeIndex>,
+    interner: &Interner,
+    name: &str,
+) -> anyhow::Result<Vec<NodeIndex>> {
+    if name.contains(['*', '?']) {
+        let pattern = glob(name);
+        let mut hits = g
+            .node_indices()
+            .filter(|idx| {
+                let node = &g[*idx];
+                !node.dashed
+                    && (pattern.is_match(interner.name(node.name))
+                        || pattern.is_match(interner.demangled(node.name))
+                        || interner
+                            .dehashed(node.name)
+                            .is_some_and(|dehashed| pattern.is_match(dehashed)))
+            })
+            .collect::<Vec<_>>();
+        if hits.is_empty() {
+            bail!("start point pattern `{}` matches no function", name);
+        }
+        hits.sort_by_key(|idx| interner.demangled(g[*idx].name));
+        return Ok(hits);
+    }
+
+    if let Some(idx) = find_node(indices, interner, name) {
+        return Ok(vec![idx]);
+    }
+
+    let mut hits = indices
+        .keys()
+        .filter(|sym| is_named(interner, **sym, name))
+        .map(|sym| interner.name(*sym))
+        .collect::<Vec<_>>();
+    match hits.len() {
+        0 => bail!(
+            "start point `{}` not found{}",
+            name,
+            suggestions(g, interner, name)
+        ),
+        1 => Ok(vec![node_named(indices, interner, hits[0])]),
+        _ => {
+            hits.sort();
+            bail!(
+                "multiple matches for the start point `{}`: {}; use the mangled name of one of \
+                 them",
+                name,
+                hits.iter()
+                    .map(|key| format!("`{}`", key))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    }
+}
+
+// a glob pattern, where `*` matches any sequence of characters and `?` any single character, as an
+// anchored regular expression
+pub(crate) fn glob(pattern: &str) -> Regex {
+    let regex = regex::escape(pattern)
+        .replace(r"\*", ".*")
+        .replace(r"\?", ".");
+    Regex::new(&format!("^{}$", regex)).expect("UNREACHABLE")
+}
+
+// returns a new graph that only contains the nodes reachable from `start`, and a map from `g`'s
+// `NodeIndex`-es to the new graph's `NodeIndex`-es
+pub(crate) fn reachable(
+    g: &Graph<Node, usize>,
+    starts: &[NodeIndex],
+) -> (Graph<Node, usize>, BTreeMap<NodeIndex, NodeIndex>) {
+    let mut g2 = DiGraph::<Node, usize>::new();
+    let mut one2two = BTreeMap::new();
+
+    let mut dfs = Dfs::empty(g);
+    for start in starts {
+        dfs.move_to(*start);
+        while let Some(caller1) = dfs.next(g) {
+            let caller2 = *one2two
+                .entry(caller1)
+                .or_insert_with(|| g2.add_node(g[caller1].clone()));
+
+            for edge in g.edges(caller1) {
+                let callee1 = edge.target();
+                let callee2 = *one2two
+                    .entry(callee1)
+                    .or_insert_with(|| g2.add_node(g[callee1].clone()));
+
+                g2.add_edge(caller2, callee2, *edge.weight());
+            }
+        }
+    }
+
+    (g2, one2two)
+}
+
+// the max stack usage of the `--main-loop` function during initialization and in its main loop
+pub(crate) struct Phases {
+    pub(crate) function: String,
+    pub(crate) init: Max,
+    pub(crate) steady: Max,
+}
+
+// the max stack usage of the program when an exception or interrupt handler preempts it
+pub(crate) struct Preemption {
+    // the reset handler
+    pub(crate) entry: String,
+    // the handler that uses the most stack
+    pub(crate) handler: String,
+    // the registers that the hardware pushes onto the stack on exception entry
+    pub(crate) frame: u64,
+    pub(crate) max: Max,
+}
+
+// (`--drop-paths`) the max stack usage of a root split by whether the path runs a destructor
+pub(crate) struct DropPath {
+    pub(crate) function: String,
+    // the paths that don't call `drop_in_place`
+    pub(crate) straight: Max,
+    // the paths that call `drop_in_place`
+    pub(crate) drop: Max,
+}
+
+// (`--drop-paths`) the max stack usage of each function through the call paths that don't run any
+// destructor (`drop_in_place` or `Drop::drop`) and through those that do; `None` if there's no
+// such path
+pub(crate) fn split_drop_paths(
+    g: &Graph<Node, usize>,
+    interner: &Interner,
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> HashMap<NodeIndex, (Option<Max>, Option<Max>)> {
+    // the drop glue is often inlined into its caller, leaving only the `Drop` implementation
+    let is_drop = |idx: NodeIndex| {
+        let name = interner.short(g[idx].name);
+        name.starts_with("core::ptr::drop_in_place")
+            || name.ends_with(" as core::ops::drop::Drop>::drop")
+    };
+
+    let mut split = HashMap::<NodeIndex, (Option<Max>, Option<Max>)>::new();
+    // SCCs come in reverse topological order
+    for scc in algo::kosaraju_scc(g) {
+        let first = scc[0];
+        let is_a_cycle = scc.len() > 1 || g.neighbors(first).any(|n| n == first);
+
+        if !is_a_cycle {
+            if is_drop(first) {
+                split.insert(first, (None, g[first].max));
+                continue;
+            }
+
+            // like `max_through_callees` but only over the given callees
+            let through = |callees: Vec<(NodeIndex, Max)>| {
+                let (tail, other): (Vec<_>, Vec<_>) = callees
+                    .into_iter()
+                    .partition(|(callee, _)| tail_calls.contains(&(first, *callee)));
+                let stacked =
+                    max_of(other.into_iter().map(|(_, max)| max)).map(|max| max + g[first].local);
+                let tail = max_of(tail.into_iter().map(|(_, max)| max));
+                match (stacked, tail) {
+                    (Some(stacked), Some(tail)) => Some(max(stacked, tail)),
+                    (stacked, tail) => stacked.or(tail),
+                }
+            };
+
+            let straight = through(
+                g.neighbors(first)
+                    .filter_map(|callee| Some((callee, split[&callee].0?)))
+                    .collect(),
+            )
+            .unwrap_or_else(|| g[first].local.into());
+            let drop = through(
+                g.neighbors(first)
+                    .filter_map(|callee| Some((callee, split[&callee].1?)))
+                    .collect(),
+            );
+            split.insert(first, (Some(straight), drop));
+        } else {
+            // like the max stack usage of a cycle, a lower bound unless its members use no stack
+            let members = scc.iter().filter(|idx| !is_drop(**idx)).collect::<Vec<_>>();
+            let local =
+                max_of(members.iter().map(|idx| g[**idx].local.into())).map(|local| match local {
+                    Max::Exact(n) if n != 0 => Max::LowerBound(n),
+                    local => local,
+                });
+            let callees = |drop: bool| {
+                max_of(members.iter().flat_map(|idx| {
+                    g.neighbors(**idx)
+                        .filter(|callee| !scc.contains(callee))
+                        .filter_map(|callee| {
+                            let (straight, drop_) = split[&callee];
+                            if drop {
+                                drop_
+                            } else {
+                                straight
+                            }
+                        })
+                }))
+            };
+
+            let straight = local.map(|local| callees(false).map_or(local, |max| max + local));
+            let drop = if members.len() != scc.len() {
+                // a destructor is part of the cycle
+                g[first].max
+            } else {
+                callees(true).and_then(|max| local.map(|local| max + local))
+            };
+
+            for idx in &scc {
+                split.insert(*idx, (straight, drop));
+            }
+        }
+    }
+
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use petgraph::graph::{DiGraph, NodeIndex};
+
+    use super::{Max, Node, SecondaryStack};
+    use crate::intern::Interner;
+
+    // a call graph without edges; `nodes` are the names and local stack usage of the functions
+    fn graph(
+        nodes: &[(&'static str, u64)],
+    ) -> (Interner<'static>, DiGraph<Node, usize>, Vec<NodeIndex>) {
+        let mut interner = Interner::new();
+        let mut g = DiGraph::new();
+        let indices = nodes
+            .iter()
+            .map(|(name, stack)| g.add_node(Node(interner.intern(*name), Some(*stack), false)))
+            .collect();
+        (interner, g, indices)
+    }
+
+    #[test]
+    fn glob() {
+        let glob = super::glob("*::interrupt::*");
+        assert!(glob.is_match("app::interrupt::TIM2"));
+        assert!(!glob.is_match("app::interrupts::TIM2"));
+        assert!(!glob.is_match("interrupt::TIM2"));
+
+        let glob = super::glob("app::ba?");
+        assert!(glob.is_match("app::bar"));
+        assert!(!glob.is_match("app::ba"));
+        assert!(!glob.is_match("app::barr"));
+
+        // other regex metacharacters are taken literally
+        assert!(super::glob("<app::Foo as app::Bar>::baz").is_match("<app::Foo as app::Bar>::baz"));
+        assert!(!super::glob("app.foo").is_match("app::foo"));
+    }
+
+    #[test]
+    fn split_tasks() {
+        let (interner, mut g, nodes) =
+            graph(&[("main", 0), ("scheduler", 0), ("task", 0), ("helper", 0)]);
+        let (main, scheduler, task, helper) = (nodes[0], nodes[1], nodes[2], nodes[3]);
+        g.add_edge(main, scheduler, 1);
+        g.add_edge(scheduler, task, 1);
+        // recursion stays on the task's stack
+        g.add_edge(task, helper, 1);
+        g.add_edge(helper, task, 1);
+
+        let mut roots = vec![main];
+        super::split_tasks(&mut g, &interner, &[("task".to_owned(), 256)], &mut roots);
+
+        assert_eq!(roots, [main, task]);
+        assert!(g.find_edge(scheduler, task).is_none());
+        assert!(g.find_edge(helper, task).is_some());
+        assert_eq!(g.edge_count(), 3);
+    }
+
+    #[test]
+    fn split_drop_paths() {
+        let (interner, mut g, nodes) = graph(&[
+            ("main", 8),
+            ("app::work", 16),
+            ("core::ptr::drop_in_place<app::Foo>", 0),
+            ("<app::Foo as core::ops::drop::Drop>::drop", 64),
+            // `foo` and `bar` form a cycle that runs a destructor
+            ("app::foo", 4),
+            ("app::bar", 0),
+        ]);
+        let (main, work, glue, drop, foo, bar) =
+            (nodes[0], nodes[1], nodes[2], nodes[3], nodes[4], nodes[5]);
+        g.add_edge(main, work, 1);
+        g.add_edge(main, glue, 1);
+        g.add_edge(glue, drop, 1);
+        g.add_edge(main, foo, 1);
+        g.add_edge(foo, bar, 1);
+        g.add_edge(bar, foo, 1);
+        g.add_edge(bar, drop, 1);
+        g[drop].max = Some(Max::Exact(64));
+        g[glue].max = Some(Max::Exact(64));
+
+        let split = super::split_drop_paths(&g, &interner, &HashSet::new());
+        assert_eq!(split[&work], (Some(Max::Exact(16)), None));
+        assert_eq!(split[&glue], (None, Some(Max::Exact(64))));
+        assert_eq!(split[&drop], (None, Some(Max::Exact(64))));
+        assert_eq!(
+            split[&foo],
+            (Some(Max::LowerBound(4)), Some(Max::LowerBound(68)))
+        );
+        assert_eq!(
+            split[&main],
+            (Some(Max::LowerBound(24)), Some(Max::LowerBound(76)))
+        );
+    }
+
+    #[test]
+    fn split_secondary_stacks() {
+        let (interner, mut g, nodes) =
+            graph(&[("main", 0), ("on_scratch", 0), ("filter", 0), ("store", 0)]);
+        let (main, switch, filter, store) = (nodes[0], nodes[1], nodes[2], nodes[3]);
+        g.add_edge(main, switch, 1);
+        g.add_edge(main, store, 1);
+        g.add_edge(switch, filter, 1);
+        g.add_edge(filter, store, 1);
+
+        let stacks = vec![SecondaryStack {
+            function: "on_scratch".to_owned(),
+            buffer: Some(("SCRATCH".to_owned(), 256)),
+        }];
+        let mut roots = vec![main];
+        let on_stacks = super::split_secondary_stacks(&mut g, &interner, stacks, &mut roots);
+
+        assert_eq!(on_stacks.len(), 1);
+        assert_eq!(on_stacks[0].1, [filter]);
+        assert_eq!(roots, [main, filter]);
+        assert!(g.find_edge(switch, filter).is_none());
+        assert!(g.find_edge(filter, store).is_some());
+        assert_eq!(g.edge_count(), 3);
+    }
+
+    #[test]
+    fn max_through_callees() {
+        let (_, mut g, nodes) = graph(&[("caller", 64), ("small", 8), ("big", 96)]);
+        let (caller, small, big) = (nodes[0], nodes[1], nodes[2]);
+        g[small].max = Some(Max::Exact(8));
+        g[big].max = Some(Max::Exact(96));
+        g.add_edge(caller, small, 1);
+        g.add_edge(caller, big, 1);
+
+        let tail = |edges: &[(_, _)]| edges.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(
+            super::max_through_callees(&g, caller, &tail(&[])),
+            Max::Exact(64 + 96)
+        );
+        // the frame of `caller` is gone by the time `big` runs
+        assert_eq!(
+            super::max_through_callees(&g, caller, &tail(&[(caller, big)])),
+            Max::Exact(96)
+        );
+        assert_eq!(
+            super::max_through_callees(&g, caller, &tail(&[(caller, small), (caller, big)])),
+            Max::Exact(96)
+        );
+        assert_eq!(
+            super::max_through_callees(&g, caller, &tail(&[(caller, small)])),
+            Max::Exact(64 + 96)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! LLVM bitcode
+//!
+//! Objects compiled with `-flto` contain bitcode instead of machine code. We turn the bitcode into
+//! textual LLVM IR using `llvm-dis` so it can be analyzed like the IR produced by `rustc`.
+
+use std::{
+    env,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{anyhow, bail};
+
+/// Whether `bytes` is LLVM bitcode
+pub fn is_bitcode(bytes: &[u8]) -> bool {
+    // raw bitcode or bitcode wrapper
+    bytes.starts_with(b"BC\xc0\xde") || bytes.starts_with(&[0xde, 0xc0, 0x17, 0x0b])
+}
+
+/// Returns the path to the `llvm-dis` tool
+pub fn llvm_dis(host: Option<&str>) -> PathBuf {
+    llvm_tool(host, "llvm-dis")
+}
+
+/// Returns the path to the LLVM `tool`, e.g. `llvm-objdump`
+///
+/// The `llvm-tools` component of the toolchain, which is installed for the `host`, is preferred as
+/// it uses the same LLVM version as `rustc`; otherwise `tool` is looked up in the `PATH`
+pub fn llvm_tool(host: Option<&str>, tool: &str) -> PathBuf {
+    let exe = format!("{}{}", tool, env::consts::EXE_SUFFIX);
+
+    host.and_then(|host| {
+        let output = Command::new("rustc").arg("--print=sysroot").output().ok()?;
+        String::from_utf8(output.stdout).ok().map(|sysroot| {
+            Path::new(sysroot.trim())
+                .join("lib/rustlib")
+                .join(host)
+                .join("bin")
+                .join(&exe)
+        })
+    })
+    .filter(|path| path.exists())
+    .unwrap_or_else(|| PathBuf::from(exe))
+}
+
+/// Disassembles `bitcode` into textual LLVM IR
+pub fn disassemble(llvm_dis: &Path, bitcode: &[u8]) -> anyhow::Result<String> {
+    let mut child = Command::new(llvm_dis)
+        .args(["-o", "-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            anyhow!(
+                "couldn't run `{}`: {}; try `rustup component add llvm-tools`",
+                llvm_dis.display(),
+                e
+            )
+        })?;
+
+    // write from another thread to avoid a deadlock if the output fills the pipe
+    let mut stdin = child.stdin.take().expect("UNREACHABLE");
+    let bitcode = bitcode.to_owned();
+    let writer = thread::spawn(move || stdin.write_all(&bitcode));
+
+    let output = child.wait_with_output()?;
+    writer.join().expect("UNREACHABLE")?;
+
+    if !output.status.success() {
+        bail!(
+            "`{}` failed: {}",
+            llvm_dis.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn is_bitcode() {
+        assert!(super::is_bitcode(b"BC\xc0\xde\x35\x14\x00\x00"));
+        assert!(super::is_bitcode(&[0xde, 0xc0, 0x17, 0x0b, 0x00, 0x00]));
+        assert!(!super::is_bitcode(b"\x7fELF\x01\x01\x01\x00"));
+    }
+}
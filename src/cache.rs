@@ -0,0 +1,305 @@
+//! On-disk cache of the `compiler_builtins` analysis
+//!
+//! `compiler_builtins` rarely changes between runs, yet parsing its LLVM IR and extracting the
+//! stack usage information from its rlib takes a good chunk of every invocation. The result of
+//! that analysis is stored next to the rlib, in a line-based text format, under a name derived from
+//! the hash of the rlib.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashMap,
+    fmt::Write as _,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::ir::{Declare, Define, Item, MemoryCall, Operation, Stmt};
+
+// bump this when the format changes
+const HEADER: &str = concat!("cargo-call-stack ", env!("CARGO_PKG_VERSION"), " 9");
+
+/// Returns the path of the cache file of the given `compiler_builtins` rlib
+pub fn path(rlib_path: &Path, rlib: &[u8]) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    HEADER.hash(&mut hasher);
+    rlib.hash(&mut hasher);
+
+    rlib_path.with_file_name(format!("cargo-call-stack-{:016x}.cache", hasher.finish()))
+}
+
+/// Serializes the definitions, declarations and stack usage information
+///
+/// Returns `None` if some function signature can't be serialized
+pub fn serialize(items: &[Item], stack_sizes: &HashMap<String, u64>) -> Option<String> {
+    let mut cache = String::new();
+    cache.push_str(HEADER);
+    cache.push('\n');
+
+    for item in items {
+        match item {
+            Item::Define(def) => {
+                let _ = writeln!(cache, "define\t{}\t{}", def.name, def.sig);
+
+                for stmt in &def.stmts {
+                    match stmt {
+                        Stmt::Asm(expr) => {
+                            let _ = writeln!(cache, "asm\t{}", expr);
+                        }
+                        Stmt::BitcastCall(Some(sym)) => {
+                            let _ = writeln!(cache, "bitcast\t{}", sym);
+                        }
+                        Stmt::BitcastCall(None) => cache.push_str("bitcast\n"),
+                        Stmt::DirectCall(func) => {
+                            let _ = writeln!(cache, "call\t{}", func);
+                        }
+                        Stmt::MemoryCall(call) => {
+                            let number = |n: Option<u64>| n.map(|n| n.to_string());
+                            let _ = writeln!(
+                                cache,
+                                "mem\t{}\t{} {} {}",
+                                call.func,
+                                call.align,
+                                number(call.len).as_deref().unwrap_or("-"),
+                                number(call.value).as_deref().unwrap_or("-")
+                            );
+                        }
+                        Stmt::IndirectCall(sig) => {
+                            let _ = writeln!(cache, "indirect\t{}", sig);
+                        }
+                        Stmt::Operation(op) => {
+                            cache.push_str("op\t");
+                            cache.push_str(op.opcode);
+                            if let Some(cond) = op.cond {
+                                let _ = write!(cache, " {}", cond);
+                            }
+                            let _ = write!(cache, "\t{}", op.ty);
+                            if let Some(to) = &op.to {
+                                let _ = write!(cache, " to {}", to);
+                            }
+                            cache.push('\n');
+                        }
+                        Stmt::VolatileStore(address, value) => {
+                            let _ = writeln!(cache, "store\t{:#x}\t{:#x}", address, value);
+                        }
+                        // comments and other statements have no effect on the analysis; labels
+                        // and terminators only drive the reachability pruning, which
+                        // `define::parse` has already done by the time the items are cached
+                        Stmt::Comment | Stmt::Label(_) | Stmt::Terminator(_) | Stmt::Other => {}
+                    }
+                }
+            }
+
+            Item::Declare(decl) => {
+                if let Some(sig) = &decl.sig {
+                    let _ = writeln!(cache, "declare\t{}\t{}", decl.name, sig);
+                } else {
+                    let _ = writeln!(cache, "declare\t{}", decl.name);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    // NOTE(sort) deterministic output
+    let mut stack_sizes = stack_sizes.iter().collect::<Vec<_>>();
+    stack_sizes.sort();
+    for (name, stack) in stack_sizes {
+        let _ = writeln!(cache, "stack\t{}\t{}", name, stack);
+    }
+
+    // the `Display` implementation of some signatures may not be understood by our parser
+    if contents_roundtrip(items, &cache) {
+        Some(cache)
+    } else {
+        None
+    }
+}
+
+/// Parses the contents of a cache file
+pub fn deserialize(cache: &str) -> anyhow::Result<(Vec<Item<'_>>, HashMap<String, u64>)> {
+    let mut lines = cache.lines().enumerate();
+
+    if lines.next().map(|(_, line)| line) != Some(HEADER) {
+        bail!("unknown cache format");
+    }
+
+    let mut items = vec![];
+    let mut stack_sizes = HashMap::new();
+    let mut define: Option<Define> = None;
+    for (n, line) in lines {
+        let err = || anyhow!("malformed cache entry in line {}", n + 1);
+
+        let mut fields = line.split('\t');
+        let kind = fields.next().unwrap_or("");
+        let first = fields.next();
+        let second = fields.next();
+
+        let stmt = match (kind, first, second) {
+            ("asm", Some(expr), None) => Stmt::Asm(expr),
+            ("bitcast", sym, None) => Stmt::BitcastCall(sym),
+            ("call", Some(func), None) => Stmt::DirectCall(func),
+            ("mem", Some(func), Some(args)) => {
+                let mut args = args.split(' ');
+                let mut number = || match args.next() {
+                    Some("-") => Ok(None),
+                    Some(n) => n.parse().map(Some).map_err(|_| err()),
+                    None => Err(err()),
+                };
+
+                Stmt::MemoryCall(MemoryCall {
+                    func,
+                    align: number()?.ok_or_else(err)?,
+                    len: number()?,
+                    value: number()?,
+                })
+            }
+            ("indirect", Some(sig), None) => {
+                Stmt::IndirectCall(crate::ir::fn_sig(sig).ok_or_else(err)?)
+            }
+            ("op", Some(opcode), Some(ty)) => {
+                let (opcode, cond) = match opcode.split_once(' ') {
+                    Some((opcode, cond)) => (opcode, Some(cond)),
+                    None => (opcode, None),
+                };
+                let (ty, to) = match ty.split_once(" to ") {
+                    Some((ty, to)) => (ty, Some(crate::ir::ty(to).ok_or_else(err)?)),
+                    None => (ty, None),
+                };
+
+                Stmt::Operation(Operation {
+                    opcode,
+                    cond,
+                    ty: crate::ir::ty(ty).ok_or_else(err)?,
+                    to,
+                })
+            }
+
+            ("store", Some(address), Some(value)) => {
+                let hex = |s: &str| {
+                    s.strip_prefix("0x")
+                        .and_then(|s| u64::from_str_radix(s, 16).ok())
+                        .ok_or_else(err)
+                };
+                Stmt::VolatileStore(hex(address)?, hex(value)?)
+            }
+
+            _ => {
+                // not a statement; this ends the current definition
+                items.extend(define.take().map(Item::Define));
+
+                match (kind, first, second) {
+                    ("define", Some(name), Some(sig)) => {
+                        define = Some(Define {
+                            name,
+                            sig: crate::ir::fn_sig(sig).ok_or_else(err)?,
+                            stmts: vec![],
+                            dbg: None,
+                            symbols: vec![],
+                        });
+                    }
+
+                    ("declare", Some(name), sig) => {
+                        let sig = if let Some(sig) = sig {
+                            Some(crate::ir::fn_sig(sig).ok_or_else(err)?)
+                        } else {
+                            None
+                        };
+
+                        items.push(Item::Declare(Declare { name, sig }));
+                    }
+
+                    ("stack", Some(name), Some(stack)) => {
+                        stack_sizes.insert(name.to_owned(), stack.parse().map_err(|_| err())?);
+                    }
+
+                    _ => return Err(err()),
+                }
+
+                continue;
+            }
+        };
+
+        define.as_mut().ok_or_else(err)?.stmts.push(stmt);
+    }
+    items.extend(define.map(Item::Define));
+
+    Ok((items, stack_sizes))
+}
+
+// checks that `cache` contains exactly the `items` that affect the analysis
+fn contents_roundtrip(items: &[Item], cache: &str) -> bool {
+    let expected = items.iter().filter_map(|item| match item {
+        Item::Define(def) => Some(Item::Define(Define {
+            stmts: def
+                .stmts
+                .iter()
+                .filter(|stmt| {
+                    !matches!(
+                        stmt,
+                        Stmt::Comment | Stmt::Label(_) | Stmt::Terminator(_) | Stmt::Other
+                    )
+                })
+                .cloned()
+                .collect(),
+            // debug info and symbol references are not cached; they are only used for the
+            // application crate
+            dbg: None,
+            symbols: vec![],
+            ..def.clone()
+        })),
+        Item::Declare(_) => Some(item.clone()),
+        _ => None,
+    });
+
+    match deserialize(cache) {
+        Ok((actual, _)) => actual.into_iter().eq(expected),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::ir::{self, Item};
+
+    #[test]
+    fn roundtrip() {
+        let ll = r#"define internal i32 @foo(i32 %0, ptr %1) unnamed_addr #0 {
+start:
+  %2 = tail call i32 @bar(i32 %0)
+  %3 = tail call i32 %1(i32 %2)
+  %4 = fcmp olt float %x, %y
+  call void @llvm.memset.p0.i32(ptr align 4 %1, i8 0, i32 %0, i1 false)
+  call void asm sideeffect "nop", ""()
+  store volatile i32 3, ptr inttoptr (i32 -536813312 to ptr), align 4
+  ret i32 %3
+}
+
+declare i32 @bar(i32) unnamed_addr #1
+"#;
+        let items = ir::parse(ll).unwrap();
+        let mut stack_sizes = HashMap::new();
+        stack_sizes.insert("foo".to_owned(), 8);
+
+        let cache = super::serialize(&items, &stack_sizes).unwrap();
+        let (items2, stack_sizes2) = super::deserialize(&cache).unwrap();
+
+        assert_eq!(stack_sizes, stack_sizes2);
+        assert_eq!(items2.len(), 2);
+        match (&items[0], &items2[0]) {
+            (Item::Define(expected), Item::Define(actual)) => {
+                assert_eq!(expected.name, actual.name);
+                assert_eq!(expected.sig, actual.sig);
+                assert_eq!(actual.stmts.len(), 6);
+            }
+            _ => panic!(),
+        }
+        assert_eq!(items[items.len() - 1], items2[1]);
+
+        assert!(super::deserialize("cargo-call-stack 0.0.0 0\n").is_err());
+    }
+}
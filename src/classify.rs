@@ -0,0 +1,105 @@
+//! Symbol classification rules
+//!
+//! A rules file assigns each symbol a class, e.g. `runtime`, `libc` or `vendor-blob`, with one
+//! `CLASS = REGEX` rule per line. The rules are tried in order and the first one whose regular
+//! expression matches the symbol name (mangled or demangled, without the hash) decides its class.
+//! Symbols that no rule matches belong to the `application` class.
+
+use anyhow::{anyhow, bail};
+use regex::Regex;
+
+/// The class of the symbols that no rule matches
+pub const DEFAULT: &str = "application";
+
+/// An ordered list of classification rules
+#[derive(Clone, Debug)]
+pub struct Rules {
+    rules: Vec<(String, Regex)>,
+}
+
+impl Rules {
+    /// Parses the rules; empty lines and lines that start with `#` are ignored
+    pub fn parse(rules: &str) -> anyhow::Result<Self> {
+        let rules = rules
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                let (class, regex) = line
+                    .split_once('=')
+                    .map(|(class, regex)| (class.trim(), regex.trim()))
+                    .filter(|(class, regex)| !class.is_empty() && !regex.is_empty())
+                    .ok_or_else(|| anyhow!("line {}: expected `CLASS = REGEX`", i + 1))?;
+                if class.contains(char::is_whitespace) {
+                    bail!("line {}: class `{}` contains whitespace", i + 1, class);
+                }
+
+                let regex = Regex::new(regex).map_err(|e| anyhow!("line {}: {}", i + 1, e))?;
+                Ok((class.to_owned(), regex))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Rules { rules })
+    }
+
+    /// Returns the class of the symbol `name`
+    pub fn classify(&self, name: &str) -> &str {
+        self.classify_demangled(name, &format!("{:#}", rustc_demangle::demangle(name)))
+    }
+
+    /// Like `classify` but reuses the `demangled` form of `name`, without its hash
+    pub fn classify_demangled(&self, name: &str, demangled: &str) -> &str {
+        self.rules
+            .iter()
+            .find(|(_, regex)| regex.is_match(name) || regex.is_match(demangled))
+            .map(|(class, _)| &**class)
+            .unwrap_or(DEFAULT)
+    }
+
+    /// Whether some symbol can be assigned `class`
+    pub fn defines(&self, class: &str) -> bool {
+        class == DEFAULT || self.rules.iter().any(|(name, _)| name == class)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rules;
+
+    #[test]
+    fn classify() {
+        let rules = Rules::parse(
+            "# comment
+
+             runtime = ^compiler_builtins::
+             libc = ^(memcpy|memset|strlen)$
+             vendor-blob = ^HAL_
+             runtime = ^__aeabi_",
+        )
+        .unwrap();
+
+        // demangled without the hash
+        assert_eq!(
+            rules.classify("_ZN17compiler_builtins3mem6memcpy17h0123456789abcdefE"),
+            "runtime"
+        );
+        assert_eq!(rules.classify("__aeabi_memcpy"), "runtime");
+        assert_eq!(rules.classify("memset"), "libc");
+        assert_eq!(rules.classify("HAL_GPIO_Init"), "vendor-blob");
+        assert_eq!(rules.classify("main"), "application");
+
+        assert!(rules.defines("runtime"));
+        assert!(rules.defines("application"));
+        assert!(!rules.defines("kernel"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Rules::parse("runtime ^__aeabi_").is_err());
+        assert!(Rules::parse("runtime =").is_err());
+        assert!(Rules::parse("= ^main$").is_err());
+        assert!(Rules::parse("vendor blob = ^HAL_").is_err());
+        assert!(Rules::parse("runtime = (").is_err());
+    }
+}
@@ -0,0 +1,3213 @@
+use core::{
+    cmp,
+    fmt::{self, Write as _},
+    iter, str,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail};
+use cargo_project::{Profile, Project};
+use clap::{Parser, ValueEnum};
+use env_logger::{Builder, Env};
+use log::{error, warn};
+use petgraph::{
+    algo,
+    graph::{EdgeIndex, NodeIndex},
+    visit::{Dfs, EdgeFiltered, EdgeRef},
+    Direction, Graph,
+};
+use rustc_version::VersionMeta;
+
+use self::build::{cargo_build, package_root, Build};
+use crate::{
+    analysis::{
+        graph::{
+            self, function_at, is_named, max_of, suggestions, uncalled, Budget, Cycle, Local, Max,
+            Node, Source, Status,
+        },
+        load,
+        propagate::{self, reachable, Analysis, DropPath, Phases, Preemption},
+        FmtHeuristic, Library, OnMismatch, Options,
+    },
+    bitcode, classify, compare, config, disassembly, dwarf,
+    editor::{self, Annotation, Annotations},
+    intern::Interner,
+    wrapper,
+};
+
+mod build;
+
+#[derive(ValueEnum, PartialEq, Debug, Clone, Copy)]
+enum OutputFormat {
+    Dot,
+    Top,
+    /// Like `top` but monomorphizations are grouped under their generic function
+    Generic,
+    Json,
+    /// The bytes of the max stack usage of the start point (or the heaviest root) that each
+    /// function owns: its own frame plus the frames only reachable through it
+    Dominators,
+    /// (Heuristic) Calls on the worst-case paths of the start point (or the heaviest root) where a
+    /// function with a small frame reaches a callee with a larger one besides lighter callees.
+    /// Splitting such a call off the common path, e.g. into an `#[inline(never)]` cold function,
+    /// lowers the max stack usage of that path
+    Splits,
+    /// The functions sorted by max stack usage along with their local stack usage and the call
+    /// chain that reaches their max stack usage
+    Table,
+}
+
+/// Generate a call graph and perform whole program stack usage analysis
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Subcommand>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Subcommand {
+    /// Perform the same analysis but, instead of printing a report, only evaluate the budgets set
+    /// with `--budget`, `--task` and `--secondary-stack`; the exit code is non-zero if one of them
+    /// is exceeded
+    Check(Box<Args>),
+
+    /// Compare the max stack usage of two call graphs produced with `--format json` and render
+    /// the changes as a table that can be posted as a pull request comment
+    Report(compare::ReportArgs),
+}
+
+/// Generate a call graph and perform whole program stack usage analysis
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub(crate) struct Args {
+    /// Target triple for which the code is compiled
+    #[arg(long, value_name = "TRIPLE")]
+    target: Option<String>,
+
+    /// Package of the workspace that contains the binary, example, library, test or benchmark to
+    /// analyze
+    #[arg(short, long, value_name = "SPEC")]
+    package: Option<String>,
+
+    /// Build only the specified binary
+    #[arg(long, value_name = "BIN")]
+    bin: Option<String>,
+
+    /// Build only the specified example
+    #[arg(long, value_name = "NAME")]
+    example: Option<String>,
+
+    /// Build only this package's library; it must be a `cdylib` or a `staticlib`
+    #[arg(long)]
+    lib: bool,
+
+    /// Build only the specified integration test; it must be an on-target test binary with
+    /// `harness = false`, like the ones `defmt-test` produces. Each test function is a root
+    #[arg(long, value_name = "NAME")]
+    test: Option<String>,
+
+    /// Build only the specified benchmark; like `--test`, it must be an on-target binary with
+    /// `harness = false`. Each benchmark function is a root
+    #[arg(long, value_name = "NAME")]
+    bench: Option<String>,
+
+    /// Analyze this ELF file, built by other means (e.g. a Makefile), instead of building the crate
+    /// with Cargo; its LLVM IR must be provided with `--ll`
+    #[arg(
+        long,
+        value_name = "PATH",
+        requires = "ll",
+        conflicts_with_all = ["package", "bin", "example", "lib", "test", "bench", "features", "all_features", "feature_matrix"]
+    )]
+    elf: Option<PathBuf>,
+
+    /// (`--elf`) The LLVM IR of the program, produced with `--emit=llvm-ir,obj`, `-Zemit-stack-sizes`
+    /// and fat LTO (`-C lto=fat -C embed-bitcode=yes`)
+    #[arg(long, value_name = "PATH", requires = "elf")]
+    ll: Option<PathBuf>,
+
+    /// (`--elf`) The object file, with a `.stack_sizes` section, of the program; defaults to the
+    /// `.o` file next to the `--ll` file
+    #[arg(long, value_name = "PATH", requires = "elf")]
+    obj: Option<PathBuf>,
+
+    /// Space-separated list of features to activate
+    #[arg(long, value_name = "FEATURES")]
+    features: Option<String>,
+
+    /// Activate all available features
+    #[arg(long)]
+    all_features: bool,
+
+    /// Analyze each of these `;`-separated feature combinations, e.g. `"a,b; a,c; default"` where
+    /// `default` activates no additional features, and report the max stack usage of the roots
+    /// under each combination side by side instead of the call graph
+    #[arg(long, value_name = "COMBINATIONS")]
+    feature_matrix: Option<String>,
+
+    /// Require Cargo.lock to be up to date
+    #[arg(long)]
+    locked: bool,
+
+    /// Require Cargo.lock and cache to be up to date
+    #[arg(long)]
+    frozen: bool,
+
+    /// Run without accessing the network
+    #[arg(long)]
+    offline: bool,
+
+    /// Directory for the artifacts of the analysis build; defaults to `call-stack` inside Cargo's
+    /// target directory so that analysis builds and normal builds don't invalidate each other
+    #[arg(long, value_name = "DIRECTORY")]
+    target_dir: Option<PathBuf>,
+
+    /// Use verbose output; this includes which symbol names were treated as aliases of the same
+    /// function and which of them was picked as its name and, on ARM Cortex-M, how many bytes of
+    /// each function are data (e.g. literal pools) and which functions are shared tails
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Output format
+    #[arg(long, default_value = "dot")]
+    format: OutputFormat,
+
+    /// Collapse each cycle into a single node in the dot output
+    #[arg(long)]
+    merge_cycles: bool,
+
+    /// Don't add the potential callees of indirect function calls (function pointers and trait
+    /// objects) to the call graph; each indirect call is reported as a warning instead
+    #[arg(long)]
+    ignore_indirect: bool,
+
+    /// Replace the `Display`, `Debug`, etc. implementations that `core::fmt` may call through a
+    /// function pointer with a single node that uses as much stack as the heaviest of them
+    #[arg(long)]
+    aggregate_fmt: bool,
+
+    /// Which functions the formatting machinery of `core::fmt` may call through the function
+    /// pointer of its arguments; only used with toolchains that emit typed pointers in the LLVM
+    /// IR. `--verbose` lists the functions it captured
+    #[arg(long, value_name = "HEURISTIC", default_value = "loose")]
+    fmt_heuristic: FmtHeuristic,
+
+    /// Add the return address that call instructions push onto the stack (e.g. 8 bytes on x86_64,
+    /// 2 on AVR and MSP430) to the stack usage of each function that calls others, unless its
+    /// stack usage was overridden; on most targets the callee's frame already accounts for it
+    #[arg(long)]
+    call_overhead: bool,
+
+    /// File that describes the local stack usage and the callees of functions the analysis can't
+    /// see into, e.g. hand-written assembly; by default `call-stack.toml` next to `Cargo.toml`, if
+    /// it exists
+    #[arg(long, value_name = "PATH", value_parser = parse_config)]
+    config: Option<config::Config>,
+
+    /// Use BYTES as the local stack usage of SYMBOL; can be repeated. Takes precedence over the
+    /// `--config` file
+    #[arg(long, value_name = "SYMBOL=BYTES", value_parser = parse_override)]
+    override_stack: Vec<(String, u64)>,
+
+    /// Don't analyze `compiler_builtins`; its functions are treated as external symbols without
+    /// stack usage information, which can be provided with `--override-stack`
+    #[arg(long)]
+    no_builtins_analysis: bool,
+
+    /// What to do when the local stack usage that LLVM reports for a function doesn't match the
+    /// analysis of its machine code; functions that contain inline assembly and outlined
+    /// functions, which LLVM is known to get wrong, always use the machine code. ARM Cortex-M only
+    #[arg(long, value_name = "POLICY", default_value = "max")]
+    on_mismatch: OnMismatch,
+
+    /// Hide the functions whose max stack usage is known to be below this many bytes
+    #[arg(long, value_name = "BYTES")]
+    min_max: Option<u64>,
+
+    /// Additional LLVM IR file to analyze, e.g. C code compiled with `clang -S -emit-llvm`
+    #[arg(long, value_name = "PATH")]
+    extra_ll: Vec<PathBuf>,
+
+    /// Additional object file (`.o`) or archive (`.a`) whose stack usage information to use
+    #[arg(long, value_name = "PATH")]
+    extra_obj: Vec<PathBuf>,
+
+    /// File that maps LLVM IR symbol names to ELF symbol names, one `ir_name -> elf_name` per line
+    #[arg(long, value_name = "PATH")]
+    symbol_map: Option<PathBuf>,
+
+    /// Treat the contents of this linker section as function pointers registered at build time,
+    /// like `linkme`'s distributed slices; needed for sections placed by a linker script, which
+    /// have no `__start_` symbol. Can be repeated. Adds to the `[distributed-slices]` of the
+    /// `--config` file
+    #[arg(long, value_name = "SECTION")]
+    distributed_slice: Vec<String>,
+
+    /// Account for the static constructors (`.preinit_array` and `.init_array`), which run before
+    /// `main` on the same stack, in the max stack usage of the entry point
+    #[arg(long)]
+    include_constructors: bool,
+
+    /// Write the stack usage of each function, keyed by the source location of its definition, to
+    /// this file; this is meant to be consumed by editors
+    #[arg(long, value_name = "PATH")]
+    editor_data: Option<PathBuf>,
+
+    /// Write the disassembly of each function to its own file in this directory and link it from
+    /// the `dot` (`URL` attribute) and `json` (`disassembly` field) outputs
+    #[arg(long, value_name = "DIR")]
+    disassembly: Option<PathBuf>,
+
+    /// (`--disassembly`) The disassembler, an `objdump` compatible tool like
+    /// `arm-none-eabi-objdump`; defaults to the `llvm-objdump` of the `llvm-tools` component or, if
+    /// that's not installed, the one in `PATH`
+    #[arg(long, value_name = "PATH", requires = "disassembly")]
+    objdump: Option<PathBuf>,
+
+    /// Produce the same output for the same inputs on any machine: the dot output has no date,
+    /// unless `SOURCE_DATE_EPOCH` is set, and the paths in the outputs are relative to the current
+    /// directory, `$SYSROOT` or `$CARGO_HOME`
+    #[arg(long)]
+    reproducible: bool,
+
+    /// Also write the call graph of each root to its own file in this directory, named after the
+    /// root; only the `dot` and `json` formats are supported
+    #[arg(long, value_name = "DIR")]
+    split_output: Option<PathBuf>,
+
+    /// The `longjmp` calls in FUNCTION return to TARGET, the function that called `setjmp`; can be
+    /// repeated. Without it the max stack usage of the callers of `longjmp` is a lower bound
+    #[arg(long, value_name = "FUNCTION=TARGET", value_parser = parse_longjmp_target)]
+    longjmp_target: Vec<(String, String)>,
+
+    /// The `svc #NUMBER` instructions perform the system call that FUNCTION implements; the call
+    /// graph gets an edge from each `svc` call site to FUNCTION. Can be repeated; ARM Cortex-M only
+    #[arg(long, value_name = "NUMBER=FUNCTION", value_parser = parse_syscall)]
+    syscall: Vec<(u8, String)>,
+
+    /// Array of function pointers, indexed by the `svc` number, that the SVCall handler dispatches
+    /// the system calls to; an alternative to listing them with `--syscall`. ARM Cortex-M only
+    #[arg(long, value_name = "SYMBOL")]
+    syscall_table: Option<String>,
+
+    /// Don't stack the frame of a function on top of the functions it tail calls, which the
+    /// machine code does with a `B` instruction after popping the frame; by default tail calls are
+    /// treated like any other call. ARM Cortex-M only
+    #[arg(long)]
+    tail_calls: bool,
+
+    /// Exception and interrupt handlers can't preempt the calls performed with interrupts masked,
+    /// i.e. within a critical section, so leave those calls out of the max stack usage of the
+    /// reset handler when it's preempted. ARM Cortex-M only
+    #[arg(long)]
+    critical_sections: bool,
+
+    /// Report the max stack usage of FUNCTION (usually `main`) before it enters its main loop and
+    /// within the loop separately; ARM Cortex-M only
+    #[arg(long, value_name = "FUNCTION")]
+    main_loop: Option<String>,
+
+    /// Report the max stack usage of each root through the calls that run destructors
+    /// (`drop_in_place`) separately from that of the other calls
+    #[arg(long)]
+    drop_paths: bool,
+
+    /// Instead of the call graph, print how the stack frame of FUNCTION breaks down into its local
+    /// variables, according to the debug information (DWARF) of the program
+    #[arg(long, value_name = "FUNCTION")]
+    explain_frame: Option<String>,
+
+    /// The max stack usage of FUNCTION, or of each root if FUNCTION is omitted, should not exceed
+    /// BYTES; every report format marks the budgeted functions as OK, near limit or over limit.
+    /// Can be repeated
+    #[arg(long, value_name = "[FUNCTION=]BYTES", value_parser = parse_budget)]
+    budget: Vec<(Option<String>, u64)>,
+
+    /// Like `--budget` but the tool exits with a non-zero code, after printing the report, if the
+    /// max stack usage exceeds BYTES. Takes a comma separated list, e.g. `main=4096,SysTick=512`,
+    /// and can be repeated
+    #[arg(
+        long,
+        value_name = "[FUNCTION=]BYTES",
+        value_parser = parse_budget,
+        value_delimiter = ','
+    )]
+    max_stack: Vec<(Option<String>, u64)>,
+
+    /// A budgeted function is near its limit when its max stack usage exceeds this percentage of
+    /// its budget
+    #[arg(long, value_name = "PERCENT", default_value_t = 80)]
+    budget_warning: u64,
+
+    /// FUNCTION is the entry point of a task that runs on its own stack of BYTES (e.g. the threads of
+    /// an RTOS that switches contexts in `PendSV`); its max stack usage is checked against BYTES and
+    /// left out of the max stack usage of the code that spawns it. Can be repeated. Adds to, and
+    /// takes precedence over, the `[tasks]` of the `--config` file
+    #[arg(long, value_name = "FUNCTION=BYTES", value_parser = parse_override)]
+    task: Vec<(String, u64)>,
+
+    /// FUNCTION switches the stack pointer to the static buffer SYMBOL (e.g. a `static mut` array
+    /// used as a coroutine or DSP scratch stack) before it calls other functions; their max stack
+    /// usage is checked against the size of SYMBOL and left out of the max stack usage of FUNCTION.
+    /// Can be repeated. Adds to, and takes precedence over, the `[secondary-stacks]` of the
+    /// `--config` file
+    #[arg(long, value_name = "FUNCTION=SYMBOL", value_parser = parse_secondary_stack)]
+    secondary_stack: Vec<(String, String)>,
+
+    /// File of rules, one `CLASS = REGEX` per line, that classify the symbols (e.g. as `runtime`,
+    /// `libc` or `vendor-blob`); the first matching rule wins and unmatched symbols are
+    /// `application`. `--verbose` reports statistics per class
+    #[arg(long, value_name = "PATH", value_parser = parse_classify)]
+    classify: Option<classify::Rules>,
+
+    /// Hide the functions of this class (see `--classify`) from the output; the max stack usage of
+    /// their callers still accounts for them. Can be repeated
+    #[arg(long, value_name = "CLASS")]
+    hide_class: Vec<String>,
+
+    /// Don't warn about missing stack usage or type information of the functions of this class
+    /// (see `--classify`). Can be repeated
+    #[arg(long, value_name = "CLASS")]
+    quiet_class: Vec<String>,
+
+    /// consider only the call graph that starts from this node
+    start: Option<String>,
+
+    /// Another start point; can be repeated. `*` and `?` are wildcards, e.g. `'*::interrupt::*'`
+    #[arg(long = "start", value_name = "FUNCTION")]
+    starts: Vec<String>,
+
+    // (`check` subcommand) evaluate the budgets instead of producing a report
+    #[arg(skip)]
+    check: bool,
+}
+
+impl Args {
+    // the options of the analysis; `lib` is the kind of library that was built, if any
+    fn options(&self, lib: Option<Library>) -> Options {
+        Options {
+            verbose: self.verbose,
+            lib,
+            test: self.test.clone(),
+            bench: self.bench.clone(),
+            ignore_indirect: self.ignore_indirect,
+            fmt_heuristic: self.fmt_heuristic,
+            call_overhead: self.call_overhead,
+            config: self.config.clone(),
+            override_stack: self.override_stack.clone(),
+            no_builtins_analysis: self.no_builtins_analysis,
+            on_mismatch: self.on_mismatch,
+            distributed_slice: self.distributed_slice.clone(),
+            include_constructors: self.include_constructors,
+            longjmp_target: self.longjmp_target.clone(),
+            syscall: self.syscall.clone(),
+            syscall_table: self.syscall_table.clone(),
+            tail_calls: self.tail_calls,
+            critical_sections: self.critical_sections,
+            main_loop: self.main_loop.clone(),
+            drop_paths: self.drop_paths,
+            task: self.task.clone(),
+            secondary_stack: self.secondary_stack.clone(),
+            classify: self.classify.clone(),
+            quiet_class: self.quiet_class.clone(),
+            starts: self.start.iter().chain(&self.starts).cloned().collect(),
+        }
+    }
+}
+
+pub(crate) fn main() -> anyhow::Result<()> {
+    match run() {
+        Ok(ec) => process::exit(ec),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1)
+        }
+    }
+}
+
+// Font used in the dot graphs
+const FONT: &str = "monospace";
+
+// Number of callees of an indirect call listed in the JSON output
+const TOP_CALLEES: usize = 5;
+
+const SUPPORTED_NIGHTLY_HASH: &str = "2b603f95a48f10f931a61dd208fe3e5ffd64e491";
+const SUPPORTED_NIGHTLY_NAME: &str = "nightly-2023-11-13";
+const UNSUPPORTED_MODE_KEY: &str = "CARGO_CALL_STACK_UNSPPORTED_NIGHTLY";
+const UNSUPPORTED_MODE_VALUE: &str = "I won't open issues about unsupported toolchains";
+
+#[allow(deprecated)]
+fn run() -> anyhow::Result<i32> {
+    if env::var_os("CARGO_CALL_STACK_RUSTC_WRAPPER").is_some() {
+        return wrapper::wrapper();
+    }
+
+    let mut argv = env::args_os().collect::<Vec<_>>();
+    if argv.get(1).map(|arg| arg == "call-stack") == Some(true) {
+        argv.remove(1);
+    }
+    let cli = Cli::parse_from(&argv);
+
+    // `cargo call-stack report (..)`; this doesn't build anything so any toolchain will do
+    let (mut args, check) = match cli.command {
+        Some(Subcommand::Report(args)) => return compare::report(args),
+        // `cargo call-stack check (..)`; the analysis without the report, only the budgets are
+        // evaluated
+        Some(Subcommand::Check(args)) => (*args, true),
+        None => (cli.args, false),
+    };
+    args.check = check;
+
+    let meta = rustc_version::version_meta()?;
+
+    if meta.commit_hash.as_deref() != Some(SUPPORTED_NIGHTLY_HASH)
+        && env::var(UNSUPPORTED_MODE_KEY).as_deref() != Ok(UNSUPPORTED_MODE_VALUE)
+    {
+        eprintln!("Your rust toolchain does not match the last known working version, which is {SUPPORTED_NIGHTLY_NAME}.
+
+You can override the toolchain that cargo-call-stack uses like this `cargo +{SUPPORTED_NIGHTLY_NAME} call-stack (..)`.
+See the rustup documentation for other methods to change / pin the toolchain version.
+Note that the `rust-src` component must be available for the specified toolchain;
+that is you may want to run `rustup component add --toolchain {SUPPORTED_NIGHTLY_NAME} rust-src` first.
+
+If you would like to use cargo-call-stack with your current toolchain, which most likely won't work, set the following environment variable as shown below
+
+    export {UNSUPPORTED_MODE_KEY}=\"{UNSUPPORTED_MODE_VALUE}\"
+");
+
+        bail!("unsupported rust toolchain")
+    }
+
+    Builder::from_env(Env::default().default_filter_or("warn")).init();
+
+    if let Some(matrix) = &args.feature_matrix {
+        if args.features.is_some() || args.all_features {
+            bail!("--feature-matrix can't be combined with --features or --all-features");
+        }
+        if check || args.split_output.is_some() || args.editor_data.is_some() {
+            bail!(
+                "--feature-matrix can't be combined with `check`, --split-output or --editor-data"
+            );
+        }
+
+        return feature_matrix(matrix, &argv);
+    }
+
+    analysis(args, &argv, meta)
+}
+
+// everything but the command line handling
+fn analysis(mut args: Args, argv: &[OsString], meta: VersionMeta) -> anyhow::Result<i32> {
+    if args.split_output.is_some() && !matches!(args.format, OutputFormat::Dot | OutputFormat::Json)
+    {
+        bail!("--split-output only supports the `dot` and `json` formats");
+    }
+
+    let profile = Profile::Release;
+
+    let host = meta.host;
+    let cwd = env::current_dir()?;
+    let project = if args.elf.is_some() {
+        // the program has already been built; Cargo is not involved
+        None
+    } else if let Some(package) = &args.package {
+        // the artifacts of another package of the workspace
+        Some(Project::query(package_root(package)?)?)
+    } else {
+        Some(Project::query(&cwd)?)
+    };
+
+    if args.config.is_none() {
+        if let Some(project) = &project {
+            let path = project.toml().with_file_name(config::FILE_NAME);
+            if path.exists() {
+                args.config = Some(config::Config::load(&path)?);
+            }
+        }
+    }
+
+    let file = match (&args.example, &args.bin, args.lib, &args.test, &args.bench) {
+        _ if args.elf.is_some() => "",
+        (Some(f), None, false, None, None) => &**f,
+        (None, Some(f), false, None, None) => f,
+        (None, None, true, None, None) => project.as_ref().expect("UNREACHABLE").name(),
+        (None, None, false, Some(f), None) => f,
+        (None, None, false, None, Some(f)) => f,
+        _ => bail!(
+            "Please specify either --example <NAME>, --bin <NAME>, --lib, --test <NAME> or \
+             --bench <NAME>."
+        ),
+    };
+
+    for class in args.hide_class.iter().chain(&args.quiet_class) {
+        match &args.classify {
+            None => bail!("--hide-class and --quiet-class require --classify"),
+            Some(rules) if !rules.defines(class) => {
+                bail!("class `{}` is not defined by the --classify rules", class)
+            }
+            Some(_) => {}
+        }
+    }
+    let target_flag = args.target.as_deref();
+    let target = project
+        .as_ref()
+        .and_then(|project| project.target())
+        .or(target_flag)
+        .unwrap_or(&host);
+
+    let cfg = load::Cfg::query(target)?;
+    let is_wasm = cfg.is_wasm();
+    let build = match (&args.elf, &project) {
+        (Some(elf), _) => Build::external(elf, &args),
+        (None, Some(project)) => match cargo_build(
+            &args,
+            project,
+            file,
+            target_flag,
+            cfg.is_no_std,
+            is_wasm,
+            profile,
+        )? {
+            Ok(build) => build,
+            Err(code) => return Ok(code),
+        },
+        (None, None) => unreachable!(),
+    };
+
+    // `--reproducible`: the paths that differ between machines are rewritten in the outputs
+    let prefixes = if args.reproducible {
+        path_prefixes(&cwd)
+    } else {
+        vec![]
+    };
+
+    // describes the analyzed build in the dot output
+    let metadata = Metadata {
+        target: target.to_owned(),
+        profile: if args.elf.is_some() {
+            "external"
+        } else if profile.is_release() {
+            "release"
+        } else {
+            "dev"
+        },
+        toolchain: meta.short_version_string.clone(),
+        date: if args.reproducible {
+            env::var("SOURCE_DATE_EPOCH")
+                .ok()
+                .and_then(|secs| secs.trim().parse().ok())
+                .map(date)
+        } else {
+            Some(date(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|elapsed| elapsed.as_secs())
+                    .unwrap_or(0),
+            ))
+        },
+        command: iter::once("cargo call-stack".to_owned())
+            .chain(argv.iter().skip(1).map(|arg| {
+                let arg = remap_arg(&arg.to_string_lossy(), &prefixes);
+                if arg.contains(char::is_whitespace) {
+                    format!("'{}'", arg)
+                } else {
+                    arg
+                }
+            }))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+
+    let options = args.options(build.library(&args));
+    let input = build.input(&args, target, &host, file)?;
+    let inputs = load::Inputs::read(&input, &cfg, &options)?;
+    let mut program = graph::build(&inputs, &cfg, &options, args.editor_data.is_some())?;
+
+    if let Some(dir) = &args.disassembly {
+        if is_wasm {
+            warn!("--disassembly: only ELF files are supported");
+        } else {
+            let objdump = args
+                .objdump
+                .clone()
+                .unwrap_or_else(|| bitcode::llvm_tool(Some(&host), "llvm-objdump"));
+            write_disassembly(
+                dir,
+                &objdump,
+                &inputs.path,
+                &program.addr2node,
+                &prefixes,
+                &mut program.g,
+                &mut program.interner,
+            )?;
+        }
+    }
+
+    if let (Some(function), Some(elf)) = (&args.explain_frame, &program.elf) {
+        let address = |idx| {
+            program
+                .addr2node
+                .iter()
+                .find(|(_, (node, _))| *node == idx)
+                .map(|(address, _)| {
+                    if program.target.is_thumb() {
+                        address & !1
+                    } else {
+                        *address
+                    }
+                })
+        };
+        return explain_frame(function, elf, &program.g, &program.interner, address).map(|_| 0);
+    }
+
+    let analysis = propagate::analyze(program, &options)?;
+    report(analysis, &metadata, &prefixes, &args)
+}
+
+// the feature combinations of `--feature-matrix`; `default` is the empty combination
+fn parse_feature_matrix(matrix: &str) -> anyhow::Result<Vec<String>> {
+    let mut combinations = vec![];
+    for combination in matrix.split(';') {
+        let combination = combination.trim();
+        if combination.is_empty() {
+            bail!("empty combination in --feature-matrix `{}`", matrix);
+        }
+
+        let combination = if combination == "default" {
+            String::new()
+        } else {
+            combination
+                .split([',', ' '])
+                .filter(|feature| !feature.is_empty())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        if combinations.contains(&combination) {
+            bail!("duplicated combination in --feature-matrix `{}`", matrix);
+        }
+        combinations.push(combination);
+    }
+    Ok(combinations)
+}
+
+// runs the analysis once per feature combination, reusing the rest of the command line, and
+// reports the max stack usage of the roots under each combination side by side
+fn feature_matrix(matrix: &str, argv: &[OsString]) -> anyhow::Result<i32> {
+    let combinations = parse_feature_matrix(matrix)?;
+
+    // drop `--feature-matrix` and `--format`; the latter is replaced with `--format json`
+    let mut rest = vec![];
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--feature-matrix" || arg == "--format" {
+            args.next();
+        } else if !arg
+            .to_str()
+            .is_some_and(|arg| arg.starts_with("--feature-matrix=") || arg.starts_with("--format="))
+        {
+            rest.push(arg);
+        }
+    }
+
+    let exe = env::current_exe()?;
+    let mut columns = vec![];
+    for combination in combinations {
+        let name = if combination.is_empty() {
+            "default".to_owned()
+        } else {
+            combination.clone()
+        };
+        eprintln!("feature-matrix: analyzing `{}`", name);
+
+        let mut analysis = Command::new(&exe);
+        analysis.args(&rest).args(["--format", "json"]);
+        if !combination.is_empty() {
+            analysis.args(["--features", &combination]);
+        }
+        let output = analysis.stderr(Stdio::inherit()).output()?;
+        if !output.status.success() {
+            error!("the analysis of the features `{}` failed", name);
+            return Ok(output.status.code().unwrap_or(1));
+        }
+
+        let maxima = compare::maxima(str::from_utf8(&output.stdout)?)
+            .map_err(|e| anyhow!("features `{}`: {}", name, e))?;
+        columns.push((name, maxima));
+    }
+
+    print!("{}", compare::matrix(&columns));
+
+    Ok(0)
+}
+
+// prints the local variables that take up the stack frame of `function`, largest first
+fn explain_frame(
+    function: &str,
+    elf: &object::File,
+    g: &Graph<Node, usize>,
+    interner: &Interner,
+    address: impl Fn(NodeIndex) -> Option<u64>,
+) -> anyhow::Result<()> {
+    let Some(idx) = g
+        .node_indices()
+        .find(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, function))
+    else {
+        bail!(
+            "--explain-frame: function `{}` not found{}",
+            function,
+            suggestions(g, interner, function)
+        );
+    };
+
+    let variables = match address(idx).map(|address| dwarf::frame_variables(elf, address)) {
+        Some(Ok(Some(variables))) => variables,
+        Some(Err(e)) => bail!(
+            "--explain-frame: couldn't parse the DWARF information: {}",
+            e
+        ),
+        _ => bail!(
+            "--explain-frame: there's no debug information for `{}`; build the program with \
+             `debug = true` in its profile",
+            interner.demangled(g[idx].name)
+        ),
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(
+        stdout,
+        "{}: {} bytes",
+        interner.short(g[idx].name),
+        g[idx].local
+    )?;
+
+    let mut variables = variables;
+    variables.sort_by(|a, b| b.size.cmp(&a.size).then(a.offset.cmp(&b.offset)));
+
+    let width = variables
+        .iter()
+        .map(|variable| variable.size.to_string().len())
+        .chain([g[idx].local.to_string().len()])
+        .max()
+        .unwrap_or(0);
+    for variable in &variables {
+        write!(stdout, "{:>width$} {}", variable.size, variable.name)?;
+        if let Some(ty) = &variable.ty {
+            write!(stdout, ": {}", ty)?;
+        }
+        if let Some(inlined_from) = &variable.inlined_from {
+            write!(stdout, " (inlined from `{}`)", inlined_from)?;
+        }
+        writeln!(stdout)?;
+    }
+
+    // variables whose lifetimes don't overlap may share a stack slot so count each byte once
+    let mut ranges = variables
+        .iter()
+        .map(|variable| (variable.offset, variable.offset + variable.size as i64))
+        .collect::<Vec<_>>();
+    ranges.sort_unstable();
+    let mut attributed = 0;
+    let mut end = i64::MIN;
+    for (start, stop) in ranges {
+        let start = start.max(end);
+        if stop > start {
+            attributed += (stop - start) as u64;
+            end = stop;
+        }
+    }
+
+    if let Local::Exact(local) = g[idx].local {
+        writeln!(
+            stdout,
+            "{:>width$} (saved registers, spills and padding)",
+            local.saturating_sub(attributed)
+        )?;
+    }
+
+    Ok(())
+}
+
+// parses a `FUNCTION=TARGET` argument
+fn parse_longjmp_target(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((function, target)) if !function.trim().is_empty() && !target.trim().is_empty() => {
+            Ok((function.trim().to_owned(), target.trim().to_owned()))
+        }
+        _ => Err(format!("expected `FUNCTION=TARGET`, found `{}`", s)),
+    }
+}
+
+// parses a `FUNCTION=SYMBOL` argument
+fn parse_secondary_stack(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((function, symbol)) if !function.trim().is_empty() && !symbol.trim().is_empty() => {
+            Ok((function.trim().to_owned(), symbol.trim().to_owned()))
+        }
+        _ => Err(format!("expected `FUNCTION=SYMBOL`, found `{}`", s)),
+    }
+}
+
+// reads and parses a `--config` file
+fn parse_config(path: &str) -> Result<config::Config, String> {
+    config::Config::load(Path::new(path)).map_err(|e| e.to_string())
+}
+
+// reads and parses a `--classify` rules file
+fn parse_classify(path: &str) -> Result<classify::Rules, String> {
+    let rules = fs::read_to_string(path).map_err(|e| format!("couldn't read `{}`: {}", path, e))?;
+    classify::Rules::parse(&rules).map_err(|e| format!("`{}`: {}", path, e))
+}
+
+// parses a `SYMBOL=BYTES` argument
+fn parse_override(s: &str) -> Result<(String, u64), String> {
+    let (symbol, bytes) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected `SYMBOL=BYTES`, found `{}`", s))?;
+    let bytes = bytes
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of bytes", bytes.trim()))?;
+
+    Ok((symbol.trim().to_owned(), bytes))
+}
+
+// parses a `NUMBER=FUNCTION` argument
+fn parse_syscall(s: &str) -> Result<(u8, String), String> {
+    let (number, function) = s
+        .split_once('=')
+        .filter(|(_, function)| !function.trim().is_empty())
+        .ok_or_else(|| format!("expected `NUMBER=FUNCTION`, found `{}`", s))?;
+    let number = number.trim();
+    let number = if let Some(hex) = number.strip_prefix("0x") {
+        u8::from_str_radix(hex, 16)
+    } else {
+        number.parse()
+    }
+    .map_err(|_| format!("`{}` is not an `svc` number (0-255)", number))?;
+
+    Ok((number, function.trim().to_owned()))
+}
+
+// parses a `[FUNCTION=]BYTES` argument
+fn parse_budget(s: &str) -> Result<(Option<String>, u64), String> {
+    let (function, bytes) = match s.rsplit_once('=') {
+        Some((function, _)) if function.trim().is_empty() => {
+            return Err(format!("expected `[FUNCTION=]BYTES`, found `{}`", s))
+        }
+        Some((function, bytes)) => (Some(function.trim().to_owned()), bytes),
+        None => (None, s),
+    };
+    let bytes = bytes
+        .trim()
+        .parse()
+        .map_err(|_| format!("`{}` is not a number of bytes", bytes.trim()))?;
+
+    Ok((function, bytes))
+}
+
+// prints the result of the analysis in the format selected with `--format`
+fn report(
+    mut analysis: Analysis,
+    metadata: &Metadata,
+    prefixes: &[(PathBuf, &str)],
+    args: &Args,
+) -> anyhow::Result<i32> {
+    let interner = &mut analysis.interner;
+    let mut g = analysis.g;
+    let mut roots = analysis.roots;
+    let start_nodes = analysis.starts;
+    let mut cycles = analysis.cycles;
+    let tail_calls = analysis.tail_calls;
+    let mut shortened = analysis.shortened;
+    let locations = analysis.locations;
+    let phases = analysis.phases;
+    let preemption = analysis.preemption;
+    let drop_paths = analysis.drop_paths;
+    let tasks = analysis.tasks;
+    let on_secondary_stacks = analysis.on_secondary_stacks;
+
+    // (`--max-stack`) the functions whose max stack usage exceeds the limit, evaluated before the
+    // report hides any of them
+    let mut exceeded = vec![];
+    for (function, bytes) in &args.max_stack {
+        let limited = if let Some(function) = function {
+            g.node_indices()
+                .filter(|idx| !g[*idx].dashed && is_named(interner, g[*idx].name, function))
+                .collect::<Vec<_>>()
+        } else if roots.is_empty() {
+            uncalled(&g)
+        } else {
+            roots.clone()
+        };
+
+        for idx in limited {
+            if let Some(max) = g[idx].max.filter(|max| max.bytes() > *bytes) {
+                exceeded.push((interner.demangled(g[idx].name).to_owned(), max, *bytes));
+            }
+        }
+    }
+
+    if let Some(path) = &args.editor_data {
+        let mut annotations = Annotations::new();
+        for node in g.node_weights() {
+            if let Some((file, line)) = locations.get(&node.name) {
+                annotations.push(
+                    remap(file, prefixes),
+                    Annotation {
+                        line: *line,
+                        local: match node.local {
+                            Local::Exact(n) => Some(n),
+                            Local::Unknown => None,
+                        },
+                        max: node.max.map(|max| match max {
+                            Max::Exact(n) => editor::Max::Exact(n),
+                            Max::LowerBound(n) => editor::Max::LowerBound(n),
+                        }),
+                        function: interner.short(node.name).to_owned(),
+                    },
+                );
+            }
+        }
+
+        fs::write(path, annotations.to_string())
+            .map_err(|e| anyhow!("couldn't write `{}`: {}", path.display(), e))?;
+    }
+
+    if args.verbose && args.classify.is_some() {
+        classes(&g, interner);
+    }
+
+    // the functions that were only reachable through the aggregated formatters
+    let formatters = if args.aggregate_fmt {
+        aggregate_formatters(&mut g, interner, &roots)
+    } else {
+        HashSet::new()
+    };
+
+    // the functions that own the max stack usage of the heaviest start point or, if none was
+    // given, of the heaviest root
+    let analyzed_root = if matches!(args.format, OutputFormat::Dominators | OutputFormat::Splits) {
+        let candidates = if !start_nodes.is_empty() {
+            start_nodes.clone()
+        } else if roots.is_empty() {
+            uncalled(&g)
+        } else {
+            roots.clone()
+        };
+        candidates
+            .into_iter()
+            .max_by_key(|idx| g[*idx].max.map(Max::bytes))
+    } else {
+        None
+    };
+    let mut ownership = analyzed_root
+        .filter(|_| args.format == OutputFormat::Dominators)
+        .map(|root| (root, ownership(&g, root, &cycles, &tail_calls)));
+    // the calls that keep the common path of a function above its max stack usage without them
+    let mut split_calls = analyzed_root
+        .filter(|_| args.format == OutputFormat::Splits)
+        .map(|root| split_candidates(&g, root, &cycles, &tail_calls))
+        .unwrap_or_default();
+
+    if args.min_max.is_some() || !args.hide_class.is_empty() || !formatters.is_empty() {
+        if roots.is_empty() && !args.hide_class.is_empty() {
+            // the functions called only by hidden ones are not roots
+            roots = uncalled(&g);
+        }
+
+        // the max stack usage of a function is never smaller than that of its callees so hiding a
+        // node also hides all the functions it calls; the max of its callers already accounts for
+        // them. Lower bounds are kept as their actual stack usage may be above the threshold
+        let mut old2new = BTreeMap::new();
+        g = g.filter_map(
+            |idx, node| {
+                if formatters.contains(&idx) {
+                    return None;
+                }
+
+                if let (Some(min_max), Some(Max::Exact(max))) = (args.min_max, node.max) {
+                    if max < min_max {
+                        return None;
+                    }
+                }
+
+                // `--hide-class`; the max of their callers also accounts for them
+                if node.class.is_some_and(|class| {
+                    args.hide_class
+                        .iter()
+                        .any(|hidden| hidden == interner.name(class))
+                }) {
+                    return None;
+                }
+
+                old2new.insert(idx, NodeIndex::new(old2new.len()));
+                Some(node.clone())
+            },
+            |_, sites| Some(*sites),
+        );
+
+        shortened = shortened
+            .into_iter()
+            .filter_map(|(idx, name)| old2new.get(&idx).map(|idx| (*idx, name)))
+            .collect();
+        roots = roots
+            .iter()
+            .filter_map(|root| old2new.get(root).cloned())
+            .collect();
+        // a function owns at most its max stack usage so the hidden ones are below the threshold
+        ownership = ownership.and_then(|(root, owned)| {
+            let owned = owned
+                .into_iter()
+                .filter_map(|(idx, bytes)| old2new.get(&idx).map(|idx| (*idx, bytes)))
+                .collect();
+            old2new.get(&root).map(|root| (*root, owned))
+        });
+        // the common path of a caller is below its max so the hidden callers have no splits
+        split_calls = split_calls
+            .into_iter()
+            .filter_map(|(caller, callee, common)| {
+                Some((*old2new.get(&caller)?, *old2new.get(&callee)?, common))
+            })
+            .collect();
+        cycles = cycles
+            .into_iter()
+            .filter_map(|cycle| {
+                let members = cycle
+                    .members
+                    .iter()
+                    .filter_map(|member| old2new.get(member).cloned())
+                    .collect::<Vec<_>>();
+
+                if members.is_empty() {
+                    None
+                } else {
+                    Some(Cycle { members, ..cycle })
+                }
+            })
+            .collect();
+    }
+
+    // here we shorten the name of the symbols if it doesn't result in ambiguity
+    for (idx, name) in shortened {
+        g[idx].name = interner.intern(name);
+    }
+
+    // explain why the max stack usage of a root is only a lower bound
+    let confidence = if roots.is_empty() {
+        // like in `cargo call-stack report`, fall back to the functions that no one calls
+        uncalled(&g)
+    } else {
+        roots.clone()
+    }
+    .into_iter()
+    .map(|root| (root, causes(&g, root, &cycles, interner)))
+    .collect::<Vec<_>>();
+
+    // a task's stack is its budget; the entry point may have been filtered out by the start point
+    for (function, bytes) in &tasks {
+        for idx in g.node_indices() {
+            if !g[idx].dashed && is_named(interner, g[idx].name, function) {
+                if let Some(max) = g[idx].max {
+                    g[idx].budget = Some(Budget::new(max, *bytes, args.budget_warning));
+                }
+            }
+        }
+    }
+
+    // the functions called on a secondary stack can use all of it
+    for (stack, entries) in &on_secondary_stacks {
+        let (symbol, size) = match &stack.buffer {
+            Some((symbol, size)) => (symbol, *size),
+            None => continue,
+        };
+        for entry in entries {
+            if let Some(max) = g[*entry].max {
+                g[*entry].budget = Some(Budget::new(max, size, args.budget_warning));
+
+                if args.verbose {
+                    eprintln!(
+                        "secondary stack: `{}` runs on `{}` ({} bytes) and uses {}{} bytes of it",
+                        interner.demangled(g[*entry].name),
+                        symbol,
+                        size,
+                        if let Max::LowerBound(_) = max {
+                            "at least "
+                        } else {
+                            ""
+                        },
+                        max.bytes()
+                    );
+                }
+            }
+        }
+    }
+
+    // budgets for specific functions take precedence over the one for the roots
+    let budgets = args
+        .budget
+        .iter()
+        .map(|budget| ("--budget", budget))
+        .chain(args.max_stack.iter().map(|budget| ("--max-stack", budget)));
+    for (option, (function, bytes)) in budgets {
+        if let Some(function) = function {
+            let mut found = false;
+            for idx in g.node_indices() {
+                if !g[idx].dashed && is_named(interner, g[idx].name, function) {
+                    found = true;
+
+                    if let Some(max) = g[idx].max {
+                        g[idx].budget = Some(Budget::new(max, *bytes, args.budget_warning));
+                    }
+                }
+            }
+
+            if !found {
+                warn!(
+                    "{}: function `{}` not found{}",
+                    option,
+                    function,
+                    suggestions(&g, interner, function)
+                );
+            }
+        }
+    }
+
+    for (function, bytes) in args.budget.iter().chain(&args.max_stack) {
+        if function.is_none() {
+            for (root, _) in &confidence {
+                if let (None, Some(max)) = (g[*root].budget, g[*root].max) {
+                    g[*root].budget = Some(Budget::new(max, *bytes, args.budget_warning));
+                }
+            }
+        }
+    }
+
+    if args.check {
+        if !g.node_weights().any(|node| node.budget.is_some()) {
+            bail!("check: there are no budgets to evaluate; set them with `--budget` or `--task`");
+        }
+
+        return Ok(check(&g, &cycles, &tail_calls, interner)?);
+    }
+
+    if matches!(args.format, OutputFormat::Dot | OutputFormat::Generic) {
+        // these formats have no place for this information
+        for (root, causes) in &confidence {
+            if !causes.is_empty() {
+                warn!(
+                    "the max stack usage of `{}` is a lower bound because of: {}",
+                    interner.demangled(g[*root].name),
+                    causes
+                        .iter()
+                        .map(|cause| cause.display(&g, interner))
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                );
+            }
+        }
+    }
+
+    if let Some(phases) = &phases {
+        if !matches!(args.format, OutputFormat::Top | OutputFormat::Json) {
+            // these formats have no place for this information
+            warn!(
+                "the max stack usage of `{}` is {} bytes during initialization and {} bytes \
+                 in its main loop",
+                phases.function, phases.init, phases.steady
+            );
+        }
+    }
+
+    if let Some(preemption) = &preemption {
+        if args.verbose && !matches!(args.format, OutputFormat::Top | OutputFormat::Json) {
+            eprintln!(
+                "preemption: the max stack usage of `{}` preempted by `{}` is {}{} bytes, \
+                 including the {}-byte exception frame",
+                preemption.entry,
+                preemption.handler,
+                if let Max::LowerBound(_) = preemption.max {
+                    "at least "
+                } else {
+                    ""
+                },
+                preemption.max.bytes(),
+                preemption.frame
+            );
+        }
+    }
+
+    if args.verbose && !matches!(args.format, OutputFormat::Top | OutputFormat::Json) {
+        for path in &drop_paths {
+            eprintln!(
+                "drop paths: the max stack usage of `{}` is {} bytes without destructors and {} \
+                 bytes with them",
+                path.function, path.straight, path.drop
+            );
+        }
+    }
+
+    // the call chain through which each root reaches its max stack usage
+    let critical = critical_edges(&g, &cycles, &tail_calls);
+    let paths = confidence
+        .iter()
+        .map(|(root, _)| (*root, worst_path(&g, &critical, *root)))
+        .collect::<HashMap<_, _>>();
+
+    if let Some(dir) = &args.split_output {
+        let roots = confidence.iter().map(|(root, _)| *root).collect::<Vec<_>>();
+        split_output(&g, &cycles, &roots, &paths, dir, metadata, args, interner)?;
+    }
+
+    match args.format {
+        OutputFormat::Dot => dot(
+            g,
+            &cycles,
+            args.merge_cycles,
+            metadata,
+            interner,
+            &mut io::stdout().lock(),
+        )?,
+        OutputFormat::Top => top(
+            g,
+            &confidence,
+            &paths,
+            phases.as_ref(),
+            preemption.as_ref(),
+            &drop_paths,
+            interner,
+        )?,
+        OutputFormat::Generic => generic(g, interner)?,
+        OutputFormat::Json => json(
+            g,
+            &cycles,
+            &confidence,
+            &paths,
+            &roots,
+            phases.as_ref(),
+            preemption.as_ref(),
+            &drop_paths,
+            interner,
+            &mut io::stdout().lock(),
+        )?,
+        OutputFormat::Dominators => dominators(g, ownership, interner)?,
+        OutputFormat::Splits => splits(g, &split_calls, interner)?,
+        OutputFormat::Table => table(g, &cycles, &tail_calls, interner)?,
+    }
+
+    if exceeded.is_empty() {
+        Ok(0)
+    } else {
+        for (function, max, bytes) in &exceeded {
+            error!(
+                "--max-stack: the max stack usage of `{}` is {}{} bytes, above the limit of {} \
+                 bytes",
+                function,
+                if let Max::LowerBound(_) = max {
+                    "at least "
+                } else {
+                    ""
+                },
+                max.bytes(),
+                bytes
+            );
+        }
+
+        Ok(1)
+    }
+}
+
+// (`--classify --verbose`) prints, for each class, the number of functions, their combined local
+// stack usage and the function with the highest max stack usage
+fn classes(g: &Graph<Node, usize>, interner: &Interner) {
+    #[derive(Default)]
+    struct Stats {
+        functions: usize,
+        local: u64,
+        unknown: usize,
+        heaviest: Option<NodeIndex>,
+    }
+
+    let mut classes = BTreeMap::<&str, Stats>::new();
+    for idx in g.node_indices() {
+        let class = match g[idx].class {
+            Some(class) => interner.name(class),
+            None => continue,
+        };
+
+        let stats = classes.entry(class).or_default();
+        stats.functions += 1;
+        match g[idx].local {
+            Local::Exact(n) => stats.local += n,
+            Local::Unknown => stats.unknown += 1,
+        }
+        let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes);
+        if stats
+            .heaviest
+            .is_none_or(|heaviest| bytes(idx) > bytes(heaviest))
+        {
+            stats.heaviest = Some(idx);
+        }
+    }
+
+    for (class, stats) in classes {
+        let unknown = if stats.unknown == 0 {
+            String::new()
+        } else {
+            format!(" ({} unknown)", stats.unknown)
+        };
+        let heaviest = stats
+            .heaviest
+            .and_then(|idx| {
+                g[idx].max.map(|max| {
+                    format!(
+                        "; the highest max is {} bytes (`{}`)",
+                        max,
+                        interner.demangled(g[idx].name)
+                    )
+                })
+            })
+            .unwrap_or_default();
+        eprintln!(
+            "class `{}`: {} function(s), {} bytes of local stack usage{}{}",
+            class, stats.functions, stats.local, unknown, heaviest
+        );
+    }
+}
+
+// (`check` subcommand) prints whether each budgeted function is within its budget and, for the
+// ones that are not, the path that reaches their max stack usage. Returns the exit code: 1 if some
+// budget is exceeded
+fn check(
+    g: &Graph<Node, usize>,
+    cycles: &[Cycle],
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+    interner: &Interner,
+) -> io::Result<i32> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes).unwrap_or(0);
+    let critical = critical_edges(g, cycles, tail_calls);
+
+    let mut budgeted = g
+        .node_indices()
+        .filter_map(|idx| g[idx].budget.map(|budget| (idx, budget)))
+        .collect::<Vec<_>>();
+    // the failures first
+    budgeted.sort_by_key(|(idx, budget)| (cmp::Reverse(budget.status), *idx));
+
+    let mut failures = 0;
+    for (idx, budget) in &budgeted {
+        let verdict = match budget.status {
+            Status::Ok => "PASS",
+            Status::Near => "NEAR",
+            Status::Over => "FAIL",
+        };
+        let lower_bound = if let Some(Max::LowerBound(_)) = g[*idx].max {
+            " (lower bound)"
+        } else {
+            ""
+        };
+        writeln!(
+            stdout,
+            "{} {}: {} bytes{}, budget {} bytes",
+            verdict,
+            interner.demangled(g[*idx].name),
+            bytes(*idx),
+            lower_bound,
+            budget.bytes
+        )?;
+
+        if budget.status == Status::Over {
+            failures += 1;
+
+            writeln!(
+                stdout,
+                "    {}",
+                worst_path(g, &critical, *idx)
+                    .iter()
+                    .map(|idx| format!("{} ({})", interner.demangled(g[*idx].name), g[*idx].local))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            )?;
+        }
+    }
+
+    if failures == 0 {
+        writeln!(stdout, "check: all {} budgets met", budgeted.len())?;
+        Ok(0)
+    } else {
+        writeln!(
+            stdout,
+            "check: {} of {} budgets exceeded",
+            failures,
+            budgeted.len()
+        )?;
+        Ok(1)
+    }
+}
+
+// why the max stack usage of `root` is only a lower bound; empty if it's exact
+fn causes(
+    g: &Graph<Node, usize>,
+    root: NodeIndex,
+    cycles: &[Cycle],
+    interner: &Interner,
+) -> BTreeSet<Cause> {
+    let mut causes = BTreeSet::new();
+    if !matches!(g[root].max, Some(Max::LowerBound(_))) {
+        return causes;
+    }
+
+    let mut reachable = HashSet::new();
+    let mut dfs = Dfs::new(g, root);
+    while let Some(idx) = dfs.next(g) {
+        reachable.insert(idx);
+
+        if g[idx].local == Local::Unknown {
+            if interner.name(g[idx].name) == "?" {
+                for caller in g.neighbors_directed(idx, Direction::Incoming) {
+                    causes.insert(Cause::Untyped(caller));
+                }
+            } else {
+                causes.insert(Cause::Unknown(idx));
+            }
+        }
+    }
+
+    for cycle in cycles {
+        if matches!(cycle.local, Max::LowerBound(_))
+            && cycle
+                .members
+                .iter()
+                .any(|member| reachable.contains(member))
+        {
+            let mut members = cycle.members.clone();
+            members.sort();
+            causes.insert(Cause::Cycle(members));
+        }
+    }
+
+    causes
+}
+
+// (`--aggregate-fmt`) replaces the `core::fmt` trait methods that indirect calls may reach with a
+// single fictitious node whose stack usage is the max over all of them. Returns the functions that
+// are no longer reachable from the roots or, if there are none, from the functions no one calls
+fn aggregate_formatters(
+    g: &mut Graph<Node, usize>,
+    interner: &mut Interner,
+    roots: &[NodeIndex],
+) -> HashSet<NodeIndex> {
+    let calls = g
+        .edge_references()
+        .filter(|edge| g[edge.source()].callees.is_some() && g[edge.target()].formatter)
+        .map(|edge| edge.id())
+        .collect::<Vec<_>>();
+    let mut formatters = calls
+        .iter()
+        .map(|edge| g.edge_endpoints(*edge).expect("UNREACHABLE").1)
+        .collect::<Vec<_>>();
+    formatters.sort();
+    formatters.dedup();
+
+    // without stack usage information there's nothing to aggregate
+    let Some(max) = formatters
+        .iter()
+        .map(|formatter| g[*formatter].max)
+        .collect::<Option<Vec<_>>>()
+        .and_then(|maxes| max_of(maxes.into_iter()))
+    else {
+        return HashSet::new();
+    };
+
+    let entries = if roots.is_empty() {
+        uncalled(g)
+    } else {
+        roots.to_vec()
+    };
+
+    let any = g.add_node(Node(
+        interner.intern("<any formatter>"),
+        Some(max.bytes()),
+        true,
+    ));
+    g[any].max = Some(max);
+    g[any].callees = Some(formatters.len());
+
+    // NOTE removing an edge invalidates the index of the last one so remove from the end
+    let mut calls = calls
+        .into_iter()
+        .map(|edge| {
+            let (caller, _) = g.edge_endpoints(edge).expect("UNREACHABLE");
+            (edge, caller)
+        })
+        .collect::<Vec<_>>();
+    calls.sort_by_key(|(edge, _)| cmp::Reverse(*edge));
+    let mut callers = vec![];
+    for (edge, caller) in calls {
+        g.remove_edge(edge);
+        if !callers.contains(&caller) {
+            callers.push(caller);
+        }
+    }
+    for caller in callers {
+        g.add_edge(caller, any, 1);
+    }
+
+    let mut reachable = HashSet::new();
+    let mut dfs = Dfs::empty(&*g);
+    for entry in entries {
+        dfs.move_to(entry);
+        while let Some(idx) = dfs.next(&*g) {
+            reachable.insert(idx);
+        }
+    }
+
+    g.node_indices()
+        .filter(|idx| !reachable.contains(idx))
+        .collect()
+}
+
+// (`--disassembly`) writes the disassembly of each function to its own file in `dir`
+fn write_disassembly<'a>(
+    dir: &Path,
+    objdump: &Path,
+    elf: &Path,
+    addr2node: &BTreeMap<u64, (NodeIndex, u64)>,
+    prefixes: &[(PathBuf, &str)],
+    g: &mut Graph<Node, usize>,
+    interner: &mut Interner<'a>,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)
+        .map_err(|e| anyhow!("couldn't create directory `{}`: {}", dir.display(), e))?;
+
+    // the blocks of the data in a function, e.g. its literal pool, belong to the function
+    let mut functions = BTreeMap::<NodeIndex, String>::new();
+    for (address, text) in disassembly::disassemble(objdump, elf)? {
+        if let Some(idx) = function_at(addr2node, address) {
+            let function = functions.entry(idx).or_default();
+            if !function.is_empty() {
+                function.push('\n');
+            }
+            function.push_str(&text);
+        }
+    }
+
+    let mut names = HashSet::new();
+    for (idx, text) in functions {
+        // monomorphizations that share a name are told apart by `file_stem`
+        let name = interner.short(g[idx].name);
+        let path = dir.join(format!("{}.s", file_stem(name, &mut names)));
+        fs::write(&path, text)
+            .map_err(|e| anyhow!("couldn't write `{}`: {}", path.display(), e))?;
+        // the link, which `--reproducible` makes relative
+        let link = remap(&path, prefixes);
+        g[idx].disassembly = Some(interner.intern(link.display().to_string()));
+    }
+
+    Ok(())
+}
+
+// a file name for the function `name` that's not in `names` yet, e.g.
+// `<app::Foo as app::Bar>::baz` -> `_app__Foo_as_app__Bar___baz`
+fn file_stem(name: &str, names: &mut HashSet<String>) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    // functions whose names only differ in the characters that were replaced
+    let mut unique = name.clone();
+    let mut i = 1;
+    while !names.insert(unique.clone()) {
+        unique = format!("{}-{}", name, i);
+        i += 1;
+    }
+    unique
+}
+
+// writes the call graph of each root to its own file in `dir`
+#[allow(clippy::too_many_arguments)]
+fn split_output(
+    g: &Graph<Node, usize>,
+    cycles: &[Cycle],
+    roots: &[NodeIndex],
+    paths: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    dir: &Path,
+    metadata: &Metadata,
+    args: &Args,
+    interner: &Interner,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)
+        .map_err(|e| anyhow!("couldn't create directory `{}`: {}", dir.display(), e))?;
+
+    let mut names = HashSet::new();
+    for root in roots {
+        let (g2, one2two) = reachable(g, &[*root]);
+        let cycles = cycles
+            .iter()
+            .filter_map(|cycle| {
+                let members = cycle
+                    .members
+                    .iter()
+                    .filter_map(|member| one2two.get(member).cloned())
+                    .collect::<Vec<_>>();
+
+                // a cycle is either fully reachable or not reachable at all
+                if members.is_empty() {
+                    None
+                } else {
+                    Some(Cycle {
+                        members,
+                        local: cycle.local,
+                    })
+                }
+            })
+            .collect::<Vec<_>>();
+        let paths = paths
+            .iter()
+            .filter_map(|(root, path)| {
+                Some((
+                    *one2two.get(root)?,
+                    path.iter()
+                        .map(|idx| one2two.get(idx).cloned())
+                        .collect::<Option<Vec<_>>>()?,
+                ))
+            })
+            .collect::<HashMap<_, _>>();
+        let root = one2two[root];
+        let causes = causes(&g2, root, &cycles, interner);
+
+        let name = file_stem(interner.demangled(g2[root].name), &mut names);
+        let path = dir.join(format!(
+            "{}.{}",
+            name,
+            if args.format == OutputFormat::Json {
+                "json"
+            } else {
+                "dot"
+            }
+        ));
+        let file = fs::File::create(&path)
+            .map_err(|e| anyhow!("couldn't create `{}`: {}", path.display(), e))?;
+        let mut out = io::BufWriter::new(file);
+        if args.format == OutputFormat::Json {
+            json(
+                g2,
+                &cycles,
+                &[(root, causes)],
+                &paths,
+                &[root],
+                None,
+                None,
+                &[],
+                interner,
+                &mut out,
+            )?;
+        } else {
+            dot(g2, &cycles, args.merge_cycles, metadata, interner, &mut out)?;
+        }
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+// the edges on the worst-case paths: those through which the caller reaches its max stack usage
+fn critical_edges(
+    g: &Graph<Node, usize>,
+    cycles: &[Cycle],
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> HashSet<EdgeIndex> {
+    let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes).unwrap_or(0);
+
+    let mut cycle_of = HashMap::new();
+    for (i, cycle) in cycles.iter().enumerate() {
+        for member in &cycle.members {
+            cycle_of.insert(*member, i);
+        }
+    }
+
+    g.edge_references()
+        .filter(|edge| {
+            let (caller, callee) = (edge.source(), edge.target());
+            if tail_calls.contains(&(caller, callee)) {
+                return bytes(callee) == bytes(caller);
+            }
+
+            let frame = match cycle_of.get(&caller) {
+                Some(i) if cycle_of.get(&callee) == Some(i) => return true,
+                Some(i) => cycles[*i].local.bytes(),
+                None => match g[caller].local {
+                    Local::Exact(n) => n,
+                    Local::Unknown => 0,
+                },
+            };
+            bytes(callee) + frame == bytes(caller)
+        })
+        .map(|edge| edge.id())
+        .collect()
+}
+
+// follows the worst-case path from `start` down to the function that reaches its max stack usage
+fn worst_path(
+    g: &Graph<Node, usize>,
+    critical: &HashSet<EdgeIndex>,
+    start: NodeIndex,
+) -> Vec<NodeIndex> {
+    let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes).unwrap_or(0);
+
+    let mut path = vec![start];
+    let mut current = start;
+    while let Some(next) = g
+        .edges(current)
+        .filter(|edge| critical.contains(&edge.id()))
+        .map(|edge| edge.target())
+        .max_by_key(|callee| (bytes(*callee), cmp::Reverse(*callee)))
+    {
+        if path.contains(&next) {
+            // recursion
+            break;
+        }
+        path.push(next);
+        current = next;
+    }
+    path
+}
+
+// the number of bytes of the max stack usage of `root` that each function "owns": its own frame
+// plus the frames that, on the worst-case paths, can only be reached through it. That's how much
+// slimming down or removing the function saves, unless another worst-case path bypasses it.
+// Sorted in descending order; functions that own nothing are omitted
+fn ownership(
+    g: &Graph<Node, usize>,
+    root: NodeIndex,
+    cycles: &[Cycle],
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> Vec<(NodeIndex, u64)> {
+    let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes).unwrap_or(0);
+
+    let critical = critical_edges(g, cycles, tail_calls);
+    let worst = EdgeFiltered::from_fn(g, |edge| critical.contains(&edge.id()));
+    let dominators = algo::dominators::simple_fast(&worst, root);
+
+    // the largest max stack usage among the functions that are reached from a subtree of the
+    // dominator tree but are not part of it; the subtree's root doesn't own that part
+    let mut exits = HashMap::new();
+    for edge in g
+        .edge_references()
+        .filter(|edge| critical.contains(&edge.id()))
+    {
+        let callee = edge.target();
+        if let (Some(caller_doms), Some(callee_doms)) = (
+            dominators.dominators(edge.source()),
+            dominators.dominators(callee),
+        ) {
+            let callee_doms = callee_doms.collect::<HashSet<_>>();
+            for dom in caller_doms.filter(|dom| !callee_doms.contains(dom)) {
+                let exit = exits.entry(dom).or_insert(0);
+                *exit = cmp::max(*exit, bytes(callee));
+            }
+        }
+    }
+
+    let mut owned = g
+        .node_indices()
+        .filter(|idx| dominators.dominators(*idx).is_some())
+        .map(|idx| {
+            (
+                idx,
+                bytes(idx).saturating_sub(exits.get(&idx).cloned().unwrap_or(0)),
+            )
+        })
+        .filter(|(_, bytes)| *bytes != 0)
+        .collect::<Vec<_>>();
+    owned.sort_by_key(|(_, bytes)| cmp::Reverse(*bytes));
+    owned
+}
+
+// (heuristic) the calls on the worst-case paths of `root` where a function with a small frame
+// reaches a callee with a larger frame that it's not limited to, i.e. it also has other, lighter
+// callees. The callee must be reachable, on the worst-case paths, only through that caller.
+// Returns the caller, the callee and the max stack usage of the caller without that call (its
+// "common path"); sorted by how much that is below the caller's max, in descending order
+fn split_candidates(
+    g: &Graph<Node, usize>,
+    root: NodeIndex,
+    cycles: &[Cycle],
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+) -> Vec<(NodeIndex, NodeIndex, u64)> {
+    let bytes = |idx: NodeIndex| g[idx].max.map(Max::bytes).unwrap_or(0);
+
+    let critical = critical_edges(g, cycles, tail_calls);
+    let worst = EdgeFiltered::from_fn(g, |edge| critical.contains(&edge.id()));
+    let dominators = algo::dominators::simple_fast(&worst, root);
+
+    let mut splits = vec![];
+    for edge in g
+        .edge_references()
+        .filter(|edge| critical.contains(&edge.id()))
+    {
+        let (caller, callee) = (edge.source(), edge.target());
+        if dominators.immediate_dominator(callee) != Some(caller)
+            || cycles.iter().any(|cycle| cycle.members.contains(&caller))
+        {
+            continue;
+        }
+
+        let frame = match (g[caller].local, g[callee].local) {
+            (Local::Exact(frame), Local::Exact(callee_frame)) if frame < callee_frame => frame,
+            _ => continue,
+        };
+
+        let others = g
+            .neighbors(caller)
+            .filter(|other| *other != callee && *other != caller)
+            .collect::<HashSet<_>>();
+        if others.is_empty() {
+            // the call may be unconditional
+            continue;
+        }
+
+        let common = others
+            .iter()
+            .map(|other| {
+                if tail_calls.contains(&(caller, *other)) {
+                    bytes(*other)
+                } else {
+                    frame + bytes(*other)
+                }
+            })
+            .fold(frame, cmp::max);
+        if common < bytes(caller) {
+            splits.push((caller, callee, common));
+        }
+    }
+
+    splits.sort_by_key(|(caller, callee, common)| {
+        (cmp::Reverse(bytes(*caller) - common), *caller, *callee)
+    });
+    splits
+}
+
+fn dot(
+    g: Graph<Node, usize>,
+    cycles: &[Cycle],
+    merge_cycles: bool,
+    metadata: &Metadata,
+    interner: &Interner,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // maps the members of a cycle to the node that replaces the cycle
+    let mut merged = HashMap::new();
+    if merge_cycles {
+        for (i, cycle) in cycles.iter().enumerate() {
+            for member in &cycle.members {
+                merged.insert(*member, i);
+            }
+        }
+    }
+
+    let id = |node: NodeIndex| {
+        if let Some(i) = merged.get(&node) {
+            format!("scc{}", i)
+        } else {
+            node.index().to_string()
+        }
+    };
+
+    writeln!(out, "digraph {{")?;
+    writeln!(out, "    node [fontname={} shape=box]", FONT)?;
+
+    for (i, node) in g.raw_nodes().iter().enumerate() {
+        if merged.contains_key(&NodeIndex::new(i)) {
+            continue;
+        }
+
+        let node = &node.weight;
+
+        // HTML-like label; the max stack usage stands out and the local one is secondary
+        write!(
+            out,
+            "    {} [label=<{}",
+            i,
+            html(interner.demangled(node.name))
+        )?;
+
+        if let Some(max) = node.max {
+            write!(out, "<br/><b>max {}</b>", html(&max.to_string()))?;
+        }
+
+        write!(
+            out,
+            "<br/><font color=\"gray\">local = {}{}</font>",
+            html(&node.local.to_string()),
+            if node.overridden { " (overridden)" } else { "" }
+        )?;
+
+        if let Some(callees) = node.callees {
+            write!(out, "<br/>callees = {}", callees)?;
+        }
+
+        if let Some(budget) = node.budget {
+            write!(out, "<br/>{}", html(&budget.marker(false)))?;
+        }
+
+        write!(out, ">")?;
+
+        if node.dashed {
+            write!(out, " style=dashed")?;
+        }
+
+        if let Some(disassembly) = node.disassembly {
+            write!(
+                out,
+                " URL=\"{}\"",
+                interner.name(disassembly).replace('"', "\\\"")
+            )?;
+        }
+
+        if let Some(budget) = node.budget {
+            let color = match budget.status {
+                Status::Ok => "green",
+                Status::Near => "orange",
+                Status::Over => "red",
+            };
+            write!(out, " color={}", color)?;
+        }
+
+        writeln!(out, "]")?;
+    }
+
+    if merge_cycles {
+        for (i, cycle) in cycles.iter().enumerate() {
+            write!(
+                out,
+                "    scc{} [label=<SCC{}<br/>{} functions",
+                i,
+                i,
+                cycle.members.len()
+            )?;
+
+            if let Some(max) = g[cycle.members[0]].max {
+                write!(out, "<br/><b>max {}</b>", html(&max.to_string()))?;
+            }
+
+            writeln!(
+                out,
+                "<br/><font color=\"gray\">local {}</font>> style=dashed]",
+                html(&cycle.local.to_string())
+            )?;
+        }
+    }
+
+    // (source, target, call sites)
+    let mut edges = vec![];
+    let mut seen = HashMap::new();
+    for edge in g.raw_edges() {
+        let (source, target) = (id(edge.source()), id(edge.target()));
+
+        // drop the edges within a merged cycle and fold the duplicates created by the merge
+        if merge_cycles {
+            if source == target {
+                continue;
+            }
+
+            if let Some(i) = seen.get(&(source.clone(), target.clone())) {
+                let (_, _, sites) = &mut edges[*i];
+                *sites += edge.weight;
+                continue;
+            }
+
+            seen.insert((source.clone(), target.clone()), edges.len());
+        }
+
+        edges.push((source, target, edge.weight));
+    }
+
+    for (source, target, sites) in edges {
+        if sites > 1 {
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{} calls\"]",
+                source, target, sites
+            )?;
+        } else {
+            writeln!(out, "    {} -> {}", source, target)?;
+        }
+    }
+
+    if !merge_cycles {
+        for (i, cycle) in cycles.iter().enumerate() {
+            writeln!(out, "\n    subgraph cluster_{} {{", i)?;
+            writeln!(out, "        style=dashed")?;
+            writeln!(out, "        fontname={}", FONT)?;
+            writeln!(
+                out,
+                "        label=<SCC{}<br/>{} functions<br/>local {}>",
+                i,
+                cycle.members.len(),
+                html(&cycle.local.to_string())
+            )?;
+
+            for node in &cycle.members {
+                writeln!(out, "        {}", node.index())?;
+            }
+
+            writeln!(out, "    }}")?;
+        }
+    }
+
+    // makes the rendered graph self-describing
+    write!(
+        out,
+        "\n    legend [shape=plaintext label=<<table border=\"0\" cellborder=\"1\" \
+         cellspacing=\"0\" cellpadding=\"4\">"
+    )?;
+    let mut row = |key: &str, value: &str| {
+        write!(
+            out,
+            "<tr><td align=\"left\">{}</td><td align=\"left\">{}</td></tr>",
+            html(key),
+            html(value)
+        )
+    };
+    row("max = N", "max stack usage, in bytes")?;
+    row(
+        "max >= N",
+        "lower bound of the max stack usage, e.g. because of recursion",
+    )?;
+    row("local = N", "stack usage of the function itself, in bytes")?;
+    row("local = ?", "no stack usage information")?;
+    row(
+        "?",
+        "unknown callee, e.g. an external symbol without type information",
+    )?;
+    row(
+        "dashed box",
+        "indirect function call (function pointer or trait object)",
+    )?;
+    row(
+        "SCC",
+        "cycle (strongly connected component) of recursive calls",
+    )?;
+    row("N calls", "number of call sites between two functions")?;
+    row("target", &metadata.target)?;
+    row("profile", metadata.profile)?;
+    row("toolchain", &metadata.toolchain)?;
+    if let Some(date) = &metadata.date {
+        row("date", date)?;
+    }
+    row("command", &metadata.command)?;
+    writeln!(out, "</table>>]")?;
+
+    writeln!(out, "}}")
+}
+
+// the build that the call graph describes
+struct Metadata {
+    target: String,
+    profile: &'static str,
+    toolchain: String,
+    // UTC; `YYYY-MM-DD`. `None` if `--reproducible` and `SOURCE_DATE_EPOCH` is not set
+    date: Option<String>,
+    command: String,
+}
+
+// (`--reproducible`) the directories whose location depends on the machine and what replaces them
+// in the outputs, most specific first. Paths in the current directory become relative paths
+fn path_prefixes(cwd: &Path) -> Vec<(PathBuf, &'static str)> {
+    let mut prefixes = vec![(cwd.to_owned(), "")];
+
+    if let Ok(output) = Command::new("rustc").args(["--print", "sysroot"]).output() {
+        if let Ok(sysroot) = str::from_utf8(&output.stdout) {
+            if output.status.success() {
+                prefixes.push((PathBuf::from(sysroot.trim()), "$SYSROOT"));
+            }
+        }
+    }
+
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    if let Some(cargo_home) = cargo_home {
+        prefixes.push((cargo_home, "$CARGO_HOME"));
+    }
+
+    prefixes.sort_by_key(|(prefix, _)| cmp::Reverse(prefix.components().count()));
+    prefixes
+}
+
+// rewrites the start of `path` according to `prefixes` (see `path_prefixes`)
+fn remap(path: &Path, prefixes: &[(PathBuf, &str)]) -> PathBuf {
+    for (prefix, replacement) in prefixes {
+        if let Ok(rest) = path.strip_prefix(prefix) {
+            return match (*replacement, rest.as_os_str().is_empty()) {
+                ("", true) => PathBuf::from("."),
+                _ => Path::new(replacement).join(rest),
+            };
+        }
+    }
+
+    path.to_owned()
+}
+
+// `remap` for a command line argument, which may be a path or an `--option=PATH` pair
+fn remap_arg(arg: &str, prefixes: &[(PathBuf, &str)]) -> String {
+    let (option, value) = match arg.split_once('=') {
+        Some((option, value)) if option.starts_with("--") => (Some(option), value),
+        _ => (None, arg),
+    };
+    if !Path::new(value).is_absolute() {
+        return arg.to_owned();
+    }
+
+    let value = remap(Path::new(value), prefixes);
+    match option {
+        Some(option) => format!("{}={}", option, value.display()),
+        None => value.display().to_string(),
+    }
+}
+
+// formats a UNIX timestamp as a `YYYY-MM-DD` date (UTC)
+fn date(secs: u64) -> String {
+    // the days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = secs / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// the whole call graph, including the members of each cycle, in machine readable form
+#[allow(clippy::too_many_arguments)]
+fn json(
+    g: Graph<Node, usize>,
+    cycles: &[Cycle],
+    confidence: &[(NodeIndex, BTreeSet<Cause>)],
+    paths: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    roots: &[NodeIndex],
+    phases: Option<&Phases>,
+    preemption: Option<&Preemption>,
+    drop_paths: &[DropPath],
+    interner: &Interner,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    fn max(max: Option<Max>) -> String {
+        match max {
+            Some(Max::Exact(n)) => format!("{{\"exact\":{}}}", n),
+            Some(Max::LowerBound(n)) => format!("{{\"lower_bound\":{}}}", n),
+            None => "null".to_string(),
+        }
+    }
+
+    fn list(indices: &[NodeIndex]) -> String {
+        indices
+            .iter()
+            .map(|idx| idx.index().to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    writeln!(out, "{{")?;
+
+    writeln!(out, "  \"nodes\": [")?;
+    for (i, node) in g.raw_nodes().iter().enumerate() {
+        let node = &node.weight;
+        let local = match node.local {
+            Local::Exact(n) => n.to_string(),
+            Local::Unknown => "null".to_string(),
+        };
+
+        // the callees of an indirect call that use the most stack
+        let callees = if let Some(count) = node.callees {
+            let mut top = g
+                .neighbors_directed(NodeIndex::new(i), Direction::Outgoing)
+                .collect::<Vec<_>>();
+            top.sort_by_key(|callee| {
+                cmp::Reverse(match g[*callee].max {
+                    Some(Max::Exact(n)) | Some(Max::LowerBound(n)) => n,
+                    None => 0,
+                })
+            });
+            top.truncate(TOP_CALLEES);
+
+            format!(
+                ",\"callees\":{{\"count\":{},\"top\":[{}]}}",
+                count,
+                list(&top)
+            )
+        } else {
+            String::new()
+        };
+
+        let budget = if let Some(budget) = node.budget {
+            let status = match budget.status {
+                Status::Ok => "ok",
+                Status::Near => "near_limit",
+                Status::Over => "over_limit",
+            };
+
+            format!(
+                ",\"budget\":{{\"bytes\":{},\"status\":\"{}\"}}",
+                budget.bytes, status
+            )
+        } else {
+            String::new()
+        };
+
+        // where the local stack usage comes from; LLVM's value if the machine code analysis
+        // replaced it
+        let source = match node.source {
+            Source::Corrected { llvm } => {
+                format!(
+                    ",\"source\":\"{}\",\"llvm_local\":{}",
+                    node.source.as_str(),
+                    llvm
+                )
+            }
+            source => format!(",\"source\":\"{}\"", source.as_str()),
+        };
+
+        let class = if let Some(class) = node.class {
+            format!(",\"class\":{}", JsonStr(interner.name(class)))
+        } else {
+            String::new()
+        };
+
+        let disassembly = if let Some(disassembly) = node.disassembly {
+            format!(",\"disassembly\":{}", JsonStr(interner.name(disassembly)))
+        } else {
+            String::new()
+        };
+
+        writeln!(
+            out,
+            "    {{\"id\":{},\"name\":{},\"local\":{}{},\"max\":{},\"dashed\":{},\"overridden\":{}{}{}{}{}}}{}",
+            i,
+            JsonStr(interner.demangled(node.name)),
+            local,
+            source,
+            max(node.max),
+            node.dashed,
+            node.overridden,
+            callees,
+            budget,
+            class,
+            disassembly,
+            if i + 1 == g.node_count() { "" } else { "," }
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"edges\": [")?;
+    for (i, edge) in g.raw_edges().iter().enumerate() {
+        writeln!(
+            out,
+            "    [{},{},{}]{}",
+            edge.source().index(),
+            edge.target().index(),
+            edge.weight,
+            if i + 1 == g.edge_count() { "" } else { "," }
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"cycles\": [")?;
+    for (i, cycle) in cycles.iter().enumerate() {
+        writeln!(
+            out,
+            "    {{\"local\":{},\"members\":[{}]}}{}",
+            max(Some(cycle.local)),
+            list(&cycle.members),
+            if i + 1 == cycles.len() { "" } else { "," }
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    writeln!(out, "  \"confidence\": [")?;
+    for (i, (root, causes)) in confidence.iter().enumerate() {
+        let kind = match g[*root].max {
+            Some(Max::Exact(_)) => "exact",
+            Some(Max::LowerBound(_)) => "lower_bound",
+            None => "unknown",
+        };
+        let causes = causes
+            .iter()
+            .map(|cause| match cause {
+                Cause::Cycle(members) => format!("{{\"cycle\":[{}]}}", list(members)),
+                Cause::Unknown(idx) => format!("{{\"unknown\":{}}}", idx.index()),
+                Cause::Untyped(idx) => format!("{{\"untyped\":{}}}", idx.index()),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        // the call chain that reaches the max stack usage
+        let path = paths
+            .get(root)
+            .map(|path| format!(",\"path\":[{}]", list(path)))
+            .unwrap_or_default();
+
+        writeln!(
+            out,
+            "    {{\"root\":{},\"confidence\":\"{}\",\"causes\":[{}]{}}}{}",
+            root.index(),
+            kind,
+            causes,
+            path,
+            if i + 1 == confidence.len() { "" } else { "," }
+        )?;
+    }
+    writeln!(out, "  ],")?;
+
+    if let Some(phases) = phases {
+        writeln!(
+            out,
+            "  \"main_loop\": {{\"function\":{},\"init\":{},\"steady_state\":{}}},",
+            JsonStr(&phases.function),
+            max(Some(phases.init)),
+            max(Some(phases.steady))
+        )?;
+    }
+
+    if let Some(preemption) = preemption {
+        writeln!(
+            out,
+            "  \"preemption\": {{\"entry\":{},\"handler\":{},\"exception_frame\":{},\"max\":{}}},",
+            JsonStr(&preemption.entry),
+            JsonStr(&preemption.handler),
+            preemption.frame,
+            max(Some(preemption.max))
+        )?;
+    }
+
+    if !drop_paths.is_empty() {
+        let paths = drop_paths
+            .iter()
+            .map(|path| {
+                format!(
+                    "{{\"function\":{},\"straight\":{},\"drop\":{}}}",
+                    JsonStr(&path.function),
+                    max(Some(path.straight)),
+                    max(Some(path.drop))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(out, "  \"drop_paths\": [{}],", paths)?;
+    }
+
+    writeln!(out, "  \"roots\": [{}]", list(roots))?;
+
+    writeln!(out, "}}")
+}
+
+// a string literal in JSON
+pub(crate) struct JsonStr<'a>(pub(crate) &'a str);
+
+impl fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+fn top(
+    g: Graph<Node, usize>,
+    roots: &[(NodeIndex, BTreeSet<Cause>)],
+    paths: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    phases: Option<&Phases>,
+    preemption: Option<&Preemption>,
+    drop_paths: &[DropPath],
+    interner: &Interner,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    // highlight the budget status unless the output is redirected
+    let color = stdout.is_terminal();
+    let mut stdout = stdout.lock();
+
+    assert!(g.is_directed());
+
+    let mut nodes: Vec<Node> = Vec::new();
+    for node in g.raw_nodes().iter() {
+        nodes.push(node.weight.clone());
+    }
+
+    // Locate max
+    if let Some(max) = max_of(nodes.iter().map(|n| n.max.unwrap_or(Max::Exact(0)))) {
+        writeln!(
+            stdout,
+            "{} MAX",
+            match max {
+                Max::Exact(n) => n,
+                Max::LowerBound(n) => n,
+            }
+        )?;
+    }
+
+    if !roots.is_empty() {
+        writeln!(stdout, "Max Root")?;
+
+        for (root, causes) in roots {
+            let node = &g[*root];
+            write!(stdout, "{} ", node.max.unwrap_or(Max::LowerBound(0)))?;
+
+            let mut escaper = Escaper::new(&mut stdout);
+            write!(escaper, "{}", interner.demangled(node.name)).ok();
+            escaper.error?;
+
+            if let Some(budget) = node.budget {
+                write!(stdout, " {}", budget.marker(color))?;
+            }
+            writeln!(stdout)?;
+
+            for cause in causes {
+                let mut escaper = Escaper::new(&mut stdout);
+                writeln!(escaper, "  lower bound: {}", cause.display(&g, interner)).ok();
+                escaper.error?;
+            }
+
+            if let Some(path) = paths.get(root) {
+                let mut escaper = Escaper::new(&mut stdout);
+                writeln!(
+                    escaper,
+                    "  path: {}",
+                    path.iter()
+                        .map(|idx| format!(
+                            "{} ({})",
+                            interner.demangled(g[*idx].name),
+                            g[*idx].local
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                )
+                .ok();
+                escaper.error?;
+            }
+        }
+    }
+
+    if let Some(phases) = phases {
+        writeln!(stdout, "Max Phase")?;
+
+        for (max, phase) in [(phases.init, "init"), (phases.steady, "main loop")].iter() {
+            write!(stdout, "{} ", max)?;
+
+            let mut escaper = Escaper::new(&mut stdout);
+            writeln!(escaper, "{} ({})", phases.function, phase).ok();
+            escaper.error?;
+        }
+    }
+
+    if let Some(preemption) = preemption {
+        writeln!(stdout, "Max Preempted")?;
+        write!(stdout, "{} ", preemption.max)?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        writeln!(
+            escaper,
+            "{} preempted by {} (exception frame: {})",
+            preemption.entry, preemption.handler, preemption.frame
+        )
+        .ok();
+        escaper.error?;
+    }
+
+    if !drop_paths.is_empty() {
+        writeln!(stdout, "Max Path")?;
+
+        for path in drop_paths {
+            for (max, kind) in [(path.straight, "straight-line"), (path.drop, "drop")].iter() {
+                write!(stdout, "{} ", max)?;
+
+                let mut escaper = Escaper::new(&mut stdout);
+                writeln!(escaper, "{} ({})", path.function, kind).ok();
+                escaper.error?;
+            }
+        }
+    }
+
+    writeln!(stdout, "Usage Function")?;
+
+    nodes.sort_by(|a, b| {
+        let a: u64 = if let Local::Exact(n) = a.local { n } else { 0 };
+        let b: u64 = if let Local::Exact(n) = b.local { n } else { 0 };
+        b.cmp(&a)
+    });
+
+    for node in nodes.iter() {
+        let name = interner.demangled(node.name);
+        let val: u64 = if let Local::Exact(n) = node.local {
+            n
+        } else {
+            0
+        };
+        write!(stdout, "{} ", val)?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        write!(escaper, "{}", name).ok();
+        escaper.error?;
+
+        if let Some(budget) = node.budget {
+            write!(stdout, " {}", budget.marker(color))?;
+        }
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
+
+fn dominators(
+    g: Graph<Node, usize>,
+    ownership: Option<(NodeIndex, Vec<(NodeIndex, u64)>)>,
+    interner: &Interner,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let color = stdout.is_terminal();
+    let mut stdout = stdout.lock();
+
+    if let Some((root, owned)) = ownership {
+        writeln!(stdout, "Max Root")?;
+        write!(stdout, "{} ", g[root].max.unwrap_or(Max::LowerBound(0)))?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        write!(escaper, "{}", interner.demangled(g[root].name)).ok();
+        escaper.error?;
+
+        if let Some(budget) = g[root].budget {
+            write!(stdout, " {}", budget.marker(color))?;
+        }
+        writeln!(stdout)?;
+
+        writeln!(stdout, "Owned Function")?;
+        for (idx, bytes) in owned {
+            write!(stdout, "{} ", bytes)?;
+
+            let mut escaper = Escaper::new(&mut stdout);
+            write!(escaper, "{}", interner.demangled(g[idx].name)).ok();
+            escaper.error?;
+
+            if let Some(budget) = g[idx].budget {
+                write!(stdout, " {}", budget.marker(color))?;
+            }
+            writeln!(stdout)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn splits(
+    g: Graph<Node, usize>,
+    splits: &[(NodeIndex, NodeIndex, u64)],
+    interner: &Interner,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    writeln!(stdout, "Saves Common Max Call")?;
+    for (caller, callee, common) in splits {
+        let max = g[*caller].max.unwrap_or(Max::LowerBound(0));
+        write!(stdout, "{} {} {} ", max.bytes() - common, common, max)?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        write!(
+            escaper,
+            "{} -> {}",
+            interner.demangled(g[*caller].name),
+            interner.demangled(g[*callee].name)
+        )
+        .ok();
+        escaper.error?;
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+fn table(
+    g: Graph<Node, usize>,
+    cycles: &[Cycle],
+    tail_calls: &HashSet<(NodeIndex, NodeIndex)>,
+    interner: &Interner,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let color = stdout.is_terminal();
+    let mut stdout = stdout.lock();
+
+    let critical = critical_edges(&g, cycles, tail_calls);
+
+    // fictitious nodes are not functions
+    let mut functions = g
+        .node_indices()
+        .filter(|idx| !g[*idx].dashed)
+        .collect::<Vec<_>>();
+    functions.sort_by_key(|idx| (cmp::Reverse(g[*idx].max.map(Max::bytes)), *idx));
+
+    let max = |idx: NodeIndex| match g[idx].max {
+        Some(Max::Exact(n)) => n.to_string(),
+        Some(Max::LowerBound(n)) => format!(">={}", n),
+        None => "?".to_string(),
+    };
+    let max_width = functions
+        .iter()
+        .map(|idx| max(*idx).len())
+        .chain(["Max".len()])
+        .max()
+        .unwrap_or(0);
+    let local_width = functions
+        .iter()
+        .map(|idx| g[*idx].local.to_string().len())
+        .chain(["Local".len()])
+        .max()
+        .unwrap_or(0);
+
+    writeln!(
+        stdout,
+        "{:>max_width$} {:>local_width$} Call chain",
+        "Max", "Local"
+    )?;
+    for idx in functions {
+        write!(
+            stdout,
+            "{:>max_width$} {:>local_width$} ",
+            max(idx),
+            g[idx].local.to_string()
+        )?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        write!(
+            escaper,
+            "{}",
+            worst_path(&g, &critical, idx)
+                .iter()
+                .map(|idx| interner.demangled(g[*idx].name))
+                .collect::<Vec<_>>()
+                .join(" -> ")
+        )
+        .ok();
+        escaper.error?;
+
+        if let Some(budget) = g[idx].budget {
+            write!(stdout, " {}", budget.marker(color))?;
+        }
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+// groups the instances of generic functions and reports, for each group, the max stack usage of
+// all its instances
+fn generic(g: Graph<Node, usize>, interner: &Interner) -> io::Result<()> {
+    let stdout = io::stdout();
+    let color = stdout.is_terminal();
+    let mut stdout = stdout.lock();
+
+    struct Group {
+        max: Max,
+        local: Local,
+        count: usize,
+        // the budgeted instance in the worst standing
+        budget: Option<Budget>,
+    }
+
+    let mut groups = BTreeMap::<String, Group>::new();
+    for node in g.node_weights() {
+        if node.dashed {
+            // fictitious nodes are not functions
+            continue;
+        }
+
+        let origin = generic_origin(interner.short(node.name));
+        let max = node.max.unwrap_or(Max::LowerBound(0));
+
+        let group = groups.entry(origin).or_insert(Group {
+            max,
+            local: node.local,
+            count: 0,
+            budget: None,
+        });
+        group.max = graph::max(group.max, max);
+        group.local = match (group.local, node.local) {
+            (Local::Exact(a), Local::Exact(b)) => Local::Exact(a.max(b)),
+            _ => Local::Unknown,
+        };
+        group.count += 1;
+
+        if let Some(budget) = node.budget {
+            if group
+                .budget
+                .map(|worst| budget.status > worst.status)
+                .unwrap_or(true)
+            {
+                group.budget = Some(budget);
+            }
+        }
+    }
+
+    let mut groups = groups.into_iter().collect::<Vec<_>>();
+    groups.sort_by(|(a_name, a), (b_name, b)| {
+        let a_max = match a.max {
+            Max::Exact(n) | Max::LowerBound(n) => n,
+        };
+        let b_max = match b.max {
+            Max::Exact(n) | Max::LowerBound(n) => n,
+        };
+        b_max.cmp(&a_max).then_with(|| a_name.cmp(b_name))
+    });
+
+    writeln!(stdout, "Max Local Instances Function")?;
+    for (name, group) in groups {
+        write!(stdout, "{} {} {} ", group.max, group.local, group.count)?;
+
+        let mut escaper = Escaper::new(&mut stdout);
+        write!(escaper, "{}", name).ok();
+        escaper.error?;
+
+        if let Some(budget) = group.budget {
+            write!(stdout, " {}", budget.marker(color))?;
+        }
+        writeln!(stdout)?;
+    }
+
+    Ok(())
+}
+
+// removes the generic arguments from a demangled path
+// e.g. `<app::Foo<u8> as app::Bar>::baz::<u16>` -> `<app::Foo as app::Bar>::baz`
+fn generic_origin(demangled: &str) -> String {
+    let mut origin = String::with_capacity(demangled.len());
+    // whether each of the open `<` brackets started a list of generic arguments
+    let mut brackets = vec![];
+    // number of open lists of generic arguments
+    let mut depth = 0;
+
+    let mut chars = demangled.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // `->` in function pointer types
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+
+                if depth == 0 {
+                    origin.push_str("->");
+                }
+            }
+
+            '<' => {
+                let turbofish = origin.ends_with("::");
+                // a `<` that follows an identifier or `::` starts a list of generic arguments;
+                // otherwise it's a qualified path (e.g. `<T as Trait>`)
+                let generic_args = depth != 0
+                    || turbofish
+                    || origin
+                        .chars()
+                        .last()
+                        .map(|c| c.is_alphanumeric() || c == '_')
+                        .unwrap_or(false);
+
+                brackets.push(generic_args);
+                if generic_args {
+                    if depth == 0 && turbofish {
+                        origin.truncate(origin.len() - 2);
+                    }
+
+                    depth += 1;
+                } else {
+                    origin.push(c);
+                }
+            }
+
+            '>' => {
+                if brackets.pop().unwrap_or(false) {
+                    depth -= 1;
+                } else if depth == 0 {
+                    origin.push(c);
+                }
+            }
+
+            _ => {
+                if depth == 0 {
+                    origin.push(c);
+                }
+            }
+        }
+    }
+
+    origin
+}
+
+// escapes the characters that have a special meaning in Graphviz's HTML-like labels, e.g. the
+// angle brackets of generic Rust names
+fn html(s: &str) -> Cow<'_, str> {
+    if !s.contains(['&', '<', '>', '"']) {
+        return s.into();
+    }
+
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.into()
+}
+
+pub(crate) struct Escaper<W>
+where
+    W: io::Write,
+{
+    writer: W,
+    error: io::Result<()>,
+}
+
+impl<W> Escaper<W>
+where
+    W: io::Write,
+{
+    fn new(writer: W) -> Self {
+        Escaper {
+            writer,
+            error: Ok(()),
+        }
+    }
+}
+
+impl<W> fmt::Write for Escaper<W>
+where
+    W: io::Write,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.write_char(c)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        match (|| -> io::Result<()> {
+            if c == '"' {
+                write!(self.writer, "\\")?;
+            }
+
+            write!(self.writer, "{}", c)
+        })() {
+            Err(e) => {
+                self.error = Err(e);
+
+                Err(fmt::Error)
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+}
+
+// the reason why the max stack usage of a root is only a lower bound
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Cause {
+    // a cycle (recursion) whose members use the stack
+    Cycle(Vec<NodeIndex>),
+    // a function with unknown stack usage, e.g. written in assembly or external
+    Unknown(NodeIndex),
+    // an indirect function call (caller) that may reach functions without type information
+    Untyped(NodeIndex),
+}
+
+impl Cause {
+    fn display(&self, g: &Graph<Node, usize>, interner: &Interner) -> String {
+        let name = |idx: &NodeIndex| format!("`{}`", interner.demangled(g[*idx].name));
+
+        match self {
+            Cause::Cycle(members) => format!(
+                "recursion through {}",
+                members.iter().map(name).collect::<Vec<_>>().join(", ")
+            ),
+            Cause::Unknown(idx) => format!("unknown stack usage of {}", name(idx)),
+            Cause::Untyped(idx) if g[*idx].dashed => format!(
+                "indirect call {} may reach functions without type information",
+                name(idx)
+            ),
+            Cause::Untyped(idx) => {
+                format!("indirect call without type information in {}", name(idx))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        path::{Path, PathBuf},
+    };
+
+    #[test]
+    fn parse_feature_matrix() {
+        assert_eq!(
+            super::parse_feature_matrix("a,b; a c ;default").unwrap(),
+            ["a,b", "a,c", ""]
+        );
+
+        assert!(super::parse_feature_matrix("a;;b").is_err());
+        assert!(super::parse_feature_matrix("a; b;").is_err());
+        assert!(super::parse_feature_matrix("a,b; a b").is_err());
+    }
+
+    #[test]
+    fn ownership() {
+        use petgraph::graph::DiGraph;
+
+        use super::{Max, Node};
+        use crate::intern::Interner;
+
+        let mut interner = Interner::new();
+        let mut g = DiGraph::new();
+        let mut node = |name, local, max| {
+            let mut node = Node(interner.intern(name), Some(local), false);
+            node.max = Some(Max::Exact(max));
+            g.add_node(node)
+        };
+        // `a` and `b` reach `shared` on the worst-case paths; `c` is not on them
+        let root = node("root", 8, 40);
+        let a = node("a", 16, 32);
+        let b = node("b", 16, 32);
+        let shared = node("shared", 16, 16);
+        let c = node("c", 4, 4);
+        g.add_edge(root, a, 1);
+        g.add_edge(root, b, 1);
+        g.add_edge(root, c, 1);
+        g.add_edge(a, shared, 1);
+        g.add_edge(b, shared, 1);
+
+        assert_eq!(
+            super::ownership(&g, root, &[], &HashSet::new()),
+            [(root, 40), (a, 16), (b, 16), (shared, 16)]
+        );
+    }
+
+    #[test]
+    fn split_candidates() {
+        use petgraph::graph::DiGraph;
+
+        use super::{Max, Node};
+        use crate::intern::Interner;
+
+        let mut interner = Interner::new();
+        let mut g = DiGraph::new();
+        let mut node = |name, local, max| {
+            let mut node = Node(interner.intern(name), Some(local), false);
+            node.max = Some(Max::Exact(max));
+            g.add_node(node)
+        };
+        // `hot` reaches `cold` besides `light`; `cold` is the only callee of `only`
+        let root = node("root", 8, 280);
+        let hot = node("hot", 16, 272);
+        let light = node("light", 16, 16);
+        let cold = node("cold", 256, 256);
+        let only = node("only", 16, 272);
+        let other = node("other", 8, 280);
+        g.add_edge(root, hot, 1);
+        g.add_edge(root, only, 1);
+        g.add_edge(hot, light, 1);
+        g.add_edge(hot, cold, 1);
+        g.add_edge(only, cold, 1);
+        g.add_edge(other, hot, 1);
+
+        // from `root`, `cold` can be reached bypassing `hot`
+        assert!(super::split_candidates(&g, root, &[], &HashSet::new()).is_empty());
+
+        assert_eq!(
+            super::split_candidates(&g, other, &[], &HashSet::new()),
+            [(hot, cold, 32)]
+        );
+    }
+
+    #[test]
+    fn date() {
+        assert_eq!(super::date(0), "1970-01-01");
+        assert_eq!(super::date(951_782_400), "2000-02-29");
+        assert_eq!(super::date(1_709_251_199), "2024-02-29");
+        assert_eq!(super::date(1_735_689_600), "2025-01-01");
+    }
+
+    #[test]
+    fn remap() {
+        let prefixes = [
+            (
+                PathBuf::from("/home/ci/.rustup/toolchains/nightly"),
+                "$SYSROOT",
+            ),
+            (PathBuf::from("/home/ci/app"), ""),
+            (PathBuf::from("/home/ci/.cargo"), "$CARGO_HOME"),
+        ];
+        let remap = |path: &str| super::remap(Path::new(path), &prefixes);
+
+        assert_eq!(remap("/home/ci/app/src/main.rs"), Path::new("src/main.rs"));
+        assert_eq!(remap("/home/ci/app"), Path::new("."));
+        assert_eq!(
+            remap("/home/ci/.cargo/registry/src/foo/lib.rs"),
+            Path::new("$CARGO_HOME/registry/src/foo/lib.rs")
+        );
+        assert_eq!(
+            remap("/home/ci/.rustup/toolchains/nightly/lib/rustlib/src/rust/library/core/lib.rs"),
+            Path::new("$SYSROOT/lib/rustlib/src/rust/library/core/lib.rs")
+        );
+        // not a prefix at a component boundary
+        assert_eq!(
+            remap("/home/ci/application"),
+            Path::new("/home/ci/application")
+        );
+        assert_eq!(remap("/rustc/abc/lib.rs"), Path::new("/rustc/abc/lib.rs"));
+
+        assert_eq!(
+            super::remap_arg("/home/ci/app/target/app.ll", &prefixes),
+            "target/app.ll"
+        );
+        assert_eq!(
+            super::remap_arg("--ll=/home/ci/app/target/app.ll", &prefixes),
+            "--ll=target/app.ll"
+        );
+        assert_eq!(super::remap_arg("--format", &prefixes), "--format");
+        assert_eq!(super::remap_arg("app::foo=16", &prefixes), "app::foo=16");
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(super::html("app::foo"), "app::foo");
+        assert_eq!(
+            super::html("<Vec<u8> as core::ops::Drop>::drop"),
+            "&lt;Vec&lt;u8&gt; as core::ops::Drop&gt;::drop"
+        );
+        assert_eq!(
+            super::html("core::ptr::drop_in_place<&mut \"str\">"),
+            "core::ptr::drop_in_place&lt;&amp;mut &quot;str&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn parse_override() {
+        assert_eq!(
+            super::parse_override("asm_routine=128"),
+            Ok(("asm_routine".to_owned(), 128))
+        );
+        assert_eq!(
+            super::parse_override("<app::Foo as app::Bar>::baz = 16"),
+            Ok(("<app::Foo as app::Bar>::baz".to_owned(), 16))
+        );
+
+        assert!(super::parse_override("asm_routine").is_err());
+        assert!(super::parse_override("asm_routine=lots").is_err());
+    }
+
+    #[test]
+    fn parse_budget() {
+        assert_eq!(super::parse_budget("4096"), Ok((None, 4096)));
+        assert_eq!(
+            super::parse_budget("app::idle = 512"),
+            Ok((Some("app::idle".to_owned()), 512))
+        );
+
+        assert!(super::parse_budget("=512").is_err());
+        assert!(super::parse_budget("app::idle=lots").is_err());
+    }
+
+    #[test]
+    fn parse_syscall() {
+        assert_eq!(
+            super::parse_syscall("3=kernel::sys_write"),
+            Ok((3, "kernel::sys_write".to_owned()))
+        );
+        assert_eq!(
+            super::parse_syscall("0x10 = sys_yield"),
+            Ok((16, "sys_yield".to_owned()))
+        );
+
+        assert!(super::parse_syscall("256=sys_write").is_err());
+        assert!(super::parse_syscall("3=").is_err());
+        assert!(super::parse_syscall("sys_write").is_err());
+    }
+
+    #[test]
+    fn parse_longjmp_target() {
+        assert_eq!(
+            super::parse_longjmp_target("png_error = png_read_image"),
+            Ok(("png_error".to_owned(), "png_read_image".to_owned()))
+        );
+
+        assert!(super::parse_longjmp_target("png_error").is_err());
+        assert!(super::parse_longjmp_target("png_error=").is_err());
+    }
+
+    #[test]
+    fn parse_secondary_stack() {
+        assert_eq!(
+            super::parse_secondary_stack("dsp::on_scratch = SCRATCH"),
+            Ok(("dsp::on_scratch".to_owned(), "SCRATCH".to_owned()))
+        );
+
+        assert!(super::parse_secondary_stack("dsp::on_scratch").is_err());
+        assert!(super::parse_secondary_stack("=SCRATCH").is_err());
+    }
+
+    #[test]
+    fn generic_origin() {
+        assert_eq!(super::generic_origin("app::foo"), "app::foo");
+        assert_eq!(super::generic_origin("app::foo::<u8>"), "app::foo");
+        assert_eq!(
+            super::generic_origin("core::ptr::drop_in_place::<alloc::vec::Vec<u8>>"),
+            "core::ptr::drop_in_place"
+        );
+        assert_eq!(
+            super::generic_origin("<app::Foo<u8> as app::Bar>::baz::<u16>"),
+            "<app::Foo as app::Bar>::baz"
+        );
+        assert_eq!(
+            super::generic_origin("app::call::<fn() -> u8>::{closure#0}"),
+            "app::call::{closure#0}"
+        );
+    }
+}
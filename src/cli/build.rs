@@ -0,0 +1,616 @@
+//! Building the crate with Cargo and locating the artifacts of the build
+
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str, thread,
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, bail};
+use cargo_project::{Profile, Project};
+use filetime::FileTime;
+use log::warn;
+use walkdir::WalkDir;
+
+use super::Args;
+use crate::{
+    analysis::{AnalysisInput, CompilerBuiltins, Library},
+    compare::{self, Json},
+    wrapper::{self, WRAPPER_HINT},
+};
+
+// the artifacts of the build being analyzed
+pub(crate) struct Build {
+    // the ELF file (or WebAssembly module)
+    path: PathBuf,
+    ll_path: Option<PathBuf>,
+    obj_path: Option<PathBuf>,
+    compiler_builtins_rlib_path: Option<String>,
+    compiler_builtins_ll_path: Option<String>,
+    // the arguments of the final link
+    search_paths: Vec<PathBuf>,
+    native_libs: Vec<String>,
+    linker_scripts: Vec<String>,
+    wrapped_symbols: Vec<String>,
+}
+
+impl Build {
+    // `--elf`: a program built by other means; `compiler_builtins` is not analyzed
+    pub(crate) fn external(elf: &Path, args: &Args) -> Self {
+        let ll_path = args.ll.clone().expect("UNREACHABLE");
+        Build {
+            path: elf.to_owned(),
+            obj_path: Some(
+                args.obj
+                    .clone()
+                    .unwrap_or_else(|| ll_path.with_extension("o")),
+            ),
+            ll_path: Some(ll_path),
+            compiler_builtins_rlib_path: None,
+            compiler_builtins_ll_path: None,
+            search_paths: vec![],
+            native_libs: vec![],
+            linker_scripts: vec![],
+            wrapped_symbols: vec![],
+        }
+    }
+
+    // (`--lib`) the kind of library that was built
+    pub(crate) fn library(&self, args: &Args) -> Option<Library> {
+        if !args.lib {
+            None
+        } else if self.path.extension().is_some_and(|ext| ext == "a") {
+            Some(Library::Static)
+        } else {
+            Some(Library::Shared)
+        }
+    }
+
+    // the files to analyze; a `staticlib` is linked into an executable first
+    pub(crate) fn input(
+        self,
+        args: &Args,
+        target: &str,
+        host: &str,
+        file: &str,
+    ) -> anyhow::Result<AnalysisInput> {
+        let library = self.library(args);
+        let Build {
+            path,
+            ll_path,
+            obj_path,
+            compiler_builtins_rlib_path,
+            compiler_builtins_ll_path,
+            search_paths,
+            native_libs,
+            linker_scripts,
+            wrapped_symbols,
+        } = self;
+
+        let mut input = AnalysisInput::new(path, PathBuf::new(), target);
+        input.host = Some(host.to_owned());
+        if target.starts_with("wasm32-") {
+            // WebAssembly modules contain all the information we need; no need to look at LLVM IR
+            return Ok(input);
+        }
+
+        // `None` if the program was not built by us (`--elf`)
+        input.compiler_builtins = match (compiler_builtins_rlib_path, compiler_builtins_ll_path) {
+            _ if args.elf.is_some() => None,
+            // only the symbol table of the archive is needed; its LLVM IR is not
+            (Some(rlib), _) if args.no_builtins_analysis => Some(CompilerBuiltins {
+                rlib: rlib.into(),
+                ll: None,
+            }),
+            (Some(rlib), Some(ll)) => Some(CompilerBuiltins {
+                rlib: rlib.into(),
+                ll: Some(ll.into()),
+            }),
+            (None, None) if ll_path.is_none() && obj_path.is_none() => {
+                bail!(
+                    "the rustc wrapper was not invoked by Cargo so `-Zemit-stack-sizes` was not \
+                     passed to rustc; {}",
+                    WRAPPER_HINT
+                )
+            }
+            (None, _) => bail!("`compiler_builtins` was not linked"),
+            (_, None) => bail!(
+                "`compiler_builtins` was not built from source so its LLVM IR is unavailable; \
+                 is the `rust-src` component installed?"
+            ),
+        };
+
+        // a `staticlib` is an archive of object files; it's analyzed like the executable that
+        // results from linking all of them
+        if library == Some(Library::Static) {
+            input.elf = link_staticlib(&input.elf, host)?;
+        }
+
+        // the wrapper reports the location of the LLVM IR and object files of the crate being
+        // analyzed
+        (input.ll, input.obj) = match (ll_path, obj_path) {
+            (Some(ll_path), Some(obj_path)) => (ll_path, Some(obj_path)),
+            _ => {
+                // Cargo replays the output of cached builds; if that output was produced by an
+                // older version of this tool it won't contain the markers we are looking for
+                warn!("the location of the LLVM IR file was not reported; searching for it");
+
+                let ll_path = find_ll(
+                    &input.elf,
+                    file,
+                    args.example.is_none() && args.test.is_none() && args.bench.is_none(),
+                )?;
+                (ll_path, None)
+            }
+        };
+
+        input.extra_ll = args.extra_ll.clone();
+        input.extra_obj = args.extra_obj.clone();
+        input.symbol_map = args.symbol_map.clone();
+        input.search_paths = search_paths;
+        input.native_libs = native_libs;
+        input.linker_scripts = linker_scripts;
+        input.wrapped_symbols = wrapped_symbols;
+
+        Ok(input)
+    }
+}
+
+// builds the crate with the rustc wrapper, which reports the location of the artifacts we need,
+// and returns them or the exit code of a failed build
+pub(crate) fn cargo_build(
+    args: &Args,
+    project: &Project,
+    file: &str,
+    target_flag: Option<&str>,
+    is_no_std: bool,
+    is_wasm: bool,
+    profile: Profile,
+) -> anyhow::Result<Result<Build, i32>> {
+    let mut cargo = Command::new("cargo");
+    cargo.arg("rustc");
+
+    // NOTE we do *not* use `project.target()` here because Cargo will figure things out on
+    // its own (i.e. it will search and parse .cargo/config, etc.)
+    if let Some(target) = target_flag {
+        cargo.args(["--target", target]);
+    }
+
+    if args.all_features {
+        cargo.arg("--all-features");
+    } else if let Some(features) = &args.features {
+        cargo.args(["--features", features]);
+    }
+
+    // the analysis build should resolve dependencies like the production build does
+    if args.locked {
+        cargo.arg("--locked");
+    }
+
+    if args.frozen {
+        cargo.arg("--frozen");
+    }
+
+    if args.offline {
+        cargo.arg("--offline");
+    }
+
+    // the analysis build uses different `rustc` flags than a normal build; sharing a target
+    // directory would make each of them rebuild everything the other one built
+    let target_dir = args
+        .target_dir
+        .clone()
+        .unwrap_or_else(|| project.target_dir().join("call-stack"));
+    cargo.arg("--target-dir").arg(&target_dir);
+
+    if let Some(package) = &args.package {
+        cargo.args(["--package", package]);
+    }
+
+    if args.example.is_some() {
+        cargo.args(["--example", file]);
+    }
+
+    if args.bin.is_some() {
+        cargo.args(["--bin", file]);
+    }
+
+    if args.lib {
+        cargo.arg("--lib");
+    }
+
+    if args.test.is_some() {
+        cargo.args(["--test", file]);
+    }
+
+    if args.bench.is_some() {
+        cargo.args(["--bench", file]);
+    }
+
+    if profile.is_release() {
+        cargo.arg("--release");
+    }
+
+    let build_std = if is_no_std {
+        "-Zbuild-std=core,alloc,compiler_builtins"
+    } else {
+        "-Zbuild-std"
+    };
+
+    cargo.args([
+        build_std,
+        "--color=always",
+        // the location of the artifacts is reported in the JSON messages
+        "--message-format=json-diagnostic-rendered-ansi",
+        "--",
+        // .ll file
+        "--emit=llvm-ir,obj",
+        // needed to produce a single .ll file
+        "-C",
+        "embed-bitcode=yes",
+        "-C",
+        "lto=fat",
+    ]);
+
+    cargo.env("CARGO_CALL_STACK_RUSTC_WRAPPER", "1");
+    cargo.env("RUSTC_WRAPPER", env::current_exe()?);
+    cargo.stdout(Stdio::piped());
+    cargo.stderr(Stdio::piped());
+
+    // "touch" some source file to trigger a rebuild
+    let root = project.toml().parent().expect("UNREACHABLE");
+    let now = FileTime::from_system_time(SystemTime::now());
+    // an integration test or benchmark is not rebuilt when the binary crate changes
+    let test_dir = if args.test.is_some() {
+        Some("tests")
+    } else if args.bench.is_some() {
+        Some("benches")
+    } else {
+        None
+    };
+    let test_touched = test_dir.is_some_and(|dir| {
+        filetime::set_file_times(root.join(dir).join(format!("{}.rs", file)), now, now).is_ok()
+    });
+    if !test_touched
+        && filetime::set_file_times(root.join("src/main.rs"), now, now).is_err()
+        && filetime::set_file_times(root.join("src/lib.rs"), now, now).is_err()
+    {
+        // look for some rust source file and "touch" it
+        let src = root.join("src");
+        let haystack = if src.exists() { &src } else { root };
+
+        for entry in WalkDir::new(haystack) {
+            let entry = entry?;
+            let path = entry.path();
+
+            if path.extension().map(|ext| ext == "rs").unwrap_or(false) {
+                filetime::set_file_times(path, now, now)?;
+                break;
+            }
+        }
+    }
+
+    if args.verbose {
+        eprintln!("{:?}", cargo);
+    }
+
+    let mut child = cargo.spawn()?;
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    // drained concurrently with stderr to not block Cargo
+    let messages = thread::spawn(move || -> io::Result<_> {
+        let mut artifacts = vec![];
+        for line in stdout.lines() {
+            let line = line?;
+            let message = if let Some(message) = compare::parse_json(&line) {
+                message
+            } else {
+                eprintln!("{}", line);
+                continue;
+            };
+
+            match message.get("reason").and_then(Json::string) {
+                Some("compiler-message") => {
+                    if let Some(rendered) = message
+                        .get("message")
+                        .and_then(|message| message.get("rendered"))
+                        .and_then(Json::string)
+                    {
+                        eprint!("{}", rendered);
+                    }
+                }
+                Some("compiler-artifact") => artifacts.push(message),
+                _ => {}
+            }
+        }
+
+        Ok(artifacts)
+    });
+    let stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut compiler_builtins_rlib_path = None;
+    let mut compiler_builtins_ll_path = None;
+    let mut ll_path = None;
+    let mut obj_path = None;
+    // the arguments of the final link
+    let mut search_paths = vec![];
+    let mut native_libs = vec![];
+    let mut linker_scripts = vec![];
+    let mut wrapped_symbols = vec![];
+    for line in stderr.lines() {
+        let line = line?;
+        if let Some(path) = line.strip_prefix(wrapper::COMPILER_BUILTINS_RLIB_PATH_MARKER) {
+            compiler_builtins_rlib_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix(wrapper::COMPILER_BUILTINS_LL_PATH_MARKER) {
+            compiler_builtins_ll_path = Some(path.to_string());
+        } else if let Some(path) = line.strip_prefix(wrapper::LL_PATH_MARKER) {
+            ll_path = Some(PathBuf::from(path));
+        } else if let Some(path) = line.strip_prefix(wrapper::OBJ_PATH_MARKER) {
+            obj_path = Some(PathBuf::from(path));
+        } else if let Some(path) = line.strip_prefix(wrapper::SEARCH_PATH_MARKER) {
+            search_paths.push(PathBuf::from(path));
+        } else if let Some(lib) = line.strip_prefix(wrapper::NATIVE_LIB_MARKER) {
+            native_libs.push(lib.to_owned());
+        } else if let Some(script) = line.strip_prefix(wrapper::LINKER_SCRIPT_MARKER) {
+            linker_scripts.push(script.to_owned());
+        } else if let Some(symbol) = line.strip_prefix(wrapper::WRAPPED_SYMBOL_MARKER) {
+            wrapped_symbols.push(symbol.to_owned());
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
+    let status = child.wait()?;
+    let artifacts = messages.join().expect("UNREACHABLE")?;
+
+    if !status.success() {
+        return Ok(Err(status.code().unwrap_or(1)));
+    }
+
+    let kind = if args.example.is_some() {
+        "example"
+    } else if args.lib {
+        if artifact_path(&artifacts, "cdylib", file, is_wasm).is_some() || is_wasm {
+            "cdylib"
+        } else {
+            "staticlib"
+        }
+    } else if args.test.is_some() {
+        "test"
+    } else if args.bench.is_some() {
+        "bench"
+    } else {
+        "bin"
+    };
+    let path = artifact_path(&artifacts, kind, file, is_wasm).ok_or_else(|| {
+        anyhow!(
+            "Cargo didn't report the location of the {} `{}`",
+            if args.lib { "library" } else { kind },
+            file
+        )
+    })?;
+
+    Ok(Ok(Build {
+        path,
+        ll_path,
+        obj_path,
+        compiler_builtins_rlib_path,
+        compiler_builtins_ll_path,
+        search_paths,
+        native_libs,
+        linker_scripts,
+        wrapped_symbols,
+    }))
+}
+
+// looks for the most recently modified `.ll` file that belongs to the artifact at `path`
+fn find_ll(path: &Path, file: &str, in_deps: bool) -> anyhow::Result<PathBuf> {
+    let mut ll = None;
+    // most recently modified
+    let mut mrm = SystemTime::UNIX_EPOCH;
+    let prefix = format!("{}-", file.replace('-', "_"));
+
+    let mut path = path.parent().expect("unreachable").to_path_buf();
+
+    if in_deps {
+        path = path.join("deps"); // the .ll file is placed in ../deps
+    }
+
+    for e in fs::read_dir(path)? {
+        let e = e?;
+        let p = e.path();
+
+        if p.extension().map(|e| e == "ll").unwrap_or(false)
+            && p.file_stem()
+                .expect("unreachable")
+                .to_str()
+                .expect("unreachable")
+                .starts_with(&prefix)
+        {
+            let modified = e.metadata()?.modified()?;
+            if ll.is_none() || modified > mrm {
+                ll = Some(p);
+                mrm = modified;
+            }
+        }
+    }
+
+    ll.ok_or_else(|| anyhow!("couldn't find the LLVM IR file of `{}`", file))
+}
+
+// the location of the `kind` artifact named `name` per the `compiler-artifact` messages that Cargo
+// emits with `--message-format=json`
+fn artifact_path(artifacts: &[Json], kind: &str, name: &str, is_wasm: bool) -> Option<PathBuf> {
+    let normalize = |name: &str| name.replace('-', "_");
+
+    let artifact = artifacts.iter().rev().find(|artifact| {
+        let target = artifact.get("target");
+        target
+            .and_then(|target| target.get("name"))
+            .and_then(Json::string)
+            .map(normalize)
+            == Some(normalize(name))
+            && target
+                .and_then(|target| target.get("kind"))
+                .and_then(Json::array)
+                .unwrap_or(&[])
+                .iter()
+                .any(|k| k.string() == Some(kind))
+    })?;
+
+    if kind == "cdylib" || kind == "staticlib" {
+        // the `.rlib` and other crate types are also listed
+        let extensions: &[&str] = match (kind, is_wasm) {
+            ("staticlib", _) => &["a"],
+            (_, true) => &["wasm"],
+            // ELF, Mach-O and PE shared libraries
+            (_, false) => &["so", "dylib", "dll"],
+        };
+        artifact
+            .get("filenames")
+            .and_then(Json::array)?
+            .iter()
+            .filter_map(Json::string)
+            .map(PathBuf::from)
+            .find(|path| {
+                path.extension()
+                    .is_some_and(|ext| extensions.iter().any(|extension| ext == *extension))
+            })
+    } else {
+        artifact
+            .get("executable")
+            .and_then(Json::string)
+            .map(PathBuf::from)
+    }
+}
+
+// links all the object files in the `archive` of a `staticlib` into an executable using the
+// toolchain's `rust-lld`. The undefined symbols are provided by the program that links the library
+// so they are left unresolved
+fn link_staticlib(archive: &Path, host: &str) -> anyhow::Result<PathBuf> {
+    let output = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()?;
+    if !output.status.success() {
+        bail!("`rustc --print sysroot` failed");
+    }
+    let sysroot = PathBuf::from(str::from_utf8(&output.stdout)?.trim());
+    let lld = sysroot
+        .join("lib/rustlib")
+        .join(host)
+        .join("bin")
+        .join("rust-lld");
+
+    let elf = archive.with_extension("elf");
+    let output = Command::new(&lld)
+        .args([
+            "-flavor",
+            "gnu",
+            "--entry=0",
+            "--unresolved-symbols=ignore-all",
+        ])
+        .arg("--whole-archive")
+        .arg(archive)
+        .arg("-o")
+        .arg(&elf)
+        .output()
+        .map_err(|e| anyhow!("couldn't run `{}`: {}", lld.display(), e))?;
+    if !output.status.success() {
+        bail!(
+            "couldn't link `{}`:\n{}",
+            archive.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(elf)
+}
+
+// the directory of the workspace package that matches `spec`, as reported by `cargo metadata`
+pub(crate) fn package_root(spec: &str) -> anyhow::Result<PathBuf> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        bail!("`cargo metadata` failed");
+    }
+
+    let metadata = compare::parse_json(str::from_utf8(&output.stdout)?)
+        .ok_or_else(|| anyhow!("couldn't parse the output of `cargo metadata`"))?;
+
+    // `name` or `name@version`
+    let (name, version) = match spec.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (spec, None),
+    };
+    let package = metadata
+        .get("packages")
+        .and_then(Json::array)
+        .unwrap_or(&[])
+        .iter()
+        .find(|package| {
+            package.get("name").and_then(Json::string) == Some(name)
+                && version.is_none_or(|version| {
+                    package.get("version").and_then(Json::string) == Some(version)
+                })
+        })
+        .ok_or_else(|| anyhow!("package `{}` not found in the workspace", spec))?;
+
+    let manifest = package
+        .get("manifest_path")
+        .and_then(Json::string)
+        .ok_or_else(|| anyhow!("`cargo metadata` didn't report the manifest of `{}`", spec))?;
+    Ok(Path::new(manifest)
+        .parent()
+        .expect("UNREACHABLE")
+        .to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    #[test]
+    fn artifact_path() {
+        let artifacts = [
+            r#"{"reason":"compiler-artifact","target":{"kind":["bin"],"crate_types":["bin"],"name":"my-app"},"filenames":["/ws/target/release/my-app"],"executable":"/ws/target/release/my-app","fresh":false}"#,
+            r#"{"reason":"compiler-artifact","target":{"kind":["rlib","cdylib"],"crate_types":["rlib","cdylib"],"name":"my_lib"},"filenames":["/ws/target/release/libmy_lib.rlib","/ws/target/release/libmy_lib.so"],"executable":null,"fresh":true}"#,
+            r#"{"reason":"compiler-artifact","target":{"kind":["cdylib"],"crate_types":["cdylib"],"name":"my-ffi"},"filenames":["/ws/target/release/libmy_ffi.dylib"],"executable":null,"fresh":true}"#,
+        ]
+        .iter()
+        .map(|line| crate::compare::parse_json(line).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            super::artifact_path(&artifacts, "bin", "my-app", false),
+            Some(Path::new("/ws/target/release/my-app").to_owned())
+        );
+        assert_eq!(
+            super::artifact_path(&artifacts, "cdylib", "my-lib", false),
+            Some(Path::new("/ws/target/release/libmy_lib.so").to_owned())
+        );
+        assert_eq!(
+            super::artifact_path(&artifacts, "cdylib", "my-ffi", false),
+            Some(Path::new("/ws/target/release/libmy_ffi.dylib").to_owned())
+        );
+        assert_eq!(
+            super::artifact_path(&artifacts, "example", "my-app", false),
+            None
+        );
+    }
+
+    #[test]
+    fn package_root() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(super::package_root("cargo-call-stack").unwrap(), root);
+        assert_eq!(
+            super::package_root(&format!("cargo-call-stack@{}", env!("CARGO_PKG_VERSION")))
+                .unwrap(),
+            root
+        );
+
+        assert!(super::package_root("cargo-call-stack@0.0.0").is_err());
+    }
+}
@@ -0,0 +1,565 @@
+//! `cargo call-stack report --compare old.json new.json`
+//!
+//! Compares the maximum stack usage of the roots of two call graphs produced with `--format json`
+//! and renders the changes as a table that can be posted as a pull request comment
+
+use core::fmt::{self, Write as _};
+use std::{borrow::Cow, collections::BTreeMap, fs, path::PathBuf};
+
+use anyhow::anyhow;
+use clap::ValueEnum;
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag},
+    character::complete::{anychar, char, digit1, multispace0},
+    combinator::{all_consuming, map, map_res, value},
+    multi::{many0, separated_list0},
+    sequence::{delimited, preceded, separated_pair},
+    IResult,
+};
+
+use crate::analysis::graph::Max;
+
+/// Compare the max stack usage of two call graphs produced with `--format json`
+#[derive(clap::Args, Debug)]
+pub struct ReportArgs {
+    /// The call graphs to compare: the baseline and the new version
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"], required = true)]
+    compare: Vec<PathBuf>,
+
+    /// Output format
+    #[arg(long, default_value = "markdown")]
+    format: ReportFormat,
+
+    /// Increases of the max stack usage above this percentage are flagged
+    #[arg(long, value_name = "PERCENT", default_value_t = 10)]
+    threshold: u64,
+}
+
+#[derive(ValueEnum, PartialEq, Debug, Clone, Copy)]
+enum ReportFormat {
+    Markdown,
+}
+
+pub fn report(args: ReportArgs) -> anyhow::Result<i32> {
+    let read = |path: &PathBuf| {
+        let json = fs::read_to_string(path)
+            .map_err(|e| anyhow!("couldn't read `{}`: {}", path.display(), e))?;
+        maxima(&json).map_err(|e| anyhow!("`{}`: {}", path.display(), e))
+    };
+    let old = read(&args.compare[0])?;
+    let new = read(&args.compare[1])?;
+
+    match args.format {
+        ReportFormat::Markdown => print!("{}", markdown(&old, &new, args.threshold)),
+    }
+
+    Ok(0)
+}
+
+// returns the max stack usage of each root of the call graph
+//
+// the roots are the functions listed in `roots`, e.g. the exports of a `cdylib`, or, if there are
+// none, the functions that are not called by any other function
+pub(crate) fn maxima(json: &str) -> anyhow::Result<BTreeMap<String, Option<Max>>> {
+    let graph = parse_json(json).ok_or_else(|| anyhow!("malformed JSON"))?;
+
+    let err = || anyhow!("not a call graph produced with `--format json`");
+    let nodes = graph.get("nodes").and_then(Json::array).ok_or_else(err)?;
+    let edges = graph.get("edges").and_then(Json::array).ok_or_else(err)?;
+    let roots = graph.get("roots").and_then(Json::array).ok_or_else(err)?;
+
+    let mut roots = roots
+        .iter()
+        .map(|root| root.number().ok_or_else(err))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if roots.is_empty() {
+        let mut called = vec![false; nodes.len()];
+        for edge in edges {
+            match edge.array() {
+                // the third element, the number of call sites, is absent in older files
+                Some([caller, callee]) | Some([caller, callee, _]) => {
+                    let (caller, callee) = (
+                        caller.number().ok_or_else(err)?,
+                        callee.number().ok_or_else(err)?,
+                    );
+                    // recursive functions can still be roots
+                    if caller != callee {
+                        if let Some(called) = called.get_mut(callee as usize) {
+                            *called = true;
+                        }
+                    }
+                }
+                _ => return Err(err()),
+            }
+        }
+
+        roots = (0..nodes.len() as u64)
+            .filter(|i| !called[*i as usize])
+            .collect();
+    }
+
+    let mut maxima = BTreeMap::new();
+    for root in roots {
+        let node = nodes.get(root as usize).ok_or_else(err)?;
+        let name = node.get("name").and_then(Json::string).ok_or_else(err)?;
+        let max = match node.get("max").ok_or_else(err)? {
+            Json::Null => None,
+            max => Some(if let Some(n) = max.get("exact").and_then(Json::number) {
+                Max::Exact(n)
+            } else {
+                Max::LowerBound(
+                    max.get("lower_bound")
+                        .and_then(Json::number)
+                        .ok_or_else(err)?,
+                )
+            }),
+        };
+
+        // the hash changes from build to build
+        let name = crate::intern::dehash(name).unwrap_or(Cow::Borrowed(name));
+        maxima.insert(name.into_owned(), max);
+    }
+
+    Ok(maxima)
+}
+
+fn markdown(
+    old: &BTreeMap<String, Option<Max>>,
+    new: &BTreeMap<String, Option<Max>>,
+    threshold: u64,
+) -> String {
+    fn cell(max: Option<Max>) -> String {
+        match max {
+            Some(Max::Exact(n)) => n.to_string(),
+            Some(Max::LowerBound(n)) => format!(">= {}", n),
+            None => "?".to_string(),
+        }
+    }
+
+    fn bytes(max: Option<Max>) -> Option<u64> {
+        match max? {
+            Max::Exact(n) | Max::LowerBound(n) => Some(n),
+        }
+    }
+
+    let mut rows = String::new();
+    let mut unchanged = 0;
+    let mut names = old.keys().chain(new.keys()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let (old, new) = (old.get(name).copied(), new.get(name).copied());
+
+        let change = match (old, new) {
+            (None, Some(_)) => "🆕 added".to_string(),
+            (Some(_), None) => "🗑️ removed".to_string(),
+            (Some(old), Some(new)) if old == new => {
+                unchanged += 1;
+                continue;
+            }
+            (Some(old), Some(new)) => match (bytes(old), bytes(new)) {
+                (Some(before), Some(after)) if after > before => {
+                    let increase = after - before;
+                    let emoji = if before == 0 || increase * 100 > before * threshold {
+                        "🔴"
+                    } else {
+                        "🟠"
+                    };
+                    format!("{} +{}{}", emoji, increase, percent(increase, before))
+                }
+                (Some(before), Some(after)) if after < before => {
+                    let decrease = before - after;
+                    format!("🟢 -{}{}", decrease, percent(decrease, before))
+                }
+                // same number of bytes but it went from exact to lower bound or vice versa
+                _ => "⚠️ changed".to_string(),
+            },
+            (None, None) => unreachable!(),
+        };
+
+        let _ = writeln!(
+            rows,
+            "| `{}` | {} | {} | {} |",
+            name.replace('`', "'"),
+            old.map(cell).as_deref().unwrap_or("-"),
+            new.map(cell).as_deref().unwrap_or("-"),
+            change
+        );
+    }
+
+    let mut md = String::from("### Stack usage\n\n");
+    if rows.is_empty() {
+        md.push_str("No changes in the max stack usage ✅\n");
+    } else {
+        md.push_str("| Function | Old (bytes) | New (bytes) | Change |\n");
+        md.push_str("| --- | ---: | ---: | --- |\n");
+        md.push_str(&rows);
+    }
+
+    if unchanged != 0 {
+        let _ = writeln!(
+            md,
+            "\n{} unchanged function{}",
+            unchanged,
+            if unchanged == 1 { "" } else { "s" }
+        );
+    }
+
+    md
+}
+
+// renders the max stack usage of the roots under each feature combination (`--feature-matrix`) as
+// a table with one column per combination plus the combination that needs the most stack
+pub(crate) fn matrix(columns: &[(String, BTreeMap<String, Option<Max>>)]) -> String {
+    fn cell(max: Option<&Option<Max>>) -> String {
+        match max {
+            Some(Some(Max::Exact(n))) => n.to_string(),
+            Some(Some(Max::LowerBound(n))) => format!(">= {}", n),
+            Some(None) => "?".to_string(),
+            None => "-".to_string(),
+        }
+    }
+
+    let mut names = columns
+        .iter()
+        .flat_map(|(_, maxima)| maxima.keys())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    let mut header = vec!["Root".to_owned()];
+    header.extend(columns.iter().map(|(name, _)| name.clone()));
+    header.push("Worst".to_owned());
+
+    let mut rows = vec![header];
+    for name in names {
+        let mut row = vec![name.clone()];
+        row.extend(columns.iter().map(|(_, maxima)| cell(maxima.get(name))));
+
+        let bytes = columns
+            .iter()
+            .map(|(_, maxima)| match maxima.get(name) {
+                Some(Some(Max::Exact(n))) | Some(Some(Max::LowerBound(n))) => Some(*n),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let worst = bytes.iter().flatten().max();
+        row.push(if bytes.iter().all(|n| n.as_ref() == worst) {
+            "(all)".to_owned()
+        } else {
+            let worst = bytes.iter().position(|n| n.as_ref() == worst).unwrap();
+            columns[worst].0.clone()
+        });
+        rows.push(row);
+    }
+
+    let mut widths = vec![0; rows[0].len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for (i, row) in rows.iter().enumerate() {
+        let mut line = String::new();
+        for (j, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            if j != 0 {
+                line.push_str("  ");
+            }
+            if i != 0 && j != 0 && j != row.len() - 1 {
+                // numbers are right aligned
+                let _ = write!(line, "{:>1$}", cell, width);
+            } else {
+                let _ = write!(line, "{:<1$}", cell, width);
+            }
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+
+    table
+}
+
+fn percent(delta: u64, before: u64) -> String {
+    if before == 0 {
+        String::new()
+    } else {
+        format!(" ({:.1}%)", delta as f64 * 100. / before as f64)
+    }
+}
+
+// NOTE only the subset of JSON that `--format json` and `cargo metadata` produce is supported: no
+// floats or negative numbers
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn number(&self) -> Option<u64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn string(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write!(f, "{}", crate::cli::JsonStr(s)),
+            Json::Array(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}:{}", crate::cli::JsonStr(key), value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_json(json: &str) -> Option<Json> {
+    all_consuming(delimited(multispace0, json_value, multispace0))(json)
+        .ok()
+        .map(|(_, value)| value)
+}
+
+fn json_value(i: &str) -> IResult<&str, Json> {
+    alt((
+        value(Json::Null, tag("null")),
+        value(Json::Bool(true), tag("true")),
+        value(Json::Bool(false), tag("false")),
+        map_res(digit1, |n: &str| n.parse().map(Json::Number)),
+        map(json_string, Json::String),
+        map(
+            delimited(
+                char('['),
+                separated_list0(char(','), delimited(multispace0, json_value, multispace0)),
+                preceded(multispace0, char(']')),
+            ),
+            Json::Array,
+        ),
+        map(
+            delimited(
+                char('{'),
+                separated_list0(
+                    char(','),
+                    delimited(
+                        multispace0,
+                        separated_pair(
+                            json_string,
+                            delimited(multispace0, char(':'), multispace0),
+                            json_value,
+                        ),
+                        multispace0,
+                    ),
+                ),
+                preceded(multispace0, char('}')),
+            ),
+            Json::Object,
+        ),
+    ))(i)
+}
+
+fn json_string(i: &str) -> IResult<&str, String> {
+    let (i, parts) = delimited(
+        char('"'),
+        many0(alt((
+            map(is_not("\"\\"), str::to_owned),
+            map_res(preceded(char('\\'), anychar), |c| {
+                Ok::<_, ()>(
+                    match c {
+                        '"' | '\\' | '/' => c,
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        _ => return Err(()),
+                    }
+                    .to_string(),
+                )
+            }),
+            map_res(
+                preceded(tag("\\u"), nom::bytes::complete::take(4usize)),
+                |hex: &str| {
+                    u32::from_str_radix(hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .map(|c| c.to_string())
+                        .ok_or(())
+                },
+            ),
+        ))),
+        char('"'),
+    )(i)?;
+
+    Ok((i, parts.concat()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::Json;
+    use crate::analysis::graph::Max;
+
+    const OLD: &str = r#"{
+  "nodes": [
+    {"id":0,"name":"_start","local":8,"max":{"exact":24},"dashed":false,"overridden":false},
+    {"id":1,"name":"app::foo::h0123456789abcdef","local":16,"max":{"exact":16},"dashed":false,"overridden":false},
+    {"id":2,"name":"app::bar","local":null,"max":{"lower_bound":0},"dashed":false,"overridden":false},
+    {"id":3,"name":"app::baz","local":0,"max":{"exact":0},"dashed":false,"overridden":false}
+  ],
+  "edges": [
+    [0,1],
+    [2,2]
+  ],
+  "cycles": [
+    {"local":{"lower_bound":0},"members":[2]}
+  ],
+  "roots": []
+}"#;
+
+    #[test]
+    fn json() {
+        let (rest, value) = super::json_value(r#"{"a":[1, true,null],"b\"A":{}}"#).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(
+            value,
+            Json::Object(vec![
+                (
+                    "a".to_owned(),
+                    Json::Array(vec![Json::Number(1), Json::Bool(true), Json::Null])
+                ),
+                ("b\"A".to_owned(), Json::Object(vec![])),
+            ])
+        );
+        assert_eq!(value.to_string(), r#"{"a":[1,true,null],"b\"A":{}}"#);
+    }
+
+    #[test]
+    fn maxima() {
+        let maxima = super::maxima(OLD).unwrap();
+
+        assert_eq!(
+            maxima.into_iter().collect::<Vec<_>>(),
+            [
+                ("_start".to_owned(), Some(Max::Exact(24))),
+                ("app::bar".to_owned(), Some(Max::LowerBound(0))),
+                ("app::baz".to_owned(), Some(Max::Exact(0))),
+            ]
+        );
+
+        // newer files include the number of call sites of each edge
+        let new = OLD.replace("[0,1]", "[0,1,2]").replace("[2,2]", "[2,2,1]");
+        assert_eq!(super::maxima(&new).unwrap(), super::maxima(OLD).unwrap());
+
+        assert!(super::maxima("{}").is_err());
+        assert!(super::maxima("{").is_err());
+    }
+
+    #[test]
+    fn markdown() {
+        let old = super::maxima(OLD).unwrap();
+        let mut new = old.clone();
+        new.insert("_start".to_owned(), Some(Max::Exact(32)));
+        new.insert("app::bar".to_owned(), Some(Max::LowerBound(0)));
+        new.remove("app::baz");
+        new.insert("app::quux".to_owned(), Some(Max::Exact(8)));
+
+        assert_eq!(
+            super::markdown(&old, &new, 10),
+            "### Stack usage\n\
+             \n\
+             | Function | Old (bytes) | New (bytes) | Change |\n\
+             | --- | ---: | ---: | --- |\n\
+             | `_start` | 24 | 32 | 🔴 +8 (33.3%) |\n\
+             | `app::baz` | 0 | - | 🗑️ removed |\n\
+             | `app::quux` | - | 8 | 🆕 added |\n\
+             \n\
+             1 unchanged function\n"
+        );
+
+        new.insert("_start".to_owned(), Some(Max::Exact(20)));
+        assert!(super::markdown(&old, &new, 10).contains("| `_start` | 24 | 20 | 🟢 -4 (16.7%) |"));
+
+        new.insert("_start".to_owned(), Some(Max::Exact(26)));
+        assert!(super::markdown(&old, &new, 10).contains("| `_start` | 24 | 26 | 🟠 +2 (8.3%) |"));
+
+        assert_eq!(
+            super::markdown(&old, &old, 10),
+            "### Stack usage\n\nNo changes in the max stack usage ✅\n\n3 unchanged functions\n"
+        );
+
+        assert_eq!(
+            super::markdown(&BTreeMap::new(), &BTreeMap::new(), 10),
+            "### Stack usage\n\nNo changes in the max stack usage ✅\n"
+        );
+    }
+
+    #[test]
+    fn matrix() {
+        let default = super::maxima(OLD).unwrap();
+        let mut logging = default.clone();
+        logging.insert("_start".to_owned(), Some(Max::Exact(120)));
+        logging.remove("app::baz");
+
+        assert_eq!(
+            super::matrix(&[
+                ("default".to_owned(), default),
+                ("logging".to_owned(), logging)
+            ]),
+            "Root      default  logging  Worst\n\
+             _start         24      120  logging\n\
+             app::bar     >= 0     >= 0  (all)\n\
+             app::baz        0        -  default\n"
+        );
+    }
+}
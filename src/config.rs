@@ -0,0 +1,390 @@
+//! Stack usage information about the functions the analysis can't see into
+//!
+//! The configuration file, `call-stack.toml` next to `Cargo.toml` by default, describes functions
+//! like hand-written assembly or the ones of precompiled C libraries: their local stack usage and
+//! the functions they call. Functions are named by their mangled name, their demangled name or
+//! their demangled name without the hash.
+//!
+//! ``` toml
+//! [functions.vendor_init]
+//! stack = 32
+//! calls = ["vendor_log", "memset"]
+//!
+//! [functions."app::asm_routine"]
+//! stack = 16
+//! ```
+//!
+//! The `tasks` table declares the entry points of the tasks that run on their own stack, e.g. the
+//! threads of an RTOS, and the size of that stack in bytes, like `--task` does.
+//!
+//! ``` toml
+//! [tasks]
+//! "app::worker" = 1024
+//! ```
+//!
+//! The `secondary-stacks` table declares the functions that switch the stack pointer to a static
+//! buffer, like `--secondary-stack` does. The size of the buffer, in bytes, is read from the
+//! symbol table unless `size` is given, e.g. for a region that a linker script reserves.
+//!
+//! ``` toml
+//! [secondary-stacks."dsp::on_scratch"]
+//! symbol = "SCRATCH"
+//!
+//! [secondary-stacks.on_irq_stack]
+//! symbol = "_irq_stack_start"
+//! size = 2048
+//! ```
+//!
+//! The `distributed-slices` table lists, under `sections`, the linker sections that hold function
+//! pointers registered at build time, like `--distributed-slice` does. Sections named `linkme_*`
+//! or that have a linker-generated `__start_*` symbol are found without it; the ones placed by a
+//! linker script, whose start is marked by a symbol the script defines, must be listed.
+//!
+//! ``` toml
+//! [distributed-slices]
+//! sections = [".handlers"]
+//! ```
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, bail};
+use toml::Value;
+
+/// Name of the configuration file that is used if none is specified
+pub const FILE_NAME: &str = "call-stack.toml";
+
+/// The contents of a configuration file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// The described functions, sorted by name
+    pub functions: Vec<Function>,
+    /// The entry points of the tasks and the size, in bytes, of their stacks, sorted by name
+    pub tasks: Vec<(String, u64)>,
+    /// The functions that switch to a secondary stack, sorted by name
+    pub secondary_stacks: Vec<SecondaryStack>,
+    /// Linker sections that hold function pointers registered at build time
+    pub distributed_slices: Vec<String>,
+}
+
+/// Stack usage information about a function
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    /// Mangled name, demangled name or demangled name without the hash
+    pub name: String,
+    /// Local stack usage, in bytes
+    pub stack: Option<u64>,
+    /// The functions that this one calls
+    pub calls: Vec<String>,
+}
+
+/// A function that switches the stack pointer to a static buffer
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecondaryStack {
+    /// Mangled name, demangled name or demangled name without the hash
+    pub function: String,
+    /// The symbol of the buffer
+    pub symbol: String,
+    /// Size of the buffer, in bytes; if unset, the size of `symbol` in the symbol table
+    pub size: Option<u64>,
+}
+
+impl Config {
+    /// Reads and parses the configuration file at `path`
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let toml = fs::read_to_string(path)
+            .map_err(|e| anyhow!("couldn't read `{}`: {}", path.display(), e))?;
+        Self::parse(&toml).map_err(|e| anyhow!("`{}`: {}", path.display(), e))
+    }
+
+    /// Parses the contents of a configuration file
+    pub fn parse(toml: &str) -> anyhow::Result<Self> {
+        let toml = toml.parse::<Value>()?;
+        let root = toml.as_table().expect("UNREACHABLE");
+
+        let mut functions = vec![];
+        let mut tasks = vec![];
+        let mut secondary_stacks = vec![];
+        let mut distributed_slices = vec![];
+        for (key, value) in root {
+            let table = value
+                .as_table()
+                .ok_or_else(|| anyhow!("`{}` must be a table", key))?;
+
+            match &**key {
+                "functions" => {
+                    for (name, value) in table {
+                        functions.push(function(name, value)?);
+                    }
+                }
+
+                "tasks" => {
+                    for (name, value) in table {
+                        let stack =
+                            value
+                                .as_integer()
+                                .filter(|stack| *stack >= 0)
+                                .ok_or_else(|| {
+                                    anyhow!("`tasks.\"{}\"` must be a number of bytes", name)
+                                })?;
+                        tasks.push((name.clone(), stack as u64));
+                    }
+                }
+
+                "secondary-stacks" => {
+                    for (name, value) in table {
+                        secondary_stacks.push(secondary_stack(name, value)?);
+                    }
+                }
+
+                "distributed-slices" => {
+                    for (key, value) in table {
+                        if key != "sections" {
+                            bail!("unknown key `distributed-slices.{}`", key);
+                        }
+
+                        distributed_slices = value
+                            .as_array()
+                            .and_then(|array| {
+                                array
+                                    .iter()
+                                    .map(|section| section.as_str().map(str::to_owned))
+                                    .collect::<Option<Vec<_>>>()
+                            })
+                            .ok_or_else(|| {
+                                anyhow!("`distributed-slices.sections` must be a list of sections")
+                            })?;
+                    }
+                }
+
+                _ => bail!("unknown section `{}`", key),
+            }
+        }
+
+        Ok(Config {
+            functions,
+            tasks,
+            secondary_stacks,
+            distributed_slices,
+        })
+    }
+
+    /// Returns the description of the function `name`, a mangled symbol name
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        let demangled = rustc_demangle::demangle(name);
+        let (demangled, dehashed) = (demangled.to_string(), format!("{:#}", demangled));
+
+        self.functions.iter().find(|function| {
+            function.name == name || function.name == demangled || function.name == dehashed
+        })
+    }
+}
+
+fn function(name: &str, value: &Value) -> anyhow::Result<Function> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("`functions.{}` must be a table", name))?;
+
+    let mut stack = None;
+    let mut calls = vec![];
+    for (key, value) in table {
+        match &**key {
+            "stack" => {
+                stack = Some(
+                    value
+                        .as_integer()
+                        .filter(|stack| *stack >= 0)
+                        .ok_or_else(|| {
+                            anyhow!("`functions.{}.stack` must be a number of bytes", name)
+                        })? as u64,
+                );
+            }
+
+            "calls" => {
+                calls = value
+                    .as_array()
+                    .and_then(|array| {
+                        array
+                            .iter()
+                            .map(|callee| callee.as_str().map(str::to_owned))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "`functions.{}.calls` must be a list of function names",
+                            name
+                        )
+                    })?;
+            }
+
+            _ => bail!("unknown key `functions.{}.{}`", name, key),
+        }
+    }
+
+    Ok(Function {
+        name: name.to_owned(),
+        stack,
+        calls,
+    })
+}
+
+fn secondary_stack(name: &str, value: &Value) -> anyhow::Result<SecondaryStack> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("`secondary-stacks.{}` must be a table", name))?;
+
+    let mut symbol = None;
+    let mut size = None;
+    for (key, value) in table {
+        match &**key {
+            "symbol" => {
+                symbol = Some(value.as_str().map(str::to_owned).ok_or_else(|| {
+                    anyhow!("`secondary-stacks.{}.symbol` must be a symbol name", name)
+                })?);
+            }
+
+            "size" => {
+                size = Some(value.as_integer().filter(|size| *size > 0).ok_or_else(|| {
+                    anyhow!("`secondary-stacks.{}.size` must be a number of bytes", name)
+                })? as u64);
+            }
+
+            _ => bail!("unknown key `secondary-stacks.{}.{}`", name, key),
+        }
+    }
+
+    Ok(SecondaryStack {
+        function: name.to_owned(),
+        symbol: symbol.ok_or_else(|| anyhow!("`secondary-stacks.{}.symbol` is missing", name))?,
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Function, SecondaryStack};
+
+    #[test]
+    fn parse() {
+        let config = Config::parse(
+            r#"
+# hand-written assembly
+[functions.asm_memcpy]
+stack = 16
+
+[functions."vendor::init"]
+stack = 32
+calls = ["vendor_log", "memset"]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.functions,
+            [
+                Function {
+                    name: "asm_memcpy".to_owned(),
+                    stack: Some(16),
+                    calls: vec![],
+                },
+                Function {
+                    name: "vendor::init".to_owned(),
+                    stack: Some(32),
+                    calls: vec!["vendor_log".to_owned(), "memset".to_owned()],
+                }
+            ]
+        );
+
+        assert!(Config::parse("").unwrap().functions.is_empty());
+        assert!(Config::parse("functions = 1").is_err());
+        assert!(Config::parse("[function.foo]\nstack = 8").is_err());
+        assert!(Config::parse("[functions.foo]\nstack = -8").is_err());
+        assert!(Config::parse("[functions.foo]\nstack = \"8\"").is_err());
+        assert!(Config::parse("[functions.foo]\ncalls = [1]").is_err());
+        assert!(Config::parse("[functions.foo]\nstak = 8").is_err());
+    }
+
+    #[test]
+    fn tasks() {
+        let config = Config::parse(
+            r#"
+[tasks]
+"app::worker" = 1024
+idle = 256
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.tasks,
+            [("app::worker".to_owned(), 1024), ("idle".to_owned(), 256)]
+        );
+        assert!(config.functions.is_empty());
+
+        assert!(Config::parse("[tasks]\nidle = -8").is_err());
+        assert!(Config::parse("[tasks]\nidle = \"8\"").is_err());
+    }
+
+    #[test]
+    fn secondary_stacks() {
+        let config = Config::parse(
+            r#"
+[secondary-stacks."dsp::on_scratch"]
+symbol = "SCRATCH"
+
+[secondary-stacks.on_irq_stack]
+symbol = "_irq_stack_start"
+size = 2048
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.secondary_stacks,
+            [
+                SecondaryStack {
+                    function: "dsp::on_scratch".to_owned(),
+                    symbol: "SCRATCH".to_owned(),
+                    size: None,
+                },
+                SecondaryStack {
+                    function: "on_irq_stack".to_owned(),
+                    symbol: "_irq_stack_start".to_owned(),
+                    size: Some(2048),
+                }
+            ]
+        );
+
+        assert!(Config::parse("[secondary-stacks.f]\nsize = 8").is_err());
+        assert!(Config::parse("[secondary-stacks.f]\nsymbol = 1").is_err());
+        assert!(Config::parse("[secondary-stacks.f]\nsymbol = \"S\"\nsize = 0").is_err());
+        assert!(Config::parse("[secondary-stacks.f]\nsymbol = \"S\"\nsise = 8").is_err());
+    }
+
+    #[test]
+    fn distributed_slices() {
+        let config =
+            Config::parse("[distributed-slices]\nsections = [\".handlers\", \"hooks\"]").unwrap();
+        assert_eq!(config.distributed_slices, [".handlers", "hooks"]);
+
+        assert!(Config::parse("[distributed-slices]\nsections = \"hooks\"").is_err());
+        assert!(Config::parse("[distributed-slices]\nsection = [\"hooks\"]").is_err());
+    }
+
+    #[test]
+    fn function() {
+        let config = Config::parse(
+            r#"
+[functions.asm_memcpy]
+stack = 16
+
+[functions."app::foo"]
+stack = 8
+"#,
+        )
+        .unwrap();
+
+        assert!(config.function("asm_memcpy").is_some());
+        assert!(config.function("_ZN3app3foo17h0123456789abcdefE").is_some());
+        assert!(config.function("_ZN3app3bar17h0123456789abcdefE").is_none());
+    }
+}
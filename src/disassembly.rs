@@ -0,0 +1,110 @@
+//! Per-function disassembly
+//!
+//! The program is disassembled once with an `objdump` compatible tool (GNU `objdump`,
+//! `llvm-objdump` or a vendor's `arm-none-eabi-objdump`) and its output is split at the symbol
+//! headers, e.g. `00020154 <_start>:`, so that the instructions of each function can be written to
+//! a file of their own.
+
+use std::{path::Path, process::Command, str};
+
+use anyhow::{anyhow, bail};
+
+/// Disassembles the `elf` file with `objdump`; returns the address of each symbol header followed
+/// by the text of its block, header included
+pub fn disassemble(objdump: &Path, elf: &Path) -> anyhow::Result<Vec<(u64, String)>> {
+    let output = Command::new(objdump)
+        .args(["-d", "-C"])
+        .arg(elf)
+        .output()
+        .map_err(|e| anyhow!("couldn't run `{}`: {}", objdump.display(), e))?;
+    if !output.status.success() {
+        bail!(
+            "`{} -d -C {}` failed:\n{}",
+            objdump.display(),
+            elf.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(split(str::from_utf8(&output.stdout)?))
+}
+
+fn split(output: &str) -> Vec<(u64, String)> {
+    let mut blocks: Vec<(u64, String)> = vec![];
+    for line in output.lines() {
+        if let Some(address) = header(line) {
+            blocks.push((address, String::new()));
+        } else if line.starts_with("Disassembly of section ") {
+            // the next block starts a new section
+            continue;
+        }
+
+        if let Some((_, text)) = blocks.last_mut() {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+
+    for (_, text) in &mut blocks {
+        // blank lines separate the blocks
+        let len = text.trim_end().len();
+        text.truncate(len);
+        text.push('\n');
+    }
+
+    blocks
+}
+
+// `00020154 <_start>:`
+fn header(line: &str) -> Option<u64> {
+    let (address, rest) = line.split_once(' ')?;
+    if rest.starts_with('<') && rest.ends_with(">:") {
+        u64::from_str_radix(address, 16).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn split() {
+        let output = "
+app:	file format elf32-littlearm
+
+Disassembly of section .text:
+
+00020154 <_start>:
+   20154: 0e 48        	ldr	r0, [pc, #56]           @ 0x20190 <$d.1>
+   2018e: fe e7        	b	0x2018e <_start+0x3a>   @ imm = #-4
+
+00020190 <$d.1>:
+   20190:	04 01 01 00	.word	0x00010104
+
+Disassembly of section .text.app::foo:
+
+000201a0 <app::foo>:
+   201a0: 80 b5        	push	{r7, lr}
+";
+
+        let blocks = super::split(output);
+        assert_eq!(
+            blocks
+                .iter()
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>(),
+            [0x20154, 0x20190, 0x201a0]
+        );
+        assert_eq!(
+            blocks[0].1,
+            "00020154 <_start>:
+   20154: 0e 48        	ldr	r0, [pc, #56]           @ 0x20190 <$d.1>
+   2018e: fe e7        	b	0x2018e <_start+0x3a>   @ imm = #-4
+"
+        );
+        assert_eq!(
+            blocks[2].1,
+            "000201a0 <app::foo>:\n   201a0: 80 b5        \tpush\t{r7, lr}\n"
+        );
+    }
+}
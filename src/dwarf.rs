@@ -0,0 +1,530 @@
+//! DWARF parsing
+//!
+//! Used to recover the signatures of functions that don't appear in the LLVM IR, like the ones in C
+//! libraries that were compiled with debug information (`-g`), and to attribute the stack frame of
+//! a function to its local variables (`--explain-frame`)
+
+use std::collections::{HashMap, HashSet};
+
+use gimli::{
+    AttributeValue, DebuggingInformationEntry, Dwarf, EndianSlice, Expression, Operation, Reader,
+    RunTimeEndian, SectionId, Unit, UnitOffset,
+};
+use object::{Architecture, File, Object, ObjectSection};
+
+use crate::ir::{FnSig, Type};
+
+/// Returns the signature, as LLVM IR types, of the `functions` defined in the DWARF information
+///
+/// Functions whose parameters or return value can't be lowered to a single LLVM IR type (e.g.
+/// structs passed by value) or that are variadic are left out
+pub fn signatures(
+    elf: &File,
+    functions: &HashSet<&str>,
+) -> gimli::Result<HashMap<String, FnSig<'static>>> {
+    let dwarf = load(elf)?;
+
+    let mut sigs = HashMap::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram
+                || entry.attr_value(gimli::DW_AT_declaration)?.is_some()
+            {
+                continue;
+            }
+
+            let name = if let Some(name) = entry
+                .attr_value(gimli::DW_AT_linkage_name)?
+                .or(entry.attr_value(gimli::DW_AT_name)?)
+            {
+                dwarf.attr_string(&unit, name)?
+            } else {
+                continue;
+            };
+            let name = name.to_string()?;
+
+            if functions.contains(name) && !sigs.contains_key(name) {
+                if let Some(sig) = signature(&unit, entry.offset())? {
+                    sigs.insert(name.to_owned(), sig);
+                }
+            }
+        }
+    }
+
+    Ok(sigs)
+}
+
+fn load<'a>(elf: &File<'a>) -> gimli::Result<Dwarf<EndianSlice<'a, RunTimeEndian>>> {
+    let endian = if elf.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+
+    Dwarf::load(|id: SectionId| -> gimli::Result<_> {
+        let data = elf
+            .section_by_name(id.name())
+            .and_then(|section| section.data().ok())
+            .unwrap_or(&[]);
+        Ok(EndianSlice::new(data, endian))
+    })
+}
+
+/// A local variable, or parameter, of a function that lives in its stack frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variable {
+    /// The name of the variable
+    pub name: String,
+    /// The name of its type, if known
+    pub ty: Option<String>,
+    /// Size of the variable, in bytes
+    pub size: u64,
+    /// Offset of the variable from the frame base
+    pub offset: i64,
+    /// The name of the function the variable belongs to, if it was inlined into the analyzed one
+    pub inlined_from: Option<String>,
+}
+
+/// Returns the variables of the function at `address` that, according to the DWARF information,
+/// live in its stack frame
+///
+/// Returns `None` if there's no debug information for the function. Variables that only live in
+/// registers, or whose location is more complex than an offset from the frame base or the stack
+/// pointer, are left out
+pub fn frame_variables(elf: &File, address: u64) -> gimli::Result<Option<Vec<Variable>>> {
+    let dwarf = load(elf)?;
+    let stack_pointer = stack_pointer(elf.architecture());
+
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+
+            let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+                Some(low_pc) => dwarf.attr_address(&unit, low_pc)?,
+                None => None,
+            };
+            if low_pc != Some(address) {
+                continue;
+            }
+
+            let mut variables = vec![];
+            let mut tree = unit.entries_tree(Some(entry.offset()))?;
+            let root = tree.root()?;
+            let mut children = root.children();
+            while let Some(child) = children.next()? {
+                collect(&dwarf, &unit, child, None, stack_pointer, &mut variables)?;
+            }
+            return Ok(Some(variables));
+        }
+    }
+
+    Ok(None)
+}
+
+// the DWARF register number of the stack pointer
+fn stack_pointer(architecture: Architecture) -> Option<u16> {
+    match architecture {
+        Architecture::Arm => Some(13),
+        Architecture::Aarch64 => Some(31),
+        Architecture::I386 => Some(4),
+        Architecture::X86_64 => Some(7),
+        Architecture::Riscv32 | Architecture::Riscv64 => Some(2),
+        _ => None,
+    }
+}
+
+// collects the variables in the frame from the subtree rooted at `node`
+fn collect<R>(
+    dwarf: &Dwarf<R>,
+    unit: &Unit<R>,
+    node: gimli::EntriesTreeNode<R>,
+    inlined_from: Option<&str>,
+    stack_pointer: Option<u16>,
+    variables: &mut Vec<Variable>,
+) -> gimli::Result<()>
+where
+    R: Reader,
+{
+    let entry = node.entry();
+    let tag = entry.tag();
+
+    if tag == gimli::DW_TAG_variable || tag == gimli::DW_TAG_formal_parameter {
+        let offset = match entry.attr_value(gimli::DW_AT_location)? {
+            Some(location) => frame_offset(dwarf, unit, location, stack_pointer)?,
+            None => None,
+        };
+
+        if let Some(offset) = offset {
+            let name = match attr(unit, entry, gimli::DW_AT_name)? {
+                Some(name) => dwarf
+                    .attr_string(unit, name)?
+                    .to_string_lossy()?
+                    .into_owned(),
+                None => "?".to_owned(),
+            };
+            let (ty, size) = match attr(unit, entry, gimli::DW_AT_type)? {
+                Some(ty) => type_at(dwarf, unit, ty, 0)?,
+                None => (None, None),
+            };
+
+            if let Some(size) = size.filter(|size| *size != 0) {
+                variables.push(Variable {
+                    name,
+                    ty,
+                    size,
+                    offset,
+                    inlined_from: inlined_from.map(|name| name.to_owned()),
+                });
+            }
+        }
+
+        return Ok(());
+    }
+
+    let inlined_name;
+    let inlined_from = if tag == gimli::DW_TAG_inlined_subroutine {
+        inlined_name = match attr(unit, entry, gimli::DW_AT_name)? {
+            Some(name) => dwarf
+                .attr_string(unit, name)?
+                .to_string_lossy()?
+                .into_owned(),
+            None => "?".to_owned(),
+        };
+        Some(&*inlined_name)
+    } else if tag == gimli::DW_TAG_lexical_block {
+        inlined_from
+    } else {
+        // nested functions, types, etc.
+        return Ok(());
+    };
+
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        collect(dwarf, unit, child, inlined_from, stack_pointer, variables)?;
+    }
+
+    Ok(())
+}
+
+// the attribute `name` of `entry` or, if it has none, of its abstract origin (the declaration of
+// an inlined function or variable)
+fn attr<R>(
+    unit: &Unit<R>,
+    entry: &DebuggingInformationEntry<R>,
+    name: gimli::DwAt,
+) -> gimli::Result<Option<AttributeValue<R>>>
+where
+    R: Reader,
+{
+    if let Some(value) = entry.attr_value(name)? {
+        return Ok(Some(value));
+    }
+
+    let mut origin = entry.attr_value(gimli::DW_AT_abstract_origin)?;
+    // bound the search in case of malformed debug information
+    for _ in 0..8 {
+        let Some(AttributeValue::UnitRef(offset)) = origin else {
+            break;
+        };
+        let entry = unit.entry(offset)?;
+        if let Some(value) = entry.attr_value(name)? {
+            return Ok(Some(value));
+        }
+        origin = entry.attr_value(gimli::DW_AT_abstract_origin)?;
+    }
+
+    Ok(None)
+}
+
+// the offset of a variable from the frame base, or the stack pointer, if `location` puts it in the
+// stack frame at some point
+fn frame_offset<R>(
+    dwarf: &Dwarf<R>,
+    unit: &Unit<R>,
+    location: AttributeValue<R>,
+    stack_pointer: Option<u16>,
+) -> gimli::Result<Option<i64>>
+where
+    R: Reader,
+{
+    if let AttributeValue::Exprloc(expression) = location {
+        return stack_slot(unit, expression, stack_pointer);
+    }
+
+    // location list: the variable moves around during the execution of the function
+    if let Some(mut locations) = dwarf.attr_locations(unit, location)? {
+        while let Some(location) = locations.next()? {
+            if let Some(offset) = stack_slot(unit, location.data, stack_pointer)? {
+                return Ok(Some(offset));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+fn stack_slot<R>(
+    unit: &Unit<R>,
+    expression: Expression<R>,
+    stack_pointer: Option<u16>,
+) -> gimli::Result<Option<i64>>
+where
+    R: Reader,
+{
+    let mut operations = expression.operations(unit.encoding());
+    let offset = match operations.next()? {
+        Some(Operation::FrameOffset { offset }) => offset,
+        Some(Operation::RegisterOffset {
+            register, offset, ..
+        }) if Some(register.0) == stack_pointer => offset,
+        _ => return Ok(None),
+    };
+
+    // anything else, e.g. `DW_OP_deref`, means the variable is not (only) in the frame
+    if operations.next()?.is_some() {
+        return Ok(None);
+    }
+
+    Ok(Some(offset))
+}
+
+// the name and size, in bytes, of the type that `ty` refers to, which may be in another unit
+fn type_at<R>(
+    dwarf: &Dwarf<R>,
+    unit: &Unit<R>,
+    ty: AttributeValue<R>,
+    depth: usize,
+) -> gimli::Result<(Option<String>, Option<u64>)>
+where
+    R: Reader,
+{
+    match ty {
+        AttributeValue::UnitRef(offset) => type_of(dwarf, unit, offset, depth),
+        AttributeValue::DebugInfoRef(offset) => {
+            if let Some(offset) = offset.to_unit_offset(&unit.header) {
+                return type_of(dwarf, unit, offset, depth);
+            }
+
+            let mut units = dwarf.units();
+            while let Some(header) = units.next()? {
+                if let Some(offset) = offset.to_unit_offset(&header) {
+                    let unit = dwarf.unit(header)?;
+                    return type_of(dwarf, &unit, offset, depth);
+                }
+            }
+
+            Ok((None, None))
+        }
+        _ => Ok((None, None)),
+    }
+}
+
+// the name and size, in bytes, of the type at `offset`
+fn type_of<R>(
+    dwarf: &Dwarf<R>,
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+    depth: usize,
+) -> gimli::Result<(Option<String>, Option<u64>)>
+where
+    R: Reader,
+{
+    // bound the recursion in case of malformed debug information
+    if depth > 16 {
+        return Ok((None, None));
+    }
+
+    let entry = unit.entry(offset)?;
+    let name = match entry.attr_value(gimli::DW_AT_name)? {
+        Some(name) => Some(
+            dwarf
+                .attr_string(unit, name)?
+                .to_string_lossy()?
+                .into_owned(),
+        ),
+        None => None,
+    };
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|size| size.udata_value());
+    let inner = match entry.attr_value(gimli::DW_AT_type)? {
+        Some(inner) => type_at(dwarf, unit, inner, depth + 1)?,
+        None => (None, None),
+    };
+
+    let tag = entry.tag();
+    Ok(if tag == gimli::DW_TAG_array_type {
+        // the number of elements of each dimension
+        let mut lengths = vec![];
+        let mut tree = unit.entries_tree(Some(offset))?;
+        let root = tree.root()?;
+        let mut children = root.children();
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_subrange_type {
+                continue;
+            }
+
+            let count = entry
+                .attr_value(gimli::DW_AT_count)?
+                .and_then(|count| count.udata_value());
+            let upper_bound = entry
+                .attr_value(gimli::DW_AT_upper_bound)?
+                .and_then(|bound| bound.udata_value());
+            let lower_bound = entry
+                .attr_value(gimli::DW_AT_lower_bound)?
+                .and_then(|bound| bound.udata_value())
+                .unwrap_or(0);
+            lengths.push(count.or(upper_bound.map(|bound| bound + 1 - lower_bound)));
+        }
+
+        let (element, element_size) = inner;
+        let element = element.unwrap_or_else(|| "?".to_owned());
+        let name = lengths
+            .iter()
+            .rev()
+            .fold(element, |ty, length| match length {
+                Some(length) => format!("[{}; {}]", ty, length),
+                None => format!("[{}]", ty),
+            });
+        let size = size.or_else(|| {
+            lengths
+                .iter()
+                .try_fold(element_size?, |size, length| Some(size * (*length)?))
+        });
+        (Some(name), size)
+    } else if tag == gimli::DW_TAG_typedef
+        || tag == gimli::DW_TAG_const_type
+        || tag == gimli::DW_TAG_volatile_type
+        || tag == gimli::DW_TAG_restrict_type
+    {
+        (name.or(inner.0), size.or(inner.1))
+    } else {
+        (name, size)
+    })
+}
+
+// the signature of the subprogram at `offset`
+fn signature<R>(
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+) -> gimli::Result<Option<FnSig<'static>>>
+where
+    R: Reader,
+{
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let root = tree.root()?;
+
+    let output = match root.entry().attr_value(gimli::DW_AT_type)? {
+        // `void`
+        None => None,
+        Some(ty) => match lower(unit, ty)? {
+            Some(ty) => Some(Box::new(ty)),
+            None => return Ok(None),
+        },
+    };
+
+    let mut inputs = vec![];
+    let mut children = root.children();
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+
+        if entry.tag() == gimli::DW_TAG_formal_parameter {
+            match entry.attr_value(gimli::DW_AT_type)? {
+                Some(ty) => match lower(unit, ty)? {
+                    Some(ty) => inputs.push(ty),
+                    None => return Ok(None),
+                },
+                None => return Ok(None),
+            }
+        } else if entry.tag() == gimli::DW_TAG_unspecified_parameters {
+            // variadic function
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(FnSig { inputs, output }))
+}
+
+// lowers a C type to the LLVM IR type that Clang uses to pass it to, or return it from, a function
+fn lower<R>(unit: &Unit<R>, ty: AttributeValue<R>) -> gimli::Result<Option<Type<'static>>>
+where
+    R: Reader,
+{
+    let offset = if let AttributeValue::UnitRef(offset) = ty {
+        offset
+    } else {
+        return Ok(None);
+    };
+
+    let entry = unit.entry(offset)?;
+    let size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|size| size.udata_value());
+    let integer = |size| match size {
+        Some(bytes @ 1) | Some(bytes @ 2) | Some(bytes @ 4) | Some(bytes @ 8) => {
+            Some(Type::Integer(8 * bytes as usize))
+        }
+        _ => None,
+    };
+
+    let tag = entry.tag();
+    Ok(if tag == gimli::DW_TAG_base_type {
+        let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+            Some(AttributeValue::Encoding(encoding)) => encoding,
+            _ => return Ok(None),
+        };
+
+        if encoding == gimli::DW_ATE_float {
+            match size {
+                Some(4) => Some(Type::Float),
+                Some(8) => Some(Type::Double),
+                _ => None,
+            }
+        } else if encoding == gimli::DW_ATE_boolean {
+            Some(Type::Integer(1))
+        } else if [
+            gimli::DW_ATE_signed,
+            gimli::DW_ATE_signed_char,
+            gimli::DW_ATE_unsigned,
+            gimli::DW_ATE_unsigned_char,
+            gimli::DW_ATE_UTF,
+        ]
+        .contains(&encoding)
+        {
+            integer(size)
+        } else {
+            None
+        }
+    } else if tag == gimli::DW_TAG_enumeration_type {
+        integer(size)
+    } else if tag == gimli::DW_TAG_pointer_type
+        || tag == gimli::DW_TAG_reference_type
+        || tag == gimli::DW_TAG_rvalue_reference_type
+    {
+        Some(Type::OpaquePointer)
+    } else if tag == gimli::DW_TAG_typedef
+        || tag == gimli::DW_TAG_const_type
+        || tag == gimli::DW_TAG_volatile_type
+        || tag == gimli::DW_TAG_restrict_type
+    {
+        match entry.attr_value(gimli::DW_AT_type)? {
+            Some(ty) => lower(unit, ty)?,
+            // e.g. `const void`
+            None => None,
+        }
+    } else {
+        // structs, unions, arrays, etc.
+        None
+    })
+}
@@ -0,0 +1,236 @@
+//! Editor integration data
+//!
+//! `cargo call-stack --editor-data <PATH>` writes the stack usage of each function keyed by the
+//! source location of its definition so that editors can render it inline. The file is plain text:
+//! a header line followed by one tab-separated (`\t`) line per function
+//!
+//! ``` text
+//! cargo-call-stack editor-data 1
+//! /home/user/app/src/main.rs\t19\t8\t16\tapp::foo
+//! /home/user/app/src/main.rs\t27\t?\t>24\tapp::bar
+//! ```
+//!
+//! The columns are: file path, line number (1-based), local stack usage in bytes (`?` if unknown),
+//! max stack usage in bytes (`>N` if only a lower bound is known, `?` if the analysis was skipped)
+//! and the demangled name of the function. Tabs and newlines in file paths are not supported.
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+
+/// First line of the file; the trailing number is the version of the format
+const HEADER: &str = "cargo-call-stack editor-data 1";
+
+/// The maximum stack usage of a function
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Max {
+    /// The exact number of bytes
+    Exact(u64),
+    /// At least this many bytes; e.g. the function calls a function with unknown stack usage
+    LowerBound(u64),
+}
+
+/// The stack usage of a function defined at some source location
+#[derive(Clone, Debug, PartialEq)]
+pub struct Annotation {
+    /// Line, 1-based, where the function is defined
+    pub line: u32,
+    /// Local stack usage, in bytes
+    pub local: Option<u64>,
+    /// Maximum stack usage, in bytes
+    pub max: Option<Max>,
+    /// The demangled name of the function
+    pub function: String,
+}
+
+/// Stack usage annotations grouped by source file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Annotations {
+    // NOTE(BTreeMap) deterministic output; each list is sorted by line
+    files: BTreeMap<PathBuf, Vec<Annotation>>,
+}
+
+impl Annotations {
+    /// Creates an empty set of annotations
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an annotation to `file`
+    pub fn push(&mut self, file: impl Into<PathBuf>, annotation: Annotation) {
+        let annotations = self.files.entry(file.into()).or_default();
+        let pos = annotations
+            .partition_point(|a| (a.line, &a.function) <= (annotation.line, &annotation.function));
+        annotations.insert(pos, annotation);
+    }
+
+    /// Parses the contents of an editor data file
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut lines = contents.lines();
+        if lines.next() != Some(HEADER) {
+            bail!("not a cargo-call-stack editor data file or unsupported version");
+        }
+
+        let mut annotations = Annotations::new();
+        for (i, line) in lines.enumerate() {
+            let err = || anyhow!("malformed line {}: `{}`", i + 2, line);
+
+            let mut columns = line.splitn(5, '\t');
+            let mut next = || columns.next().ok_or_else(err);
+            let (file, line, local, max, function) = (next()?, next()?, next()?, next()?, next()?);
+
+            let local = match local {
+                "?" => None,
+                n => Some(n.parse().map_err(|_| err())?),
+            };
+            let max = match max {
+                "?" => None,
+                n => Some(if let Some(n) = n.strip_prefix('>') {
+                    Max::LowerBound(n.parse().map_err(|_| err())?)
+                } else {
+                    Max::Exact(n.parse().map_err(|_| err())?)
+                }),
+            };
+
+            annotations.push(
+                file,
+                Annotation {
+                    line: line.parse().map_err(|_| err())?,
+                    local,
+                    max,
+                    function: function.to_owned(),
+                },
+            );
+        }
+
+        Ok(annotations)
+    }
+
+    /// Returns the files that have annotations
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|path| &**path)
+    }
+
+    /// Returns the annotations of `file`, sorted by line
+    pub fn file(&self, file: impl AsRef<Path>) -> &[Annotation] {
+        self.files
+            .get(file.as_ref())
+            .map(|annotations| &annotations[..])
+            .unwrap_or(&[])
+    }
+
+    /// Returns the annotations of the functions defined at `line` of `file`
+    ///
+    /// There may be more than one, e.g. the instances of a generic function or closures
+    pub fn line(&self, file: impl AsRef<Path>, line: u32) -> &[Annotation] {
+        let annotations = self.file(file);
+        let start = annotations.partition_point(|a| a.line < line);
+        let end = annotations.partition_point(|a| a.line <= line);
+        &annotations[start..end]
+    }
+}
+
+impl fmt::Display for Annotations {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+
+        for (file, annotations) in &self.files {
+            for annotation in annotations {
+                write!(f, "{}\t{}\t", file.display(), annotation.line)?;
+
+                match annotation.local {
+                    Some(n) => write!(f, "{}\t", n)?,
+                    None => f.write_str("?\t")?,
+                }
+
+                match annotation.max {
+                    Some(Max::Exact(n)) => write!(f, "{}\t", n)?,
+                    Some(Max::LowerBound(n)) => write!(f, ">{}\t", n)?,
+                    None => f.write_str("?\t")?,
+                }
+
+                writeln!(f, "{}", annotation.function)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Annotation, Annotations, Max};
+
+    #[test]
+    fn roundtrip() {
+        let mut annotations = Annotations::new();
+        annotations.push(
+            "/app/src/main.rs",
+            Annotation {
+                line: 27,
+                local: None,
+                max: Some(Max::LowerBound(24)),
+                function: "app::bar".to_owned(),
+            },
+        );
+        annotations.push(
+            "/app/src/main.rs",
+            Annotation {
+                line: 19,
+                local: Some(8),
+                max: Some(Max::Exact(16)),
+                function: "app::foo".to_owned(),
+            },
+        );
+        annotations.push(
+            "/app/src/lib.rs",
+            Annotation {
+                line: 1,
+                local: Some(0),
+                max: None,
+                function: "app::baz".to_owned(),
+            },
+        );
+
+        let contents = annotations.to_string();
+        assert_eq!(
+            contents,
+            "cargo-call-stack editor-data 1\n\
+             /app/src/lib.rs\t1\t0\t?\tapp::baz\n\
+             /app/src/main.rs\t19\t8\t16\tapp::foo\n\
+             /app/src/main.rs\t27\t?\t>24\tapp::bar\n"
+        );
+        assert_eq!(Annotations::parse(&contents).unwrap(), annotations);
+    }
+
+    #[test]
+    fn query() {
+        let annotations = Annotations::parse(
+            "cargo-call-stack editor-data 1\n\
+             /app/src/main.rs\t19\t8\t16\tapp::foo\n\
+             /app/src/main.rs\t27\t0\t0\tapp::bar::<u8>\n\
+             /app/src/main.rs\t27\t0\t0\tapp::bar::<u16>\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            annotations.files().collect::<Vec<_>>(),
+            ["/app/src/main.rs".as_ref() as &std::path::Path]
+        );
+        assert_eq!(annotations.file("/app/src/main.rs").len(), 3);
+        assert_eq!(
+            annotations.line("/app/src/main.rs", 19)[0].function,
+            "app::foo"
+        );
+        assert_eq!(annotations.line("/app/src/main.rs", 27).len(), 2);
+        assert!(annotations.line("/app/src/main.rs", 20).is_empty());
+        assert!(annotations.file("/app/src/lib.rs").is_empty());
+
+        assert!(Annotations::parse("cargo-call-stack editor-data 2\n").is_err());
+        assert!(Annotations::parse("cargo-call-stack editor-data 1\nfoo\t1\n").is_err());
+    }
+}
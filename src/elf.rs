@@ -0,0 +1,768 @@
+//! ELF parsing
+//!
+//! Both little-endian and big-endian (e.g. `armebv7r-none-eabi`) files are supported
+//!
+//! Reference: https://llvm.org/docs/CodeGenerator.html#emitting-function-stack-size-information
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryInto,
+};
+
+use anyhow::{anyhow, bail};
+use object::{
+    elf, Architecture, Endian, Endianness, File, FileFlags, Object, ObjectKind, ObjectSection,
+    ObjectSymbol, ObjectSymbolTable, RelocationTarget, SectionKind, SymbolFlags,
+};
+
+use crate::thumb::Tag;
+
+/// Functions found after analyzing an executable
+pub struct Functions<'a> {
+    /// "undefined" symbols, symbols that need to be dynamically loaded
+    pub undefined: HashSet<&'a str>,
+
+    /// "defined" symbols, symbols with known locations (addresses)
+    pub defined: BTreeMap<u64, Function<'a>>,
+}
+
+/// A symbol that represents a function (subroutine)
+pub struct Function<'a> {
+    names: Vec<&'a str>,
+    size: u64,
+}
+
+impl<'a> Function<'a> {
+    /// Returns the (mangled) name of the function and its aliases
+    pub fn names(&self) -> &[&'a str] {
+        &self.names
+    }
+
+    /// Returns the size of this subroutine in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Parses an ELF file
+pub fn parse(bytes: &[u8]) -> anyhow::Result<File<'_>> {
+    File::parse(bytes).map_err(|e| anyhow!("malformed ELF file: {}", e))
+}
+
+/// Returns the `st_type` of an ELF symbol
+fn st_type<'a>(symbol: &impl ObjectSymbol<'a>) -> Option<u8> {
+    match symbol.flags() {
+        SymbolFlags::Elf { st_info, .. } => Some(st_info & 0xf),
+        _ => None,
+    }
+}
+
+fn st_visibility<'a>(symbol: &impl ObjectSymbol<'a>) -> Option<u8> {
+    match symbol.flags() {
+        SymbolFlags::Elf { st_other, .. } => Some(st_other & 0x3),
+        _ => None,
+    }
+}
+
+// is this symbol a tag used to delimit code / data sections within a subroutine?
+fn is_tag(name: &str) -> bool {
+    name == "$a" || name == "$t" || name == "$d" || name == "$x" || {
+        (name.starts_with("$a.")
+            || name.starts_with("$d.")
+            || name.starts_with("$t.")
+            || name.starts_with("$x."))
+            && name.split_once('.').unwrap().1.parse::<u64>().is_ok()
+    }
+}
+
+/// Stack usage of a function in an object file
+pub struct StackSize<'a> {
+    pub name: &'a str,
+    pub stack: u64,
+}
+
+/// Parses an *input* (AKA relocatable) object file (`.o`) and returns a list of symbols and their
+/// stack usage
+pub fn analyze_object(obj: &[u8]) -> anyhow::Result<Vec<StackSize<'_>>> {
+    let file = parse(obj)?;
+
+    if file.kind() != ObjectKind::Relocatable {
+        bail!("object file is not relocatable")
+    }
+
+    let endian = file.endianness();
+
+    // section -> (address -> symbol-name)
+    let mut names = HashMap::<_, HashMap<_, _>>::new();
+    for symbol in file.symbols() {
+        let (section, name) = match (symbol.section_index(), symbol.name()) {
+            (Some(section), Ok(name)) if !name.is_empty() && !is_tag(name) => (section, name),
+            _ => continue,
+        };
+
+        let ty = st_type(&symbol);
+        if ty == Some(elf::STT_FUNC) || ty == Some(elf::STT_NOTYPE) {
+            // clear the thumb bit
+            names
+                .entry(section)
+                .or_default()
+                .entry(symbol.address() & !1)
+                .or_insert(name);
+        }
+    }
+
+    let mut sizes = vec![];
+    let mut seen = HashSet::new();
+    for section in file.sections() {
+        if section.name() != Ok(".stack_sizes") {
+            continue;
+        }
+
+        let data = section.data().map_err(anyhow::Error::msg)?;
+        let mut relocations = section.relocations().collect::<Vec<_>>();
+        relocations.sort_by_key(|(offset, _)| *offset);
+
+        let mut cursor = 0;
+        for (offset, relocation) in relocations {
+            let offset = offset as usize;
+            if offset != cursor {
+                bail!("`.stack_sizes` entries and relocations are not in sync");
+            }
+
+            let size = usize::from(relocation.size() / 8);
+            let implicit = read_address(data, offset, size, endian)?;
+            let addend = if relocation.has_implicit_addend() {
+                implicit
+            } else {
+                relocation.addend() as u64
+            };
+
+            let (stack, len) = uleb128(&data[offset + size..])?;
+            cursor = offset + size + len;
+
+            let symbol = match relocation.target() {
+                RelocationTarget::Symbol(index) => {
+                    file.symbol_by_index(index).map_err(anyhow::Error::msg)?
+                }
+                _ => bail!("unexpected relocation in `.stack_sizes`"),
+            };
+
+            let name = if st_type(&symbol) == Some(elf::STT_FUNC) {
+                symbol.name().map_err(anyhow::Error::msg)?
+            } else {
+                // relocation against a section symbol
+                let section = symbol
+                    .section_index()
+                    .ok_or_else(|| anyhow!("relocation against an undefined symbol"))?;
+
+                *names
+                    .get(&section)
+                    .and_then(|names| names.get(&((symbol.address() + addend) & !1)))
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "symbol with address {} not found at section {}",
+                            addend,
+                            section.0
+                        )
+                    })?
+            };
+
+            if !seen.insert(name) {
+                bail!("`{}` has more than one `.stack_sizes` entry", name);
+            }
+            sizes.push(StackSize { name, stack });
+        }
+
+        if cursor != data.len() {
+            bail!("the number of relocations doesn't match the number of `.stack_sizes` entries");
+        }
+    }
+
+    Ok(sizes)
+}
+
+/// Parses the `.stack_sizes` section of an executable, if the linker kept it, and returns the stack
+/// usage of each function indexed by its address (with the thumb bit cleared)
+///
+/// Unlike the entries of the input object files, these are not keyed by name so they tell apart
+/// local functions with the same name
+pub fn linked_stack_sizes(file: &File) -> anyhow::Result<HashMap<u64, u64>> {
+    let mut sizes = HashMap::new();
+    let section = if let Some(section) = file.section_by_name(".stack_sizes") {
+        section
+    } else {
+        return Ok(sizes);
+    };
+
+    let data = section.data().map_err(anyhow::Error::msg)?;
+    let size = if file.is_64() { 8 } else { 4 };
+    let mut cursor = 0;
+    while cursor < data.len() {
+        let address = read_address(data, cursor, size, file.endianness())?;
+        let (stack, len) = uleb128(&data[cursor + size..])?;
+        cursor += size + len;
+
+        sizes.insert(address & !1, stack);
+    }
+
+    Ok(sizes)
+}
+
+/// Whether an object file contains a `.stack_sizes` section
+///
+/// The section is missing if the object was compiled without `-Zemit-stack-sizes`, or without
+/// `-fstack-size-section` in the case of C code
+pub fn has_stack_sizes(obj: &[u8]) -> bool {
+    parse(obj)
+        .map(|file| file.section_by_name(".stack_sizes").is_some())
+        .unwrap_or(false)
+}
+
+/// Returns the functions defined in an *input* (AKA relocatable) object file (`.o`)
+pub fn object_functions(obj: &[u8]) -> anyhow::Result<HashSet<&str>> {
+    let file = parse(obj)?;
+
+    Ok(file
+        .symbols()
+        .filter(|symbol| !symbol.is_undefined() && st_type(symbol) == Some(elf::STT_FUNC))
+        .filter_map(|symbol| symbol.name().ok())
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+/// Parses an executable file and returns all the functions in it
+pub fn analyze_executable<'a>(file: &File<'a>) -> anyhow::Result<Functions<'a>> {
+    let mut defined = BTreeMap::new();
+    let mut maybe_aliases = BTreeMap::<_, Vec<_>>::new();
+    let mut undefined = HashSet::new();
+
+    for symbol in file.symbols() {
+        let ty = st_type(&symbol);
+        let value = symbol.address();
+        let size = symbol.size();
+        let name = symbol.name();
+
+        if ty == Some(elf::STT_FUNC) {
+            let name = name.map_err(anyhow::Error::msg)?;
+
+            if value == 0 && size == 0 {
+                undefined.insert(name);
+            } else {
+                defined
+                    .entry(value)
+                    .or_insert(Function {
+                        names: vec![],
+                        size,
+                    })
+                    .names
+                    .push(name);
+            }
+        } else if ty == Some(elf::STT_NOTYPE) {
+            if let Ok(name) = name {
+                if !name.is_empty() && !is_tag(name) {
+                    maybe_aliases.entry(value).or_default().push(name);
+                }
+            }
+        }
+    }
+
+    for (value, alias) in maybe_aliases {
+        // try with the thumb bit both set and clear
+        if let Some(sym) = defined.get_mut(&(value | 1)) {
+            sym.names.extend(alias);
+        } else if let Some(sym) = defined.get_mut(&(value & !1)) {
+            sym.names.extend(alias);
+        }
+    }
+
+    Ok(Functions { defined, undefined })
+}
+
+/// Returns the tags (mapping symbols) that delimit code and data within ARM subroutines, sorted by
+/// address
+pub fn tags(file: &File) -> Vec<(u32, Tag)> {
+    let mut tags = file
+        .symbols()
+        .filter_map(|symbol| {
+            let addr = symbol.address() as u32;
+            let name = symbol.name().ok()?;
+
+            if name.starts_with("$d") {
+                Some((addr, Tag::Data))
+            } else if name.starts_with("$t") {
+                Some((addr, Tag::Thumb))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    tags.sort_by_key(|tag| tag.0);
+    tags
+}
+
+/// Whether the instructions of this big-endian ARM image are stored big-endian (BE-32). In BE-8
+/// images, which ARMv6 and newer use (e.g. `thumbv7r`/`thumbv7m` big-endian), only the data is
+/// big-endian; the instructions stay little-endian
+pub fn big_endian_code(file: &File) -> bool {
+    file.endianness() == Endianness::Big
+        && match file.flags() {
+            FileFlags::Elf { e_flags, .. } => e_flags & elf::EF_ARM_BE8 == 0,
+            _ => true,
+        }
+}
+
+/// Returns the addresses, with the thumb bit cleared, of all the defined symbols, sorted and
+/// deduplicated; tags are excluded
+pub fn symbol_addresses(file: &File) -> Vec<u32> {
+    let mut addresses = file
+        .symbols()
+        .filter(|symbol| symbol.section_index().is_some())
+        .filter(|symbol| match symbol.name() {
+            Ok(name) => !name.is_empty() && !is_tag(name),
+            Err(_) => false,
+        })
+        .map(|symbol| symbol.address() as u32 & !1)
+        .collect::<Vec<_>>();
+
+    addresses.sort_unstable();
+    addresses.dedup();
+    addresses
+}
+
+/// Returns the start address and contents of all the allocated, executable sections
+pub fn code_sections<'a>(file: &File<'a>) -> Vec<(u32, &'a [u8])> {
+    file.sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+        .filter_map(|section| Some((section.address() as u32, section.data().ok()?)))
+        .collect()
+}
+
+/// Returns the contents of the array `name`, e.g. a table of function pointers, as a list of
+/// addresses; `name` can also be the demangled path of a `static` without its hash
+pub fn table(file: &File, name: &str) -> anyhow::Result<Vec<u64>> {
+    let symbol = symbol(file, name)?;
+    let section = file
+        .section_by_index(symbol.section_index().expect("UNREACHABLE"))
+        .map_err(anyhow::Error::msg)?;
+    let data = section.data().map_err(anyhow::Error::msg)?;
+
+    let size = if file.is_64() { 8 } else { 4 };
+    let offset = (symbol.address() - section.address()) as usize;
+    (0..symbol.size() as usize / size)
+        .map(|i| {
+            read_address(data, offset + i * size, size, file.endianness())
+                .map_err(|_| anyhow!("`{}` is not located in an initialized section", name))
+        })
+        .collect()
+}
+
+/// Returns the size, in bytes, of the object `name`, e.g. a `static` buffer; `name` can also be the
+/// demangled path of a `static` without its hash
+pub fn symbol_size(file: &File, name: &str) -> anyhow::Result<u64> {
+    Ok(symbol(file, name)?.size())
+}
+
+// the defined symbol whose mangled name, or demangled name without the hash, is `name`
+fn symbol<'data, 'file>(
+    file: &'file File<'data>,
+    name: &str,
+) -> anyhow::Result<object::Symbol<'data, 'file>> {
+    file.symbols()
+        .filter(|symbol| symbol.section_index().is_some())
+        .find(|symbol| match symbol.name() {
+            Ok(symbol) => {
+                symbol == name || format!("{:#}", rustc_demangle::demangle(symbol)) == name
+            }
+            Err(_) => false,
+        })
+        .ok_or_else(|| anyhow!("symbol `{}` not found", name))
+}
+
+/// Returns the contents of the ARM Cortex-M vector table (`.vector_table` or `.isr_vector` section)
+/// as a list of words, if the program has one
+pub fn vector_table(file: &File) -> Option<Vec<u64>> {
+    let section = file
+        .sections()
+        .find(|section| matches!(section.name(), Ok(".vector_table" | ".isr_vector")))?;
+    let data = section.data().ok()?;
+
+    (0..data.len() / 4)
+        .map(|i| read_address(data, i * 4, 4, file.endianness()).ok())
+        .collect()
+}
+
+/// A section that holds function pointers registered at build time
+pub struct Registrations<'a> {
+    /// Name of the section
+    pub section: String,
+    /// Symbols located at the start of the section, e.g. `__start_<section>`; code that iterates
+    /// over the section refers to one of these
+    pub start: Vec<&'a str>,
+    /// The contents of the section, as a list of addresses
+    pub addresses: Vec<u64>,
+}
+
+/// Returns the sections that hold function pointers registered at build time: `.init_array` and
+/// the like, the sections that have a linker-generated `__start_<section>` symbol (e.g. the
+/// `linkme_*` sections or `#[link_section = "foo"]` statics) and the `extra` sections
+pub fn registrations<'a>(file: &File<'a>, extra: &[String]) -> Vec<Registrations<'a>> {
+    let bounded = file
+        .symbols()
+        .filter_map(|symbol| symbol.name().ok()?.strip_prefix("__start_"))
+        .collect::<HashSet<_>>();
+    let size = if file.is_64() { 8 } else { 4 };
+
+    file.sections()
+        .filter(|section| section.kind() != SectionKind::Text)
+        .filter_map(|section| {
+            let name = section.name().ok()?;
+            if !matches!(name, ".preinit_array" | ".init_array" | ".fini_array")
+                && !bounded.contains(name)
+                && !extra.iter().any(|extra| extra == name)
+            {
+                return None;
+            }
+
+            let start = file
+                .symbols()
+                .filter(|symbol| {
+                    symbol.section_index() == Some(section.index())
+                        && symbol.address() == section.address()
+                        && st_type(symbol) != Some(elf::STT_FUNC)
+                })
+                .filter_map(|symbol| symbol.name().ok())
+                .filter(|name| !name.is_empty() && !is_tag(name))
+                .collect();
+
+            let data = section.data().ok()?;
+            let addresses = (0..data.len() / size)
+                .filter_map(|i| read_address(data, i * size, size, file.endianness()).ok())
+                .collect();
+
+            Some(Registrations {
+                section: name.to_owned(),
+                start,
+                addresses,
+            })
+        })
+        .collect()
+}
+
+/// Returns the functions exported by a shared object
+pub fn exported_functions<'a>(file: &File<'a>) -> anyhow::Result<HashSet<&'a str>> {
+    if file.dynamic_symbol_table().is_none() {
+        bail!("`.dynsym` section not found; is this a shared object?");
+    }
+
+    Ok(file
+        .dynamic_symbols()
+        .filter(|symbol| {
+            // defined, global or weak functions
+            !symbol.is_undefined()
+                && st_type(symbol) == Some(elf::STT_FUNC)
+                && (symbol.is_global() || symbol.is_weak())
+        })
+        .filter_map(|symbol| symbol.name().ok())
+        .collect())
+}
+
+/// Returns the global functions that a linked static library defines; hidden and weak functions,
+/// e.g. the ones of `compiler_builtins`, are not included
+pub fn global_functions<'a>(file: &File<'a>) -> HashSet<&'a str> {
+    file.symbols()
+        .filter(|symbol| {
+            !symbol.is_undefined()
+                && st_type(symbol) == Some(elf::STT_FUNC)
+                && symbol.is_global()
+                && !symbol.is_weak()
+                && st_visibility(symbol) == Some(elf::STV_DEFAULT)
+        })
+        .filter_map(|symbol| symbol.name().ok())
+        .collect()
+}
+
+/// Returns the entries of the procedure linkage table (`.plt`) of an ARM shared object as
+/// (address, size, name) tuples, where `name` is the imported function the entry jumps to. Only
+/// ARM (A32) entries are recognized; the Thumb-only entries of `thumbv6m`/`thumbv8m.base` are not.
+/// Other architectures have no entries
+pub fn plt_entries<'a>(file: &File<'a>) -> Vec<(u64, u64, &'a str)> {
+    if file.architecture() != Architecture::Arm {
+        return vec![];
+    }
+
+    let (start, data) = match file
+        .section_by_name(".plt")
+        .and_then(|section| Some((section.address(), section.data().ok()?)))
+    {
+        Some(plt) => plt,
+        None => return vec![],
+    };
+    let dynsym = match file.dynamic_symbol_table() {
+        Some(dynsym) => dynsym,
+        None => return vec![],
+    };
+
+    // the GOT slots that the dynamic linker fills with the address of an imported function
+    let mut slots = HashMap::new();
+    for (offset, relocation) in file.dynamic_relocations().into_iter().flatten() {
+        if let RelocationTarget::Symbol(index) = relocation.target() {
+            if let Some(name) = dynsym
+                .symbol_by_index(index)
+                .ok()
+                .and_then(|symbol| symbol.name().ok())
+            {
+                slots.insert(offset, name);
+            }
+        }
+    }
+
+    let big_endian = big_endian_code(file);
+    let words = data
+        .chunks_exact(4)
+        .map(|word| {
+            let word = word.try_into().unwrap();
+            if big_endian {
+                u32::from_be_bytes(word)
+            } else {
+                u32::from_le_bytes(word)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    arm_plt_entries(start, &words)
+        .into_iter()
+        .filter_map(|(address, size, slot)| Some((address, size, *slots.get(&slot)?)))
+        .collect()
+}
+
+// decodes the ARM PLT entries in `words`, which start at address `start`, into (address, size, GOT
+// slot) tuples. Each entry loads the PC from a GOT slot whose address is relative to its own, e.g.
+//
+//   add ip, pc, #0, #12
+//   add ip, ip, #16, #20
+//   ldr pc, [ip, #140]!
+fn arm_plt_entries(start: u64, words: &[u32]) -> Vec<(u64, u64, u64)> {
+    // the immediate operand of an ARM data-processing instruction: 8 bits rotated right
+    fn immediate(word: u32) -> u32 {
+        (word & 0xff).rotate_right((word >> 8 & 0xf) * 2)
+    }
+
+    let mut entries = vec![];
+    let mut i = 0;
+    while i < words.len() {
+        // add ip, pc, #imm
+        if words[i] & 0xffff_f000 != 0xe28f_c000 {
+            i += 1;
+            continue;
+        }
+
+        let address = start + 4 * i as u64;
+        // the PC reads as the address of the instruction plus 8
+        let mut slot = (address as u32)
+            .wrapping_add(8)
+            .wrapping_add(immediate(words[i]));
+        let mut j = i + 1;
+        // add ip, ip, #imm
+        while j < words.len() && words[j] & 0xffff_f000 == 0xe28c_c000 {
+            slot = slot.wrapping_add(immediate(words[j]));
+            j += 1;
+        }
+
+        // ldr pc, [ip, #imm]!
+        match words.get(j) {
+            Some(word) if word & 0xffff_f000 == 0xe5bc_f000 => {
+                slot = slot.wrapping_add(word & 0xfff);
+                entries.push((address, 4 * (j + 1 - i) as u64, u64::from(slot)));
+                i = j + 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    entries
+}
+
+fn read_address(
+    data: &[u8],
+    offset: usize,
+    size: usize,
+    endian: Endianness,
+) -> anyhow::Result<u64> {
+    let bytes = data
+        .get(offset..offset + size)
+        .ok_or_else(|| anyhow!("`.stack_sizes` entry is out of bounds"))?;
+
+    Ok(match size {
+        4 => u64::from(endian.read_u32_bytes(bytes.try_into().unwrap())),
+        8 => endian.read_u64_bytes(bytes.try_into().unwrap()),
+        _ => bail!("unsupported relocation size: {} bytes", size),
+    })
+}
+
+// returns the decoded integer and the number of bytes it occupied
+fn uleb128(bytes: &[u8]) -> anyhow::Result<(u64, usize)> {
+    let mut result = 0;
+    for (i, byte) in bytes.iter().enumerate().take(10) {
+        result |= u64::from(byte & 0x7f) << (7 * i);
+
+        if byte & 0x80 == 0 {
+            return Ok((result, i + 1));
+        }
+    }
+
+    bail!("malformed ULEB128 integer")
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn uleb128() {
+        assert_eq!(super::uleb128(&[0x00]).unwrap(), (0, 1));
+        assert_eq!(
+            super::uleb128(&[0xe5, 0x8e, 0x26, 0xff]).unwrap(),
+            (624485, 3)
+        );
+        assert!(super::uleb128(&[0x80]).is_err());
+    }
+
+    #[test]
+    fn big_endian_code() {
+        // the header of an ARM executable without sections or segments
+        fn header(big_endian: bool, e_flags: u32) -> Vec<u8> {
+            let half = |x: u16| {
+                if big_endian {
+                    x.to_be_bytes()
+                } else {
+                    x.to_le_bytes()
+                }
+            };
+            let word = |x: u32| {
+                if big_endian {
+                    x.to_be_bytes()
+                } else {
+                    x.to_le_bytes()
+                }
+            };
+
+            let mut bytes = vec![0x7f, b'E', b'L', b'F', 1, if big_endian { 2 } else { 1 }, 1];
+            bytes.resize(16, 0);
+            bytes.extend(half(2)); // e_type: ET_EXEC
+            bytes.extend(half(40)); // e_machine: EM_ARM
+            bytes.extend(word(1)); // e_version
+            bytes.extend(word(0)); // e_entry
+            bytes.extend(word(0)); // e_phoff
+            bytes.extend(word(0)); // e_shoff
+            bytes.extend(word(e_flags));
+            bytes.extend(half(52)); // e_ehsize
+            bytes.extend(half(32)); // e_phentsize
+            bytes.extend(half(0)); // e_phnum
+            bytes.extend(half(40)); // e_shentsize
+            bytes.extend(half(0)); // e_shnum
+            bytes.extend(half(0)); // e_shstrndx
+            bytes
+        }
+
+        let be32 = header(true, 0x0500_0200);
+        let be8 = header(true, 0x0500_0200 | object::elf::EF_ARM_BE8);
+        let le = header(false, 0x0500_0200);
+
+        assert!(super::big_endian_code(&super::parse(&be32).unwrap()));
+        assert!(!super::big_endian_code(&super::parse(&be8).unwrap()));
+        assert!(!super::big_endian_code(&super::parse(&le).unwrap()));
+    }
+
+    #[test]
+    fn linked_stack_sizes() {
+        // a little-endian ARM executable whose only sections are `.stack_sizes` and `.shstrtab`
+        let shstrtab = b"\0.stack_sizes\0.shstrtab\0";
+        // two functions with the same local name; the second address has its thumb bit set
+        let stack_sizes = [
+            0xec, 0x00, 0x02, 0x00, 0x40, 0xfd, 0x00, 0x02, 0x00, 0x80, 0x02,
+        ];
+
+        let mut bytes = vec![0x7f, b'E', b'L', b'F', 1, 1, 1];
+        bytes.resize(16, 0);
+        let half = |bytes: &mut Vec<u8>, x: u16| bytes.extend(x.to_le_bytes());
+        let word = |bytes: &mut Vec<u8>, x: u32| bytes.extend(x.to_le_bytes());
+        // the section headers must be 4-byte aligned
+        let shoff = (52 + shstrtab.len() + stack_sizes.len() + 3) & !3;
+        half(&mut bytes, 2); // e_type: ET_EXEC
+        half(&mut bytes, 40); // e_machine: EM_ARM
+        word(&mut bytes, 1); // e_version
+        word(&mut bytes, 0); // e_entry
+        word(&mut bytes, 0); // e_phoff
+        word(&mut bytes, shoff as u32); // e_shoff
+        word(&mut bytes, 0x0500_0200); // e_flags
+        half(&mut bytes, 52); // e_ehsize
+        half(&mut bytes, 32); // e_phentsize
+        half(&mut bytes, 0); // e_phnum
+        half(&mut bytes, 40); // e_shentsize
+        half(&mut bytes, 3); // e_shnum
+        half(&mut bytes, 2); // e_shstrndx
+        bytes.extend(shstrtab);
+        bytes.extend(stack_sizes);
+        bytes.resize(shoff, 0);
+
+        // sh_name, sh_type, sh_offset and sh_size; the null section comes first
+        let sections = [
+            (0, 0, 0, 0),
+            (1, 1, 52 + shstrtab.len(), stack_sizes.len()),
+            (14, 3, 52, shstrtab.len()),
+        ];
+        for (name, ty, offset, size) in sections {
+            word(&mut bytes, name);
+            word(&mut bytes, ty);
+            word(&mut bytes, 0); // sh_flags
+            word(&mut bytes, 0); // sh_addr
+            word(&mut bytes, offset as u32);
+            word(&mut bytes, size as u32);
+            for _ in 0..4 {
+                // sh_link, sh_info, sh_addralign and sh_entsize
+                word(&mut bytes, 0);
+            }
+        }
+
+        let sizes = super::linked_stack_sizes(&super::parse(&bytes).unwrap()).unwrap();
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes[&0x200ec], 64);
+        assert_eq!(sizes[&0x200fc], 256);
+    }
+
+    #[test]
+    fn arm_plt_entries() {
+        // `.plt` of a `thumbv7neon-unknown-linux-gnueabihf` shared object linked by LLD: the header
+        // followed by two entries padded to 16 bytes
+        let words = [
+            0xe52de004, 0xe28fe600, 0xe28eea10, 0xe5bef0a4, 0xd4d4d4d4, 0xd4d4d4d4, 0xd4d4d4d4,
+            0xd4d4d4d4, // header
+            0xe28fc600, 0xe28cca10, 0xe5bcf08c, 0xd4d4d4d4, // `host_log`
+            0xe28fc600, 0xe28cca10, 0xe5bcf080, 0xd4d4d4d4, // `host_write`
+        ];
+
+        assert_eq!(
+            super::arm_plt_entries(0x10220, &words),
+            [(0x10240, 12, 0x202d4), (0x10250, 12, 0x202d8)]
+        );
+
+        // the GNU ld entry with a single `add ip, pc`
+        assert_eq!(
+            super::arm_plt_entries(0x8000, &[0xe28fc000, 0xe5bcf010]),
+            [(0x8000, 8, 0x8018)]
+        );
+
+        // no `ldr pc`
+        assert_eq!(
+            super::arm_plt_entries(0x8000, &[0xe28fc600, 0xe28cca10]),
+            []
+        );
+    }
+
+    #[test]
+    fn is_tag() {
+        assert!(super::is_tag("$t"));
+        assert!(super::is_tag("$d.12"));
+        assert!(!super::is_tag("$t.foo"));
+        assert!(!super::is_tag("main"));
+    }
+}
@@ -0,0 +1,233 @@
+//! Symbol name interning
+//!
+//! Large programs have tens of thousands of symbols; the call graph refers to their names using
+//! `Symbol` handles instead of strings. Names borrowed from the ELF file are not copied and the
+//! demangled form of a name is computed at most once, and only if needed.
+
+use core::cell::OnceCell;
+use std::{borrow::Cow, collections::HashMap, rc::Rc};
+
+/// Handle to an interned symbol name
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Symbol(u32);
+
+#[derive(Default)]
+pub struct Interner<'a> {
+    symbols: HashMap<&'a str, Symbol>,
+    // names that didn't come from the ELF / IR file
+    owned: HashMap<Rc<str>, Symbol>,
+    names: Vec<Name<'a>>,
+}
+
+struct Name<'a> {
+    raw: Raw<'a>,
+    demangled: OnceCell<String>,
+    dehashed: OnceCell<Dehashed>,
+}
+
+// the demangled name without its hash
+enum Dehashed {
+    // it has no hash
+    None,
+    // legacy mangling: a prefix of the demangled name, this many bytes long
+    Prefix(usize),
+    // v0 mangling: the crate disambiguators are removed from anywhere in the name
+    Owned(Box<str>),
+}
+
+enum Raw<'a> {
+    Borrowed(&'a str),
+    Owned(Rc<str>),
+}
+
+impl<'a> Interner<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interns `name` and returns its handle
+    pub fn intern<S>(&mut self, name: S) -> Symbol
+    where
+        S: Into<Cow<'a, str>>,
+    {
+        match name.into() {
+            Cow::Borrowed(name) => {
+                if let Some(sym) = self.lookup(name) {
+                    return sym;
+                }
+
+                let sym = self.push(Raw::Borrowed(name));
+                self.symbols.insert(name, sym);
+                sym
+            }
+
+            Cow::Owned(name) => {
+                if let Some(sym) = self.lookup(&name) {
+                    return sym;
+                }
+
+                let name = Rc::<str>::from(name);
+                let sym = self.push(Raw::Owned(name.clone()));
+                self.owned.insert(name, sym);
+                sym
+            }
+        }
+    }
+
+    /// Returns the handle of `name`, if it has been interned
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.symbols
+            .get(name)
+            .or_else(|| self.owned.get(name))
+            .cloned()
+    }
+
+    /// Returns the name of `sym`
+    pub fn name(&self, sym: Symbol) -> &str {
+        match &self.names[sym.0 as usize].raw {
+            Raw::Borrowed(name) => name,
+            Raw::Owned(name) => name,
+        }
+    }
+
+    /// Returns the demangled name of `sym`
+    pub fn demangled(&self, sym: Symbol) -> &str {
+        self.names[sym.0 as usize]
+            .demangled
+            .get_or_init(|| rustc_demangle::demangle(self.name(sym)).to_string())
+    }
+
+    /// Returns the demangled name of `sym` without its hash, if it has one
+    pub fn dehashed(&self, sym: Symbol) -> Option<&str> {
+        let demangled = self.demangled(sym);
+        let dehashed =
+            self.names[sym.0 as usize]
+                .dehashed
+                .get_or_init(|| match dehash(demangled) {
+                    None => Dehashed::None,
+                    Some(Cow::Borrowed(prefix)) => Dehashed::Prefix(prefix.len()),
+                    Some(Cow::Owned(dehashed)) => Dehashed::Owned(dehashed.into()),
+                });
+
+        match dehashed {
+            Dehashed::None => None,
+            Dehashed::Prefix(len) => Some(&demangled[..*len]),
+            Dehashed::Owned(dehashed) => Some(dehashed),
+        }
+    }
+
+    /// Returns the demangled name of `sym` without its hash or, if it has none, its demangled name;
+    /// this is how `{:#}` formats a demangled name
+    pub fn short(&self, sym: Symbol) -> &str {
+        self.dehashed(sym).unwrap_or_else(|| self.demangled(sym))
+    }
+
+    fn push(&mut self, raw: Raw<'a>) -> Symbol {
+        let sym = Symbol(self.names.len() as u32);
+        self.names.push(Name {
+            raw,
+            demangled: OnceCell::new(),
+            dehashed: OnceCell::new(),
+        });
+        sym
+    }
+}
+
+// removes the hash, like `::hfc5adc5d79855638`, of a legacy mangled name or the crate
+// disambiguators, like `[5b9ba4a8e3d4f7c2]`, of a v0 mangled name, if present
+pub(crate) fn dehash(demangled: &str) -> Option<Cow<str>> {
+    const HASH_LENGTH: usize = 19;
+
+    let len = demangled.len();
+    if len > HASH_LENGTH
+        && demangled
+            .get(len - HASH_LENGTH..)
+            .is_some_and(|hash| hash.starts_with("::h"))
+    {
+        return Some(Cow::Borrowed(&demangled[..len - HASH_LENGTH]));
+    }
+
+    // `app[1234abcd]::foo`; the disambiguator directly follows the crate name, which tells it apart
+    // from a slice like the one in `<[f32] as app::Trait>::method`
+    let mut dehashed = String::new();
+    let mut copied = 0;
+    for (start, _) in demangled.match_indices('[') {
+        let follows_name = demangled[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let end = demangled[start..].find(']').map(|end| start + end);
+        if let (true, Some(end)) = (follows_name, end) {
+            let disambiguator = &demangled[start + 1..end];
+            if !disambiguator.is_empty() && disambiguator.bytes().all(|b| b.is_ascii_hexdigit()) {
+                dehashed.push_str(&demangled[copied..start]);
+                copied = end + 1;
+            }
+        }
+    }
+
+    if copied == 0 {
+        None
+    } else {
+        dehashed.push_str(&demangled[copied..]);
+        Some(Cow::Owned(dehashed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+
+    #[test]
+    fn intern() {
+        let mut interner = Interner::new();
+
+        let a = interner.intern("_ZN3app3foo17h0123456789abcdefE");
+        let b = interner.intern(String::from("_ZN3app3foo17h0123456789abcdefE"));
+        let c = interner.intern(String::from("i32 ()*"));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(interner.lookup("i32 ()*"), Some(c));
+        assert_eq!(interner.lookup("bar"), None);
+
+        assert_eq!(interner.demangled(a), "app::foo::h0123456789abcdef");
+        assert_eq!(interner.dehashed(a), Some("app::foo"));
+        assert_eq!(interner.dehashed(c), None);
+        assert_eq!(interner.short(a), "app::foo");
+        assert_eq!(interner.short(c), "i32 ()*");
+
+        // v0 mangling
+        let d = interner.intern("_RNvCs1234_3app3foo");
+        assert_eq!(interner.demangled(d), "app[3c1c0]::foo");
+        assert_eq!(interner.dehashed(d), Some("app::foo"));
+    }
+
+    #[test]
+    fn dehash() {
+        let dehash = |demangled| super::dehash(demangled).map(|dehashed| dehashed.into_owned());
+
+        // legacy mangling
+        assert_eq!(
+            dehash("app::foo::h0123456789abcdef").as_deref(),
+            Some("app::foo")
+        );
+        assert_eq!(dehash("app::foo"), None);
+        assert_eq!(dehash("_start"), None);
+
+        // v0 mangling
+        assert_eq!(
+            dehash("app[5b9ba4a8e3d4f7c2]::foo").as_deref(),
+            Some("app::foo")
+        );
+        assert_eq!(
+            dehash("<app[1a2b]::Sensor as core[d3e4f5]::fmt::Debug>::fmt").as_deref(),
+            Some("<app::Sensor as core::fmt::Debug>::fmt")
+        );
+        assert_eq!(
+            dehash("<[f32] as app[ab12]::Sum>::sum::<[u8; 4]>").as_deref(),
+            Some("<[f32] as app::Sum>::sum::<[u8; 4]>")
+        );
+        assert_eq!(dehash("<[f32] as app::Sum>::sum"), None);
+    }
+}
@@ -1,4 +1,4 @@
-use core::fmt;
+use core::fmt::{self, Write as _};
 
 use anyhow::anyhow;
 use nom::{
@@ -18,7 +18,11 @@ mod item;
 mod ty;
 
 use crate::ir::ty::type_;
-pub use crate::ir::{define::Stmt, item::Item, ty::Type};
+pub use crate::ir::{
+    define::{Define, MemoryCall, Operation, Stmt},
+    item::{DebugInfo, Declare, Item},
+    ty::Type,
+};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct FnSig<'a> {
@@ -26,7 +30,35 @@ pub struct FnSig<'a> {
     pub output: Option<Box<Type<'a>>>,
 }
 
+/// How the calling convention of the target passes arguments and return values, as far as telling
+/// apart the signatures of functions goes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Abi {
+    /// Width, in bits, of the general purpose registers
+    pub register_width: usize,
+    /// Floating point values are passed in general purpose registers (e.g. ARM's `eabi` as opposed
+    /// to `eabihf`)
+    pub soft_float: bool,
+}
+
 impl<'a> FnSig<'a> {
+    /// Rewrites the signature into the shape in which `abi` passes the arguments and the return
+    /// value
+    ///
+    /// Compilers coerce small aggregates passed by value in different ways (e.g. Clang passes a
+    /// `struct { int a, b; }` as `[2 x i32]` on ARM and as `i64` on x86_64 while rustc may use
+    /// `{ i32, i32 }`) and soft-float ABIs pass floats like integers of the same size; after
+    /// canonicalization these signatures compare equal
+    pub fn canonicalize(&self, abi: Abi) -> FnSig<'a> {
+        FnSig {
+            inputs: self.inputs.iter().map(|ty| ty.canonicalize(abi)).collect(),
+            output: self
+                .output
+                .as_ref()
+                .map(|output| Box::new(output.canonicalize(abi))),
+        }
+    }
+
     pub fn loosely_equal(&self, other: &Self) -> bool {
         self.inputs.len() == other.inputs.len()
             && self
@@ -65,27 +97,96 @@ impl<'a> fmt::Display for FnSig<'a> {
     }
 }
 
-pub fn parse(ll: &str) -> anyhow::Result<Vec<Item>> {
+pub fn parse(ll: &str) -> anyhow::Result<Vec<Item<'_>>> {
     items(ll).map(|t| t.1).map_err(|e| {
-        let e = e.map(|e| {
-            let offset = ll.len()-e.input.len();
-            let mut cur = offset;
-            for (n, line) in ll.split_inclusive('\n').enumerate() {
-                match cur.checked_sub(line.len()) {
-                    Some(it) => cur = it,
-                    None => return format!("{:?} in line {}", e.code, n + 1),
-                }
-            }
+        let rest = match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e.input,
+            nom::Err::Incomplete(_) => "",
+        };
+
+        // `items` stops at the first item it can't parse; parse that item again on its own to find
+        // out which production failed, and where
+        let item_line = line_column(ll, ll.len() - rest.len()).0;
+        let (mut production, rest) = crate::ir::item::diagnose(rest);
+        // point at the statement, not at its indentation
+        let rest = rest.trim_start_matches([' ', '\t']);
+        let offset = ll.len() - rest.len();
+        let (line, column) = line_column(ll, offset);
+        if production == "function definition" && line != item_line {
+            production = "statement";
+        }
 
-            unreachable!("couldn't find line the parse error at offset {} refers to", offset)
-        });
         anyhow!(
-            "BUG: failed to parse LLVM IR; please submit a cargo-call-stack bug report and attach the `.ll` file: {:?}",
-            e
+            "BUG: failed to parse {} at line {}, column {}\n\n{}\n\
+             please submit a cargo-call-stack bug report and attach the `.ll` file",
+            production,
+            line,
+            column,
+            snippet(ll, line, column),
         )
     })
 }
 
+// returns the line and column (both 1-indexed) that `offset` corresponds to
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map(|line| line.chars().count())
+        .unwrap_or(0)
+        + 1;
+    (line, column)
+}
+
+// the source line at `line` and its neighbors, with a caret pointing at `column`
+fn snippet(src: &str, line: usize, column: usize) -> std::string::String {
+    const CONTEXT: usize = 2;
+
+    let first = line.saturating_sub(CONTEXT).max(1);
+    let width = (line + CONTEXT).to_string().len();
+
+    let mut snippet = std::string::String::new();
+    for (n, text) in src
+        .lines()
+        .enumerate()
+        .skip(first - 1)
+        .take(line - first + CONTEXT + 1)
+    {
+        let n = n + 1;
+        let _ = writeln!(snippet, "{:>width$} | {}", n, text, width = width);
+
+        if n == line {
+            let _ = writeln!(
+                snippet,
+                "{:>width$} | {:>column$}",
+                "",
+                "^",
+                width = width,
+                column = column
+            );
+        }
+    }
+    snippet
+}
+
+/// Parses a function signature in the format produced by `FnSig`'s `Display` implementation
+pub fn fn_sig(i: &str) -> Option<FnSig<'_>> {
+    match type_(i) {
+        Ok(("", Type::Fn(sig))) => Some(sig),
+        _ => None,
+    }
+}
+
+/// Parses a type in the format produced by `Type`'s `Display` implementation
+pub fn ty(i: &str) -> Option<Type<'_>> {
+    match type_(i) {
+        Ok(("", ty)) => Some(ty),
+        _ => None,
+    }
+}
+
 fn items(i: &str) -> IResult<&str, Vec<Item>> {
     let (i, items) = separated_list0(many1(line_ending), crate::ir::item::item)(i)?;
     let i = many0(line_ending)(i)?.0;
@@ -304,7 +405,7 @@ fn string(i: &str) -> IResult<&str, String> {
 
 #[cfg(test)]
 mod tests {
-    use super::{Alias, Comment, FnSig, GetElementPtr, Ident, Local, String, Type};
+    use super::{Abi, Alias, Comment, FnSig, GetElementPtr, Ident, Local, String, Type};
 
     #[test]
     fn alias() {
@@ -331,6 +432,69 @@ mod tests {
         super::bitcast("bitcast (i8* getelementptr inbounds (<{ [228 x i8] }>, <{ [228 x i8] }>* @_ZN17at28c_rs_firmware3APP7usb_dev17h0475a05cee83d665E, i32 0, i32 0, i32 44) to i32*)").unwrap();
     }
 
+    #[test]
+    fn canonicalize() {
+        let canonical = |ty: &'static str, abi| super::type_(ty).unwrap().1.canonicalize(abi);
+        let eabi = Abi {
+            register_width: 32,
+            soft_float: true,
+        };
+        let eabihf = Abi {
+            soft_float: false,
+            ..eabi
+        };
+        let sysv = Abi {
+            register_width: 64,
+            soft_float: false,
+        };
+
+        // small aggregates are coerced into registers
+        assert_eq!(
+            canonical("{ i32, i32 }", eabi),
+            canonical("[2 x i32]", eabi)
+        );
+        assert_eq!(canonical("{ i16, i16 }", eabi), Type::Integer(32));
+        assert_eq!(canonical("[1 x i32]", eabi), Type::Integer(32));
+        assert_eq!(canonical("{ i32, i32 }", sysv), Type::Integer(64));
+        assert_eq!(canonical("[2 x i32]", sysv), canonical("i64", sysv));
+        assert_eq!(
+            canonical("{ ptr, i32 }", eabi),
+            canonical("[2 x i32]", eabi)
+        );
+
+        // 64-bit aligned values go in an even register pair on ARM
+        assert_ne!(canonical("i64", eabi), canonical("[2 x i32]", eabi));
+        assert_eq!(canonical("i64", eabi), canonical("{ i64 }", eabi));
+
+        // the soft-float ABI passes floats in general purpose registers
+        assert_eq!(canonical("float", eabi), Type::Integer(32));
+        assert_eq!(canonical("double", eabi), canonical("i64", eabi));
+        assert_eq!(canonical("float", eabihf), Type::Float);
+        assert_eq!(
+            canonical("{ float, float }", eabi),
+            canonical("[2 x i32]", eabi)
+        );
+        assert_eq!(
+            canonical("{ float, float }", eabihf),
+            super::type_("{ float, float }").unwrap().1
+        );
+
+        // narrow integers and named types are left alone
+        assert_eq!(canonical("i8", eabi), Type::Integer(8));
+        assert_eq!(canonical("%Foo", eabi), Type::Alias("Foo"));
+
+        let sig = |sig: &'static str| match super::type_(sig).unwrap().1 {
+            Type::Fn(sig) => sig,
+            _ => unreachable!(),
+        };
+        assert!(sig("void ([2 x i32], float)")
+            .canonicalize(eabi)
+            .loosely_equal(&sig("void ({ i32, i32 }, i32)").canonicalize(eabi)));
+        assert!(!sig("void ([2 x i32], float)")
+            .canonicalize(eabihf)
+            .loosely_equal(&sig("void ({ i32, i32 }, i32)").canonicalize(eabihf)));
+    }
+
     #[test]
     fn comment() {
         assert_eq!(
@@ -413,6 +577,65 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_error() {
+        let ll = r#"source_filename = "app"
+
+define void @foo() {
+start:
+  call void @@bar()
+  ret void
+}
+"#;
+
+        let e = super::parse(ll).unwrap_err().to_string();
+        assert!(e.contains("failed to parse statement at line 5, column 3"));
+        assert!(e.contains("5 |   call void @@bar()\n  |   ^\n"));
+        assert!(e.contains("3 | define void @foo() {\n"));
+
+        let e = super::parse("define void @foo(i32 %0 {\n}\n")
+            .unwrap_err()
+            .to_string();
+        assert!(e.contains("failed to parse function definition at line 1"));
+    }
+
+    #[test]
+    fn parse_clang() {
+        // IR produced by `clang -S -emit-llvm`
+        let ll = r#"; ModuleID = 'vendor.c'
+source_filename = "vendor.c"
+target datalayout = "e-m:e-p:32:32-Fi8-i64:64-v128:64:128-a:0:32-n32-S64"
+target triple = "thumbv7m-none-unknown-eabi"
+
+; Function Attrs: noinline nounwind optsize
+define dso_local i32 @vendor_init(i32 noundef %0) local_unnamed_addr #0 {
+  %2 = tail call i32 @vendor_helper(i32 noundef %0) #2
+  ret i32 %2
+}
+
+declare dso_local i32 @vendor_helper(i32 noundef) local_unnamed_addr #1
+
+attributes #0 = { noinline nounwind optsize "frame-pointer"="none" }
+attributes #1 = { optsize }
+attributes #2 = { nounwind }
+
+!llvm.module.flags = !{!0}
+!0 = !{i32 1, !"wchar_size", i32 4}
+"#;
+
+        let items = super::parse(ll).unwrap();
+        let names = items
+            .iter()
+            .filter_map(|item| match item {
+                super::Item::Define(def) => Some(def.name),
+                super::Item::Declare(decl) => Some(decl.name),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["vendor_init", "vendor_helper"]);
+    }
+
     #[test]
     fn string() {
         assert_eq!(
@@ -15,6 +15,11 @@ pub struct Define<'a> {
     pub name: &'a str,
     pub sig: FnSig<'a>,
     pub stmts: Vec<Stmt<'a>>,
+    // `!dbg !4512`
+    pub dbg: Option<u32>,
+    // global symbols the function refers to, e.g. `@__start_foo`, the start of the `foo` linker
+    // section; LLVM intrinsics are excluded
+    pub symbols: Vec<&'a str>,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -26,16 +31,59 @@ pub enum Stmt<'a> {
 
     DirectCall(&'a str),
 
+    // `call void @llvm.memcpy.p0.p0.i32(ptr align 4 %0, ptr align 4 %1, i32 16, i1 false)`
+    MemoryCall(MemoryCall<'a>),
+
     IndirectCall(FnSig<'a>),
 
+    // `%2 = fmul float %0, %1`, `%3 = fptosi double %2 to i64`, `%4 = udiv i64 %0, %1`
+    // floating point operations and integer multiplications, divisions and shifts, which may lower
+    // to library calls (e.g. `__aeabi_fmul`, `__udivdi3`)
+    Operation(Operation<'a>),
+
+    // `store volatile i32 3, ptr inttoptr (i32 -536813312 to ptr)`
+    // (address, value) of a write to a memory-mapped register, e.g. the NVIC's
+    VolatileStore(u64, u64),
+
     Comment,
 
     // `start:`
-    Label,
+    Label(&'a str),
+
+    // `br i1 %0, label %bb1, label %bb2`, `switch`, `ret`, `unreachable` or `resume`
+    // the blocks the terminator may transfer control to; `br` and `switch` on a constant can only
+    // take one of their targets
+    Terminator(Vec<&'a str>),
 
     Other,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct Operation<'a> {
+    // e.g. `fadd`
+    pub opcode: &'a str,
+    // the condition of `fcmp`, e.g. `olt`
+    pub cond: Option<&'a str>,
+    // the type of the operand(s)
+    pub ty: Type<'a>,
+    // the result type of conversions, e.g. `sitofp i32 %0 to float`
+    pub to: Option<Type<'a>>,
+}
+
+// a call to `llvm.memcpy`, `llvm.memmove` or `llvm.memset`; its arguments tell which library
+// function, if any, it lowers to
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryCall<'a> {
+    // e.g. `llvm.memcpy.p0.p0.i32`
+    pub func: &'a str,
+    // the smallest alignment of the pointer arguments, in bytes
+    pub align: u64,
+    // the number of bytes, if it's a constant
+    pub len: Option<u64>,
+    // (`llvm.memset`) the value the bytes are set to, if it's a constant
+    pub value: Option<u64>,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 struct Parameter<'a>(Type<'a>);
 
@@ -82,11 +130,18 @@ pub fn parse(i: &str) -> IResult<&str, Define> {
     )(i)?;
     let i = char(')')(i)?.0;
 
-    // TODO we likely want to parse the metadata (`!dbg !0`) that comes after the parameter list
-    // NOTE shortcut
-    let i = not_line_ending(i)?.0;
+    // NOTE shortcut: of the attributes and metadata that come after the parameter list we only care
+    // about the debug info (`!dbg !0`)
+    let (i, rest) = not_line_ending(i)?;
+    let dbg = rest
+        .split_once("!dbg !")
+        .and_then(|(_, id)| id.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|id| id.parse().ok());
     let i = line_ending(i)?.0;
+    let body = i;
     let (i, stmts) = separated_list1(many1(line_ending), super::define::stmt)(i)?;
+    let stmts = prune(stmts);
+    let symbols = symbols(&body[..body.len() - i.len()]);
     let i = opt(line_ending)(i)?.0;
     let i = tag("}")(i)?.0;
     Ok((
@@ -98,24 +153,223 @@ pub fn parse(i: &str) -> IResult<&str, Define> {
                 inputs,
                 output: output.map(Box::new),
             },
+            dbg,
+            symbols,
         },
     ))
 }
 
+// NOTE shortcut: symbols can appear in any kind of statement so we scan the text of the body
+fn symbols(body: &str) -> Vec<&str> {
+    let mut symbols = vec![];
+    for (start, _) in body.match_indices('@') {
+        let symbol = body[start + 1..]
+            .split(|c: char| !(c.is_ascii_alphanumeric() || "-$._".contains(c)))
+            .next()
+            .unwrap_or("");
+
+        if !symbol.is_empty() && !symbol.starts_with("llvm.") && !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    symbols
+}
+
+// removes the statements of the basic blocks that can't be reached from the entry block, e.g.
+// because the condition of a branch was folded into a constant
+//
+// NOTE nothing is removed if the successors of some block are unknown, e.g. the block ends in an
+// `invoke`
+fn prune(stmts: Vec<Stmt>) -> Vec<Stmt> {
+    // (name, first statement, terminator); the entry block may have no label
+    let mut blocks = vec![];
+    for (pos, stmt) in stmts.iter().enumerate() {
+        match stmt {
+            Stmt::Label(name) => blocks.push((Some(*name), pos, None)),
+            Stmt::Comment => {}
+            _ => {
+                if blocks.is_empty() {
+                    blocks.push((None, pos, None));
+                }
+                let terminator = &mut blocks.last_mut().expect("UNREACHABLE").2;
+                *terminator = match stmt {
+                    Stmt::Terminator(targets) => Some(targets),
+                    _ => None,
+                };
+            }
+        }
+    }
+
+    let mut live = vec![false; blocks.len()];
+    let mut stack = vec![0];
+    while let Some(block) = stack.pop() {
+        if block >= blocks.len() || live[block] {
+            continue;
+        }
+        live[block] = true;
+
+        let Some(targets) = blocks[block].2 else {
+            return stmts;
+        };
+        for target in targets {
+            match blocks.iter().position(|(name, ..)| *name == Some(*target)) {
+                Some(successor) => stack.push(successor),
+                None => return stmts,
+            }
+        }
+    }
+
+    if live.iter().all(|live| *live) {
+        return stmts;
+    }
+
+    let mut block = 0;
+    let starts = blocks
+        .iter()
+        .map(|(_, start, _)| *start)
+        .collect::<Vec<_>>();
+    stmts
+        .into_iter()
+        .enumerate()
+        .filter(|(pos, _)| {
+            while starts.get(block + 1).is_some_and(|start| start <= pos) {
+                block += 1;
+            }
+            live.get(block).copied().unwrap_or(true)
+        })
+        .map(|(_, stmt)| stmt)
+        .collect()
+}
+
+// the name of a basic block: `bb1`, `"foo.exit"` or `12`
+fn block(i: &str) -> IResult<&str, &str> {
+    alt((
+        map(super::ident, |ident| ident.0),
+        map(super::string, |s| s.0),
+        digit1,
+    ))(i)
+}
+
+// `label %bb1`
+fn target(i: &str) -> IResult<&str, &str> {
+    let i = tag("label")(i)?.0;
+    let i = space1(i)?.0;
+    let i = char('%')(i)?.0;
+    block(i)
+}
+
 fn label(i: &str) -> IResult<&str, Stmt> {
-    let i = alt((
-        map(super::ident, drop),
-        map(super::string, drop),
-        map(digit1, drop),
-    ))(i)?
-    .0;
+    let (i, name) = block(i)?;
     let i = char(':')(i)?.0;
     let i = opt(|i| {
         let i = space1(i)?.0;
         super::comment(i)
     })(i)?
     .0;
-    Ok((i, Stmt::Label))
+    Ok((i, Stmt::Label(name)))
+}
+
+// `true`, `false` or an integer constant; `None` if the value is not known at compile time, e.g. a
+// local variable
+fn constant(i: &str) -> IResult<&str, Option<i64>> {
+    alt((
+        map(super::local, |_| None),
+        map(tag("true"), |_| Some(1)),
+        map(tag("false"), |_| Some(0)),
+        map_res(is_not(" ,\t\r\n"), |n: &str| n.parse().map(Some)),
+    ))(i)
+}
+
+fn br(i: &str) -> IResult<&str, Stmt<'_>> {
+    let i = tag("br")(i)?.0;
+    let i = space1(i)?.0;
+    let (i, targets) = alt((map(target, |target| vec![target]), |i| {
+        let i = tag("i1")(i)?.0;
+        let i = space1(i)?.0;
+        let (i, cond) = constant(i)?;
+        let i = char(',')(i)?.0;
+        let i = space1(i)?.0;
+        let (i, then) = target(i)?;
+        let i = char(',')(i)?.0;
+        let i = space1(i)?.0;
+        let (i, else_) = target(i)?;
+        let targets = match cond {
+            Some(0) => vec![else_],
+            Some(_) => vec![then],
+            None => vec![then, else_],
+        };
+        Ok((i, targets))
+    }))(i)?;
+    // NOTE shortcut
+    let i = not_line_ending(i)?.0;
+    Ok((i, Stmt::Terminator(targets)))
+}
+
+// ```
+// switch i32 %0, label %bb1 [
+//   i32 0, label %bb2
+//   i32 1, label %bb3
+// ]
+// ```
+fn switch(i: &str) -> IResult<&str, Stmt<'_>> {
+    let i = tag("switch")(i)?.0;
+    let i = space1(i)?.0;
+    let i = super::type_(i)?.0;
+    let i = space1(i)?.0;
+    let (i, value) = constant(i)?;
+    let i = char(',')(i)?.0;
+    let i = space1(i)?.0;
+    let (i, default) = target(i)?;
+    let i = space1(i)?.0;
+    let i = char('[')(i)?.0;
+    let (i, cases) = many0(|i| {
+        let i = line_ending(i)?.0;
+        let i = space1(i)?.0;
+        let i = super::type_(i)?.0;
+        let i = space1(i)?.0;
+        let (i, case) = constant(i)?;
+        let i = char(',')(i)?.0;
+        let i = space1(i)?.0;
+        let (i, target) = target(i)?;
+        Ok((i, (case, target)))
+    })(i)?;
+    let i = line_ending(i)?.0;
+    let i = space1(i)?.0;
+    let i = char(']')(i)?.0;
+    // NOTE shortcut
+    let i = not_line_ending(i)?.0;
+
+    let targets = match value {
+        Some(value) => vec![cases
+            .iter()
+            .find(|(case, _)| *case == Some(value))
+            .map(|(_, target)| *target)
+            .unwrap_or(default)],
+        None => {
+            let mut targets = vec![default];
+            for (_, target) in cases {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            targets
+        }
+    };
+    Ok((i, Stmt::Terminator(targets)))
+}
+
+// `ret`, `unreachable` and `resume` end the function
+fn exit(i: &str) -> IResult<&str, Stmt<'_>> {
+    let i = alt((tag("ret"), tag("unreachable"), tag("resume")))(i)?.0;
+    if !i.is_empty() && !i.starts_with([' ', ',', '\r', '\n']) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    // NOTE shortcut
+    let i = not_line_ending(i)?.0;
+    Ok((i, Stmt::Terminator(vec![])))
 }
 
 fn comment(i: &str) -> IResult<&str, Stmt> {
@@ -225,6 +479,98 @@ fn direct_call(i: &str) -> IResult<&str, Stmt> {
     Ok((i, Stmt::DirectCall(name.0)))
 }
 
+fn memory_call(i: &str) -> IResult<&str, Stmt<'_>> {
+    let i = opt(|i| {
+        let i = tag("tail")(i)?.0;
+        space1(i)
+    })(i)?
+    .0;
+    let i = tag("call")(i)?.0;
+    let i = space1(i)?.0;
+    let i = tag("void")(i)?.0;
+    let i = space1(i)?.0;
+    let (i, name) = super::function(i)?;
+    let func = name.0;
+    if !["llvm.memcpy.", "llvm.memmove.", "llvm.memset."]
+        .iter()
+        .any(|prefix| func.starts_with(prefix))
+    {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        )));
+    }
+    let i = char('(')(i)?.0;
+
+    // NOTE the pointer arguments may be constant expressions, e.g. `getelementptr (..)`, so split
+    // the argument list at the commas that are not nested in parentheses
+    let mut args = vec![];
+    let (mut depth, mut start, mut end) = (0, 0, None);
+    for (pos, c) in i.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => {
+                args.push(&i[start..pos]);
+                end = Some(pos + 1);
+                break;
+            }
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(&i[start..pos]);
+                start = pos + 1;
+            }
+            _ => {}
+        }
+    }
+    let i = match end {
+        Some(end) if args.len() == 4 => &i[end..],
+        _ => {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            )))
+        }
+    };
+
+    // the `align N` attribute of a pointer argument; 1 if absent
+    let align = |arg: &str| {
+        let mut words = arg.split_whitespace();
+        words
+            .by_ref()
+            .find(|word| *word == "align")
+            .and(words.next())
+            .and_then(|n| n.parse::<u64>().ok())
+            .unwrap_or(1)
+    };
+    // the value of an integer argument, e.g. `i32 16`
+    let constant = |arg: &str| {
+        arg.split_whitespace()
+            .last()
+            .and_then(|n| n.parse::<i64>().ok())
+            .map(|n| n as u64)
+    };
+
+    let call = if func.starts_with("llvm.memset.") {
+        MemoryCall {
+            func,
+            align: align(args[0]),
+            len: constant(args[2]),
+            value: constant(args[1]).map(|n| n & 0xff),
+        }
+    } else {
+        MemoryCall {
+            func,
+            align: align(args[0]).min(align(args[1])),
+            len: constant(args[2]),
+            value: None,
+        }
+    };
+
+    // NOTE shortcut
+    let i = not_line_ending(i)?.0;
+    Ok((i, Stmt::MemoryCall(call)))
+}
+
 fn indirect_call(i: &str) -> IResult<&str, Stmt> {
     let i = opt(|i| {
         let i = tag("tail")(i)?.0;
@@ -277,25 +623,145 @@ fn other(i: &str) -> IResult<&str, Stmt> {
     Ok((i, Stmt::Other))
 }
 
+// NOTE only operations on scalar floating point values and integer multiplications, divisions and
+// shifts are recognized; the rest are `Other`
+fn operation(i: &str) -> IResult<&str, Stmt<'_>> {
+    const BINARY: &[&str] = &["fadd", "fsub", "fmul", "fdiv", "frem"];
+    const INTEGER: &[&str] = &["mul", "udiv", "sdiv", "urem", "srem", "shl", "lshr", "ashr"];
+    const CONVERSIONS: &[&str] = &["fptosi", "fptoui", "sitofp", "uitofp", "fpext", "fptrunc"];
+    // fast-math flags and poison-generating flags
+    const FLAGS: &[&str] = &[
+        "nnan", "ninf", "nsz", "arcp", "contract", "afn", "reassoc", "fast", "nuw", "nsw", "exact",
+    ];
+
+    let word = |i| is_not(" \t\r\n")(i);
+    let (i, opcode) = map_res(word, |opcode| {
+        if opcode == "fcmp"
+            || BINARY.contains(&opcode)
+            || CONVERSIONS.contains(&opcode)
+            || INTEGER.contains(&opcode)
+        {
+            Ok(opcode)
+        } else {
+            Err(())
+        }
+    })(i)?;
+    let i = space1(i)?.0;
+    let i = many0(|i| {
+        let i = map_res(word, |flag| {
+            if FLAGS.contains(&flag) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        })(i)?
+        .0;
+        space1(i)
+    })(i)?
+    .0;
+    let (i, cond) = if opcode == "fcmp" {
+        let (i, cond) = word(i)?;
+        (space1(i)?.0, Some(cond))
+    } else {
+        (i, None)
+    };
+    let (i, ty) = super::type_(i)?;
+    let (i, rest) = not_line_ending(i)?;
+
+    let to = if CONVERSIONS.contains(&opcode) {
+        rest.rsplit_once(" to ")
+            .and_then(|(_, to)| super::type_(to.split(',').next().unwrap_or(to)).ok())
+            .map(|(_, ty)| ty)
+    } else {
+        None
+    };
+
+    let is_float = |ty: &Type| matches!(ty, Type::Float | Type::Double);
+    let is_integer = INTEGER.contains(&opcode) && matches!(ty, Type::Integer(_));
+    if is_integer || is_float(&ty) || to.as_ref().map(is_float) == Some(true) {
+        Ok((
+            i,
+            Stmt::Operation(Operation {
+                opcode,
+                cond,
+                ty,
+                to,
+            }),
+        ))
+    } else {
+        Ok((i, Stmt::Other))
+    }
+}
+
+// NOTE only stores of constants to constant addresses are recognized
+fn volatile_store(i: &str) -> IResult<&str, Stmt<'_>> {
+    // an integer constant of type `ty`
+    fn constant(i: &str) -> IResult<&str, u64> {
+        let (i, ty) = super::type_(i)?;
+        let i = space1(i)?.0;
+        let (i, n) = map_res(is_not(" ,)\t\r\n"), str::parse::<i64>)(i)?;
+        match ty {
+            Type::Integer(bits) if bits < 64 => Ok((i, n as u64 & ((1 << bits) - 1))),
+            Type::Integer(_) => Ok((i, n as u64)),
+            _ => Err(nom::Err::Error(nom::error::Error::new(
+                i,
+                nom::error::ErrorKind::Verify,
+            ))),
+        }
+    }
+
+    let i = tag("store volatile")(i)?.0;
+    let i = space1(i)?.0;
+    let (i, value) = constant(i)?;
+    let i = char(',')(i)?.0;
+    let i = space1(i)?.0;
+    let i = super::type_(i)?.0;
+    let i = space1(i)?.0;
+    let i = tag("inttoptr (")(i)?.0;
+    let (i, address) = constant(i)?;
+    // NOTE shortcut
+    let i = not_line_ending(i)?.0;
+    Ok((i, Stmt::VolatileStore(address, value)))
+}
+
 // NOTE we discard the LHS of assignments
 fn assign(i: &str) -> IResult<&str, Stmt> {
     let i = super::local(i)?.0;
     let i = space1(i)?.0;
     let i = char('=')(i)?.0;
     let i = space1(i)?.0;
-    alt((asm, bitcast_call, direct_call, indirect_call, other))(i)
+    alt((
+        asm,
+        bitcast_call,
+        direct_call,
+        indirect_call,
+        operation,
+        other,
+    ))(i)
 }
 
 fn stmt(i: &str) -> IResult<&str, Stmt> {
     alt((label, comment, |i| {
         let i = space1(i)?.0;
-        alt((assign, asm, bitcast_call, direct_call, indirect_call, other))(i)
+        alt((
+            assign,
+            asm,
+            bitcast_call,
+            memory_call,
+            direct_call,
+            indirect_call,
+            volatile_store,
+            br,
+            switch,
+            exit,
+            other,
+        ))(i)
     }))(i)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Argument, Define, Parameter};
+    use super::{Argument, Define, MemoryCall, Operation, Parameter};
     use crate::ir::{FnSig, Stmt, Type};
 
     #[test]
@@ -409,6 +875,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn memory_call() {
+        assert_eq!(
+            super::memory_call(
+                r#"call void @llvm.memcpy.p0.p0.i32(ptr noundef nonnull align 4 dereferenceable(64) %buf, ptr align 8 %src, i32 64, i1 false), !dbg !123"#
+            ),
+            Ok((
+                "",
+                Stmt::MemoryCall(MemoryCall {
+                    func: "llvm.memcpy.p0.p0.i32",
+                    align: 4,
+                    len: Some(64),
+                    value: None,
+                })
+            ))
+        );
+
+        assert_eq!(
+            super::memory_call(
+                r#"tail call void @llvm.memset.p0.i64(ptr getelementptr inbounds (i8, ptr @BUF, i64 8), i8 -1, i64 %len, i1 false)"#
+            ),
+            Ok((
+                "",
+                Stmt::MemoryCall(MemoryCall {
+                    func: "llvm.memset.p0.i64",
+                    align: 1,
+                    len: None,
+                    value: Some(0xff),
+                })
+            ))
+        );
+
+        assert!(super::memory_call(r#"call void @foo(ptr align 4 %0)"#).is_err());
+        // falls back to a plain direct call
+        assert_eq!(
+            super::stmt(r#"  call void @llvm.memmove.p0.p0.i32(ptr %0, ptr %1)"#),
+            Ok(("", Stmt::DirectCall("llvm.memmove.p0.p0.i32")))
+        );
+    }
+
     #[test]
     fn direct_call() {
         assert_eq!(
@@ -587,13 +1093,156 @@ mod tests {
     fn label() {
         assert_eq!(
             super::label(r#""payload_mut.exit.i.i": ; preds = %bb3.i96.i"#),
-            Ok(("", Stmt::Label))
+            Ok(("", Stmt::Label("payload_mut.exit.i.i")))
         );
 
         assert_eq!(
             super::label(r#"bb3.i96.i: ; preds = %bb37.i"#),
-            Ok(("", Stmt::Label))
+            Ok(("", Stmt::Label("bb3.i96.i")))
+        );
+    }
+
+    #[test]
+    fn br() {
+        assert_eq!(
+            super::br("br label %bb3, !dbg !1377"),
+            Ok(("", Stmt::Terminator(vec!["bb3"])))
+        );
+        assert_eq!(
+            super::br(r#"br i1 %_4, label %bb1, label %"foo.exit""#),
+            Ok(("", Stmt::Terminator(vec!["bb1", "foo.exit"])))
+        );
+
+        // folded conditions
+        assert_eq!(
+            super::br("br i1 true, label %bb1, label %bb2"),
+            Ok(("", Stmt::Terminator(vec!["bb1"])))
+        );
+        assert_eq!(
+            super::br("br i1 false, label %5, label %7, !dbg !42"),
+            Ok(("", Stmt::Terminator(vec!["7"])))
+        );
+    }
+
+    #[test]
+    fn switch() {
+        assert_eq!(
+            super::switch(
+                "switch i32 %0, label %bb1 [\n    i32 0, label %bb2\n    i32 -1, label %bb3\n    \
+                 i32 2, label %bb2\n  ], !dbg !7"
+            ),
+            Ok(("", Stmt::Terminator(vec!["bb1", "bb2", "bb3"])))
+        );
+
+        // folded value
+        assert_eq!(
+            super::switch(
+                "switch i8 1, label %bb1 [\n    i8 0, label %bb2\n    i8 1, label %bb3\n  ]"
+            ),
+            Ok(("", Stmt::Terminator(vec!["bb3"])))
+        );
+        assert_eq!(
+            super::switch("switch i8 7, label %bb1 [\n    i8 0, label %bb2\n  ]"),
+            Ok(("", Stmt::Terminator(vec!["bb1"])))
+        );
+    }
+
+    #[test]
+    fn exit() {
+        assert_eq!(
+            super::exit("ret void, !dbg !1377"),
+            Ok(("", Stmt::Terminator(vec![])))
+        );
+        assert_eq!(
+            super::exit("unreachable"),
+            Ok(("", Stmt::Terminator(vec![])))
+        );
+        assert!(super::exit("retain").is_err());
+    }
+
+    #[test]
+    fn prune() {
+        let define = super::parse(
+            "define void @foo() {\n\
+             start:\n\
+             \x20 br i1 false, label %bb1, label %bb2\n\
+             \n\
+             bb1:\n\
+             \x20 call void @dead()\n\
+             \x20 br label %bb2\n\
+             \n\
+             bb2:\n\
+             \x20 call void @live()\n\
+             \x20 ret void\n\
+             }",
+        )
+        .unwrap()
+        .1;
+        assert_eq!(
+            define.stmts,
+            [
+                Stmt::Label("start"),
+                Stmt::Terminator(vec!["bb2"]),
+                Stmt::Label("bb2"),
+                Stmt::DirectCall("live"),
+                Stmt::Terminator(vec![]),
+            ]
+        );
+
+        // the successors of the `invoke` are unknown so nothing is removed
+        let define = super::parse(
+            "define void @foo() {\n\
+             start:\n\
+             \x20 invoke void @bar()\n\
+             \x20         to label %bb1 unwind label %cleanup\n\
+             \n\
+             bb1:\n\
+             \x20 ret void\n\
+             \n\
+             cleanup:\n\
+             \x20 call void @dead()\n\
+             \x20 unreachable\n\
+             }",
+        )
+        .unwrap()
+        .1;
+        assert!(define.stmts.contains(&Stmt::DirectCall("dead")));
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(
+            super::symbols(
+                "  %_3 = icmp eq ptr @__start_linkme_INIT, @__stop_linkme_INIT\n\
+                 \x20 call void @llvm.dbg.value(metadata ptr @_shandlers, metadata !1)\n\
+                 \x20 call void @foo(ptr @__start_linkme_INIT)\n"
+            ),
+            [
+                "__start_linkme_INIT",
+                "__stop_linkme_INIT",
+                "_shandlers",
+                "foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn volatile_store() {
+        assert_eq!(
+            super::volatile_store(
+                "store volatile i32 3, ptr inttoptr (i32 -536813312 to ptr), align 256, !dbg !804"
+            ),
+            Ok(("", Stmt::VolatileStore(0xe000_e100, 3)))
         );
+        assert_eq!(
+            super::volatile_store(
+                "store volatile i32 -2147483648, i32* inttoptr (i32 -536813308 to i32*), align 4"
+            ),
+            Ok(("", Stmt::VolatileStore(0xe000_e104, 0x8000_0000)))
+        );
+
+        // not a constant
+        assert!(super::volatile_store("store volatile i32 %x, ptr %p, align 4").is_err());
     }
 
     #[test]
@@ -651,11 +1300,13 @@ mod tests {
                 "",
                 Define {
                     name: "_ZN4core3ptr18real_drop_in_place17h10d0d6d6b26fb8afE",
-                    stmts: vec![Stmt::Label, Stmt::Other],
+                    stmts: vec![Stmt::Label("start"), Stmt::Terminator(vec![])],
                     sig: FnSig {
                         inputs: vec![Type::Pointer(Box::new(Type::Alias("blue_pill::ItmLogger")))],
                         output: None,
                     },
+                    dbg: Some(2105),
+                    symbols: vec![],
                 }
             ))
         );
@@ -667,7 +1318,7 @@ mod tests {
                 "",
                 Define {
                     name,
-                    stmts: vec![Stmt::Label, Stmt::Other],
+                    stmts: vec![Stmt::Label("start"), Stmt::Terminator(vec![])],
                     sig: FnSig {
                         inputs: vec![
                             Type::Pointer(Box::new(Type::Integer(8))),
@@ -678,6 +1329,8 @@ mod tests {
                         ],
                         output: None,
                     },
+                    dbg: Some(5158),
+                    symbols: vec![],
                 }
             ))
         );
@@ -688,7 +1341,7 @@ mod tests {
                 "",
                 Define {
                     name: "_ZN3std9panicking20rust_panic_with_hook17hac9cf78024704ab4E",
-                    stmts: vec![Stmt::Label, Stmt::Other],
+                    stmts: vec![Stmt::Label("start"), Stmt::Terminator(vec![])],
                     sig: FnSig {
                         inputs: vec![
                             Type::Pointer(Box::new(Type::Struct(vec![]))),
@@ -712,6 +1365,8 @@ mod tests {
                         ],
                         output: None,
                     },
+                    dbg: Some(6634),
+                    symbols: vec![],
                 }
             ))
         );
@@ -722,7 +1377,7 @@ mod tests {
                 "",
                 Define {
                     name: "foo",
-                    stmts: vec![Stmt::Label, Stmt::Other],
+                    stmts: vec![Stmt::Label("start"), Stmt::Terminator(vec![])],
                     sig: FnSig {
                         inputs: vec![],
                         output: Some(Box::new(Type::Pointer(Box::new(Type::Pointer(Box::new(
@@ -732,6 +1387,8 @@ mod tests {
                             })
                         )))))),
                     },
+                    dbg: Some(1272),
+                    symbols: vec![],
                 }
             ))
         );
@@ -743,15 +1400,22 @@ mod tests {
                 Define {
                     name: "_ZN3app3foo17h3337355bfdc88d96E",
                     stmts: vec![
-                        Stmt::Label,
+                        Stmt::Label("start"),
                         Stmt::DirectCall("llvm.dbg.value"),
-                        Stmt::Other,
-                        Stmt::Other,
+                        Stmt::Operation(Operation {
+                            opcode: "fmul",
+                            cond: None,
+                            ty: Type::Float,
+                            to: None,
+                        }),
+                        Stmt::Terminator(vec![]),
                     ],
                     sig: FnSig {
                         inputs: vec![Type::Float],
                         output: Some(Box::new(Type::Float)),
                     },
+                    dbg: Some(1183),
+                    symbols: vec![],
                 }
             ))
         );
@@ -762,13 +1426,15 @@ mod tests {
                 "",
                 Define {
                     name: "_defmt_acquire",
-                    stmts: vec![Stmt::Other],
+                    stmts: vec![Stmt::Terminator(vec![])],
                     sig: FnSig {
                         inputs: vec![Type::Pointer(Box::new(Type::Alias(
                             "core::option::Option<defmt::InternalFormatter>"
                         )))],
                         output: None,
                     },
+                    dbg: Some(5972),
+                    symbols: vec![],
                 }
             ))
         );
@@ -779,11 +1445,17 @@ mod tests {
                 "",
                 Define {
                     name: "__aeabi_uidivmod",
-                    stmts: vec![Stmt::Label, Stmt::Asm("push {lr}"), Stmt::Other],
+                    stmts: vec![
+                        Stmt::Label("start"),
+                        Stmt::Asm("push {lr}"),
+                        Stmt::Terminator(vec![]),
+                    ],
                     sig: FnSig {
                         inputs: vec![],
                         output: None,
                     },
+                    dbg: Some(258),
+                    symbols: vec![],
                 }
             ))
         );
@@ -793,7 +1465,7 @@ mod tests {
             .unwrap()
             .1;
         assert_eq!("__rust_i128_addo", define.name);
-        assert_eq!(vec![Stmt::Other], define.stmts);
+        assert_eq!(vec![Stmt::Terminator(vec![])], define.stmts);
         assert!(define.sig.output.is_none());
         assert_eq!(Type::OpaquePointer, define.sig.inputs[0]);
         assert_eq!(
@@ -810,7 +1482,7 @@ mod tests {
             "_ZN17compiler_builtins3mem6memcpy17he02e6130a63bff3aE",
             define.name
         );
-        assert_eq!(vec![Stmt::Other], define.stmts);
+        assert_eq!(vec![Stmt::Terminator(vec![])], define.stmts);
         assert_eq!(Some(&Type::OpaquePointer), define.sig.output.as_deref(),);
         assert_eq!(Type::OpaquePointer, define.sig.inputs[0]);
         assert_eq!(Type::OpaquePointer, define.sig.inputs[1]);
@@ -836,4 +1508,70 @@ mod tests {
             define.name
         );
     }
+
+    #[test]
+    fn operation() {
+        assert_eq!(
+            super::assign("%2 = fmul float %0, %1"),
+            Ok((
+                "",
+                Stmt::Operation(Operation {
+                    opcode: "fmul",
+                    cond: None,
+                    ty: Type::Float,
+                    to: None,
+                })
+            ))
+        );
+
+        assert_eq!(
+            super::assign("%_5 = fcmp fast olt double %x, 0.000000e+00"),
+            Ok((
+                "",
+                Stmt::Operation(Operation {
+                    opcode: "fcmp",
+                    cond: Some("olt"),
+                    ty: Type::Double,
+                    to: None,
+                })
+            ))
+        );
+
+        assert_eq!(
+            super::assign("%3 = sitofp i64 %2 to float, !dbg !123"),
+            Ok((
+                "",
+                Stmt::Operation(Operation {
+                    opcode: "sitofp",
+                    cond: None,
+                    ty: Type::Integer(64),
+                    to: Some(Type::Float),
+                })
+            ))
+        );
+
+        assert_eq!(
+            super::assign("%_4 = udiv exact i64 %x, %y, !dbg !1264"),
+            Ok((
+                "",
+                Stmt::Operation(Operation {
+                    opcode: "udiv",
+                    cond: None,
+                    ty: Type::Integer(64),
+                    to: None,
+                })
+            ))
+        );
+
+        // vectors and integer additions are not of interest
+        assert_eq!(
+            super::assign("%4 = fadd <4 x float> %0, %1"),
+            Ok(("", Stmt::Other))
+        );
+        assert_eq!(
+            super::assign("%4 = shl <2 x i64> %0, %1"),
+            Ok(("", Stmt::Other))
+        );
+        assert_eq!(super::assign("%5 = add i32 %0, %1"), Ok(("", Stmt::Other)));
+    }
 }
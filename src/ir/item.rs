@@ -39,6 +39,10 @@ pub enum Item<'a> {
     // `attributes #0 = { norecurse nounwind readnone "target-cpu"="generic" }`
     Attributes,
 
+    // `!752 = distinct !DISubprogram(name: "foo", scope: !735, file: !734, line: 19, (..))`
+    // `!734 = !DIFile(filename: "examples/cycle.rs", directory: "/root/firmware")`
+    DebugInfo(u32, DebugInfo<'a>),
+
     // `!0 = !DIGlobalVariableExpression(var: !1, expr: !DIExpression())`
     Metadata,
 
@@ -46,6 +50,19 @@ pub enum Item<'a> {
     ModuleAsm,
 }
 
+/// The debug info metadata used to locate functions in the source code
+#[derive(Clone, Debug, PartialEq)]
+pub enum DebugInfo<'a> {
+    Subprogram {
+        file: u32,
+        line: u32,
+    },
+    File {
+        filename: &'a str,
+        directory: &'a str,
+    },
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Declare<'a> {
     pub name: &'a str,
@@ -186,8 +203,46 @@ fn attributes(i: &str) -> IResult<&str, Item> {
 fn metadata(i: &str) -> IResult<&str, Item> {
     let i = tag("!")(i)?.0;
     // NOTE shortcut
-    let i = not_line_ending(i)?.0;
-    Ok((i, Item::Metadata))
+    let (i, node) = not_line_ending(i)?;
+    Ok((i, debug_info(node).unwrap_or(Item::Metadata)))
+}
+
+// NOTE shortcut: the fields are looked up by name rather than parsed
+fn debug_info(node: &str) -> Option<Item<'_>> {
+    let (id, node) = node.split_once(" = ")?;
+    let id = id.parse().ok()?;
+    let node = node.strip_prefix("distinct ").unwrap_or(node);
+
+    let field = |name: &str| {
+        node.split_once(&format!(", {}: ", name))
+            .or_else(|| node.split_once(&format!("({}: ", name)))
+            .map(|(_, value)| value)
+    };
+    let string = |name: &str| field(name)?.strip_prefix('"')?.split('"').next();
+    let number = |name: &str| {
+        field(name)?
+            .trim_start_matches('!')
+            .split(|c: char| !c.is_ascii_digit())
+            .next()?
+            .parse()
+            .ok()
+    };
+
+    let info = if node.starts_with("!DISubprogram(") {
+        DebugInfo::Subprogram {
+            file: number("file")?,
+            line: number("line")?,
+        }
+    } else if node.starts_with("!DIFile(") {
+        DebugInfo::File {
+            filename: string("filename")?,
+            directory: string("directory")?,
+        }
+    } else {
+        return None;
+    };
+
+    Some(Item::DebugInfo(id, info))
 }
 
 fn module_asm(i: &str) -> IResult<&str, Item> {
@@ -215,9 +270,45 @@ pub fn item(i: &str) -> IResult<&str, Item> {
     ))(i)
 }
 
+/// Runs all the item parsers on `i` and returns the name of the one that got the furthest, along
+/// with the input at which it failed
+pub fn diagnose(i: &str) -> (&'static str, &str) {
+    #[allow(clippy::type_complexity)]
+    let parsers: &[(&str, fn(&str) -> IResult<&str, Item>)] = &[
+        ("comment", comment),
+        ("source filename", source_filename),
+        ("target", target),
+        ("type definition", type_),
+        ("global", global),
+        ("alias", alias),
+        ("function definition", |i| {
+            map(super::define::parse, Item::Define)(i)
+        }),
+        ("function declaration", declare),
+        ("attribute group", attributes),
+        ("metadata", metadata),
+        ("module asm", module_asm),
+    ];
+
+    parsers
+        .iter()
+        .filter_map(|(production, parser)| match parser(i) {
+            Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => Some((*production, e.input)),
+            // it parsed; what comes after it is the problem
+            Ok((rest, _)) => Some((*production, rest)),
+            Err(nom::Err::Incomplete(_)) => None,
+        })
+        .min_by_key(|(_, rest)| rest.len())
+        .filter(|(_, rest)| rest.len() < i.len())
+        .unwrap_or(("item", i))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::ir::{item::Declare, FnSig, Item, Type};
+    use crate::ir::{
+        item::{DebugInfo, Declare},
+        FnSig, Item, Type,
+    };
 
     #[test]
     fn alias() {
@@ -278,4 +369,44 @@ mod tests {
             Ok(("", Item::Type))
         );
     }
+
+    #[test]
+    fn debug_info() {
+        assert_eq!(
+            super::item(
+                r#"!752 = distinct !DISubprogram(name: "foo", linkageName: "_ZN5cycle3foo17h0E", scope: !735, file: !734, line: 19, type: !753, scopeLine: 19, flags: DIFlagPrototyped, spFlags: DISPFlagLocalToUnit | DISPFlagDefinition, unit: !80, templateParams: !89, retainedNodes: !89)"#
+            ),
+            Ok((
+                "",
+                Item::DebugInfo(
+                    752,
+                    DebugInfo::Subprogram {
+                        file: 734,
+                        line: 19
+                    }
+                )
+            ))
+        );
+
+        assert_eq!(
+            super::item(
+                r#"!734 = !DIFile(filename: "examples/cycle.rs", directory: "/root/firmware", checksumkind: CSK_MD5, checksum: "0123")"#
+            ),
+            Ok((
+                "",
+                Item::DebugInfo(
+                    734,
+                    DebugInfo::File {
+                        filename: "examples/cycle.rs",
+                        directory: "/root/firmware"
+                    }
+                )
+            ))
+        );
+
+        assert_eq!(
+            super::item(r#"!0 = !DIGlobalVariableExpression(var: !1, expr: !DIExpression())"#),
+            Ok(("", Item::Metadata))
+        );
+    }
 }
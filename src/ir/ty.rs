@@ -10,7 +10,7 @@ use nom::{
     IResult,
 };
 
-use crate::ir::FnSig;
+use crate::ir::{Abi, FnSig};
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Type<'a> {
@@ -70,6 +70,74 @@ impl<'a> Type<'a> {
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
     }
+
+    /// The shape in which `abi` passes a value of this type in registers; see
+    /// `FnSig::canonicalize`
+    pub fn canonicalize(&self, abi: Abi) -> Type<'a> {
+        let width = abi.register_width;
+        match self {
+            Type::Float if abi.soft_float => Type::Integer(32),
+            Type::Double if abi.soft_float => self.canonicalize_integer(abi, 64, 64),
+            Type::Integer(bits) if *bits > width => self.canonicalize_integer(abi, *bits, *bits),
+            Type::Array(..) | Type::Struct(_) | Type::PackedStruct(_) => {
+                match self.layout(abi) {
+                    // aggregates made of integers (or pointers, or floats if `soft_float`) are
+                    // coerced into integer registers
+                    Some((size, align)) if size != 0 => {
+                        self.canonicalize_integer(abi, 8 * size, 8 * align)
+                    }
+                    _ => self.clone(),
+                }
+            }
+            _ => self.clone(),
+        }
+    }
+
+    // a value of `bits` bits whose alignment is `align` bits as a number of registers; the
+    // alignment matters because e.g. ARM's AAPCS passes 64-bit aligned values in an even register
+    // pair
+    fn canonicalize_integer(&self, abi: Abi, bits: usize, align: usize) -> Type<'a> {
+        let width = abi.register_width;
+        let element = align.clamp(width, 2 * width);
+        let count = bits.div_ceil(element);
+        if count == 1 && element == width {
+            Type::Integer(width)
+        } else {
+            Type::Array(count, Box::new(Type::Integer(element)))
+        }
+    }
+
+    // the size and alignment, in bytes, of a type that only contains integer-like values; `None`
+    // for anything else
+    fn layout(&self, abi: Abi) -> Option<(usize, usize)> {
+        let pointer = abi.register_width / 8;
+        Some(match self {
+            Type::Integer(bits) => {
+                let size = bits.div_ceil(8);
+                (size, size.next_power_of_two().min(8))
+            }
+            Type::Pointer(_) | Type::OpaquePointer => (pointer, pointer),
+            Type::Float if abi.soft_float => (4, 4),
+            Type::Double if abi.soft_float => (8, 8),
+            Type::Array(count, element) => {
+                let (size, align) = element.layout(abi)?;
+                (count * size, align)
+            }
+            Type::Struct(fields) | Type::PackedStruct(fields) => {
+                let packed = matches!(self, Type::PackedStruct(_));
+                let mut size = 0usize;
+                let mut max_align = 1usize;
+                for field in fields {
+                    let (field_size, align) = field.layout(abi)?;
+                    let align = if packed { 1 } else { align };
+                    size = size.div_ceil(align) * align + field_size;
+                    max_align = max_align.max(align);
+                }
+                (size.div_ceil(max_align) * max_align, max_align)
+            }
+            _ => return None,
+        })
+    }
 }
 
 fn fmt_struct(f: &mut fmt::Formatter, fields: &[Type]) -> fmt::Result {
@@ -0,0 +1,35 @@
+//! Library side of `cargo-call-stack`
+//!
+//! Contains the whole program stack usage analysis, the file formats that `cargo-call-stack`
+//! produces for other tools to consume and the machine code analysis it performs, for other tools
+//! to reuse
+
+#![deny(missing_docs)]
+
+pub mod analysis;
+pub mod classify;
+pub mod config;
+pub mod editor;
+pub mod thumb;
+
+pub use crate::analysis::{analyze, AnalysisInput, CallGraph, Options};
+
+mod bitcode;
+mod cache;
+mod cli;
+mod compare;
+mod disassembly;
+mod dwarf;
+mod elf;
+mod intern;
+mod ir;
+mod libcall;
+mod suggest;
+mod wasm;
+mod wrapper;
+
+/// The entry point of the `cargo-call-stack` binary; not part of the library API
+#[doc(hidden)]
+pub fn run_cli() -> anyhow::Result<()> {
+    cli::main()
+}
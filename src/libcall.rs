@@ -0,0 +1,536 @@
+//! Library calls that LLVM emits for operations the target has no instructions for
+//!
+//! For example, on targets without a floating point unit a `fadd float` operation lowers to a call
+//! to `__aeabi_fadd` (ARM EABI) or `__addsf3` (other architectures), and on 32-bit targets a
+//! `udiv i64` operation lowers to a call to `__udivdi3`. These functions live in
+//! `compiler_builtins`.
+
+use std::collections::HashSet;
+
+use crate::ir::{MemoryCall, Operation, Type};
+
+/// The floating point capabilities of a target
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fpu {
+    /// single precision (`float`) operations are done in hardware
+    pub single: bool,
+    /// double precision (`double`) operations are done in hardware
+    pub double: bool,
+    /// conversions between floats and 64-bit integers are done in hardware
+    pub int64: bool,
+}
+
+impl Fpu {
+    /// Derives the capabilities from the `target_arch`, `target_abi` and `target_feature`s reported
+    /// by `rustc --print=cfg`
+    pub fn new(arch: &str, abi: &str, features: &HashSet<String>) -> Self {
+        let has = |feature: &str| features.contains(feature);
+
+        match arch {
+            "x86" | "x86_64" => Fpu {
+                single: true,
+                double: true,
+                int64: arch == "x86_64",
+            },
+
+            "aarch64" => {
+                let fpu = abi != "softfloat" && (has("neon") || has("fp-armv8"));
+                Fpu {
+                    single: fpu,
+                    double: fpu,
+                    int64: fpu,
+                }
+            }
+
+            "arm" => {
+                // the `vfp*` features are not always reported (e.g. `thumbv7em-none-eabihf`) but
+                // the hard float ABI implies an FPU
+                let single = abi.ends_with("hf")
+                    || features
+                        .iter()
+                        .any(|feature| feature.starts_with("vfp") || feature == "fp-armv8");
+                // the FPU of ARMv7-M and ARMv8-M cores is usually single precision only
+                let double = single && (!has("mclass") || has("fp64"));
+                Fpu {
+                    single,
+                    double,
+                    int64: false,
+                }
+            }
+
+            "riscv32" | "riscv64" => Fpu {
+                single: has("f"),
+                double: has("d"),
+                int64: arch == "riscv64",
+            },
+
+            _ => Fpu::default(),
+        }
+    }
+}
+
+/// Returns the functions that `op` may lower to on a target with the given `fpu`
+///
+/// ARM targets use the names defined by the ARM EABI (e.g. `__aeabi_fadd` instead of `__addsf3`).
+/// The list is empty if the operation lowers to instructions
+pub fn float(op: &Operation, fpu: Fpu, is_arm: bool) -> Vec<&'static str> {
+    // `float` -> `f`, `double` -> `d`
+    let kind = |ty: &Type| match ty {
+        Type::Float => Some(('f', fpu.single)),
+        Type::Double => Some(('d', fpu.double)),
+        _ => None,
+    };
+    // integer operand of a conversion: (32 / 64 / 128 bits, done in hardware?)
+    let int = |ty: &Type| match ty {
+        Type::Integer(n) if *n <= 32 => Some((32, true)),
+        Type::Integer(64) => Some((64, fpu.int64)),
+        Type::Integer(128) => Some((128, false)),
+        _ => None,
+    };
+
+    let names: &[(&str, &str)] = match (op.opcode, kind(&op.ty), op.to.as_ref()) {
+        ("fadd" | "fsub" | "fmul" | "fdiv" | "fcmp", Some((_, true)), _) => &[],
+        ("fadd", Some(('f', _)), _) => &[("__aeabi_fadd", "__addsf3")],
+        ("fadd", Some(('d', _)), _) => &[("__aeabi_dadd", "__adddf3")],
+        ("fsub", Some(('f', _)), _) => &[("__aeabi_fsub", "__subsf3")],
+        ("fsub", Some(('d', _)), _) => &[("__aeabi_dsub", "__subdf3")],
+        ("fmul", Some(('f', _)), _) => &[("__aeabi_fmul", "__mulsf3")],
+        ("fmul", Some(('d', _)), _) => &[("__aeabi_dmul", "__muldf3")],
+        ("fdiv", Some(('f', _)), _) => &[("__aeabi_fdiv", "__divsf3")],
+        ("fdiv", Some(('d', _)), _) => &[("__aeabi_ddiv", "__divdf3")],
+        // there's no instruction for this one; it's always a call into libm
+        ("frem", Some(('f', _)), _) => &[("fmodf", "fmodf")],
+        ("frem", Some(('d', _)), _) => &[("fmod", "fmod")],
+        ("fcmp", Some((k, _)), _) => return compare(k, op.cond.unwrap_or(""), is_arm),
+
+        ("fpext", Some(('f', _)), Some(Type::Double)) if !fpu.double => {
+            &[("__aeabi_f2d", "__extendsfdf2")]
+        }
+        ("fptrunc", Some(('d', _)), Some(Type::Float)) if !fpu.double => {
+            &[("__aeabi_d2f", "__truncdfsf2")]
+        }
+
+        ("fptosi" | "fptoui", Some((k, hard)), Some(to)) => match int(to) {
+            Some((bits, int64)) if !(hard && int64) => {
+                return conversion(op.opcode, k, bits, is_arm);
+            }
+            _ => &[],
+        },
+        ("sitofp" | "uitofp", _, Some(to)) => match (int(&op.ty), kind(to)) {
+            (Some((bits, int64)), Some((k, hard))) if !(hard && int64) => {
+                return conversion(op.opcode, k, bits, is_arm);
+            }
+            _ => &[],
+        },
+
+        _ => &[],
+    };
+
+    names
+        .iter()
+        .map(|(eabi, generic)| if is_arm { *eabi } else { *generic })
+        .collect()
+}
+
+/// Returns the functions that the integer operation `op` may lower to on a target whose registers
+/// are `width` bits wide
+///
+/// Multiplications, divisions and shifts of integers wider than a register may lower to library
+/// calls, e.g. `udiv i64` to `__udivdi3` on RISC-V 32 and to `__aeabi_uldivmod` on ARM. Some of
+/// these operations are lowered to instructions instead, e.g. `mul i64` on targets that have a
+/// multiplier, so the candidates should be filtered by the symbols present in the binary
+pub fn integer(op: &Operation, width: usize, is_arm: bool) -> Vec<String> {
+    let bits = match op.ty {
+        Type::Integer(bits) if bits > width => bits,
+        _ => return vec![],
+    };
+    // `si` = 32-bit, `di` = 64-bit, `ti` = 128-bit
+    let mode = match bits {
+        0..=32 => "si",
+        33..=64 => "di",
+        _ => "ti",
+    };
+
+    let (generic, eabi): (&[&str], &str) = match op.opcode {
+        "mul" => (&["__mul{}3"], "__aeabi_lmul"),
+        "udiv" => (&["__udiv{}3", "__udivmod{}4"], "__aeabi_uldivmod"),
+        "sdiv" => (&["__div{}3", "__divmod{}4"], "__aeabi_ldivmod"),
+        "urem" => (&["__umod{}3", "__udivmod{}4"], "__aeabi_uldivmod"),
+        "srem" => (&["__mod{}3", "__divmod{}4"], "__aeabi_ldivmod"),
+        "shl" => (&["__ashl{}3"], "__aeabi_llsl"),
+        "lshr" => (&["__lshr{}3"], "__aeabi_llsr"),
+        "ashr" => (&["__ashr{}3"], "__aeabi_lasr"),
+        _ => return vec![],
+    };
+
+    let mut names = generic
+        .iter()
+        .map(|name| name.replace("{}", mode))
+        .collect::<Vec<_>>();
+    if is_arm && mode == "di" {
+        names.push(eabi.to_owned());
+    }
+    names
+}
+
+/// Returns the functions that the memory intrinsic `func` (e.g. `llvm.memcpy.p0.p0.i32`) may lower
+/// to, or `None` if `func` is not one of them
+pub fn memory(func: &str, is_arm: bool) -> Option<&'static [&'static str]> {
+    // the `.inline` variants (e.g. `llvm.memcpy.inline.p0.p0.i32`) are guaranteed to be lowered to
+    // machine code
+    if func.starts_with("llvm.memcpy.inline.") || func.starts_with("llvm.memset.inline.") {
+        return Some(&[]);
+    }
+
+    // ARMv7-R and the like use the ARM EABI variants
+    Some(if func.starts_with("llvm.memcpy.") {
+        if is_arm {
+            &[
+                "memcpy",
+                "__aeabi_memcpy",
+                "__aeabi_memcpy4",
+                "__aeabi_memcpy8",
+            ]
+        } else {
+            &["memcpy"]
+        }
+    } else if func.starts_with("llvm.memmove.") {
+        if is_arm {
+            &[
+                "memmove",
+                "__aeabi_memmove",
+                "__aeabi_memmove4",
+                "__aeabi_memmove8",
+            ]
+        } else {
+            &["memmove"]
+        }
+    } else if func.starts_with("llvm.memset.") {
+        if is_arm {
+            &[
+                "memset",
+                "memclr",
+                "__aeabi_memset",
+                "__aeabi_memset4",
+                "__aeabi_memset8",
+                "__aeabi_memclr",
+                "__aeabi_memclr4",
+                "__aeabi_memclr8",
+            ]
+        } else {
+            &["memset", "memclr"]
+        }
+    } else {
+        return None;
+    })
+}
+
+/// Narrows down the functions that a call to a memory intrinsic may lower to using its arguments
+///
+/// Copies of a constant length that fit in two stores of the widest size the alignment allows are
+/// expanded into loads and stores by LLVM on every target, so they call nothing. On ARM the
+/// alignment selects the EABI variant (e.g. `__aeabi_memcpy4`) and a `memset` of zero lowers to
+/// `__aeabi_memclr*`.
+pub fn memory_call(call: &MemoryCall, is_arm: bool, pointer_width: usize) -> Vec<&'static str> {
+    let all = match memory(call.func, is_arm) {
+        Some(all) if !all.is_empty() => all,
+        _ => return vec![],
+    };
+
+    let word = (pointer_width / 8) as u64;
+    if let Some(len) = call.len {
+        if len <= 2 * call.align.min(word) {
+            return vec![];
+        }
+    }
+
+    if !is_arm {
+        // `memcpy`, `memmove` or `memset`
+        return vec![all[0]];
+    }
+
+    // no suffix, `4` or `8`; alignments are powers of two
+    let variant = if call.align >= 8 {
+        2
+    } else if call.align >= 4 {
+        1
+    } else {
+        0
+    };
+    if call.func.starts_with("llvm.memset.") {
+        // `__aeabi_memset*` and `__aeabi_memclr*`
+        let (memset, memclr) = (all[2 + variant], all[5 + variant]);
+        match call.value {
+            Some(0) => vec![memclr],
+            Some(_) => vec![memset],
+            None => vec![memset, memclr],
+        }
+    } else {
+        vec![all[1 + variant]]
+    }
+}
+
+fn compare(kind: char, cond: &str, is_arm: bool) -> Vec<&'static str> {
+    // ARM EABI: `__aeabi_fcmplt`, etc.; others: `__ltsf2`, etc.
+    const EABI: &[(&str, [&str; 2])] = &[
+        ("eq", ["__aeabi_fcmpeq", "__aeabi_dcmpeq"]),
+        ("lt", ["__aeabi_fcmplt", "__aeabi_dcmplt"]),
+        ("le", ["__aeabi_fcmple", "__aeabi_dcmple"]),
+        ("gt", ["__aeabi_fcmpgt", "__aeabi_dcmpgt"]),
+        ("ge", ["__aeabi_fcmpge", "__aeabi_dcmpge"]),
+        ("un", ["__aeabi_fcmpun", "__aeabi_dcmpun"]),
+    ];
+    const GENERIC: &[(&str, [&str; 2])] = &[
+        ("eq", ["__eqsf2", "__eqdf2"]),
+        ("ne", ["__nesf2", "__nedf2"]),
+        ("lt", ["__ltsf2", "__ltdf2"]),
+        ("le", ["__lesf2", "__ledf2"]),
+        ("gt", ["__gtsf2", "__gtdf2"]),
+        ("ge", ["__gesf2", "__gedf2"]),
+        ("un", ["__unordsf2", "__unorddf2"]),
+    ];
+
+    let i = if kind == 'f' { 0 } else { 1 };
+    let (ordered, base) = match cond {
+        "true" | "false" => return vec![],
+        "ord" | "uno" => (true, "un"),
+        _ => match (cond.strip_prefix('o'), cond.strip_prefix('u')) {
+            (Some(base), _) => (true, base),
+            (_, Some(base)) => (false, base),
+            _ => return vec![],
+        },
+    };
+    // EABI has no "not equal" function; the result of "equal" is negated
+    let base = if is_arm && base == "ne" { "eq" } else { base };
+
+    let table = if is_arm { EABI } else { GENERIC };
+    let mut names = table
+        .iter()
+        .filter(|(cond, _)| *cond == base)
+        .map(|(_, names)| names[i])
+        .collect::<Vec<_>>();
+
+    // unordered comparisons (and `one`) also check whether any operand is NaN
+    if !ordered || cond == "one" {
+        names.extend(
+            table
+                .iter()
+                .filter(|(cond, _)| *cond == "un")
+                .map(|(_, names)| names[i]),
+        );
+    }
+
+    names.dedup();
+    names
+}
+
+fn conversion(opcode: &str, kind: char, bits: u32, is_arm: bool) -> Vec<&'static str> {
+    // (opcode, float kind, integer bits, EABI name, generic name)
+    const CONVERSIONS: &[(&str, char, u32, &str, &str)] = &[
+        ("fptosi", 'f', 32, "__aeabi_f2iz", "__fixsfsi"),
+        ("fptosi", 'f', 64, "__aeabi_f2lz", "__fixsfdi"),
+        ("fptosi", 'f', 128, "__fixsfti", "__fixsfti"),
+        ("fptosi", 'd', 32, "__aeabi_d2iz", "__fixdfsi"),
+        ("fptosi", 'd', 64, "__aeabi_d2lz", "__fixdfdi"),
+        ("fptosi", 'd', 128, "__fixdfti", "__fixdfti"),
+        ("fptoui", 'f', 32, "__aeabi_f2uiz", "__fixunssfsi"),
+        ("fptoui", 'f', 64, "__aeabi_f2ulz", "__fixunssfdi"),
+        ("fptoui", 'f', 128, "__fixunssfti", "__fixunssfti"),
+        ("fptoui", 'd', 32, "__aeabi_d2uiz", "__fixunsdfsi"),
+        ("fptoui", 'd', 64, "__aeabi_d2ulz", "__fixunsdfdi"),
+        ("fptoui", 'd', 128, "__fixunsdfti", "__fixunsdfti"),
+        ("sitofp", 'f', 32, "__aeabi_i2f", "__floatsisf"),
+        ("sitofp", 'f', 64, "__aeabi_l2f", "__floatdisf"),
+        ("sitofp", 'f', 128, "__floattisf", "__floattisf"),
+        ("sitofp", 'd', 32, "__aeabi_i2d", "__floatsidf"),
+        ("sitofp", 'd', 64, "__aeabi_l2d", "__floatdidf"),
+        ("sitofp", 'd', 128, "__floattidf", "__floattidf"),
+        ("uitofp", 'f', 32, "__aeabi_ui2f", "__floatunsisf"),
+        ("uitofp", 'f', 64, "__aeabi_ul2f", "__floatundisf"),
+        ("uitofp", 'f', 128, "__floatuntisf", "__floatuntisf"),
+        ("uitofp", 'd', 32, "__aeabi_ui2d", "__floatunsidf"),
+        ("uitofp", 'd', 64, "__aeabi_ul2d", "__floatundidf"),
+        ("uitofp", 'd', 128, "__floatuntidf", "__floatuntidf"),
+    ];
+
+    CONVERSIONS
+        .iter()
+        .filter(|(op, k, n, _, _)| *op == opcode && *k == kind && *n == bits)
+        .map(|(_, _, _, eabi, generic)| if is_arm { *eabi } else { *generic })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Fpu;
+    use crate::ir::{MemoryCall, Operation, Type};
+
+    fn features(features: &[&str]) -> HashSet<String> {
+        features.iter().map(|f| f.to_string()).collect()
+    }
+
+    fn op<'a>(
+        opcode: &'a str,
+        cond: Option<&'a str>,
+        ty: Type<'a>,
+        to: Option<Type<'a>>,
+    ) -> Operation<'a> {
+        Operation {
+            opcode,
+            cond,
+            ty,
+            to,
+        }
+    }
+
+    #[test]
+    fn fpu() {
+        // thumbv7em-none-eabihf
+        let m4f = Fpu::new("arm", "eabihf", &features(&["mclass", "thumb2"]));
+        assert!(m4f.single && !m4f.double);
+
+        // armv7r-none-eabihf
+        let r5f = Fpu::new("arm", "eabihf", &features(&["rclass", "vfp2"]));
+        assert!(r5f.single && r5f.double);
+
+        assert_eq!(
+            Fpu::new("arm", "eabi", &features(&["rclass"])),
+            Fpu::default()
+        );
+        assert_eq!(
+            Fpu::new("riscv32", "", &features(&["m", "a", "c"])),
+            Fpu::default()
+        );
+        assert!(Fpu::new("riscv32", "", &features(&["f"])).single);
+        assert_eq!(
+            Fpu::new("aarch64", "softfloat", &features(&[])),
+            Fpu::default()
+        );
+        assert!(Fpu::new("aarch64", "", &features(&["neon"])).double);
+    }
+
+    #[test]
+    fn integer() {
+        let op = |opcode, bits| Operation {
+            opcode,
+            cond: None,
+            ty: Type::Integer(bits),
+            to: None,
+        };
+
+        assert_eq!(
+            super::integer(&op("udiv", 64), 32, false),
+            ["__udivdi3", "__udivmoddi4"]
+        );
+        assert_eq!(
+            super::integer(&op("srem", 64), 32, true),
+            ["__moddi3", "__divmoddi4", "__aeabi_ldivmod"]
+        );
+        assert_eq!(super::integer(&op("mul", 32), 16, false), ["__mulsi3"]);
+        assert_eq!(super::integer(&op("shl", 128), 64, false), ["__ashlti3"]);
+        assert!(super::integer(&op("udiv", 64), 64, false).is_empty());
+        assert!(super::integer(&op("fmul", 64), 32, false).is_empty());
+    }
+
+    #[test]
+    fn memory() {
+        assert_eq!(
+            super::memory("llvm.memmove.p0.p0.i64", false),
+            Some(&["memmove"][..])
+        );
+        assert!(super::memory("llvm.memset.p0.i32", true)
+            .unwrap()
+            .contains(&"__aeabi_memclr4"));
+        assert_eq!(super::memory("llvm.abs.i32", true), None);
+        assert_eq!(
+            super::memory("llvm.memcpy.inline.p0.p0.i32", true),
+            Some(&[][..])
+        );
+        assert_eq!(
+            super::memory("llvm.memset.inline.p0.i64", false),
+            Some(&[][..])
+        );
+    }
+
+    #[test]
+    fn memory_call() {
+        let call = |func, align, len, value| MemoryCall {
+            func,
+            align,
+            len,
+            value,
+        };
+
+        let memcpy = call("llvm.memcpy.p0.p0.i32", 4, Some(64), None);
+        assert_eq!(super::memory_call(&memcpy, true, 32), ["__aeabi_memcpy4"]);
+        assert_eq!(super::memory_call(&memcpy, false, 32), ["memcpy"]);
+        let memmove = call("llvm.memmove.p0.p0.i32", 2, None, None);
+        assert_eq!(super::memory_call(&memmove, true, 32), ["__aeabi_memmove"]);
+
+        // expanded into two word-sized loads and stores
+        let small = call("llvm.memcpy.p0.p0.i32", 4, Some(8), None);
+        assert!(super::memory_call(&small, true, 32).is_empty());
+        let unaligned = call("llvm.memcpy.p0.p0.i64", 1, Some(8), None);
+        assert_eq!(super::memory_call(&unaligned, false, 64), ["memcpy"]);
+
+        let memclr = call("llvm.memset.p0.i32", 8, Some(256), Some(0));
+        assert_eq!(super::memory_call(&memclr, true, 32), ["__aeabi_memclr8"]);
+        let memset = call("llvm.memset.p0.i32", 1, None, None);
+        assert_eq!(
+            super::memory_call(&memset, true, 32),
+            ["__aeabi_memset", "__aeabi_memclr"]
+        );
+        assert_eq!(super::memory_call(&memset, false, 32), ["memset"]);
+
+        let inline = call("llvm.memcpy.inline.p0.p0.i32", 4, Some(64), None);
+        assert!(super::memory_call(&inline, true, 32).is_empty());
+    }
+
+    #[test]
+    fn float() {
+        let soft = Fpu::default();
+        let m4f = Fpu {
+            single: true,
+            double: false,
+            int64: false,
+        };
+
+        let fmul = op("fmul", None, Type::Float, None);
+        assert_eq!(super::float(&fmul, soft, true), ["__aeabi_fmul"]);
+        assert_eq!(super::float(&fmul, soft, false), ["__mulsf3"]);
+        assert!(super::float(&fmul, m4f, true).is_empty());
+
+        let dmul = op("fmul", None, Type::Double, None);
+        assert_eq!(super::float(&dmul, m4f, true), ["__aeabi_dmul"]);
+
+        let fpext = op("fpext", None, Type::Float, Some(Type::Double));
+        assert_eq!(super::float(&fpext, m4f, true), ["__aeabi_f2d"]);
+
+        // the FPU can't convert to 64-bit integers
+        let f2l = op("fptosi", None, Type::Float, Some(Type::Integer(64)));
+        assert_eq!(super::float(&f2l, m4f, true), ["__aeabi_f2lz"]);
+        let f2i = op("fptosi", None, Type::Float, Some(Type::Integer(8)));
+        assert!(super::float(&f2i, m4f, true).is_empty());
+        assert_eq!(super::float(&f2i, soft, false), ["__fixsfsi"]);
+
+        let u2d = op("uitofp", None, Type::Integer(32), Some(Type::Double));
+        assert_eq!(super::float(&u2d, soft, true), ["__aeabi_ui2d"]);
+
+        let lt = op("fcmp", Some("olt"), Type::Float, None);
+        assert_eq!(super::float(&lt, soft, true), ["__aeabi_fcmplt"]);
+        assert_eq!(super::float(&lt, soft, false), ["__ltsf2"]);
+        assert!(super::float(&lt, m4f, false).is_empty());
+
+        let une = op("fcmp", Some("une"), Type::Double, None);
+        assert_eq!(
+            super::float(&une, soft, true),
+            ["__aeabi_dcmpeq", "__aeabi_dcmpun"]
+        );
+        assert_eq!(super::float(&une, soft, false), ["__nedf2", "__unorddf2"]);
+
+        let uno = op("fcmp", Some("uno"), Type::Float, None);
+        assert_eq!(super::float(&uno, soft, false), ["__unordsf2"]);
+    }
+}
@@ -0,0 +1,125 @@
+//! "Did you mean" suggestions for function names given on the command line
+//!
+//! A candidate is suggested if it contains the query or if it, or the last path segment of it, is
+//! within a few edits of the query. Substring matches rank first, then by edit distance.
+
+/// Maximum number of suggestions
+const MAX: usize = 5;
+
+/// Returns the `candidates` that are similar to `query`, best matches first
+pub fn similar<'a>(query: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let query_ = query.to_lowercase();
+    let threshold = (query.chars().count() / 4).max(1);
+
+    let mut hits = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != query)
+        .filter_map(|candidate| {
+            let lowercase = candidate.to_lowercase();
+            if lowercase.contains(&query_) {
+                return Some((0, candidate));
+            }
+
+            let last = lowercase.rsplit("::").next().unwrap_or(&lowercase);
+            let distance = edit_distance(&query_, &lowercase).min(edit_distance(&query_, last));
+            if distance <= threshold {
+                Some((distance, candidate))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    hits.sort_by(|a, b| (a.0, a.1.len(), a.1).cmp(&(b.0, b.1.len(), b.1)));
+    hits.dedup_by(|a, b| a.1 == b.1);
+    hits.into_iter().take(MAX).map(|(_, hit)| hit).collect()
+}
+
+/// Formats the output of `similar` as a suffix for a "not found" message
+pub fn did_you_mean(hits: &[&str]) -> String {
+    match hits {
+        [] => String::new(),
+        [hit] => format!("; did you mean `{}`?", hit),
+        _ => format!(
+            "; did you mean one of: {}?",
+            hits.iter()
+                .map(|hit| format!("`{}`", hit))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn edit_distance() {
+        assert_eq!(super::edit_distance("", ""), 0);
+        assert_eq!(super::edit_distance("foo", ""), 3);
+        assert_eq!(super::edit_distance("foo", "foo"), 0);
+        assert_eq!(super::edit_distance("kitten", "sitting"), 3);
+        assert_eq!(super::edit_distance("fo", "foo"), 1);
+    }
+
+    #[test]
+    fn similar() {
+        let candidates = [
+            "main",
+            "cycle::foo",
+            "cycle::bar",
+            "cycle::baz",
+            "core::panicking::panic",
+            "rust_begin_unwind",
+        ];
+
+        // typo in the last path segment
+        assert_eq!(
+            super::similar("cycle::fo", candidates.iter().cloned()),
+            ["cycle::foo"]
+        );
+        // missing path
+        assert_eq!(
+            super::similar("fooo", candidates.iter().cloned()),
+            ["cycle::foo"]
+        );
+        // substring matches rank first
+        assert_eq!(
+            super::similar("ba", candidates.iter().cloned()),
+            ["cycle::bar", "cycle::baz"]
+        );
+        // case insensitive
+        assert_eq!(
+            super::similar("PANIC", candidates.iter().cloned()),
+            ["core::panicking::panic"]
+        );
+        assert!(super::similar("reset_handler", candidates.iter().cloned()).is_empty());
+    }
+
+    #[test]
+    fn did_you_mean() {
+        assert_eq!(super::did_you_mean(&[]), "");
+        assert_eq!(super::did_you_mean(&["foo"]), "; did you mean `foo`?");
+        assert_eq!(
+            super::did_you_mean(&["foo", "bar"]),
+            "; did you mean one of: `foo`, `bar`?"
+        );
+    }
+}
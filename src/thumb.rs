@@ -1,30 +1,168 @@
-/// Analyzes a subroutine and returns all the `BL` and `B` instructions in it, plus whether this
-/// function performs an indirect function call or not
-// NOTE we assume that `bytes` is always valid input so all errors are bugs
+//! Thumb (ARMv6-M and ARMv7-M) machine code analysis
+//!
+//! This is the decoder that `cargo-call-stack` uses to find the function calls that don't appear in
+//! the LLVM IR and to compute the stack usage of functions that LLVM doesn't report. It only decodes
+//! the instructions it's interested in: function calls, branches and instructions that modify the
+//! stack pointer.
+
+use std::fmt;
+
+/// A `B` instruction whose target lies within the subroutine, e.g. a conditional or a loop
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Branch {
+    /// Offset of the instruction from the start of the subroutine, in bytes
+    pub site: i32,
+    /// Offset of the branch target from the start of the subroutine, in bytes
+    pub target: i32,
+}
+
+/// A function call
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Call {
+    /// Offset of the instruction from the start of the subroutine, in bytes
+    pub site: i32,
+    /// Offset of the callee from the start of the subroutine, in bytes; `None` for indirect calls
+    /// and supervisor calls
+    pub target: Option<i32>,
+    /// The kind of call
+    pub kind: CallKind,
+}
+
+/// The instruction that performs a function call
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum CallKind {
+    /// `BL`; the callee returns to the subroutine
+    Direct,
+    /// `B` to code outside the subroutine; the callee returns to the caller of the subroutine
+    Tail,
+    /// Conditional `B` to code outside the subroutine
+    ConditionalTail,
+    /// `BLX` or `BX` with a register other than `LR` as its operand
+    Indirect,
+    /// `SVC #imm`; the `SVCall` exception handler performs the system call that the immediate
+    /// identifies
+    Supervisor(u8),
+}
+
+/// An instruction that masks or unmasks interrupts, e.g. at the boundaries of a critical section
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Masking {
+    /// Offset of the instruction from the start of the subroutine, in bytes
+    pub site: i32,
+    /// What the instruction does
+    pub kind: MaskingKind,
+}
+
+/// The instruction that masks or unmasks interrupts
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum MaskingKind {
+    /// `CPSID i` or `CPSID f`; masks interrupts
+    Disable,
+    /// `CPSIE i` or `CPSIE f`; unmasks interrupts
+    Enable,
+    /// `MSR` to `PRIMASK`, `BASEPRI`, `BASEPRI_MAX` or `FAULTMASK`; whether it masks or unmasks
+    /// interrupts depends on the value written to the register
+    Msr,
+}
+
+/// An instruction that points the stack pointer at a different stack
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StackSwitch {
+    /// Offset of the instruction from the start of the subroutine, in bytes
+    pub site: i32,
+    /// What the instruction does
+    pub kind: StackSwitchKind,
+}
+
+/// The instruction that switches stacks
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum StackSwitchKind {
+    /// `MOV SP, Rm`; `R7`, the frame pointer, is not included as restoring the stack pointer from
+    /// it is how functions with a frame pointer deallocate their frame
+    Mov(u8),
+    /// `MSR MSP, Rn`
+    Msp(u8),
+    /// `MSR PSP, Rn`
+    Psp(u8),
+}
+
+/// The result of [`analyze`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct FunctionAnalysis {
+    /// The function calls, in program order
+    pub calls: Vec<Call>,
+    /// The branches within the subroutine, in program order
+    pub branches: Vec<Branch>,
+    /// Whether the subroutine modifies the stack pointer
+    pub modifies_sp: bool,
+    /// The stack usage of the subroutine, in bytes; `None` if the subroutine contains branches
+    /// within itself (conditionals or loops), which this analysis doesn't handle
+    pub stack: Option<u64>,
+    /// The number of bytes that were skipped because they are data (per the `$d` mapping symbols),
+    /// e.g. literal pools and padding
+    pub data: u32,
+    /// The instructions that mask or unmask interrupts, in program order
+    pub masking: Vec<Masking>,
+    /// The instructions that switch to a different stack, in program order
+    pub stack_switches: Vec<StackSwitch>,
+}
+
+impl FunctionAnalysis {
+    /// Whether the subroutine performs an indirect function call
+    pub fn indirect(&self) -> bool {
+        self.calls
+            .iter()
+            .any(|call| call.kind == CallKind::Indirect)
+    }
+}
+
+/// The error returned by [`analyze`] when the machine code can't be decoded
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct UnknownInstruction {
+    /// Address of the instruction
+    pub address: u32,
+    /// The first halfword of the instruction
+    pub halfword: u16,
+}
+
+impl fmt::Display for UnknownInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown instruction {:04x} at address {:#010x}",
+            self.halfword, self.address
+        )
+    }
+}
+
+impl std::error::Error for UnknownInstruction {}
+
+/// Analyzes a subroutine that starts at `address` and returns all the function calls and branches
+/// in it, plus whether it modifies the stack pointer and its stack usage
+///
+/// `v7` enables the ARMv7-M instructions. `big_endian` must be set for big-endian BE-32 images,
+/// whose instructions are stored big-endian, but not for BE-8 images.
+/// `tags` are the addresses of the mapping symbols (`$d` and `$t`), sorted by address; they are
+/// used to skip the data embedded in the code (e.g. literal pools).
+///
+/// # Errors
+///
+/// This function assumes that `bytes` is valid Thumb machine code and returns an error if it finds
+/// an instruction it doesn't know about, e.g. a 32-bit instruction cut off by the end of `bytes`
 // Reference: ARMv7-M Architecture Reference Manual (ARM DDI 0403E.b)
 // Reference: ARMv6-M Architecture Reference Manual (ARM DDI 0419D)
 pub fn analyze(
     bytes: &[u8],
     address: u32,
     v7: bool,
+    big_endian: bool,
     tags: &[(u32, Tag)],
-) -> (Vec<i32>, Vec<i32>, bool, bool, Option<u64>) {
-    macro_rules! bug {
-        ($first:expr) => {
-            panic!(
-                "BUG: unknown instruction {:02x}{:02x}",
-                $first[1], $first[0]
-            )
-        };
-
-        ($first:expr, $second:expr) => {
-            panic!(
-                "BUG: unknown instruction {:02x}{:02x} {:02x}{:02x}",
-                $first[1], $first[0], $second[1], $second[0]
-            )
-        };
-    }
-
+) -> Result<FunctionAnalysis, UnknownInstruction> {
     // we want to know if any of the instructions modifies the SP (stack pointer). We use this
     // information to determine if the subroutine uses stack space or not. We want to detect the
     // following instructions:
@@ -46,9 +184,25 @@ pub fn analyze(
     // of instruction. We know that instructions can be 16-bit or 32-bit so we'll only decode 16-bit
     // instructions and assume that the rest are 32-bit instructions.
     // NOTE this implementation has been optimized to be easy to write, not to be high-performance
-    let mut bls = vec![];
-    let mut bs = vec![];
-    let mut indirect = false;
+    let mut calls = vec![];
+    let mut branches = vec![];
+    let mut masking = vec![];
+    let mut stack_switches = vec![];
+    // the decoder below expects little-endian halfwords; big-endian (BE-32) images store them with
+    // their bytes swapped
+    let swapped;
+    let bytes = if big_endian {
+        swapped = bytes
+            .chunks_exact(2)
+            .flat_map(|halfword| [halfword[1], halfword[0]])
+            .collect::<Vec<_>>();
+        &swapped[..]
+    } else {
+        bytes
+    };
+
+    let mut data = 0;
+    let end_of_subroutine = address + bytes.len() as u32;
     let mut halfwords = bytes.chunks_exact(2).zip(0i32..);
     while let Some((first, i)) = halfwords.next() {
         let start = address + 2 * i as u32;
@@ -66,6 +220,7 @@ pub fn analyze(
 
                     // skip the data section
                     let end = tag.0;
+                    data += end.min(end_of_subroutine) - start;
                     // NOTE the range starts at 1 because we'll skip `first` using the `continue`
                     // that comes after this `for` loop
                     for _ in 1..(end - start) / 2 {
@@ -76,6 +231,7 @@ pub fn analyze(
                 } else {
                     // continues until the end of the binary; we won't find more instructions so
                     // let's stop decoding
+                    data += end_of_subroutine - start;
                     break;
                 }
             }
@@ -129,6 +285,11 @@ pub fn analyze(
             // NOTE we break the alphabetical order because the rule for `B` overlaps with the rule
             // for `SVC` but `SVC` takes precedence
             // A7.7.175      SVC - T1
+            calls.push(Call {
+                site: 2 * i,
+                target: None,
+                kind: CallKind::Supervisor(first[0]),
+            });
             continue;
         } else if matches(first, "0b1101_xxxx_xxxxxxxx") {
             // A7.7.12  B - T1
@@ -146,9 +307,18 @@ pub fn analyze(
             if imm32 >= 0 && (imm32 as usize) < bytes.len() {
                 // this is an `if` or `loop`; give up the stack usage analysis
                 stack = None;
-            }
 
-            bs.push(imm32);
+                branches.push(Branch {
+                    site: 2 * i,
+                    target: imm32,
+                });
+            } else {
+                calls.push(Call {
+                    site: 2 * i,
+                    target: Some(imm32),
+                    kind: CallKind::ConditionalTail,
+                });
+            }
         } else if matches(first, "0b11100_xxxxxxxxxxx") {
             // A7.7.12  B - T2
             let imm11 = (i32::from(first[1] & 0b111) << 8) | first[0] as i32;
@@ -162,9 +332,18 @@ pub fn analyze(
             if imm32 >= 0 && (imm32 as usize) < bytes.len() {
                 // this is an `if` or `loop`; give up the stack usage analysis
                 stack = None;
-            }
 
-            bs.push(imm32);
+                branches.push(Branch {
+                    site: 2 * i,
+                    target: imm32,
+                });
+            } else {
+                calls.push(Call {
+                    site: 2 * i,
+                    target: Some(imm32),
+                    kind: CallKind::Tail,
+                });
+            }
         } else if matches(first, "0b010000_1110_xxx_xxx") {
             // A7.7.16  BIC (register) - T1
             continue;
@@ -173,14 +352,22 @@ pub fn analyze(
             continue;
         } else if matches(first, "0b010001_11_1_xxxx_000") {
             // A7.7.19  BLX (register) - T1
-            indirect = true;
+            calls.push(Call {
+                site: 2 * i,
+                target: None,
+                kind: CallKind::Indirect,
+            });
         } else if matches(first, "0b010001_11_0_xxxx_000") {
             // A7.7.20  BX - T1
             let rm = (first[0] >> 3) & 0b1111;
 
             // `bx lr` is just a `return`
             if rm != 0b1110 {
-                indirect = true;
+                calls.push(Call {
+                    site: 2 * i,
+                    target: None,
+                    kind: CallKind::Indirect,
+                });
             }
         } else if v7 && matches(first, "0b1011_x_0_x_1_xxxxx_xxx") {
             // A7.7.21  CBNZ, CBZ - T1
@@ -199,6 +386,18 @@ pub fn analyze(
             continue;
         } else if matches(first, "0b1011_0110_011_x_00_xx") {
             // A7.7.29  CPS - T1
+            let disable = first[0] & (1 << 4) != 0;
+            // the `I` (PRIMASK) and `F` (FAULTMASK) bits
+            if first[0] & 0b11 != 0 {
+                masking.push(Masking {
+                    site: 2 * i,
+                    kind: if disable {
+                        MaskingKind::Disable
+                    } else {
+                        MaskingKind::Enable
+                    },
+                });
+            }
             continue;
         } else if matches(first, "0b010000_0001_xxx_xxx") {
             // A7.7.35  EOR (register) - T1
@@ -256,6 +455,14 @@ pub fn analyze(
             continue;
         } else if matches(first, "0b010001_10_x_xxxx_xxx") {
             // A7.7.76  MOV (register) - T1
+            let rd = ((first[0] >> 4) & 0b1000) | (first[0] & 0b111);
+            let rm = (first[0] >> 3) & 0b1111;
+            if rd == 13 && rm != 7 && rm != 13 {
+                stack_switches.push(StackSwitch {
+                    site: 2 * i,
+                    kind: StackSwitchKind::Mov(rm),
+                });
+            }
             continue;
         } else if matches(first, "0b000_00_00000_xxx_xxx") {
             // A7.7.76  MOV (register) - T2
@@ -381,11 +588,38 @@ pub fn analyze(
             // A7.7.260      YIELD - T1
             continue;
         } else {
-            let second = halfwords.next().unwrap_or_else(|| bug!(first)).0;
+            let second = halfwords
+                .next()
+                .ok_or(UnknownInstruction {
+                    address: start,
+                    halfword: u16::from_le_bytes([first[0], first[1]]),
+                })?
+                .0;
 
             const SP: u8 = 0b1101;
 
-            if v7
+            if matches(first, "0b11110_0_11100_0_xxxx")
+                && matches(second, "0b10_0_0_xx_00_xxxxxxxx")
+            {
+                // A7.7.83  MSR - T1
+                let rn = first[0] & 0b1111;
+                match second[0] {
+                    // PRIMASK, BASEPRI, BASEPRI_MAX and FAULTMASK
+                    16..=19 => masking.push(Masking {
+                        site: 2 * i,
+                        kind: MaskingKind::Msr,
+                    }),
+                    8 => stack_switches.push(StackSwitch {
+                        site: 2 * i,
+                        kind: StackSwitchKind::Msp(rn),
+                    }),
+                    9 => stack_switches.push(StackSwitch {
+                        site: 2 * i,
+                        kind: StackSwitchKind::Psp(rn),
+                    }),
+                    _ => {}
+                }
+            } else if v7
                 && matches(first, "0b11101_00_100_x_0_xxxx")
                 && matches(second, "0b0_x_0_xxxxxxxxxxxxx")
             {
@@ -431,7 +665,7 @@ pub fn analyze(
                 // A7.7.249      VPUSH - T1
                 modifies_sp = true;
 
-                let imm8 = second[0] & 0b1111_1111;
+                let imm8 = second[0];
                 let imm32 = u32::from(imm8) << 2;
 
                 if let Some(stack) = stack.as_mut() {
@@ -444,7 +678,7 @@ pub fn analyze(
                 // A7.7.249      VPUSH - T2
                 modifies_sp = true;
 
-                let imm8 = second[0] & 0b1111_1111;
+                let imm8 = second[0];
                 let imm32 = u32::from(imm8) << 2;
 
                 if let Some(stack) = stack.as_mut() {
@@ -482,9 +716,18 @@ pub fn analyze(
                 if imm32 >= 0 && (imm32 as usize) < bytes.len() {
                     // this is an `if` or `loop`; give up the stack usage analysis
                     stack = None;
-                }
 
-                bs.push(imm32);
+                    branches.push(Branch {
+                        site: 2 * i,
+                        target: imm32,
+                    });
+                } else {
+                    calls.push(Call {
+                        site: 2 * i,
+                        target: Some(imm32),
+                        kind: CallKind::ConditionalTail,
+                    });
+                }
             } else if v7
                 && matches(first, "0b11110_x_xxxxxxxxxx")
                 && matches(second, "0b10_x_1_x_xxxxxxxxxxx")
@@ -515,9 +758,18 @@ pub fn analyze(
                 if imm32 >= 0 && (imm32 as usize) < bytes.len() {
                     // this is an `if` or `loop`; give up the stack usage analysis
                     stack = None;
-                }
 
-                bs.push(imm32);
+                    branches.push(Branch {
+                        site: 2 * i,
+                        target: imm32,
+                    });
+                } else {
+                    calls.push(Call {
+                        site: 2 * i,
+                        target: Some(imm32),
+                        kind: CallKind::Tail,
+                    });
+                }
             } else if matches(first, "0b11110_x_xxxxxxxxxx")
                 && matches(second, "0b11_x_1_x_xxxxxxxxxxx")
             {
@@ -543,7 +795,11 @@ pub fn analyze(
                 // accordingly
                 imm32 += 2 * i + 4;
 
-                bls.push(imm32);
+                calls.push(Call {
+                    site: 2 * i,
+                    target: Some(imm32),
+                    kind: CallKind::Direct,
+                });
             } else if matches(first, "0b11111_0000100_xxxx")
                 && matches(second, "0bxxxx_1x01_xxxxxxxx")
             {
@@ -554,7 +810,7 @@ pub fn analyze(
                 if rn == SP {
                     modifies_sp = true;
 
-                    let imm8 = second[0] & 0b1111_1111;
+                    let imm8 = second[0];
                     let imm32 = u32::from(imm8);
 
                     if let Some(stack) = stack.as_mut() {
@@ -568,7 +824,15 @@ pub fn analyze(
         }
     }
 
-    (bls, bs, indirect, modifies_sp, stack)
+    Ok(FunctionAnalysis {
+        calls,
+        branches,
+        modifies_sp,
+        stack,
+        data,
+        masking,
+        stack_switches,
+    })
 }
 
 fn matches(bytes: &[u8], pattern: &str) -> bool {
@@ -620,76 +884,316 @@ fn thumb_expand_imm(imm12: u16) -> u32 {
     }
 }
 
+/// A mapping symbol, which marks the start of a sequence of data or instructions
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Tag {
-    // symbol with name `$d.123` used as a tag
+    /// Symbol with name `$d.123`: data
     Data,
 
-    // symbol with name `$t.123` used as a tag
+    /// Symbol with name `$t.123`: Thumb instructions
     Thumb,
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{
+        Branch, Call, CallKind, FunctionAnalysis, Masking, MaskingKind, UnknownInstruction,
+    };
+
     #[test]
     fn sanity() {
         assert_eq!(
-            super::analyze(&[0xff, 0xf7, 0xe4, 0xfe], 0, false, &[]).0,
-            vec![-568 + 4]
+            super::analyze(&[0xff, 0xf7, 0xe4, 0xfe], 0, false, false, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: Some(-568 + 4),
+                kind: CallKind::Direct,
+            }]
         );
 
         assert_eq!(
-            super::analyze(&[0x00, 0xf0, 0x2a, 0xfa], 0, false, &[]).0,
-            vec![1108 + 4]
+            super::analyze(&[0x00, 0xf0, 0x2a, 0xfa], 0, false, false, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: Some(1108 + 4),
+                kind: CallKind::Direct,
+            }]
         );
 
         assert_eq!(
-            super::analyze(&[0x03, 0xe2], 0, false, &[]).1,
-            vec![1030 + 4]
+            super::analyze(&[0x03, 0xe2], 0, false, false, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: Some(1030 + 4),
+                kind: CallKind::Tail,
+            }]
         );
 
         // UDF
         assert_eq!(
-            super::analyze(&[0xfe, 0xde], 0, true, &[]),
-            (vec![], vec![], false, false, Some(0))
+            super::analyze(&[0xfe, 0xde], 0, true, false, &[]).unwrap(),
+            FunctionAnalysis {
+                calls: vec![],
+                branches: vec![],
+                modifies_sp: false,
+                stack: Some(0),
+                data: 0,
+                masking: vec![],
+                stack_switches: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn data() {
+        use super::Tag;
+
+        // 4801            ldr     r0, [pc, #4]
+        // 4770            bx      lr
+        // 0000            (padding)
+        // deadbeef        .word   0xdeadbeef
+        // 4770            bx      lr
+        let bytes = [
+            0x01, 0x48, 0x70, 0x47, 0x00, 0x00, 0xef, 0xbe, 0xad, 0xde, 0x70, 0x47,
+        ];
+        let tags = [(0x104, Tag::Data), (0x10a, Tag::Thumb)];
+        let analysis = super::analyze(&bytes, 0x100, false, false, &tags).unwrap();
+        assert_eq!(analysis.data, 6);
+
+        // the data extends past the end of the subroutine
+        let analysis = super::analyze(&bytes[..6], 0x100, false, false, &tags).unwrap();
+        assert_eq!(analysis.data, 2);
+
+        // the data continues until the end of the binary
+        let analysis = super::analyze(&bytes, 0x100, false, false, &tags[..1]).unwrap();
+        assert_eq!(analysis.data, 8);
+    }
+
+    #[test]
+    fn call_kinds() {
+        // 4798            blx     r3
+        // 4770            bx      lr
+        let blx = super::analyze(&[0x98, 0x47, 0x70, 0x47], 0, false, false, &[]).unwrap();
+        assert_eq!(
+            blx.calls,
+            vec![Call {
+                site: 0,
+                target: None,
+                kind: CallKind::Indirect,
+            }]
+        );
+        assert!(blx.indirect());
+
+        // d010            beq.n   #36
+        assert_eq!(
+            super::analyze(&[0x10, 0xd0], 0, false, false, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: Some(36),
+                kind: CallKind::ConditionalTail,
+            }]
+        );
+
+        // df03            svc     3
+        assert_eq!(
+            super::analyze(&[0x03, 0xdf], 0, false, false, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: None,
+                kind: CallKind::Supervisor(3),
+            }]
+        );
+
+        // bf00            nop
+        // e7fd            b.n     #0
+        let loop_ = super::analyze(&[0x00, 0xbf, 0xfd, 0xe7], 0, false, false, &[]).unwrap();
+        assert_eq!(loop_.calls, vec![]);
+        assert_eq!(loop_.branches, vec![Branch { site: 2, target: 0 }]);
+        assert_eq!(loop_.stack, None);
+    }
+
+    #[test]
+    fn big_endian() {
+        // f7ff fee4       bl      #-568
+        assert_eq!(
+            super::analyze(&[0xf7, 0xff, 0xfe, 0xe4], 0, false, true, &[])
+                .unwrap()
+                .calls,
+            vec![Call {
+                site: 0,
+                target: Some(-568 + 4),
+                kind: CallKind::Direct,
+            }]
+        );
+
+        // b081            sub     sp, #4
+        assert_eq!(
+            super::analyze(&[0xb0, 0x81], 0, false, true, &[])
+                .unwrap()
+                .stack,
+            Some(4)
         );
     }
 
     #[test]
     fn modifies_sp() {
         // bf00            nop
-        let nop = super::analyze(&[0x00, 0xbf], 0, false, &[]);
-        assert!(!nop.3);
-        assert_eq!(nop.4, Some(0));
+        let nop = super::analyze(&[0x00, 0xbf], 0, false, false, &[]).unwrap();
+        assert!(!nop.modifies_sp);
+        assert_eq!(nop.stack, Some(0));
 
         // b081            sub     sp, #4
-        let sub = super::analyze(&[0x81, 0xb0], 0, false, &[]);
-        assert!(sub.3);
-        assert_eq!(sub.4, Some(4));
+        let sub = super::analyze(&[0x81, 0xb0], 0, false, false, &[]).unwrap();
+        assert!(sub.modifies_sp);
+        assert_eq!(sub.stack, Some(4));
 
         // b580            push    {r7, lr}
-        let push = super::analyze(&[0x80, 0xb5], 0, false, &[]);
-        assert!(push.3);
-        assert_eq!(push.4, Some(8));
+        let push = super::analyze(&[0x80, 0xb5], 0, false, false, &[]).unwrap();
+        assert!(push.modifies_sp);
+        assert_eq!(push.stack, Some(8));
 
         // e92d 41f0       stmdb   sp!, {r4, r5, r6, r7, r8, lr}
-        let stmdb = super::analyze(&[0x2d, 0xe9, 0xf0, 0x41], 0, true, &[]);
-        assert!(stmdb.3);
-        assert_eq!(stmdb.4, Some(24));
+        let stmdb = super::analyze(&[0x2d, 0xe9, 0xf0, 0x41], 0, true, false, &[]).unwrap();
+        assert!(stmdb.modifies_sp);
+        assert_eq!(stmdb.stack, Some(24));
 
         // ed2d 8b02       vpush   {d8}
-        let vpush = super::analyze(&[0x2d, 0xed, 0x02, 0x8b], 0, true, &[]);
-        assert!(vpush.3);
-        assert_eq!(vpush.4, Some(8));
+        let vpush = super::analyze(&[0x2d, 0xed, 0x02, 0x8b], 0, true, false, &[]).unwrap();
+        assert!(vpush.modifies_sp);
+        assert_eq!(vpush.stack, Some(8));
 
         // f5ad 7d02       sub.w   sp, sp, #520    ; 0x208
-        let subw = super::analyze(&[0xad, 0xf5, 0x02, 0x7d], 0, true, &[]);
-        assert!(subw.3);
-        assert_eq!(subw.4, Some(520));
+        let subw = super::analyze(&[0xad, 0xf5, 0x02, 0x7d], 0, true, false, &[]).unwrap();
+        assert!(subw.modifies_sp);
+        assert_eq!(subw.stack, Some(520));
 
         // f84d bd04       str     r11, [sp, #-4]!
-        let str = super::analyze(&[0x4d, 0xf8, 0x04, 0xbd], 0, true, &[]);
-        assert!(str.3);
-        assert_eq!(str.4, Some(4));
+        let str = super::analyze(&[0x4d, 0xf8, 0x04, 0xbd], 0, true, false, &[]).unwrap();
+        assert!(str.modifies_sp);
+        assert_eq!(str.stack, Some(4));
+    }
+
+    #[test]
+    fn masking() {
+        // b672            cpsid   i
+        // b662            cpsie   i
+        let cps = super::analyze(&[0x72, 0xb6, 0x62, 0xb6], 0, false, false, &[]).unwrap();
+        assert_eq!(
+            cps.masking,
+            vec![
+                Masking {
+                    site: 0,
+                    kind: MaskingKind::Disable,
+                },
+                Masking {
+                    site: 2,
+                    kind: MaskingKind::Enable,
+                }
+            ]
+        );
+
+        // f380 8811       msr     BASEPRI, r0
+        // f380 8809       msr     PSP, r0
+        let msr = super::analyze(
+            &[0x80, 0xf3, 0x11, 0x88, 0x80, 0xf3, 0x09, 0x88],
+            0,
+            true,
+            false,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            msr.masking,
+            vec![Masking {
+                site: 0,
+                kind: MaskingKind::Msr,
+            }]
+        );
+        assert_eq!(msr.calls, vec![]);
+
+        // f380 8810       msr     PRIMASK, r0
+        let primask = super::analyze(&[0x80, 0xf3, 0x10, 0x88], 0, false, false, &[]).unwrap();
+        assert_eq!(primask.masking.len(), 1);
+    }
+
+    #[test]
+    fn stack_switches() {
+        use super::{StackSwitch, StackSwitchKind};
+
+        // 466c            mov     r4, sp
+        // 4685            mov     sp, r0
+        // 46a5            mov     sp, r4
+        // 46bd            mov     sp, r7
+        let mov = super::analyze(
+            &[0x6c, 0x46, 0x85, 0x46, 0xa5, 0x46, 0xbd, 0x46],
+            0,
+            false,
+            false,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            mov.stack_switches,
+            vec![
+                StackSwitch {
+                    site: 2,
+                    kind: StackSwitchKind::Mov(0),
+                },
+                StackSwitch {
+                    site: 4,
+                    kind: StackSwitchKind::Mov(4),
+                }
+            ]
+        );
+
+        // f381 8808       msr     MSP, r1
+        // f380 8809       msr     PSP, r0
+        // f380 8811       msr     BASEPRI, r0
+        let msr = super::analyze(
+            &[
+                0x81, 0xf3, 0x08, 0x88, 0x80, 0xf3, 0x09, 0x88, 0x80, 0xf3, 0x11, 0x88,
+            ],
+            0,
+            true,
+            false,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            msr.stack_switches,
+            vec![
+                StackSwitch {
+                    site: 0,
+                    kind: StackSwitchKind::Msp(1),
+                },
+                StackSwitch {
+                    site: 4,
+                    kind: StackSwitchKind::Psp(0),
+                }
+            ]
+        );
+        assert_eq!(msr.masking.len(), 1);
+    }
+
+    #[test]
+    fn truncated() {
+        // the first halfword of `BL` without the second one
+        assert_eq!(
+            super::analyze(&[0x00, 0xbf, 0xff, 0xf7], 0x100, false, false, &[]),
+            Err(UnknownInstruction {
+                address: 0x102,
+                halfword: 0xf7ff,
+            })
+        );
     }
 }
@@ -0,0 +1,911 @@
+//! Analysis of WebAssembly modules
+//!
+//! LLVM doesn't emit stack usage information for the wasm32 targets so we derive it from the
+//! machine code. WebAssembly has two stacks: the operand stack, which is managed by the engine, and
+//! the "shadow" stack, which lives in linear memory and whose size is chosen when the module is
+//! linked. We are interested in the latter. Functions allocate space on the shadow stack using this
+//! instruction sequence:
+//!
+//! ``` text
+//! global.get $__stack_pointer
+//! i32.const 16
+//! i32.sub
+//! (..)
+//! global.set $__stack_pointer
+//! ```
+//!
+//! Reference: WebAssembly Core Specification, version 2.0
+
+use core::fmt;
+use std::str;
+
+use anyhow::{anyhow, bail};
+
+/// A WebAssembly module
+#[derive(Debug)]
+pub struct Module<'a> {
+    /// Function signatures (type section)
+    pub types: Vec<FuncType>,
+    /// All the functions in the module; imported functions come first
+    pub functions: Vec<Function<'a>>,
+    /// Indices of the functions that can be called through a table (`call_indirect`)
+    pub table: Vec<u32>,
+    /// Exported functions: name and function index
+    pub exports: Vec<(&'a str, u32)>,
+}
+
+#[derive(Debug)]
+pub struct Function<'a> {
+    /// Name from the "name" custom section, if available
+    pub name: Option<&'a str>,
+    /// Index into `Module.types`
+    pub ty: u32,
+    /// Imported functions have no body
+    pub imported: bool,
+    /// Shadow stack usage, in bytes
+    pub stack: Option<u64>,
+    /// Indices of the functions invoked with `call`
+    pub calls: Vec<u32>,
+    /// Type indices of the `call_indirect` instructions
+    pub indirect_calls: Vec<u32>,
+    /// Why the body couldn't be decoded, e.g. it uses an unsupported instruction; if set, the
+    /// stack usage and the callees of the function are unknown
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+impl fmt::Display for ValType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F32 => "f32",
+            ValType::F64 => "f64",
+            ValType::V128 => "v128",
+            ValType::FuncRef => "funcref",
+            ValType::ExternRef => "externref",
+        })
+    }
+}
+
+// uses the same notation as `ir::FnSig`
+impl fmt::Display for FuncType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.results[..] {
+            [] => f.write_str("void")?,
+            [result] => write!(f, "{}", result)?,
+            results => {
+                f.write_str("{ ")?;
+                for (i, result) in results.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", result)?;
+                }
+                f.write_str(" }")?;
+            }
+        }
+
+        f.write_str(" (")?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", param)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Parses a WebAssembly module and analyzes all its function bodies
+pub fn analyze(bytes: &[u8]) -> anyhow::Result<Module<'_>> {
+    let mut r = Reader::new(bytes);
+
+    if r.bytes(4)? != b"\0asm" {
+        bail!("not a WebAssembly module");
+    }
+
+    if r.bytes(4)? != [1, 0, 0, 0] {
+        bail!("unsupported WebAssembly version");
+    }
+
+    let mut types = vec![];
+    let mut functions = vec![];
+    let mut globals = 0;
+    let mut imported_globals = 0;
+    let mut table = vec![];
+    let mut exports = vec![];
+    let mut bodies = vec![];
+    let mut function_names = vec![];
+    let mut global_names = vec![];
+
+    while !r.is_empty() {
+        let id = r.u8()?;
+        let size = r.u32()? as usize;
+        let mut s = Reader::new(r.bytes(size)?);
+
+        match id {
+            // custom section
+            0 => {
+                let name = s.name()?;
+                if name == "name" {
+                    // a malformed name section is not fatal; we just won't have names
+                    let _ = names(&mut s, &mut function_names, &mut global_names);
+                }
+            }
+
+            // type section
+            1 => {
+                for _ in 0..s.u32()? {
+                    if s.u8()? != 0x60 {
+                        bail!("malformed function type");
+                    }
+
+                    let params = s.val_types()?;
+                    let results = s.val_types()?;
+                    types.push(FuncType { params, results });
+                }
+            }
+
+            // import section
+            2 => {
+                for _ in 0..s.u32()? {
+                    let _module = s.name()?;
+                    let name = s.name()?;
+
+                    match s.u8()? {
+                        // function
+                        0x00 => functions.push(Function {
+                            name: Some(name),
+                            ty: s.u32()?,
+                            imported: true,
+                            stack: None,
+                            calls: vec![],
+                            indirect_calls: vec![],
+                            error: None,
+                        }),
+
+                        // table
+                        0x01 => {
+                            s.u8()?;
+                            s.limits()?;
+                        }
+
+                        // memory
+                        0x02 => s.limits()?,
+
+                        // global
+                        0x03 => {
+                            s.u8()?;
+                            s.u8()?;
+                            imported_globals += 1;
+                        }
+
+                        _ => bail!("malformed import"),
+                    }
+                }
+            }
+
+            // function section
+            3 => {
+                for _ in 0..s.u32()? {
+                    functions.push(Function {
+                        name: None,
+                        ty: s.u32()?,
+                        imported: false,
+                        stack: None,
+                        calls: vec![],
+                        indirect_calls: vec![],
+                        error: None,
+                    });
+                }
+            }
+
+            // global section
+            6 => globals += s.u32()?,
+
+            // export section
+            7 => {
+                for _ in 0..s.u32()? {
+                    let name = s.name()?;
+                    let kind = s.u8()?;
+                    let index = s.u32()?;
+
+                    if kind == 0x00 {
+                        exports.push((name, index));
+                    }
+                }
+            }
+
+            // element section
+            9 => {
+                for _ in 0..s.u32()? {
+                    elements(&mut s, &mut table)?;
+                }
+            }
+
+            // code section
+            10 => {
+                for _ in 0..s.u32()? {
+                    let size = s.u32()? as usize;
+                    bodies.push(s.bytes(size)?);
+                }
+            }
+
+            // table, memory, start, data count and data sections
+            _ => {}
+        }
+    }
+
+    let imported = functions.iter().filter(|f| f.imported).count();
+    if imported + bodies.len() != functions.len() {
+        bail!("the number of function bodies doesn't match the number of functions");
+    }
+
+    for (index, name) in function_names {
+        if let Some(function) = functions.get_mut(index as usize) {
+            function.name = Some(name);
+        }
+    }
+
+    // the stack pointer is, by convention, the first (mutable) global. Imported globals come first
+    // in the index space so, in position independent code or with shared memory, global 0 is
+    // something else, e.g. `__memory_base`, and the stack pointer can only be found by name
+    let stack_pointer = global_names
+        .iter()
+        .find(|(_, name)| *name == "__stack_pointer")
+        .map(|(index, _)| *index)
+        .or(if imported_globals == 0 && globals != 0 {
+            Some(0)
+        } else {
+            None
+        });
+
+    // a body we can't decode, e.g. one that uses an instruction we don't know about, doesn't
+    // invalidate the analysis of the other functions
+    for (function, body) in functions[imported..].iter_mut().zip(bodies) {
+        if let Err(e) = code(body, stack_pointer, function) {
+            function.stack = None;
+            function.calls.clear();
+            function.indirect_calls.clear();
+            function.error = Some(e.to_string());
+        }
+    }
+
+    table.sort();
+    table.dedup();
+
+    Ok(Module {
+        types,
+        functions,
+        table,
+        exports,
+    })
+}
+
+fn names<'a>(
+    s: &mut Reader<'a>,
+    functions: &mut Vec<(u32, &'a str)>,
+    globals: &mut Vec<(u32, &'a str)>,
+) -> anyhow::Result<()> {
+    while !s.is_empty() {
+        let id = s.u8()?;
+        let size = s.u32()? as usize;
+        let mut ss = Reader::new(s.bytes(size)?);
+
+        let map = match id {
+            1 => &mut *functions,
+            7 => &mut *globals,
+            _ => continue,
+        };
+
+        for _ in 0..ss.u32()? {
+            let index = ss.u32()?;
+            let name = ss.name()?;
+            map.push((index, name));
+        }
+    }
+
+    Ok(())
+}
+
+// parses an element segment and collects the function indices it contains
+fn elements(s: &mut Reader, table: &mut Vec<u32>) -> anyhow::Result<()> {
+    let flags = s.u32()?;
+
+    if flags & 0b001 == 0 {
+        // active segment
+        if flags & 0b010 != 0 {
+            // table index
+            s.u32()?;
+        }
+
+        // offset
+        const_expr(s)?;
+    }
+
+    let expressions = flags & 0b100 != 0;
+    if flags & 0b011 != 0 {
+        // `elemkind` or `reftype`
+        s.u8()?;
+    }
+
+    for _ in 0..s.u32()? {
+        if expressions {
+            if let Some(index) = const_expr(s)? {
+                table.push(index);
+            }
+        } else {
+            table.push(s.u32()?);
+        }
+    }
+
+    Ok(())
+}
+
+// parses a constant expression; returns the function index if the expression is a `ref.func`
+fn const_expr(s: &mut Reader) -> anyhow::Result<Option<u32>> {
+    let mut func = None;
+
+    loop {
+        match s.u8()? {
+            // end
+            0x0b => return Ok(func),
+            // i32.const
+            0x41 => drop(s.i64()?),
+            // i64.const
+            0x42 => drop(s.i64()?),
+            // f32.const
+            0x43 => drop(s.bytes(4)?),
+            // f64.const
+            0x44 => drop(s.bytes(8)?),
+            // global.get
+            0x23 => drop(s.u32()?),
+            // ref.null
+            0xd0 => drop(s.u8()?),
+            // ref.func
+            0xd2 => func = Some(s.u32()?),
+            opcode => bail!("unexpected opcode {:#04x} in constant expression", opcode),
+        }
+    }
+}
+
+// decodes a function body
+fn code(body: &[u8], stack_pointer: Option<u32>, function: &mut Function) -> anyhow::Result<()> {
+    let mut s = Reader::new(body);
+
+    // locals
+    for _ in 0..s.u32()? {
+        s.u32()?;
+        s.u8()?;
+    }
+
+    // the last two instructions; used to match the prologue
+    let mut window = [Insn::Other; 2];
+    let mut frame: Option<u64> = None;
+    let mut modifies_sp = false;
+    let mut unknown_frame = false;
+
+    while !s.is_empty() {
+        let insn = instruction(&mut s, function)?;
+
+        match (window[0], window[1], insn) {
+            (Insn::GlobalGet(sp), Insn::I32Const(n), Insn::I32Sub) if Some(sp) == stack_pointer => {
+                if n >= 0 {
+                    frame = Some(frame.unwrap_or(0).max(n as u64));
+                } else {
+                    unknown_frame = true;
+                }
+            }
+
+            _ => {}
+        }
+
+        // if we don't know which global is the stack pointer any `global.set` may be modifying it
+        if let Insn::GlobalSet(global) = insn {
+            if stack_pointer.is_none() || stack_pointer == Some(global) {
+                modifies_sp = true;
+            }
+        }
+
+        window = [window[1], insn];
+    }
+
+    function.stack = if !modifies_sp {
+        Some(0)
+    } else if unknown_frame {
+        None
+    } else {
+        // if the stack pointer is modified but we didn't find the prologue the function probably
+        // allocates a dynamically sized frame
+        frame
+    };
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Insn {
+    GlobalGet(u32),
+    GlobalSet(u32),
+    I32Const(i64),
+    I32Sub,
+    Other,
+}
+
+// decodes a single instruction and records it in `function` if it's a call
+fn instruction(s: &mut Reader, function: &mut Function) -> anyhow::Result<Insn> {
+    let opcode = s.u8()?;
+
+    Ok(match opcode {
+        // block, loop, if
+        0x02..=0x04 => {
+            s.block_type()?;
+            Insn::Other
+        }
+
+        // br, br_if
+        0x0c | 0x0d => {
+            s.u32()?;
+            Insn::Other
+        }
+
+        // br_table
+        0x0e => {
+            for _ in 0..s.u32()? {
+                s.u32()?;
+            }
+            s.u32()?;
+            Insn::Other
+        }
+
+        // call
+        0x10 => {
+            function.calls.push(s.u32()?);
+            Insn::Other
+        }
+
+        // call_indirect
+        0x11 => {
+            function.indirect_calls.push(s.u32()?);
+            // table index
+            s.u32()?;
+            Insn::Other
+        }
+
+        // select t*
+        0x1c => {
+            for _ in 0..s.u32()? {
+                s.u8()?;
+            }
+            Insn::Other
+        }
+
+        // local.get, local.set, local.tee
+        0x20..=0x22 => {
+            s.u32()?;
+            Insn::Other
+        }
+
+        // global.get
+        0x23 => Insn::GlobalGet(s.u32()?),
+
+        // global.set
+        0x24 => Insn::GlobalSet(s.u32()?),
+
+        // table.get, table.set
+        0x25 | 0x26 => {
+            s.u32()?;
+            Insn::Other
+        }
+
+        // loads and stores
+        0x28..=0x3e => {
+            s.mem_arg()?;
+            Insn::Other
+        }
+
+        // memory.size, memory.grow
+        0x3f | 0x40 => {
+            s.u8()?;
+            Insn::Other
+        }
+
+        // i32.const
+        0x41 => Insn::I32Const(s.i64()?),
+
+        // i64.const
+        0x42 => {
+            s.i64()?;
+            Insn::Other
+        }
+
+        // f32.const
+        0x43 => {
+            s.bytes(4)?;
+            Insn::Other
+        }
+
+        // f64.const
+        0x44 => {
+            s.bytes(8)?;
+            Insn::Other
+        }
+
+        // i32.sub
+        0x6b => Insn::I32Sub,
+
+        // ref.null
+        0xd0 => {
+            s.u8()?;
+            Insn::Other
+        }
+
+        // ref.func
+        0xd2 => {
+            s.u32()?;
+            Insn::Other
+        }
+
+        0xfc => {
+            match s.u32()? {
+                // saturating truncation
+                0..=7 => {}
+                // memory.init
+                8 => {
+                    s.u32()?;
+                    s.u8()?;
+                }
+                // data.drop, elem.drop, table.grow, table.size, table.fill
+                9 | 13 | 15..=17 => {
+                    s.u32()?;
+                }
+                // memory.copy
+                10 => {
+                    s.u8()?;
+                    s.u8()?;
+                }
+                // memory.fill
+                11 => {
+                    s.u8()?;
+                }
+                // table.init, table.copy
+                12 | 14 => {
+                    s.u32()?;
+                    s.u32()?;
+                }
+                subopcode => bail!("unknown instruction 0xfc {}", subopcode),
+            }
+            Insn::Other
+        }
+
+        0xfd => simd(s)?,
+
+        0xfe => {
+            // atomic.fence has a single 0x00 byte as immediate; the rest take a memarg
+            if s.u32()? == 3 {
+                s.u8()?;
+            } else {
+                s.mem_arg()?;
+            }
+            Insn::Other
+        }
+
+        // unreachable, nop, else, end, return, drop, select, numeric instructions, ref.is_null
+        0x00 | 0x01 | 0x05 | 0x0b | 0x0f | 0x1a | 0x1b | 0x45..=0xc4 | 0xd1 => Insn::Other,
+
+        _ => bail!("unknown instruction {:#04x}", opcode),
+    })
+}
+
+fn simd(s: &mut Reader) -> anyhow::Result<Insn> {
+    match s.u32()? {
+        // loads and stores
+        0..=11 | 92 | 93 => {
+            s.mem_arg()?;
+        }
+        // v128.const, i8x16.shuffle
+        12 | 13 => {
+            s.bytes(16)?;
+        }
+        // extract_lane, replace_lane
+        21..=34 => {
+            s.u8()?;
+        }
+        // load_lane, store_lane
+        84..=91 => {
+            s.mem_arg()?;
+            s.u8()?;
+        }
+        _ => {}
+    }
+
+    Ok(Insn::Other)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            bail!("unexpected end of input");
+        }
+
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    // LEB128 encoded unsigned integer
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let mut result = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.u8()?;
+            result |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                break;
+            }
+
+            shift += 7;
+            if shift >= 35 {
+                bail!("malformed LEB128 integer");
+            }
+        }
+
+        Ok(result as u32)
+    }
+
+    // LEB128 encoded signed integer
+    fn i64(&mut self) -> anyhow::Result<i64> {
+        let mut result = 0i64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.u8()?;
+            result |= i64::from(byte & 0x7f) << shift;
+            shift += 7;
+
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    // sign extend
+                    result |= -1 << shift;
+                }
+                break;
+            }
+
+            if shift >= 70 {
+                bail!("malformed LEB128 integer");
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn name(&mut self) -> anyhow::Result<&'a str> {
+        let len = self.u32()? as usize;
+        str::from_utf8(self.bytes(len)?).map_err(|_| anyhow!("name is not valid UTF-8"))
+    }
+
+    fn val_types(&mut self) -> anyhow::Result<Vec<ValType>> {
+        (0..self.u32()?)
+            .map(|_| {
+                Ok(match self.u8()? {
+                    0x7f => ValType::I32,
+                    0x7e => ValType::I64,
+                    0x7d => ValType::F32,
+                    0x7c => ValType::F64,
+                    0x7b => ValType::V128,
+                    0x70 => ValType::FuncRef,
+                    0x6f => ValType::ExternRef,
+                    byte => bail!("unknown value type {:#04x}", byte),
+                })
+            })
+            .collect()
+    }
+
+    fn limits(&mut self) -> anyhow::Result<()> {
+        if self.u8()? & 1 != 0 {
+            // min and max
+            self.u32()?;
+        }
+        self.u32()?;
+        Ok(())
+    }
+
+    fn mem_arg(&mut self) -> anyhow::Result<()> {
+        // align and offset
+        self.u32()?;
+        self.u32()?;
+        Ok(())
+    }
+
+    fn block_type(&mut self) -> anyhow::Result<()> {
+        match self.bytes.first() {
+            // empty or a value type
+            Some(0x40) | Some(0x6f..=0x7f) => {
+                self.u8()?;
+            }
+            // type index (s33)
+            _ => {
+                self.i64()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FuncType, ValType};
+
+    // (module
+    //   (type (func))
+    //   (type (func (param i32) (result i32)))
+    //   (import "env" "ext" (func (type 0)))
+    //   (global (mut i32) (i32.const 1024))
+    //   (table 2 funcref)
+    //   (elem (i32.const 0) 2)
+    //   (func (type 1) ;; 1: uses 32 bytes of stack, calls `ext` and calls indirectly
+    //     global.get 0
+    //     i32.const 32
+    //     i32.sub
+    //     global.set 0
+    //     call 0
+    //     local.get 0
+    //     i32.const 0
+    //     call_indirect (type 1)
+    //     drop
+    //     local.get 0)
+    //   (func (type 1) ;; 2: leaf function
+    //     local.get 0))
+    #[rustfmt::skip]
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x09, 0x02, 0x60, 0x00, 0x00, 0x60, 0x01, 0x7f, 0x01, 0x7f, // types
+        0x02, 0x0b, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x03, 0x65, 0x78, 0x74, 0x00, 0x00, // imports
+        0x03, 0x03, 0x02, 0x01, 0x01, // functions
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x02, // tables
+        0x06, 0x07, 0x01, 0x7f, 0x01, 0x41, 0x80, 0x08, 0x0b, // globals
+        0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x01, 0x02, // elements
+        0x0a, 0x1c, 0x02, // code
+        0x15, 0x00, 0x23, 0x00, 0x41, 0x20, 0x6b, 0x24, 0x00, 0x10, 0x00, 0x20, 0x00, 0x41, 0x00,
+        0x11, 0x01, 0x00, 0x1a, 0x20, 0x00, 0x0b, // function 1
+        0x04, 0x00, 0x20, 0x00, 0x0b, // function 2
+    ];
+
+    #[test]
+    fn analyze() {
+        let module = super::analyze(MODULE).unwrap();
+
+        assert_eq!(module.functions.len(), 3);
+        assert!(module.functions[0].imported);
+        assert_eq!(module.functions[0].name, Some("ext"));
+
+        let f1 = &module.functions[1];
+        assert_eq!(f1.stack, Some(32));
+        assert_eq!(f1.calls, vec![0]);
+        assert_eq!(f1.indirect_calls, vec![1]);
+
+        let f2 = &module.functions[2];
+        assert_eq!(f2.stack, Some(0));
+        assert!(f2.calls.is_empty());
+
+        assert_eq!(module.table, vec![2]);
+        assert_eq!(module.types[f2.ty as usize], module.types[1]);
+    }
+
+    #[test]
+    fn unknown_instruction() {
+        // function 2 becomes `(func (type 1) 0xff 0x00)`
+        let mut bytes = MODULE.to_vec();
+        let n = bytes.len();
+        bytes[n - 3] = 0xff;
+
+        let module = super::analyze(&bytes).unwrap();
+
+        let f2 = &module.functions[2];
+        assert_eq!(f2.stack, None);
+        assert!(f2.calls.is_empty());
+        assert!(f2.error.is_some());
+
+        // the other functions are still analyzed
+        let f1 = &module.functions[1];
+        assert_eq!(f1.stack, Some(32));
+        assert_eq!(f1.calls, vec![0]);
+        assert!(f1.error.is_none());
+    }
+
+    // (module
+    //   (type (func))
+    //   (import "env" "__memory_base" (global i32))
+    //   (global (mut i32) (i32.const 1024))
+    //   (func (type 0) ;; 0: uses 16 bytes of stack
+    //     global.get 1
+    //     i32.const 16
+    //     i32.sub
+    //     global.set 1))
+    #[rustfmt::skip]
+    const PIC: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // types
+        0x02, 0x16, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x0d, 0x5f, 0x5f, 0x6d, 0x65, 0x6d, 0x6f, 0x72,
+        0x79, 0x5f, 0x62, 0x61, 0x73, 0x65, 0x03, 0x7f, 0x00, // imports
+        0x03, 0x02, 0x01, 0x00, // functions
+        0x06, 0x07, 0x01, 0x7f, 0x01, 0x41, 0x80, 0x08, 0x0b, // globals
+        0x0a, 0x0b, 0x01, // code
+        0x09, 0x00, 0x23, 0x01, 0x41, 0x10, 0x6b, 0x24, 0x01, 0x0b, // function 0
+    ];
+
+    #[test]
+    fn imported_globals() {
+        // global 0 is `__memory_base`; without names we can't tell which global is the stack
+        // pointer
+        let module = super::analyze(PIC).unwrap();
+        assert_eq!(module.functions[0].stack, None);
+
+        // "name" section that names global 1 `__stack_pointer`
+        let mut bytes = PIC.to_vec();
+        bytes.extend_from_slice(&[0x00, 0x19, 0x04]);
+        bytes.extend_from_slice(b"name");
+        bytes.extend_from_slice(&[0x07, 0x12, 0x01, 0x01, 0x0f]);
+        bytes.extend_from_slice(b"__stack_pointer");
+
+        let module = super::analyze(&bytes).unwrap();
+        assert_eq!(module.functions[0].stack, Some(16));
+    }
+
+    #[test]
+    fn fmt_func_type() {
+        assert_eq!(
+            FuncType {
+                params: vec![],
+                results: vec![]
+            }
+            .to_string(),
+            "void ()"
+        );
+
+        assert_eq!(
+            FuncType {
+                params: vec![ValType::I32, ValType::I64],
+                results: vec![ValType::I32]
+            }
+            .to_string(),
+            "i32 (i32, i64)"
+        );
+    }
+}
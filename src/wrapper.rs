@@ -12,6 +12,12 @@
 //!   created `.ll` file.
 //!   This is needed because the `compiler_builtins` LLVM IR is not included in the final program,
 //!   even with `-C lto=fat` and `--emit=llvm-ir`.
+//! - Report back the paths to the `.ll` and `.o` files of the crate being analyzed (the one that
+//!   receives our `--emit=llvm-ir,obj` flag).
+//!   This spares us from guessing which of the files in the `deps` directory is the right one.
+//! - Report back the arguments of the final link of the crate being analyzed: the native library
+//!   search paths, the native libraries, the linker scripts and the symbols wrapped with `--wrap`.
+//!   These come from build scripts and `.cargo/config` so the user doesn't have to repeat them.
 
 use std::{env, process::Command};
 
@@ -21,6 +27,18 @@ pub(crate) const COMPILER_BUILTINS_RLIB_PATH_MARKER: &str =
     "@CARGO_CALL_STACK:compiler_builtins_rlib_path@";
 pub(crate) const COMPILER_BUILTINS_LL_PATH_MARKER: &str =
     "@CARGO_CALL_STACK:compiler_builtins_ll_path@";
+pub(crate) const LL_PATH_MARKER: &str = "@CARGO_CALL_STACK:ll_path@";
+pub(crate) const OBJ_PATH_MARKER: &str = "@CARGO_CALL_STACK:obj_path@";
+pub(crate) const SEARCH_PATH_MARKER: &str = "@CARGO_CALL_STACK:search_path@";
+pub(crate) const NATIVE_LIB_MARKER: &str = "@CARGO_CALL_STACK:native_lib@";
+pub(crate) const LINKER_SCRIPT_MARKER: &str = "@CARGO_CALL_STACK:linker_script@";
+pub(crate) const WRAPPED_SYMBOL_MARKER: &str = "@CARGO_CALL_STACK:wrapped_symbol@";
+
+// explains how `-Zemit-stack-sizes` reaches rustc
+pub(crate) const WRAPPER_HINT: &str =
+    "`cargo-call-stack` injects `-Zemit-stack-sizes` using `RUSTC_WRAPPER`; \
+     check that the flag is not dropped, e.g. by a `RUSTC` script that filters its arguments, \
+     and that your toolchain is a nightly that supports the flag";
 
 pub(crate) fn wrapper() -> anyhow::Result<i32> {
     let mut args = env::args().skip(1);
@@ -47,6 +65,29 @@ pub(crate) fn wrapper() -> anyhow::Result<i32> {
             .ok_or_else(|| anyhow!("missing `--out-dir` argument"))?;
         let ll_path = format!("{}/{}{}.ll", out_dir, args.crate_name, args.extra_filename);
         eprintln!("{}{}", COMPILER_BUILTINS_LL_PATH_MARKER, ll_path);
+    } else if args.emit.iter().any(|kind| kind == "llvm-ir") {
+        // this is the crate being analyzed
+        let out_dir = args
+            .out_dir
+            .as_ref()
+            .ok_or_else(|| anyhow!("missing `--out-dir` argument"))?;
+        let stem = format!("{}/{}{}", out_dir, args.crate_name, args.extra_filename);
+        eprintln!("{}{}.ll", LL_PATH_MARKER, stem);
+        eprintln!("{}{}.o", OBJ_PATH_MARKER, stem);
+
+        for path in &args.search_paths {
+            eprintln!("{}{}", SEARCH_PATH_MARKER, path);
+        }
+        for lib in &args.native_libs {
+            eprintln!("{}{}", NATIVE_LIB_MARKER, lib);
+        }
+        let (scripts, wrapped) = linker_args(&args.link_args);
+        for script in scripts {
+            eprintln!("{}{}", LINKER_SCRIPT_MARKER, script);
+        }
+        for symbol in wrapped {
+            eprintln!("{}{}", WRAPPED_SYMBOL_MARKER, symbol);
+        }
     }
 
     rustc.arg("-Zemit-stack-sizes").args(&rustc_args);
@@ -62,6 +103,14 @@ struct RustcArgs {
     crate_name: String,
     out_dir: Option<String>,
     extern_crates: Vec<Extern>,
+    // output kinds requested via (all) the `--emit` flags
+    emit: Vec<String>,
+    // `-L`, without the kind of path
+    search_paths: Vec<String>,
+    // `-l`, e.g. `static=foo`
+    native_libs: Vec<String>,
+    // `-C link-arg` and `-C link-args`
+    link_args: Vec<String>,
 }
 
 struct Extern {
@@ -73,11 +122,16 @@ impl RustcArgs {
     fn parse(args: &mut dyn Iterator<Item = &str>) -> anyhow::Result<Self> {
         const NOPRELUDE: &str = "noprelude:";
         const DASH_C: &str = "-C";
+        const EMIT: &str = "--emit=";
 
         let mut extra_filename = None;
         let mut crate_name = None;
         let mut out_dir = None;
         let mut extern_crates = Vec::new();
+        let mut emit = Vec::new();
+        let mut search_paths = Vec::new();
+        let mut native_libs = Vec::new();
+        let mut link_args = Vec::new();
 
         while let Some(arg) = args.next() {
             match &*arg {
@@ -106,6 +160,43 @@ impl RustcArgs {
                             .to_string(),
                     );
                 }
+                "--emit" => {
+                    let arg = args
+                        .next()
+                        .ok_or_else(|| anyhow!("missing argument for `--emit`"))?;
+                    emit.extend(arg.split(',').map(ToString::to_string));
+                }
+                _ if arg.starts_with(EMIT) => {
+                    emit.extend(arg[EMIT.len()..].split(',').map(ToString::to_string));
+                }
+                _ if arg.starts_with("-L") => {
+                    let path = if arg == "-L" {
+                        args.next()
+                            .ok_or_else(|| anyhow!("missing argument for `-L`"))?
+                    } else {
+                        &arg[2..]
+                    };
+                    // e.g. `-L native=/path/to/dir`
+                    let path = match path.split_once('=') {
+                        Some((kind, path))
+                            if ["dependency", "crate", "native", "framework", "all"]
+                                .contains(&kind) =>
+                        {
+                            path
+                        }
+                        _ => path,
+                    };
+                    search_paths.push(path.to_string());
+                }
+                _ if arg.starts_with("-l") => {
+                    let lib = if arg == "-l" {
+                        args.next()
+                            .ok_or_else(|| anyhow!("missing argument for `-l`"))?
+                    } else {
+                        &arg[2..]
+                    };
+                    native_libs.push(lib.to_string());
+                }
                 "--out-dir" => {
                     out_dir = Some(
                         args.next()
@@ -128,6 +219,14 @@ impl RustcArgs {
                     let name = split.next().unwrap(); // cannot fail
 
                     match name {
+                        "link-arg" => link_args.extend(split.next().map(ToString::to_string)),
+                        "link-args" => link_args.extend(
+                            split
+                                .next()
+                                .unwrap_or_default()
+                                .split_whitespace()
+                                .map(ToString::to_string),
+                        ),
                         "extra-filename" => {
                             extra_filename = Some(
                                 split
@@ -148,6 +247,102 @@ impl RustcArgs {
             crate_name: crate_name.ok_or_else(|| anyhow!("missing `--crate-name` argument"))?,
             out_dir,
             extern_crates,
+            emit,
+            search_paths,
+            native_libs,
+            link_args,
+        })
+    }
+}
+
+// returns the linker scripts (`-T`) and the wrapped symbols (`--wrap`) in the linker arguments,
+// which may be passed through a compiler driver (`-Wl,`)
+fn linker_args(link_args: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut args = link_args
+        .iter()
+        .flat_map(|arg| match arg.strip_prefix("-Wl,") {
+            Some(args) => args.split(',').collect(),
+            None => vec![&**arg],
         })
+        .peekable();
+
+    let mut scripts = vec![];
+    let mut wrapped = vec![];
+    while let Some(arg) = args.next() {
+        let (list, value) = if let Some(script) = arg
+            .strip_prefix("--script=")
+            .or_else(|| arg.strip_prefix("-T").filter(|script| !script.is_empty()))
+        {
+            (&mut scripts, Some(script))
+        } else if let Some(symbol) = arg.strip_prefix("--wrap=") {
+            (&mut wrapped, Some(symbol))
+        } else if arg == "-T" || arg == "--script" {
+            (&mut scripts, args.next())
+        } else if arg == "--wrap" {
+            (&mut wrapped, args.next())
+        } else {
+            continue;
+        };
+
+        list.extend(value.map(ToString::to_string));
+    }
+
+    (scripts, wrapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RustcArgs;
+
+    #[test]
+    fn parse() {
+        let args = RustcArgs::parse(
+            &mut [
+                "--crate-name",
+                "app",
+                "-L",
+                "native=/out/dir",
+                "-L/lib",
+                "-l",
+                "static=vendor",
+                "-C",
+                "link-arg=-Tlink.x",
+                "-Clink-args=--nmagic --wrap=malloc",
+            ]
+            .iter()
+            .copied(),
+        )
+        .unwrap();
+
+        assert_eq!(args.search_paths, ["/out/dir", "/lib"]);
+        assert_eq!(args.native_libs, ["static=vendor"]);
+        assert_eq!(args.link_args, ["-Tlink.x", "--nmagic", "--wrap=malloc"]);
+    }
+
+    #[test]
+    fn linker_args() {
+        let args = [
+            "-Tlink.x",
+            "-T",
+            "memory.x",
+            "--wrap=malloc",
+            "-Wl,--wrap,free,-Tdefmt.x",
+            "--nmagic",
+        ]
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            super::linker_args(&args),
+            (
+                vec![
+                    "link.x".to_owned(),
+                    "memory.x".to_owned(),
+                    "defmt.x".to_owned()
+                ],
+                vec!["malloc".to_owned(), "free".to_owned()],
+            )
+        );
     }
 }
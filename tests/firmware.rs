@@ -1,4 +1,15 @@
-use std::{env, process::Command};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cargo_call_stack::{
+    analysis::Max as AnalysisMax,
+    analyze,
+    editor::{Annotations, Max},
+    AnalysisInput, Options,
+};
 
 const ALL_TARGETS: &[&str] = &[
     "thumbv6m-none-eabi",
@@ -6,6 +17,7 @@ const ALL_TARGETS: &[&str] = &[
     "aarch64-unknown-none",
 ];
 const FMUL_TARGETS: &[&str] = &["thumbv6m-none-eabi", "thumbv7m-none-eabi"];
+const THUMB_TARGETS: &[&str] = &["thumbv6m-none-eabi", "thumbv7m-none-eabi"];
 
 fn for_all_targets(mut f: impl FnMut(&str)) {
     for target in ALL_TARGETS {
@@ -20,7 +32,7 @@ fn cycle() {
 
     let mut found = false;
     for line in dot.lines() {
-        if line.contains("label=\"_start\\n") {
+        if line.contains("label=<_start<br/>") {
             found = true;
             // worst-case stack usage must be exact
             assert!(line.contains("max = "));
@@ -28,6 +40,1549 @@ fn cycle() {
     }
 
     assert!(found);
+
+    // the cluster label summarizes the cycle
+    assert!(dot.contains("label=<SCC0<br/>3 functions<br/>local = 0>"));
+}
+
+#[test]
+fn cycle_merged() {
+    let dot = call_stack_with("cycle", "thumbv7m-none-eabi", &["--merge-cycles"]);
+
+    // `foo`, `bar` and `baz` are replaced by a single node
+    assert!(dot.contains("label=<SCC0<br/>3 functions<br/>"));
+    assert!(!dot.contains("cycle::foo"));
+    assert!(!dot.contains("subgraph"));
+}
+
+#[test]
+fn min_max() {
+    let dot = call_stack_with("cycle", "thumbv7m-none-eabi", &["--min-max", "16"]);
+
+    // the cycle doesn't use the stack
+    assert!(!dot.contains("cycle::foo"));
+    assert!(!dot.contains("subgraph"));
+    assert!(dot.contains("label=<cycle::quux<br/><b>max = 16</b><br/>"));
+    assert!(dot.contains("label=<_start<br/><b>max = "));
+}
+
+#[test]
+fn asm_no_size() {
+    for target in THUMB_TARGETS {
+        let dot = call_stack("asm-no-size", target);
+
+        // the size of the symbols is derived from the symbol table; `inner` is not part of `outer`
+        assert!(dot.contains(
+            "label=<outer<br/><b>max = 16</b><br/><font color=\"gray\">local = 8</font>>"
+        ));
+        assert!(dot.contains(
+            "label=<inner<br/><b>max = 8</b><br/><font color=\"gray\">local = 8</font>>"
+        ));
+    }
+}
+
+#[test]
+fn untyped() {
+    for target in THUMB_TARGETS {
+        let (_, stderr) =
+            cargo_call_stack_output(&["--example", "untyped", "--target", target, "-v"]);
+
+        // only the functions that may be called indirectly are a problem
+        assert!(
+            stderr.contains("no type information for `inner`; every indirect function call"),
+            "{}",
+            stderr
+        );
+        assert!(
+            !stderr.contains("no type information for `OUTLINED_FUNCTION_0`"),
+            "{}",
+            stderr
+        );
+        assert!(
+            stderr.contains("untyped: `OUTLINED_FUNCTION_0` is never called indirectly"),
+            "{}",
+            stderr
+        );
+    }
+}
+
+#[test]
+fn asm_indirect() {
+    for target in THUMB_TARGETS {
+        let dot = call_stack("asm-indirect", target);
+
+        // both `blx` instructions are call sites of the unknown function
+        assert!(dot.contains(
+            "label=<call_both<br/><b>max &gt;= 8</b><br/><font color=\"gray\">local = 8</font>>"
+        ));
+        let unknown = node_id(&dot, "?");
+        assert!(dot.contains(&format!(
+            "{} -> {} [label=\"2 calls\"]\n",
+            node_id(&dot, "call_both"),
+            unknown
+        )));
+        assert!(dot.contains(&format!(
+            "    {} [label=<?<br/><b>max &gt;= 0</b><br/><font color=\"gray\">local = ?</font>>]\n",
+            unknown
+        )));
+    }
+}
+
+#[test]
+fn dwarf_prototype() {
+    for target in THUMB_TARGETS {
+        let dot = call_stack("dwarf-prototype", target);
+
+        // `c_helper`'s prototype comes from DWARF so the indirect call can be bounded
+        assert!(dot.contains("label=<void (i32)*<br/><b>max = 0</b><br/><font color=\"gray\">local = 0</font><br/>callees = 2>"));
+        assert!(dot.contains(
+            "label=<_start<br/><b>max = 16</b><br/><font color=\"gray\">local = 8</font>>"
+        ));
+        assert!(!dot.contains("label=<?<br/>"));
+    }
+}
+
+#[test]
+fn locked_offline() {
+    // the flags are forwarded to Cargo; the lock file is up to date and the dependencies are cached
+    let dot = call_stack_with("cycle", "thumbv7m-none-eabi", &["--locked", "--offline"]);
+
+    assert!(dot.contains("label=<_start<br/><b>max = "));
+}
+
+#[test]
+fn dominators() {
+    let out = call_stack_with("cycle", "thumbv7m-none-eabi", &["--format", "dominators"]);
+
+    assert!(out.starts_with("Max Root\n= 24 _start\nOwned Function\n"));
+    // the cycle doesn't use the stack so it owns nothing
+    assert!(out.ends_with("24 _start\n16 cycle::quux\n"));
+}
+
+#[test]
+fn call_sites() {
+    for_all_targets(|target| {
+        let dot = call_stack("call-sites", target);
+
+        // `_start` calls `foo` twice and `bar` once
+        assert!(dot.contains("    0 -> 1 [label=\"2 calls\"]\n"));
+        assert!(dot.contains("    0 -> 2\n"));
+
+        let json = call_stack_with("call-sites", target, &["--format", "json"]);
+        assert!(json.contains("  \"edges\": [\n    [0,1,2],\n    [0,2,1]\n  ],"));
+    });
+}
+
+#[test]
+fn budget() {
+    // `_start` uses 8 bytes of stack
+    let top = call_stack_with(
+        "call-sites",
+        "thumbv7m-none-eabi",
+        &[
+            "--format",
+            "top",
+            "--budget",
+            "4",
+            "--budget",
+            "call_sites::foo=8",
+        ],
+    );
+    assert!(top.contains("\n= 8 _start [OVER LIMIT: budget 4]\n"));
+    assert!(top.contains("\n0 call_sites::foo [OK: budget 8]\n"));
+    assert!(top.contains("\n0 call_sites::bar\n"));
+
+    let dot = call_stack_with("call-sites", "thumbv7m-none-eabi", &["--budget", "9"]);
+    assert!(dot.contains("<br/>[NEAR LIMIT: budget 9]> color=orange]"));
+
+    let json = call_stack_with(
+        "call-sites",
+        "thumbv7m-none-eabi",
+        &[
+            "--format",
+            "json",
+            "--budget",
+            "9",
+            "--budget-warning",
+            "90",
+        ],
+    );
+    assert!(json.contains(",\"budget\":{\"bytes\":9,\"status\":\"ok\"}}"));
+}
+
+#[test]
+fn max_stack() {
+    let max_stack = |limits: &str| {
+        let output = cargo_call_stack_command(&[
+            "--example",
+            "call-sites",
+            "--target",
+            "thumbv7m-none-eabi",
+            "--format",
+            "top",
+            "--max-stack",
+            limits,
+        ])
+        .output()
+        .unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    // `_start` uses 8 bytes of stack
+    let (code, stdout, _) = max_stack("8,call_sites::foo=0");
+    assert_eq!(code, Some(0));
+    assert!(
+        stdout.contains("\n= 8 _start [NEAR LIMIT: budget 8]\n"),
+        "{}",
+        stdout
+    );
+
+    // the report is still printed
+    let (code, stdout, stderr) = max_stack("_start=4,call_sites::foo=8");
+    assert_eq!(code, Some(1));
+    assert!(
+        stdout.contains("\n= 8 _start [OVER LIMIT: budget 4]\n"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stderr.contains("--max-stack: the max stack usage of `_start` is 8 bytes"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_harness() {
+    for target in THUMB_TARGETS {
+        let top = cargo_call_stack(&["--test", "harness", "--target", target, "--format", "top"]);
+
+        // the entry point of the harness and each test are roots
+        let roots = top
+            .split("Max Root\n")
+            .nth(1)
+            .and_then(|rest| rest.split("Usage Function\n").next())
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with("  path: "))
+            .map(|line| line.splitn(3, ' ').nth(2).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            roots,
+            ["main", "harness::tests::small", "harness::tests::big"]
+        );
+    }
+}
+
+#[test]
+fn bench_harness() {
+    let top = cargo_call_stack(&[
+        "--bench",
+        "bench",
+        "--target",
+        "thumbv7m-none-eabi",
+        "--format",
+        "top",
+    ]);
+
+    // the entry point of the benchmark binary and each benchmark are roots
+    let roots = top
+        .split("Max Root\n")
+        .nth(1)
+        .and_then(|rest| rest.split("Usage Function\n").next())
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with("  path: "))
+        .map(|line| line.splitn(3, ' ').nth(2).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(roots, ["main", "bench::fill", "bench::sum"], "{}", top);
+}
+
+#[test]
+fn inline_asm() {
+    for target in THUMB_TARGETS {
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "inline-asm",
+            "--target",
+            target,
+            "--format",
+            "top",
+        ]);
+
+        // the inline assembly statements are listed at the end of the analysis
+        assert!(stderr.contains(
+            "    inline_asm::nop: asm!(\"nop\") (confirmed by the machine code analysis)"
+        ));
+        assert!(stderr
+            .contains("    inline_asm::push_pop: asm!(\"push {r0}\") and 1 more (corrected from "));
+        assert!(!stderr.contains("(unverified)"));
+
+        // `push_pop` uses 4 more bytes than LLVM reported
+        let local = |name: &str| {
+            top.lines()
+                .find(|line| line.ends_with(&format!(" {}", name)))
+                .and_then(|line| line.split(' ').next())
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap()
+        };
+        assert_eq!(local("inline_asm::push_pop"), local("inline_asm::nop") + 4);
+    }
+}
+
+#[test]
+fn alias() {
+    for target in THUMB_TARGETS {
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "alias",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--verbose",
+        ]);
+
+        // `foo` and `bar` are the same function
+        assert_eq!(top.matches("\n0 ").count(), 1);
+
+        let line = stderr
+            .lines()
+            .find(|line| line.starts_with("aliases: "))
+            .unwrap();
+        assert!(line.contains("`foo`") && line.contains("`bar`"));
+        assert!(line.ends_with(": it has stack usage information"));
+    }
+}
+
+#[test]
+fn tasks() {
+    for target in THUMB_TARGETS {
+        let max = |top: &str, root: &str| {
+            top.lines()
+                .find(|line| line.starts_with("= ") && line.contains(root))
+                .and_then(|line| line.split(' ').nth(1))
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap()
+        };
+
+        // the scheduler's indirect call puts the tasks on the stack of `_start`
+        let top = call_stack_with("tasks", target, &["--format", "top"]);
+        assert!(max(&top, " _start") > 128);
+
+        // each task runs on its own stack
+        let top = call_stack_with(
+            "tasks",
+            target,
+            &[
+                "--format",
+                "top",
+                "--task",
+                "tasks::task_a=256",
+                "--task",
+                "tasks::task_b=8",
+            ],
+        );
+        assert!(max(&top, " _start") < 128);
+        assert!(top.contains(" tasks::task_a [OK: budget 256]\n"));
+        assert!(top.contains("\n= 16 tasks::task_b [OVER LIMIT: budget 8]\n"));
+
+        // the same tasks declared in the configuration file; `--task` takes precedence
+        let config = env::temp_dir().join(format!("cargo-call-stack-tasks-{}.toml", target));
+        fs::write(
+            &config,
+            "[tasks]\n\"tasks::task_a\" = 256\n\"tasks::task_b\" = 8\n",
+        )
+        .unwrap();
+        let top = call_stack_with(
+            "tasks",
+            target,
+            &[
+                "--format",
+                "top",
+                "--config",
+                config.to_str().unwrap(),
+                "--task",
+                "tasks::task_b=32",
+            ],
+        );
+        assert!(max(&top, " _start") < 128);
+        assert!(top.contains(" tasks::task_a [OK: budget 256]\n"));
+        assert!(top.contains("\n= 16 tasks::task_b [OK: budget 32]\n"));
+    }
+}
+
+#[test]
+fn vector_table() {
+    for target in THUMB_TARGETS {
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "vector-table",
+            "--target",
+            target,
+            "--format",
+            "top",
+        ]);
+
+        assert!(stderr.contains(
+            "IRQ 0 is enabled by `_start` but its vector table entry points at the default \
+             handler, `DefaultHandler`"
+        ));
+        assert!(stderr.contains("IRQ 34 is enabled by `_start` but the vector table has no entry"));
+        // `TIMER0` is installed
+        assert!(!stderr.contains("IRQ 1 "));
+        assert!(stderr.contains(
+            "neither called nor installed in the vector table; if they are interrupt handlers, \
+             check that their names match the device's interrupts: `UART0`\n"
+        ));
+
+        // the installed handlers are roots
+        assert!(top.contains("\n= 0 DefaultHandler\n"), "{}", top);
+        let timer0 = top
+            .lines()
+            .find_map(|line| line.strip_prefix("= ")?.strip_suffix(" TIMER0"))
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+        assert!(timer0 > 0);
+
+        // `TIMER0` is the deepest handler; the exception frame is 8 words plus 4 bytes of padding
+        assert!(
+            top.contains(&format!(
+                "\nMax Preempted\n= {} _start preempted by TIMER0 (exception frame: 36)\n",
+                timer0 + 36
+            )),
+            "{}",
+            top
+        );
+    }
+}
+
+#[test]
+fn literal_pool() {
+    for target in THUMB_TARGETS {
+        let (_, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "literal-pool",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--verbose",
+        ]);
+
+        assert!(stderr.contains("data: `lookup`: 16 of 20 bytes\n"));
+        assert!(stderr.contains("data: 16 bytes skipped in 1 function(s)\n"));
+        // a function that's mostly a lookup table is not a sign of a wrong symbol size
+        assert!(!stderr.contains("symbol size may be wrong"), "{}", stderr);
+    }
+}
+
+#[test]
+fn tail_calls() {
+    let local = |top: &str, name: &str| {
+        top.lines()
+            .find(|line| line.ends_with(&format!(" {}", name)) && !line.starts_with('='))
+            .and_then(|line| line.split(' ').next())
+            .and_then(|n| n.parse::<u64>().ok())
+            .unwrap()
+    };
+
+    // ARMv7-M can jump to `callee` from anywhere in the program so `caller` tail calls it
+    let target = "thumbv7m-none-eabi";
+    let top = call_stack_with("tail-call", target, &["--format", "top"]);
+    let (caller, callee) = (local(&top, "caller"), local(&top, "callee"));
+    assert!(top.contains(&format!("\n= {} _start\n", caller + callee)));
+
+    let top = call_stack_with("tail-call", target, &["--format", "top", "--tail-calls"]);
+    assert!(top.contains(&format!("\n= {} _start\n", caller.max(callee))));
+}
+
+#[test]
+fn shared_tail() {
+    for target in THUMB_TARGETS {
+        let max = |top: &str| {
+            top.lines()
+                .find(|line| line.ends_with(" _start") && line.starts_with("= "))
+                .and_then(|line| line.split(' ').nth(1))
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap()
+        };
+
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "shared-tail",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--tail-calls",
+            "--verbose",
+        ]);
+        assert!(stderr.contains("shared tail: `shared_tail` is the tail of `a`, `b`\n"));
+
+        // the frames of `a` and `b` are still on the stack when `shared_tail` calls `heavy`
+        let default = call_stack_with("shared-tail", target, &["--format", "top"]);
+        assert_eq!(max(&top), max(&default));
+    }
+}
+
+#[test]
+fn target_dir() {
+    let target = "thumbv7m-none-eabi";
+    call_stack_with("cycle", target, &[]);
+
+    // the analysis build doesn't share the target directory of the normal builds
+    let firmware = env::current_dir().unwrap().join("firmware");
+    assert!(firmware
+        .join("target/call-stack")
+        .join(target)
+        .join("release/examples/cycle")
+        .exists());
+}
+
+#[test]
+fn splits() {
+    for target in THUMB_TARGETS {
+        let splits = call_stack_with("cold-path", target, &["--format", "splits"]);
+        let mut lines = splits.lines();
+        assert_eq!(lines.next(), Some("Saves Common Max Call"));
+
+        // without `cold` the max stack usage of `hot` is that of its call to `light`
+        let line = lines.next().unwrap();
+        assert!(line.ends_with(" hot -> cold"), "{}", line);
+        assert_eq!(lines.next(), None);
+    }
+}
+
+#[test]
+fn allocator() {
+    for target in THUMB_TARGETS {
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "allocator",
+            "--target",
+            target,
+            "--format",
+            "top",
+        ]);
+
+        // the allocator shims don't make the indirect function calls unbounded
+        assert!(!stderr.contains("no type information"), "{}", stderr);
+        assert!(!stderr.contains("untyped"), "{}", stderr);
+        assert!(top.contains(" <allocator::Bump as core::alloc::global::GlobalAlloc>::alloc\n"));
+    }
+}
+
+#[test]
+fn classify() {
+    let rules = env::temp_dir().join("cargo-call-stack-classify.txt");
+    fs::write(&rules, "# the `core` library\nruntime = ^core::\n").unwrap();
+    let rules = rules.to_str().unwrap();
+
+    let target = "thumbv7m-none-eabi";
+    let (_, stderr) = cargo_call_stack_output(&[
+        "--example",
+        "core-fmt",
+        "--target",
+        target,
+        "--format",
+        "top",
+        "--classify",
+        rules,
+        "--verbose",
+    ]);
+    assert!(stderr.contains("class `application`: "), "{}", stderr);
+    assert!(stderr.contains("class `runtime`: "), "{}", stderr);
+
+    let json = call_stack_with(
+        "core-fmt",
+        target,
+        &["--format", "json", "--classify", rules],
+    );
+    assert!(json.contains(r#""name":"_start","#));
+    assert!(json.contains(r#""class":"application"}"#));
+    assert!(json.contains(r#""class":"runtime"}"#));
+
+    let top = call_stack_with(
+        "core-fmt",
+        target,
+        &[
+            "--format",
+            "top",
+            "--classify",
+            rules,
+            "--hide-class",
+            "runtime",
+        ],
+    );
+    assert!(top.contains(" _start\n"));
+    assert!(!top.contains(" core::fmt::Write::write_fmt"));
+}
+
+#[test]
+fn legend() {
+    let target = "thumbv7m-none-eabi";
+    let dot = call_stack("cycle", target);
+
+    // the rendered graph explains its notation and the build it describes
+    let legend = dot
+        .lines()
+        .find(|line| line.starts_with("    legend [shape=plaintext label=<<table"))
+        .unwrap();
+    assert!(legend.contains("<td align=\"left\">max &gt;= N</td>"));
+    assert!(legend.contains(&format!("<td align=\"left\">{}</td>", target)));
+    assert!(legend.contains(
+        "<td align=\"left\">cargo call-stack --example cycle --target thumbv7m-none-eabi</td>"
+    ));
+}
+
+#[test]
+fn source() {
+    for target in THUMB_TARGETS {
+        let json = call_stack_with(
+            "inline-asm",
+            target,
+            &["--format", "json", "--override-stack", "_start=4"],
+        );
+        let node = |name: &str| {
+            json.lines()
+                .find(|line| line.contains(&format!("\"name\":\"{}\"", name)))
+                .unwrap()
+                .to_owned()
+        };
+
+        // the JSON output records where the local stack usage of each function comes from
+        assert!(node("_start").contains(r#","source":"override","#));
+        assert!(node("inline_asm::nop").contains(r#","source":"llvm","#));
+        // LLVM ignores the `push` and `pop` instructions in the inline assembly
+        assert!(node("inline_asm::push_pop").contains(r#","source":"corrected","llvm_local":"#));
+    }
+}
+
+#[test]
+fn check() {
+    let check = |budgets: &[&str]| {
+        let mut args = vec![
+            "check",
+            "--example",
+            "call-sites",
+            "--target",
+            "thumbv7m-none-eabi",
+        ];
+        args.extend_from_slice(budgets);
+        let output = cargo_call_stack_command(&args).output().unwrap();
+        (
+            output.status.code(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+
+    // `_start` uses 8 bytes of stack; no call graph is printed, only the verdicts
+    let (code, stdout) = check(&["--budget", "16", "--budget", "call_sites::foo=8"]);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        stdout,
+        "PASS _start: 8 bytes, budget 16 bytes\n\
+         PASS call_sites::foo: 0 bytes, budget 8 bytes\n\
+         check: all 2 budgets met\n"
+    );
+
+    // the failures come first, along with the path that exceeds the budget
+    let (code, stdout) = check(&["--budget", "4", "--budget", "call_sites::foo=8"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.starts_with("FAIL _start: 8 bytes, budget 4 bytes\n    _start (8) -> "));
+    assert!(stdout.ends_with("check: 1 of 2 budgets exceeded\n"));
+
+    // there's nothing to check without budgets
+    let (code, _) = check(&[]);
+    assert_eq!(code, Some(1));
+
+    // a subcommand that takes the options of the analysis
+    let output = cargo_call_stack_command(&["check", "--help"])
+        .output()
+        .unwrap();
+    let help = String::from_utf8(output.stdout).unwrap();
+    assert!(help.starts_with("Perform the same analysis"), "{}", help);
+    assert!(help.contains("--budget <[FUNCTION=]BYTES>"), "{}", help);
+}
+
+#[test]
+fn feature_matrix() {
+    let (matrix, stderr) = cargo_call_stack_output(&[
+        "--example",
+        "features",
+        "--target",
+        "thumbv7m-none-eabi",
+        "--feature-matrix",
+        "default; logging",
+    ]);
+
+    assert!(
+        stderr.contains("feature-matrix: analyzing `logging`"),
+        "{}",
+        stderr
+    );
+    let mut lines = matrix.lines();
+    assert_eq!(
+        lines
+            .next()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>()),
+        Some(vec!["Root", "default", "logging", "Worst"])
+    );
+
+    // the large buffer of `log` is only compiled in with the `logging` feature
+    let start = lines.next().unwrap().split_whitespace().collect::<Vec<_>>();
+    assert_eq!(start[0], "_start");
+    assert!(
+        start[1].parse::<u64>().unwrap() < start[2].parse::<u64>().unwrap(),
+        "{:?}",
+        start
+    );
+    assert_eq!(start[3], "logging");
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn on_mismatch() {
+    for target in THUMB_TARGETS {
+        let args = |policy| {
+            [
+                "--example",
+                "stack-mismatch",
+                "--target",
+                target,
+                "--format",
+                "top",
+                "--on-mismatch",
+                policy,
+            ]
+        };
+
+        // `scratch` claims 8 bytes of stack but its prologue allocates 24 bytes
+        let (top, stderr) = cargo_call_stack_output(&args("max"));
+        assert!(
+            stderr.contains(
+                "LLVM reported that `scratch` uses 8 bytes of stack but the machine code uses \
+                 24 bytes; using the larger result"
+            ),
+            "{}",
+            stderr
+        );
+        assert!(top.contains("\n24 scratch\n"), "{}", top);
+
+        // the default
+        let (default, _) = cargo_call_stack_output(&args("max")[..6]);
+        assert_eq!(default, top);
+
+        // `branchy` claims 0 bytes but pushes onto the stack; the machine code analysis can't tell
+        // how much so its stack usage is unknown whatever the policy
+        assert!(
+            stderr.contains(
+                "LLVM reported that `branchy` uses 0 bytes of stack but the machine code modifies \
+                 the stack pointer; ignoring LLVM's result"
+            ),
+            "{}",
+            stderr
+        );
+        assert!(
+            top.contains("  lower bound: unknown stack usage of `branchy`\n"),
+            "{}",
+            top
+        );
+
+        let (top, stderr) = cargo_call_stack_output(&args("warn"));
+        assert!(
+            stderr.contains(
+                "LLVM reported that `scratch` uses 8 bytes of stack but the machine code uses \
+                 24 bytes; using LLVM's result"
+            ),
+            "{}",
+            stderr
+        );
+        assert!(top.contains("\n8 scratch\n"), "{}", top);
+
+        let (top, stderr) = cargo_call_stack_output(&args("prefer-elf"));
+        assert!(
+            !stderr.contains("LLVM reported that `scratch`"),
+            "{}",
+            stderr
+        );
+        assert!(top.contains("\n24 scratch\n"), "{}", top);
+
+        let (top, _) = cargo_call_stack_output(&args("prefer-llvm"));
+        assert!(top.contains("\n8 scratch\n"), "{}", top);
+
+        // the whole analysis fails but only after reporting the mismatches
+        let output = cargo_call_stack_command(&args("error")).output().unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(!output.status.success());
+        assert!(stderr.contains("uses 8 bytes of stack"), "{}", stderr);
+        assert!(!stderr.contains("panicked"), "{}", stderr);
+    }
+}
+
+#[test]
+fn critical_section() {
+    for target in THUMB_TARGETS {
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "critical-section",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--verbose",
+        ]);
+
+        assert!(
+            stderr.contains(
+                "critical section: `_start` masks interrupts; calls `update` with interrupts \
+                 masked\n"
+            ),
+            "{}",
+            stderr
+        );
+        assert!(
+            stderr.contains("critical section: `update` only runs with interrupts masked\n"),
+            "{}",
+            stderr
+        );
+        // `store` is also called with interrupts enabled
+        assert!(!stderr.contains("`store` only runs"), "{}", stderr);
+        assert!(!stderr.contains("`report` only runs"), "{}", stderr);
+
+        let preempted = |top: &str| {
+            top.lines()
+                .find_map(|line| {
+                    line.strip_prefix("= ")?
+                        .strip_suffix(" _start preempted by TIMER0 (exception frame: 36)")
+                })
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+        };
+        let (masked, _) = cargo_call_stack_output(&[
+            "--example",
+            "critical-section",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--critical-sections",
+        ]);
+
+        // `TIMER0` can't preempt `update`, which uses more stack than `report`
+        assert!(preempted(&masked) < preempted(&top), "{}\n{}", top, masked);
+    }
+}
+
+#[test]
+fn worst_path() {
+    let target = "thumbv7m-none-eabi";
+
+    // the recursion between `foo`, `bar` and `baz` doesn't use any stack so `quux` is on the
+    // worst-case path
+    let top = call_stack_with("cycle", target, &["--format", "top"]);
+    assert!(
+        top.contains("= 24 _start\n  path: _start (8) -> cycle::quux (16)\n"),
+        "{}",
+        top
+    );
+
+    let json = call_stack_with("cycle", target, &["--format", "json"]);
+    let id = |name: &str| {
+        json.lines()
+            .find(|line| line.contains(&format!("\"name\":\"{}\"", name)))
+            .and_then(|line| line.split("\"id\":").nth(1))
+            .and_then(|rest| rest.split(',').next())
+            .unwrap()
+    };
+    assert!(
+        json.contains(&format!(
+            "{{\"root\":{},\"confidence\":\"exact\",\"causes\":[],\"path\":[{},{}]}}",
+            id("_start"),
+            id("_start"),
+            id("cycle::quux")
+        )),
+        "{}",
+        json
+    );
+}
+
+#[test]
+fn table() {
+    let table = call_stack_with("cycle", "thumbv7m-none-eabi", &["--format", "table"]);
+    let mut lines = table.lines();
+
+    assert_eq!(
+        lines
+            .next()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>()),
+        Some(vec!["Max", "Local", "Call", "chain"])
+    );
+    // sorted by max stack usage; the chain ends at the function that reaches it
+    let first = lines.next().unwrap();
+    assert!(first.ends_with("_start -> cycle::quux"), "{}", table);
+    let maxes = lines
+        .map(|line| {
+            line.split_whitespace()
+                .next()
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert!(maxes.windows(2).all(|w| w[0] >= w[1]), "{}", table);
+    assert!(
+        table.contains("cycle::foo -> cycle::bar -> cycle::baz"),
+        "{}",
+        table
+    );
+}
+
+#[test]
+fn external_build() {
+    let target = "thumbv7m-none-eabi";
+    let top = call_stack_with("cycle", target, &["--format", "top"]);
+
+    // reuse the artifacts of the build above as if another build system had produced them
+    let (elf, ll) = artifacts("cycle", target);
+
+    let (external, stderr) = cargo_call_stack_output(&[
+        "--elf",
+        elf.to_str().unwrap(),
+        "--ll",
+        ll.to_str().unwrap(),
+        "--target",
+        target,
+        "--format",
+        "top",
+    ]);
+    assert!(!stderr.contains("Compiling"), "{}", stderr);
+    assert_eq!(external, top);
+}
+
+#[test]
+fn call_overhead() {
+    let target = "x86_64-unknown-none";
+    let usage = |top: &str, function: &str| {
+        top.lines()
+            .find_map(|line| {
+                let (usage, name) = line.split_once(' ')?;
+                (name == function).then(|| usage.parse::<u64>().unwrap())
+            })
+            .unwrap_or_else(|| panic!("`{}` not found\n{}", function, top))
+    };
+
+    let top = call_stack_with("call-sites", target, &["--format", "top"]);
+    let local = usage(&top, "_start");
+
+    // the return address is added to the function that performs the calls, not to its callees
+    let top = call_stack_with(
+        "call-sites",
+        target,
+        &["--format", "top", "--call-overhead"],
+    );
+    assert_eq!(usage(&top, "_start"), local + 8);
+    assert_eq!(usage(&top, "call_sites::foo"), 0);
+    assert!(top.contains(&format!("= {} _start", local + 8)), "{}", top);
+
+    // user-provided numbers are used as they are
+    let top = call_stack_with(
+        "call-sites",
+        target,
+        &[
+            "--format",
+            "top",
+            "--call-overhead",
+            "--override-stack",
+            "_start=32",
+        ],
+    );
+    assert_eq!(usage(&top, "_start"), 32);
+    assert!(top.contains("= 32 _start"), "{}", top);
+}
+
+#[test]
+fn start_suggestions() {
+    let output = cargo_call_stack_command(&[
+        "--example",
+        "cycle",
+        "--target",
+        "thumbv7m-none-eabi",
+        "cycle::fo",
+    ])
+    .output()
+    .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("start point `cycle::fo` not found; did you mean `cycle::foo`?"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn secondary_stack() {
+    for target in THUMB_TARGETS {
+        let max = |top: &str, root: &str| {
+            top.lines()
+                .find(|line| line.starts_with("= ") && line.ends_with(root))
+                .and_then(|line| line.split(' ').nth(1))
+                .and_then(|n| n.parse::<u64>().ok())
+                .unwrap()
+        };
+
+        // the stack switch is found in the machine code but the size of the stack is unknown
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "secondary-stack",
+            "--target",
+            target,
+            "--format",
+            "top",
+        ]);
+        assert!(
+            stderr.contains("the following functions switch to a different stack"),
+            "{}",
+            stderr
+        );
+        assert!(
+            stderr.contains("on_scratch: `mov sp, r0` at 0x"),
+            "{}",
+            stderr
+        );
+        assert!(max(&top, " _start") < 128);
+        assert!(max(&top, " filter") > 128);
+
+        // `filter` runs on the 256-byte `SCRATCH` buffer
+        let top = call_stack_with(
+            "secondary-stack",
+            target,
+            &["--format", "top", "--secondary-stack", "on_scratch=SCRATCH"],
+        );
+        assert!(max(&top, " _start") < 128);
+        assert!(max(&top, " filter [OK: budget 256]") > 128);
+
+        // the same stack declared in the configuration file, which can override its size
+        let config = env::temp_dir().join(format!("cargo-call-stack-secondary-{}.toml", target));
+        fs::write(
+            &config,
+            "[secondary-stacks.on_scratch]\nsymbol = \"SCRATCH\"\nsize = 64\n",
+        )
+        .unwrap();
+        let top = call_stack_with(
+            "secondary-stack",
+            target,
+            &["--format", "top", "--config", config.to_str().unwrap()],
+        );
+        assert!(max(&top, " _start") < 128);
+        assert!(max(&top, " filter [OVER LIMIT: budget 64]") > 128);
+    }
+}
+
+#[test]
+fn staticlib() {
+    for target in THUMB_TARGETS {
+        let output = cargo_call_stack_command(&["--lib", "--target", target, "--format", "top"])
+            .current_dir(env::current_dir().unwrap().join("firmware/staticlib"))
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        // the public functions are the roots; `log_value` is provided by the C program
+        let roots = stdout
+            .lines()
+            .skip_while(|line| *line != "Max Root")
+            .skip(1)
+            .take_while(|line| *line != "Usage Function")
+            .filter(|line| !line.starts_with("  path: "))
+            .collect::<Vec<_>>();
+        assert_eq!(roots.len(), 3, "{}", stdout);
+        assert!(roots[0].starts_with(">= ") && roots[0].ends_with(" sensor_read"));
+        assert_eq!(
+            roots[1],
+            "  lower bound: unknown stack usage of `log_value`"
+        );
+        assert_eq!(roots[2], "= 0 sensor_reset");
+    }
+}
+
+#[test]
+fn config() {
+    let config = env::temp_dir().join("cargo-call-stack-config.toml");
+    fs::write(
+        &config,
+        "[functions.log_value]\nstack = 24\ncalls = [\"uart_write\"]\n\n\
+         [functions.uart_write]\nstack = 8\n",
+    )
+    .unwrap();
+
+    for target in THUMB_TARGETS {
+        let output = cargo_call_stack_command(&[
+            "--lib",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--config",
+            config.to_str().unwrap(),
+        ])
+        .current_dir(env::current_dir().unwrap().join("firmware/staticlib"))
+        .output()
+        .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{}", stderr);
+
+        // `log_value`, provided by the C program, no longer makes `sensor_read` a lower bound
+        let root = stdout
+            .lines()
+            .find(|line| line.ends_with(" sensor_read"))
+            .unwrap();
+        assert!(root.starts_with("= "), "{}", stdout);
+        assert!(stdout.contains("\n24 log_value\n"), "{}", stdout);
+        assert!(stdout.contains("\n8 uart_write\n"), "{}", stdout);
+    }
+}
+
+#[test]
+fn library() {
+    let target = "thumbv7m-none-eabi";
+    let top = call_stack_with("cycle", target, &["--format", "top"]);
+
+    let (elf, ll) = artifacts("cycle", target);
+    let input = AnalysisInput::new(elf, ll, target);
+    let graph = analyze(&input, &Options::default()).unwrap();
+
+    // same results as the report
+    let start = graph
+        .roots
+        .iter()
+        .map(|root| &graph.functions[*root])
+        .find(|function| function.name == "_start")
+        .unwrap();
+    let max = match start.max.unwrap() {
+        AnalysisMax::Exact(n) => n,
+        AnalysisMax::LowerBound(n) => panic!("lower bound: {}", n),
+    };
+    assert!(top.contains(&format!("= {} _start", max)), "{}", top);
+    assert!(
+        top.contains(&format!("{} _start", start.local.unwrap())),
+        "{}",
+        top
+    );
+
+    // the cycle `foo` -> `bar` -> `baz` -> `foo`
+    let index = |name: &str| {
+        graph
+            .functions
+            .iter()
+            .position(|function| function.name == name)
+            .unwrap()
+    };
+    for (caller, callee) in [
+        ("cycle::foo", "cycle::bar"),
+        ("cycle::bar", "cycle::baz"),
+        ("cycle::baz", "cycle::foo"),
+    ] {
+        let (caller, callee) = (index(caller), index(callee));
+        assert!(graph
+            .calls
+            .iter()
+            .any(|call| call.caller == caller && call.callee == callee));
+    }
+}
+
+#[test]
+fn reproducible() {
+    let target = "thumbv7m-none-eabi";
+    let path = env::temp_dir().join("cargo-call-stack-reproducible.txt");
+    let args = ["--reproducible", "--editor-data", path.to_str().unwrap()];
+    let dot = call_stack_with("cycle", target, &args);
+    let editor_data = fs::read_to_string(&path).unwrap();
+
+    // no timestamp; and the output doesn't change between runs
+    assert!(!dot.contains(">date<"), "{}", dot);
+    assert_eq!(call_stack_with("cycle", target, &args), dot);
+
+    // the paths of the crate are relative to the current directory
+    let firmware = env::current_dir().unwrap().join("firmware");
+    assert!(!dot.contains(firmware.to_str().unwrap()), "{}", dot);
+    let annotations = Annotations::parse(&editor_data).unwrap();
+    assert!(annotations
+        .files()
+        .any(|file| file == Path::new("examples/cycle.rs")));
+    assert!(!editor_data.contains(firmware.to_str().unwrap()));
+
+    // the date can be pinned
+    let output =
+        cargo_call_stack_command(&["--example", "cycle", "--target", target, "--reproducible"])
+            .env("SOURCE_DATE_EPOCH", "951782400")
+            .output()
+            .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains(">2000-02-29<"));
+}
+
+#[test]
+fn v0_mangling() {
+    // a target directory of its own as the flags differ from the ones of the other tests
+    let target_dir = env::temp_dir().join("cargo-call-stack-v0");
+    let output = cargo_call_stack_command(&[
+        "--example",
+        "cycle",
+        "--target",
+        "thumbv7m-none-eabi",
+        "--target-dir",
+        target_dir.to_str().unwrap(),
+        "--format",
+        "top",
+        "cycle::foo",
+    ])
+    .env("RUSTFLAGS", "-C symbol-mangling-version=v0")
+    .output()
+    .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(output.status.success(), "{}", stderr);
+
+    // the crate disambiguators, e.g. `cycle[20dce616049aac1e]`, are removed like legacy hashes
+    assert!(stdout.contains(" cycle::foo\n"), "{}", stdout);
+    assert!(stdout.contains(" cycle::bar\n"), "{}", stdout);
+    assert!(!stdout.contains('['), "{}", stdout);
+}
+
+#[test]
+fn aggregate_fmt() {
+    let target = "thumbv7m-none-eabi";
+
+    let top = call_stack_with("panic-fmt", target, &["--format", "top"]);
+    let aggregated = call_stack_with("panic-fmt", target, &["--format", "top", "--aggregate-fmt"]);
+
+    // the formatters are gone but the bound is the same
+    assert!(top.contains("Display for u32>::fmt"), "{}", top);
+    assert!(
+        !aggregated.contains("Display for u32>::fmt"),
+        "{}",
+        aggregated
+    );
+    assert!(aggregated.contains(" <any formatter>\n"), "{}", aggregated);
+    assert_eq!(top.lines().next(), aggregated.lines().next());
+    assert!(aggregated.lines().count() < top.lines().count());
+}
+
+#[test]
+fn start_points() {
+    let target = "thumbv7m-none-eabi";
+
+    // one report per start point
+    let top = call_stack_with(
+        "cycle",
+        target,
+        &[
+            "--format",
+            "top",
+            "--start",
+            "cycle::foo",
+            "--start",
+            "cycle::quux",
+        ],
+    );
+    assert!(top.contains("= 0 cycle::foo\n"), "{}", top);
+    assert!(top.contains("= 16 cycle::quux\n"), "{}", top);
+    assert!(!top.contains("_start"), "{}", top);
+
+    // wildcards
+    let top = call_stack_with(
+        "cycle",
+        target,
+        &["--format", "top", "--start", "cycle::ba?"],
+    );
+    assert!(top.contains("= 0 cycle::bar\n"), "{}", top);
+    assert!(top.contains("= 0 cycle::baz\n"), "{}", top);
+    assert!(!top.contains("cycle::quux"), "{}", top);
+
+    let output = cargo_call_stack_command(&[
+        "--example",
+        "cycle",
+        "--target",
+        target,
+        "--start",
+        "*::interrupt::*",
+    ])
+    .output()
+    .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("start point pattern `*::interrupt::*` matches no function"));
+}
+
+#[test]
+fn disassembly() {
+    let dir = env::temp_dir().join("cargo-call-stack-disassembly");
+    let _ = fs::remove_dir_all(&dir);
+    let target = "thumbv7m-none-eabi";
+
+    let json = call_stack_with(
+        "cycle",
+        target,
+        &["--disassembly", dir.to_str().unwrap(), "--format", "json"],
+    );
+    let quux = dir.join("cycle__quux.s");
+    assert!(json.contains(&format!(
+        "\"name\":\"cycle::quux\",\"local\":16,\"source\":\"llvm\",\"max\":{{\"exact\":16}},\"dashed\":false,\"overridden\":false,\"disassembly\":\"{}\"}}",
+        quux.display()
+    )));
+
+    // `quux` spills registers to use them in its inline assembly
+    let disassembly = fs::read_to_string(&quux).unwrap();
+    assert!(disassembly.starts_with("000"), "{}", disassembly);
+    assert!(disassembly.contains("<cycle::quux::h"), "{}", disassembly);
+    assert!(disassembly.contains("push"), "{}", disassembly);
+    assert!(!disassembly.contains("<_start>"), "{}", disassembly);
+
+    let dot = call_stack_with("cycle", target, &["--disassembly", dir.to_str().unwrap()]);
+    assert!(
+        dot.contains(&format!(" URL=\"{}\"]", dir.join("_start.s").display())),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn syscall() {
+    for target in THUMB_TARGETS {
+        // without a description of the system calls the implementations are separate roots
+        let top = call_stack_with("svc", target, &["--format", "top"]);
+        assert!(top.contains("\n= 8 svc::sys_yield\n"));
+
+        // the system calls are dispatched through a table
+        let top = call_stack_with(
+            "svc",
+            target,
+            &["--format", "top", "--syscall-table", "SYSCALLS"],
+        );
+        assert!(top.contains("Max Root\n= 16 _start\n  path: _start "));
+
+        // only `svc #0` is described so `svc #1` may reach any system call
+        let (top, stderr) = cargo_call_stack_output(&[
+            "--example",
+            "svc",
+            "--target",
+            target,
+            "--format",
+            "top",
+            "--syscall",
+            "0=svc::sys_yield",
+        ]);
+        assert!(stderr.contains("`svc #1` at "));
+        assert!(stderr.contains(" in `_start` is not a known system call"));
+        assert!(top.contains("\n= 16 _start\n"));
+        // `sys_write` is not a known system call
+        assert!(top.contains("\n= 4 svc::sys_write\n"));
+    }
+}
+
+#[test]
+fn longjmp() {
+    for target in THUMB_TARGETS {
+        // the function that called `setjmp` is unknown
+        let top = call_stack_with("longjmp", target, &["--format", "top", "_start"]);
+        assert!(top.contains("\n>= 24 _start\n  lower bound: unknown stack usage of `longjmp`\n"));
+
+        let top = call_stack_with(
+            "longjmp",
+            target,
+            &[
+                "--format",
+                "top",
+                "--longjmp-target",
+                "longjmp::fail=_start",
+                "_start",
+            ],
+        );
+        assert!(top.contains("\n= 24 _start\n"));
+
+        // `longjmp::parse` doesn't call `setjmp`
+        let top = call_stack_with(
+            "longjmp",
+            target,
+            &[
+                "--format",
+                "top",
+                "--longjmp-target",
+                "longjmp::fail=longjmp::parse",
+                "_start",
+            ],
+        );
+        assert!(top.contains("\n>= 24 _start\n"));
+    }
+}
+
+#[test]
+fn main_loop() {
+    for target in THUMB_TARGETS {
+        let top = call_stack_with(
+            "main-loop",
+            target,
+            &["--format", "top", "--main-loop", "_start"],
+        );
+
+        // `init`, which uses a lot of stack, is only called before the loop
+        let init = top
+            .lines()
+            .find_map(|line| line.strip_suffix(" _start (init)"))
+            .unwrap();
+        assert!(init.trim_start_matches("= ").parse::<u64>().unwrap() > 100);
+        assert!(top.contains("Max Phase\n"));
+        assert!(top.contains("\n= 8 _start (main loop)\n"));
+
+        let json = call_stack_with(
+            "main-loop",
+            target,
+            &["--format", "json", "--main-loop", "_start"],
+        );
+        assert!(json.contains("\"steady_state\":{\"exact\":8}"));
+    }
+}
+
+#[test]
+fn drop_paths() {
+    let target = "thumbv7m-none-eabi";
+    let top = call_stack_with("drop-paths", target, &["--format", "top", "--drop-paths"]);
+
+    // the destructor uses more stack than `work`
+    let path = |kind| {
+        top.lines()
+            .find_map(|line| line.strip_suffix(&format!(" _start ({})", kind)))
+            .and_then(|max| max.trim_start_matches("= ").parse::<u64>().ok())
+            .unwrap()
+    };
+    assert!(top.contains("Max Path\n"), "{}", top);
+    assert!(path("drop") > path("straight-line") + 32, "{}", top);
+    assert!(
+        top.contains(&format!("= {} _start\n", path("drop"))),
+        "{}",
+        top
+    );
+
+    let json = call_stack_with("drop-paths", target, &["--format", "json", "--drop-paths"]);
+    assert!(
+        json.contains("\"drop_paths\": [{\"function\":\"_start\","),
+        "{}",
+        json
+    );
+}
+
+#[test]
+fn explain_frame() {
+    let target = "thumbv7m-none-eabi";
+    let frame = call_stack_with(
+        "drop-paths",
+        target,
+        &[
+            "--explain-frame",
+            "<drop_paths::Guard as core::ops::drop::Drop>::drop",
+        ],
+    );
+
+    let mut lines = frame.lines();
+    let local = lines
+        .next()
+        .and_then(|line| line.strip_suffix(" bytes"))
+        .and_then(|line| line.strip_prefix("<drop_paths::Guard as core::ops::drop::Drop>::drop: "))
+        .and_then(|local| local.parse::<u64>().ok())
+        .unwrap();
+    // the largest variable comes first
+    assert_eq!(
+        lines.next().map(str::trim_start),
+        Some("64 buffer: [u8; 64]"),
+        "{}",
+        frame
+    );
+    let rest = lines
+        .find_map(|line| line.strip_suffix(" (saved registers, spills and padding)"))
+        .and_then(|rest| rest.trim_start().parse::<u64>().ok())
+        .unwrap();
+    assert_eq!(local, 64 + rest, "{}", frame);
+
+    let output = cargo_call_stack_command(&[
+        "--example",
+        "drop-paths",
+        "--target",
+        target,
+        "--explain-frame",
+        "work",
+    ])
+    .output()
+    .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("did you mean `drop_paths::work`?"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn override_stack() {
+    let dot = call_stack_with(
+        "cycle",
+        "thumbv7m-none-eabi",
+        &["--override-stack", "cycle::quux=100"],
+    );
+
+    assert!(dot.contains("label=<cycle::quux<br/><b>max = 100</b><br/><font color=\"gray\">local = 100 (overridden)</font>>"));
+    assert!(dot.contains("label=<_start<br/><b>max = 108</b><br/>"));
+}
+
+#[test]
+fn editor_data() {
+    let path = env::temp_dir().join("cargo-call-stack-editor-data.txt");
+    let _dot = call_stack_with(
+        "cycle",
+        "thumbv7m-none-eabi",
+        &["--editor-data", path.to_str().unwrap()],
+    );
+
+    let annotations = Annotations::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+    let file = annotations
+        .files()
+        .find(|file| file.ends_with("examples/cycle.rs"))
+        .unwrap();
+
+    // `fn quux() {` is on line 36
+    let quux = &annotations.line(file, 36)[0];
+    assert_eq!(quux.function, "cycle::quux");
+    assert_eq!(quux.max, Some(Max::Exact(16)));
+    assert!(annotations
+        .file(file)
+        .iter()
+        .any(|annotation| annotation.function == "_start"));
+}
+
+#[test]
+fn big_endian() {
+    let dot = call_stack("cycle", "armebv7r-none-eabi");
+
+    assert!(dot.contains("label=<_start<br/><b>max = "));
+    assert!(dot.contains("label=<SCC0<br/>3 functions"));
 }
 
 #[test]
@@ -39,7 +1594,7 @@ fn fmul() {
         let mut fmul = None;
 
         for line in dot.lines() {
-            if line.contains("label=\"_start\\n") {
+            if line.contains("label=<_start<br/>") {
                 entry_point = Some(
                     line.split_whitespace()
                         .next()
@@ -47,7 +1602,7 @@ fn fmul() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"__aeabi_fmul\\n") {
+            } else if line.contains("label=<__aeabi_fmul<br/>") {
                 fmul = Some(
                     line.split_whitespace()
                         .next()
@@ -66,6 +1621,18 @@ fn fmul() {
     }
 }
 
+#[test]
+fn no_builtins_analysis() {
+    for target in FMUL_TARGETS {
+        let dot = call_stack_with("fmul", target, &["--no-builtins-analysis"]);
+
+        // the call to the `compiler_builtins` function is still in the graph
+        assert!(dot.contains("label=<__aeabi_fmul<br/>"));
+        // but it doesn't make function pointer calls unbounded
+        assert!(!dot.contains("label=<?<br/>"));
+    }
+}
+
 #[test]
 fn function_pointer() {
     for_all_targets(|target| {
@@ -76,7 +1643,7 @@ fn function_pointer() {
         let mut fn_call = None;
 
         for line in dot.lines() {
-            if line.contains("label=\"function_pointer::foo\\n") {
+            if line.contains("label=<function_pointer::foo<br/>") {
                 foo = Some(
                     line.split_whitespace()
                         .next()
@@ -84,7 +1651,7 @@ fn function_pointer() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"function_pointer::bar\\n") {
+            } else if line.contains("label=<function_pointer::bar<br/>") {
                 bar = Some(
                     line.split_whitespace()
                         .next()
@@ -92,7 +1659,7 @@ fn function_pointer() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"i1 ()*\\n") {
+            } else if line.contains("label=<i1 ()*<br/>") {
                 fn_call = Some(
                     line.split_whitespace()
                         .next()
@@ -113,6 +1680,17 @@ fn function_pointer() {
     })
 }
 
+#[test]
+fn ignore_indirect() {
+    for_all_targets(|target| {
+        let dot = call_stack_with("function-pointer", target, &["--ignore-indirect"]);
+
+        // no fictitious node for the `fn() -> bool` call
+        assert!(!dot.contains("i1 ()*"));
+        assert!(dot.contains("label=<function_pointer::foo<br/>"));
+    })
+}
+
 #[test]
 fn function_pointer_ptr() {
     for_all_targets(|target| {
@@ -123,7 +1701,7 @@ fn function_pointer_ptr() {
         let mut fn_call = None;
 
         for line in dot.lines() {
-            if line.contains("label=\"function_pointer_ptr::foo\\n") {
+            if line.contains("label=<function_pointer_ptr::foo<br/>") {
                 foo = Some(
                     line.split_whitespace()
                         .next()
@@ -131,7 +1709,7 @@ fn function_pointer_ptr() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"function_pointer_ptr::bar\\n") {
+            } else if line.contains("label=<function_pointer_ptr::bar<br/>") {
                 bar = Some(
                     line.split_whitespace()
                         .next()
@@ -139,7 +1717,7 @@ fn function_pointer_ptr() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"i1 (ptr)*\\n") {
+            } else if line.contains("label=<i1 (ptr)*<br/>") {
                 fn_call = Some(
                     line.split_whitespace()
                         .next()
@@ -171,7 +1749,7 @@ fn dynamic_dispatch() {
         let mut dyn_call = None;
 
         for line in dot.lines() {
-            if line.contains("label=\"dynamic_dispatch::Foo::foo\\n") {
+            if line.contains("label=<dynamic_dispatch::Foo::foo<br/>") {
                 bar = Some(
                     line.split_whitespace()
                         .next()
@@ -180,7 +1758,7 @@ fn dynamic_dispatch() {
                         .unwrap(),
                 );
             } else if line
-                .contains("label=\"<dynamic_dispatch::Baz as dynamic_dispatch::Foo>::foo\\n")
+                .contains("label=<&lt;dynamic_dispatch::Baz as dynamic_dispatch::Foo&gt;::foo<br/>")
             {
                 baz = Some(
                     line.split_whitespace()
@@ -189,7 +1767,7 @@ fn dynamic_dispatch() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"dynamic_dispatch::Quux::foo\\n") {
+            } else if line.contains("label=<dynamic_dispatch::Quux::foo<br/>") {
                 quux = Some(
                     line.split_whitespace()
                         .next()
@@ -197,7 +1775,7 @@ fn dynamic_dispatch() {
                         .parse::<u32>()
                         .unwrap(),
                 );
-            } else if line.contains("label=\"i1 (ptr)*\\n") {
+            } else if line.contains("label=<i1 (ptr)*<br/>") {
                 dyn_call = Some(
                     line.split_whitespace()
                         .next()
@@ -219,6 +1797,9 @@ fn dynamic_dispatch() {
 
         // but there must not be an edge from the fictitious node and `Quux`
         assert!(!dot.contains(&format!("{} -> {}", dyn_call, quux)));
+
+        // the fictitious node reports how many functions it may call
+        assert!(dot.contains("<br/>callees = 2> style=dashed"));
     })
 }
 
@@ -257,20 +1838,318 @@ fn gh74() {
     })
 }
 
-fn call_stack(ex: &str, target: &str) -> String {
-    // target/debug/deps/firmware-$HASH
-    let mut current_exe = env::current_exe().unwrap();
-    current_exe.pop();
-    current_exe.pop();
-    let output = Command::new(current_exe.join("cargo-call-stack"))
-        .args(&["--example", ex, "--target", target])
-        .current_dir(env::current_dir().unwrap().join("firmware"))
-        // (env_remove) do not inherit the parent toolchain
-        // without this `firmware/rust-toolchain.toml` is ignored
-        .env_remove("RUSTUP_TOOLCHAIN")
-        .env_remove("CARGO")
+#[test]
+fn ramfunc() {
+    for_all_targets(|target| {
+        let dot = call_stack("ramfunc", target);
+
+        // the function placed in its own section must be analyzed
+        assert!(dot.contains("label=<ramfunc::ramfunc<br/>"));
+    })
+}
+
+#[test]
+fn veneer() {
+    for target in THUMB_TARGETS {
+        // a target directory of its own as the flags differ from the ones of the other tests
+        let target_dir = env::temp_dir().join("cargo-call-stack-veneer");
+        let output = cargo_call_stack_command(&[
+            "--example",
+            "ramfunc",
+            "--target",
+            target,
+            "--target-dir",
+            target_dir.to_str().unwrap(),
+            "--format",
+            "top",
+        ])
+        // far enough apart that the linker inserts a long-branch thunk between the two
+        .env(
+            "RUSTFLAGS",
+            "-C link-arg=-Ttext=0x1000 -C link-arg=--section-start=.ramfunc=0x20000000",
+        )
         .output()
         .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{}", stderr);
+
+        // the thunk is collapsed into the function it jumps to
+        assert!(!stdout.contains("Thunk"), "{}", stdout);
+        assert!(!stderr.contains("Thunk"), "{}", stderr);
+        assert!(
+            stdout.contains("  path: _start (") && stdout.contains(") -> ramfunc::ramfunc (0)\n"),
+            "{}",
+            stdout
+        );
+
+        // the ARMv6-M thunk pushes two registers onto the stack of its caller
+        let expected = if *target == "thumbv6m-none-eabi" {
+            "= 16 _start\n"
+        } else {
+            "= 8 _start\n"
+        };
+        assert!(stdout.contains(expected), "{}", stdout);
+    }
+}
+
+#[test]
+fn registered() {
+    for_all_targets(|target| {
+        let dot = call_stack("registered", target);
+        let id = |name: &str| {
+            dot.lines()
+                .find(|line| line.contains(&format!("label=<{}<br/>", name)))
+                .and_then(|line| line.split_whitespace().next())
+                .unwrap()
+                .to_owned()
+        };
+
+        // `_start` calls the functions registered in the `handlers` section
+        let start = id("_start");
+        assert!(dot.contains(&format!("{} -> {}\n", start, id("registered::foo"))));
+        assert!(dot.contains(&format!("{} -> {}\n", start, id("registered::bar"))));
+
+        // and the indirect call that invokes them can't reach any other function
+        assert!(!dot.contains("style=dashed"));
+        assert!(dot.contains("label=<_start<br/><b>max = "));
+    })
+}
+
+#[test]
+fn distributed_slices_config() {
+    let config = env::temp_dir().join("cargo-call-stack-distributed-slices.toml");
+    fs::write(
+        &config,
+        "[distributed-slices]\nsections = [\"handlers\", \"missing\"]\n",
+    )
+    .unwrap();
+
+    let target = "thumbv7m-none-eabi";
+    let (_, stderr) = cargo_call_stack_output(&[
+        "--example",
+        "registered",
+        "--target",
+        target,
+        "--config",
+        config.to_str().unwrap(),
+    ]);
+
+    // the sections listed in the configuration file are looked up like `--distributed-slice`
+    assert!(
+        stderr.contains("distributed slice: section `missing` not found"),
+        "{}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("section `handlers` not found"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn registered_and_other_calls() {
+    for_all_targets(|target| {
+        let dot = call_stack("registered-hook", target);
+        let start = node_id(&dot, "_start");
+
+        // only the call site that iterates over the section is bound to the registered functions;
+        // the other indirect call with the same signature can reach any `fn()`
+        assert!(dot.contains(&format!("{} -> {}\n", start, node_id(&dot, "void ()*"))));
+        assert!(dot.contains(&format!(
+            "{} -> {}\n",
+            node_id(&dot, "void ()*"),
+            node_id(&dot, "registered_hook::baz")
+        )));
+    })
+}
+
+#[test]
+fn constructors() {
+    for_all_targets(|target| {
+        // the constructor is not called by any function so it's a root
+        let top = call_stack_with("constructor", target, &["--format", "top"]);
+        let roots = &top[top.find("Max Root").unwrap()..top.find("Usage Function").unwrap()];
+        assert!(roots.contains(" constructor::constructor\n"));
+
+        // but it runs before `_start` on the same stack
+        let dot = call_stack_with("constructor", target, &["--include-constructors"]);
+        assert!(dot.contains(&format!(
+            "{} -> {}\n",
+            node_id(&dot, "_start"),
+            node_id(&dot, "constructor::constructor")
+        )));
+        assert!(!dot.contains("label=<_start<br/><b>max = 0</b><br/>"));
+    })
+}
+
+#[test]
+fn pre_init() {
+    // `cortex-m-rt` provides `__pre_init` as an IR alias
+    let ll = env::temp_dir().join("cargo-call-stack-pre-init.ll");
+    fs::write(
+        &ll,
+        "@__pre_init = alias void (), ptr @DefaultPreInit\n\ndeclare void @DefaultPreInit()\n",
+    )
+    .unwrap();
+
+    for target in THUMB_TARGETS {
+        let dot = call_stack_with(
+            "pre-init",
+            target,
+            &["--include-constructors", "--extra-ll", ll.to_str().unwrap()],
+        );
+
+        // its aliasee runs before `_start` on the same stack
+        assert!(dot.contains(&format!(
+            "{} -> {}\n",
+            node_id(&dot, "_start"),
+            node_id(&dot, "DefaultPreInit")
+        )));
+        assert!(dot.contains("label=<_start<br/><b>max = 8</b><br/>"));
+    }
+}
+
+#[test]
+fn split_output() {
+    let dir = env::temp_dir().join("cargo-call-stack-split-output");
+    let _ = fs::remove_dir_all(&dir);
+    let _json = call_stack_with(
+        "constructor",
+        "thumbv7m-none-eabi",
+        &["--format", "json", "--split-output", dir.to_str().unwrap()],
+    );
+
+    // one file per root, named after it
+    let mut files = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["_start.json", "constructor__constructor.json"]);
+
+    let json = fs::read_to_string(dir.join("constructor__constructor.json")).unwrap();
+    assert!(json.contains("{\"id\":0,\"name\":\"constructor::constructor\""));
+    assert!(!json.contains("_start"));
+    assert!(json.contains("\"roots\": [0]"));
+}
+
+#[test]
+fn split_output_format() {
+    // rejected before anything is built or read, also when Cargo is not involved
+    let output = cargo_call_stack_command(&[
+        "--elf",
+        "does-not-exist",
+        "--ll",
+        "does-not-exist.ll",
+        "--format",
+        "top",
+        "--split-output",
+        env::temp_dir().to_str().unwrap(),
+    ])
+    .output()
+    .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("--split-output only supports the `dot` and `json` formats"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn confidence() {
+    // `core::fmt::write` calls itself through a trait object
+    let top = call_stack_with("core-fmt", "thumbv7m-none-eabi", &["--format", "top"]);
+    assert!(top.contains("\n>= "));
+    assert!(top.contains("  lower bound: recursion through "));
+
+    let json = call_stack_with("core-fmt", "thumbv7m-none-eabi", &["--format", "json"]);
+    assert!(json.contains("\"confidence\":\"lower_bound\",\"causes\":[{\"cycle\":["));
+
+    // the cycle doesn't use the stack
+    let top = call_stack_with("cycle", "thumbv7m-none-eabi", &["--format", "top"]);
+    assert!(top.contains("Max Root\n= "));
+    assert!(!top.contains("lower bound"));
+}
+
+#[test]
+fn wasm() {
+    let top = call_stack_with("wasm", "wasm32-unknown-unknown", &["--format", "top"]);
+
+    // the shadow stack usage is derived from the bytecode
+    assert!(top.contains("\n64 wasm::foo\n"), "{}", top);
+    assert!(top.contains("\n0 wasm::bar\n"), "{}", top);
+
+    // the function pointer can reach both `foo` and `bar`, whose type matches
+    let root = top
+        .lines()
+        .find(|line| line.ends_with(" _start") && line.starts_with("= "))
+        .unwrap();
+    let max = root[2..root.len() - " _start".len()]
+        .parse::<u64>()
+        .unwrap();
+    assert!(max >= 64, "{}", top);
+    assert!(
+        top.contains(" -> i32 (i32)* (0) -> wasm::foo (64)"),
+        "{}",
+        top
+    );
+}
+
+fn call_stack(ex: &str, target: &str) -> String {
+    call_stack_with(ex, target, &[])
+}
+
+// the ELF file and the LLVM IR of the last build of `example`
+// the id of the node labeled `name` in the dot output
+fn node_id(dot: &str, name: &str) -> String {
+    dot.lines()
+        .find(|line| line.contains(&format!("label=<{}<br/>", name)))
+        .and_then(|line| line.split_whitespace().next())
+        .unwrap_or_else(|| panic!("no node named `{}` in:\n{}", name, dot))
+        .to_owned()
+}
+
+fn artifacts(example: &str, target: &str) -> (PathBuf, PathBuf) {
+    let examples = env::current_dir()
+        .unwrap()
+        .join("firmware/target/call-stack")
+        .join(target)
+        .join("release/examples");
+    let ll = fs::read_dir(&examples)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == "ll")
+                && path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with(&format!("{}-", example))
+        })
+        .max_by_key(|path| fs::metadata(path).unwrap().modified().unwrap())
+        .unwrap();
+
+    (examples.join(example), ll)
+}
+
+fn call_stack_with(ex: &str, target: &str, extra_args: &[&str]) -> String {
+    let mut args = vec!["--example", ex, "--target", target];
+    args.extend_from_slice(extra_args);
+    cargo_call_stack(&args)
+}
+
+// runs `cargo call-stack` in the `firmware` directory and returns its standard output
+fn cargo_call_stack(args: &[&str]) -> String {
+    cargo_call_stack_output(args).0
+}
+
+// like `cargo_call_stack` but also returns the standard error, where the warnings are printed
+fn cargo_call_stack_output(args: &[&str]) -> (String, String) {
+    let output = cargo_call_stack_command(args).output().unwrap();
     if !output.status.success() {
         panic!(
             "stdout:\n{}\n\nstderr:\n{}",
@@ -278,5 +2157,25 @@ fn call_stack(ex: &str, target: &str) -> String {
             String::from_utf8(output.stderr).unwrap()
         );
     }
-    String::from_utf8(output.stdout).unwrap()
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+// `cargo call-stack` with the given arguments, to be run in the `firmware` directory
+fn cargo_call_stack_command(args: &[&str]) -> Command {
+    // target/debug/deps/firmware-$HASH
+    let mut current_exe = env::current_exe().unwrap();
+    current_exe.pop();
+    current_exe.pop();
+    let mut command = Command::new(current_exe.join("cargo-call-stack"));
+    command
+        .args(args)
+        .current_dir(env::current_dir().unwrap().join("firmware"))
+        // (env_remove) do not inherit the parent toolchain
+        // without this `firmware/rust-toolchain.toml` is ignored
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .env_remove("CARGO");
+    command
 }